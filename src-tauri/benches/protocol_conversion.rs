@@ -0,0 +1,143 @@
+//! 车辆信息数据域解码基准
+//!
+//! 对比同一批数据域（1024 条，分别位于对齐与不对齐的偏移）的几种解码方式：
+//! - `view`：布局视图零拷贝借用后转换为结构体
+//! - `view_field`：只通过视图读取单个字段（零拷贝的主要收益场景）
+//! - `copy`：逐字段 `from_le_bytes` 拷贝解码（原先未对齐输入回退到的方式）
+//! - `validated`：带取值范围验证的安全转换
+//! - `schema`：按 `messages.toml` 协议定义的通用解析
+//!
+//! 运行：`cargo bench --bench protocol_conversion`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dz_viz_lib::bench_support::*;
+use std::hint::black_box;
+
+const RECORDS: usize = 1024;
+const SIZE: usize = ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE;
+
+/// 在 `offset` 个填充字节之后连续排列的车辆信息数据域
+fn sample_stream(offset: usize) -> Vec<u8> {
+    let mut builder = ProtocolBuilder::new();
+    let mut stream = vec![0u8; offset];
+    for index in 0..RECORDS {
+        let info = VehicleInfo {
+            vehicle_id: (index % 250) as u8 + 1,
+            speed: 0.5,
+            position_x: index as f64 * 0.1,
+            position_y: -(index as f64) * 0.2,
+            orientation: 90.0,
+            battery: 80.0,
+            gear: GearPosition::DriveLevel(1),
+            steering_angle: -12.5,
+            nav_status: 1,
+            sensors: SensorStatus { camera: true, lidar: true, gyro: false },
+            parking_slot: 0,
+        };
+        stream.extend(builder.build_vehicle_info(&info));
+    }
+    stream
+}
+
+fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// 逐字段拷贝解码
+fn decode_by_copy(bytes: &[u8]) -> VehicleInfo {
+    VehicleInfo {
+        vehicle_id: bytes[ProtocolConstants::VEHICLE_INFO_VEHICLE_ID_OFFSET],
+        speed: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_SPEED_OFFSET),
+        position_x: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_POSITION_X_OFFSET),
+        position_y: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_POSITION_Y_OFFSET),
+        orientation: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_ORIENTATION_OFFSET),
+        battery: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_BATTERY_OFFSET),
+        gear: GearPosition::from_u8(bytes[ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET]),
+        steering_angle: f64_at(bytes, ProtocolConstants::VEHICLE_INFO_STEERING_ANGLE_OFFSET),
+        nav_status: bytes[ProtocolConstants::VEHICLE_INFO_NAV_STATUS_OFFSET],
+        sensors: SensorStatus {
+            camera: bytes[ProtocolConstants::VEHICLE_INFO_CAMERA_STATUS_OFFSET] != 0,
+            lidar: bytes[ProtocolConstants::VEHICLE_INFO_LIDAR_STATUS_OFFSET] != 0,
+            gyro: bytes[ProtocolConstants::VEHICLE_INFO_GYRO_STATUS_OFFSET] != 0,
+        },
+        parking_slot: bytes[ProtocolConstants::VEHICLE_INFO_PARKING_SLOT_OFFSET],
+    }
+}
+
+fn bench_vehicle_info(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vehicle_info");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+
+    for offset in [0usize, 1] {
+        let stream = sample_stream(offset);
+        let data = &stream[offset..];
+
+        group.bench_with_input(BenchmarkId::new("view", offset), data, |b, data| {
+            let mut converter = DataConverter::new();
+            b.iter(|| {
+                for raw in converter.memory_map_convert::<VehicleInfoRaw>(data, RECORDS).unwrap() {
+                    black_box(raw.to_vehicle_info());
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("view_field", offset), data, |b, data| {
+            let mut converter = DataConverter::new();
+            b.iter(|| {
+                let records = converter.memory_map_convert::<VehicleInfoRaw>(data, RECORDS).unwrap();
+                black_box(records.iter().map(|raw| raw.battery.get()).sum::<f64>())
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("copy", offset), data, |b, data| {
+            b.iter(|| {
+                for chunk in data.chunks_exact(SIZE) {
+                    black_box(decode_by_copy(chunk));
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("validated", offset), data, |b, data| {
+            let mut converter = DataConverter::new();
+            b.iter(|| {
+                for chunk in data.chunks_exact(SIZE) {
+                    black_box(converter.safe_convert_vehicle_info(chunk).unwrap());
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("schema", offset), data, |b, data| {
+            let mut parser = ProtocolParser::new(false);
+            b.iter(|| {
+                for chunk in data.chunks_exact(SIZE) {
+                    black_box(parser.parse_protocol(MessageTypes::VEHICLE_INFO, chunk));
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_stream(c: &mut Criterion) {
+    let stream = sample_stream(1);
+    let mut group = c.benchmark_group("stream_processor");
+    group.throughput(Throughput::Bytes((RECORDS * SIZE) as u64));
+    group.bench_function("process_stream", |b| {
+        let mut processor = StreamProcessor::new(0);
+        b.iter(|| {
+            let mut battery = 0.0;
+            let count = processor
+                .process_stream::<VehicleInfoRaw, _>(&stream[1..], |raw| {
+                    battery += raw.battery.get();
+                    Ok(())
+                })
+                .unwrap();
+            black_box((count, battery))
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_vehicle_info, bench_stream);
+criterion_main!(benches);
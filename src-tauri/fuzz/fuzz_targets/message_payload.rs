@@ -0,0 +1,8 @@
+//! 按协议定义解析数据域
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    dz_viz_lib::fuzzing::message_payload(data);
+});
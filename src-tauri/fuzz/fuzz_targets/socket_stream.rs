@@ -0,0 +1,8 @@
+//! 车辆Socket帧流解码
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    dz_viz_lib::fuzzing::socket_stream(data);
+});
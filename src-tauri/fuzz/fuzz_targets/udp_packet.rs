@@ -0,0 +1,8 @@
+//! UDP视频包解析与单片重组
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    dz_viz_lib::fuzzing::udp_packet(data);
+});
@@ -0,0 +1,8 @@
+//! 零拷贝数据转换
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    dz_viz_lib::fuzzing::zero_copy_convert(data);
+});
@@ -0,0 +1,4 @@
+// 无界面服务入口：在没有显示环境的主机上运行车辆后端
+fn main() {
+    dz_viz_lib::run_headless()
+}
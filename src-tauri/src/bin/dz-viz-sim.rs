@@ -0,0 +1,4 @@
+// 车辆模拟器入口：无需真车即可进行联调与演示
+fn main() {
+    dz_viz_lib::run_simulator()
+}
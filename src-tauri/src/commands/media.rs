@@ -163,7 +163,7 @@ pub async fn start_udp_video_server(app: tauri::AppHandle, port: Option<u16>) ->
     
     let mut manager = UDP_VIDEO_MANAGER.lock().await;
     
-    manager.start_server(&bind_addr, Some(app)).await
+    manager.start_server(&bind_addr, Some(Arc::new(app))).await
         .map_err(|e| format!("启动UDP视频服务器失败: {}", e))?;
     
     info!("✅ UDP视频服务器启动成功: {}", bind_addr);
@@ -12,6 +12,8 @@ pub mod protocol_processing;
 pub mod protocol_config;
 pub mod vehicle_state;
 pub mod path;
+pub mod replay;
+pub mod simulator;
 
 // 导出命令供 lib.rs 使用
 pub use system::{
@@ -30,7 +32,10 @@ pub use vehicle::{
     get_connected_vehicles, get_vehicle_connections, create_vehicle_connection,
     update_vehicle_connection, delete_vehicle_connection, get_active_vehicle_connections,
     get_socket_server_status, broadcast_taxi_order, send_taxi_order_to_vehicle, send_avp_parking, send_avp_pickup,
-    get_vehicle_online_stats, get_driving_behavior_stats, get_vehicle_server_ports,
+    get_taxi_orders, get_taxi_order_history, cancel_taxi_order, dispatch_taxi_order,
+    enqueue_taxi_order, get_taxi_order_queue, reorder_taxi_order_queue,
+    get_parking_slots, save_parking_slot, delete_parking_slot,
+    get_vehicle_online_stats, get_vehicle_telemetry, get_driving_behavior_stats, get_vehicle_server_ports,
     send_vehicle_control_command, send_data_recording_command,
     send_vehicle_function_setting_command, send_vehicle_path_display_command,
     send_vehicle_camera_toggle_command,
@@ -40,7 +45,8 @@ pub use vehicle::{
 pub use sandbox::{
     send_sandbox_control, send_sandbox_exit_control, is_sandbox_connected, send_sandbox_traffic_light_duration,
     get_traffic_light_item, update_traffic_light_item, get_traffic_light_settings,
-    update_traffic_light_settings, get_sandbox_service_settings, 
+    update_traffic_light_settings, get_sandbox_service_settings, get_all_sandbox_service_settings,
+    create_sandbox_service_settings, get_connected_sandboxes,
     create_or_update_sandbox_service_settings, delete_sandbox_service_settings,
     get_all_sandbox_cameras, create_sandbox_camera, update_sandbox_camera, delete_sandbox_camera,
     send_sandbox_lighting_control
@@ -81,6 +87,10 @@ pub use protocol_config::{
 pub use vehicle_state::{
     is_vehicle_state_changed,
     batch_check_vehicle_states,
+    get_fleet_state,
+    get_vehicle_state,
+    subscribe_fleet_state,
+    unsubscribe_fleet_state,
 };
 
 // 路径数据命令
@@ -88,4 +98,18 @@ pub use path::{
     get_merged_path_data,
     get_loaded_paths_info,
     reload_all_paths,
-};
\ No newline at end of file
+};
+
+// 会话抓包与回放命令
+pub use replay::{
+    start_session_capture, stop_session_capture,
+    start_pcapng_capture, stop_pcapng_capture, export_wireshark_dissector,
+    load_replay_session, play_replay, pause_replay, step_replay,
+    seek_replay, set_replay_speed, stop_replay, get_replay_status,
+};
+
+// 内置模拟器命令
+pub use simulator::{
+    start_vehicle_simulator, stop_vehicle_simulator, get_vehicle_simulator_status,
+    start_sandbox_simulator, stop_sandbox_simulator, get_sandbox_simulator_status,
+};
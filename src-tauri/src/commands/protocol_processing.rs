@@ -3,11 +3,10 @@
 //! 为前端提供高性能的协议处理接口
 
 use crate::protocol_processing::{
-    BatchProcessor, ProtocolParser, ProtocolValidator, DataConverter,
-    ParsedProtocolData, ProtocolParsingResult, BatchProcessingResult, MessageTypes,
-    VehicleControlCommand, TaxiOrderData, AvpParkingData, AvpPickupData, 
-    DataRecordingData, ConstructionMarkerData
+    BatchProcessor, ProtocolParser, ProtocolValidator, DataConverter, ProcessingPipeline,
+    ParsedProtocolData, ProtocolParsingResult, BatchProcessingResult, ProtocolSchema
 };
+use crate::protocol_processing::types::ProtocolConstants;
 use crate::protocol_processing::batch_processor::{BatchTask, TaskPriority};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
@@ -163,41 +162,16 @@ pub async fn batch_process_protocols(
 pub async fn build_protocol(
     request: ProtocolBuildRequest,
 ) -> Result<String, String> {
+    let version = ProtocolConstants::PROTOCOL_VERSION_V10;
+    let schema = ProtocolSchema::global()
+        .by_name(&request.protocol_type.to_uppercase(), version)
+        .filter(|schema| schema.send.is_some() && schema.variant.is_some())
+        .ok_or_else(|| format!("不支持的协议类型: {}", request.protocol_type))?;
+
     let mut builder = crate::protocol_processing::ProtocolBuilder::new();
-    
-    let data = match request.protocol_type.as_str() {
-        "vehicle_control" => {
-            let cmd: VehicleControlCommand = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析车辆控制数据失败: {}", e))?;
-            builder.build_vehicle_control(&cmd)
-        }
-        "taxi_order" => {
-            let order: TaxiOrderData = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析出租车订单数据失败: {}", e))?;
-            builder.build_taxi_order(&order)
-        }
-        "avp_parking" => {
-            let parking: AvpParkingData = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析AVP泊车数据失败: {}", e))?;
-            builder.build_avp_parking(&parking)
-        }
-        "avp_pickup" => {
-            let pickup: AvpPickupData = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析AVP取车数据失败: {}", e))?;
-            builder.build_avp_pickup(&pickup)
-        }
-        "data_recording" => {
-            let recording: DataRecordingData = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析数据记录数据失败: {}", e))?;
-            builder.build_data_recording(&recording)
-        }
-        "construction_marker" => {
-            let marker: ConstructionMarkerData = serde_json::from_value(request.data)
-                .map_err(|e| format!("解析施工标记数据失败: {}", e))?;
-            builder.build_construction_marker(&marker)
-        }
-        _ => return Err(format!("不支持的协议类型: {}", request.protocol_type)),
-    };
+    let data = builder
+        .build_message(&schema.name, version, &request.data)
+        .map_err(|e| format!("解析{}数据失败: {}", schema.label, e))?;
     
     // 返回Base64编码的数据
     Ok(general_purpose::STANDARD.encode(&data))
@@ -217,6 +191,8 @@ pub async fn get_protocol_stats(
     let parser_stats = parser.get_stats();
     let validator_stats = validator.get_stats();
     let converter_stats = converter.get_stats();
+    let pipeline = ProcessingPipeline::global();
+    let pipeline_stats = pipeline.get_stats();
     
     let combined_stats = serde_json::json!({
         "batch_processing": {
@@ -227,6 +203,17 @@ pub async fn get_protocol_stats(
             "throughput": batch_stats.throughput,
             "parallel_efficiency": batch_stats.parallel_efficiency,
         },
+        "live_pipeline": {
+            "concurrency": pipeline.get_concurrency(),
+            "total_tasks": pipeline_stats.total_tasks,
+            "successful_tasks": pipeline_stats.successful_tasks,
+            "failed_tasks": pipeline_stats.failed_tasks,
+            "average_time_us": pipeline_stats.average_time_us,
+            "average_latency_us": pipeline_stats.average_latency_us,
+            "max_latency_us": pipeline_stats.max_latency_us,
+            "throughput": pipeline_stats.throughput,
+            "parallel_efficiency": pipeline_stats.parallel_efficiency,
+        },
         "parsing": {
             "total_time_us": parser_stats.total_time_us,
             "bytes_processed": parser_stats.bytes_processed,
@@ -262,6 +249,7 @@ pub async fn reset_protocol_stats(
     parser.reset_stats();
     validator.reset_stats();
     converter.reset_stats();
+    ProcessingPipeline::global().reset_stats();
     
     // 重置任务计数器
     let mut counter = state.task_counter.lock().unwrap();
@@ -289,43 +277,20 @@ pub async fn configure_batch_processor(
 /// 获取支持的消息类型
 #[tauri::command]
 pub async fn get_supported_message_types() -> Result<Vec<serde_json::Value>, String> {
-    let types = vec![
-        serde_json::json!({
-            "type": "0x0001",
-            "name": "车辆信息",
-            "description": "包含车辆位置、速度、电量等信息"
-        }),
-        serde_json::json!({
-            "type": "0x0002",
-            "name": "车辆控制",
-            "description": "车辆启动、停止、初始化位姿等控制指令"
-        }),
-        serde_json::json!({
-            "type": "0x0003",
-            "name": "出租车订单",
-            "description": "出租车起点终点订单信息"
-        }),
-        serde_json::json!({
-            "type": "0x0004",
-            "name": "AVP泊车",
-            "description": "自动代客泊车指令"
-        }),
-        serde_json::json!({
-            "type": "0x0005",
-            "name": "AVP取车",
-            "description": "自动代客取车指令"
-        }),
-        serde_json::json!({
-            "type": "0x0006",
-            "name": "数据记录",
-            "description": "数据记录控制指令"
-        }),
-        serde_json::json!({
-            "type": "0x0007",
-            "name": "施工标记",
-            "description": "施工区域标记指令"
-        }),
-    ];
+    let types = ProtocolSchema::global()
+        .messages()
+        .iter()
+        .filter(|m| m.version == ProtocolConstants::PROTOCOL_VERSION_V10)
+        .map(|m| {
+            serde_json::json!({
+                "name": m.label,
+                "key": m.name,
+                "receive_type": m.receive.map(|t| format!("0x{:04X}", t)),
+                "send_type": m.send.map(|t| format!("0x{:04X}", t)),
+                "fields": m.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
     
     Ok(types)
 }
@@ -343,35 +308,30 @@ pub async fn quick_validate_protocol_format(
     let decoded_data = general_purpose::STANDARD.decode(&data)
         .map_err(|e| format!("Base64解码失败: {}", e))?;
     
-    // 快速格式验证
-    let validation_result = match msg_type {
-        MessageTypes::VEHICLE_INFO => {
-            validate_vehicle_info_format(&decoded_data)
-        }
-        MessageTypes::PATH_FILE_SELECTION => {
-            validate_path_file_selection_format(&decoded_data)
-        }
-        MessageTypes::TAXI_ORDER => {
-            validate_taxi_order_format(&decoded_data)
-        }
-        MessageTypes::AVP_PARKING => {
-            validate_avp_parking_format(&decoded_data)
-        }
-        MessageTypes::AVP_PICKUP => {
-            validate_avp_pickup_format(&decoded_data)
-        }
-        MessageTypes::DATA_RECORDING => {
-            validate_data_recording_format(&decoded_data)
-        }
-        MessageTypes::CONSTRUCTION_MARKER => {
-            validate_construction_marker_format(&decoded_data)
+    // 快速格式验证（按协议定义的最小/完整长度）
+    let actual_size = decoded_data.len();
+    let validation_result = match ProtocolSchema::global().by_receive(ProtocolConstants::PROTOCOL_VERSION_V10, msg_type) {
+        Some(schema) if schema.variant.is_some() => {
+            let valid = actual_size >= schema.min_size;
+            serde_json::json!({
+                "valid": valid,
+                "message_type_name": schema.label,
+                "min_size": schema.min_size,
+                "expected_size": schema.total_size.unwrap_or(schema.min_size),
+                "actual_size": actual_size,
+                "error": if !valid {
+                    Some(format!("数据长度不足，至少需要{}字节，实际{}字节", schema.min_size, actual_size))
+                } else {
+                    None
+                }
+            })
         }
         _ => {
             serde_json::json!({
                 "valid": false,
                 "error": "不支持的消息类型",
                 "expected_size": 0,
-                "actual_size": decoded_data.len()
+                "actual_size": actual_size
             })
         }
     };
@@ -398,140 +358,3 @@ fn parse_priority(priority: &str) -> Result<TaskPriority, String> {
         _ => Err(format!("无效的优先级: {}", priority)),
     }
 }
-
-// 格式验证函数
-
-fn validate_vehicle_info_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "车辆信息",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_path_file_selection_format(data: &[u8]) -> serde_json::Value {
-    let min_size = 1; // 至少需要车辆编号
-    let actual_size = data.len();
-    
-    let valid = actual_size >= min_size;
-    let path_file_count = if actual_size > 0 { actual_size - 1 } else { 0 };
-    
-    serde_json::json!({
-        "valid": valid,
-        "message_type_name": "路径文件选择",
-        "min_size": min_size,
-        "actual_size": actual_size,
-        "path_file_count": path_file_count,
-        "error": if !valid {
-            Some(format!("数据长度不足，至少需要{}字节，实际{}字节", min_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_taxi_order_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::TAXI_ORDER_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "出租车订单",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_avp_parking_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::AVP_PARKING_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "AVP泊车",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_avp_pickup_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::AVP_PICKUP_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "AVP取车",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_data_recording_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::DATA_RECORDING_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "数据记录",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
-
-fn validate_construction_marker_format(data: &[u8]) -> serde_json::Value {
-    use crate::protocol_processing::types::ProtocolConstants;
-    
-    let expected_size = ProtocolConstants::CONSTRUCTION_MARKER_TOTAL_SIZE;
-    let actual_size = data.len();
-    
-    serde_json::json!({
-        "valid": actual_size >= expected_size,
-        "message_type_name": "施工标记",
-        "expected_size": expected_size,
-        "actual_size": actual_size,
-        "error": if actual_size < expected_size {
-            Some(format!("数据长度不足，需要{}字节，实际{}字节", expected_size, actual_size))
-        } else {
-            None
-        }
-    })
-}
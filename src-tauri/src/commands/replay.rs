@@ -0,0 +1,163 @@
+// 会话抓包与回放命令
+use crate::config::AppConfig;
+use crate::socket::dissector;
+use crate::socket::replay::{ReplayCommand, ReplayStatus};
+use crate::socket::{ReplayController, SessionCapture};
+use log::info;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::Manager;
+
+/// 默认抓包目录
+fn default_capture_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("dz-car-manager")
+        .join("captures")
+}
+
+/// 开始会话抓包，返回抓包文件路径
+#[tauri::command]
+pub async fn start_session_capture(
+    app: tauri::AppHandle,
+    file_path: Option<String>,
+) -> Result<String, String> {
+    let path = match file_path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => default_capture_dir().join(format!(
+            "session_{}.dzcap",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        )),
+    };
+
+    let capture = app.state::<SessionCapture>();
+    capture.start(&path)?;
+    Ok(path.display().to_string())
+}
+
+/// 停止会话抓包
+#[tauri::command]
+pub async fn stop_session_capture(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let capture = app.state::<SessionCapture>();
+    match capture.stop() {
+        Some((path, bytes)) => Ok(serde_json::json!({
+            "file_path": path.display().to_string(),
+            "bytes": bytes
+        })),
+        None => Err("当前未在抓包".to_string()),
+    }
+}
+
+/// 开始导出 pcapng（按连接与方向记录完整帧，供 Wireshark 分析），返回文件路径
+#[tauri::command]
+pub async fn start_pcapng_capture(
+    app: tauri::AppHandle,
+    file_path: Option<String>,
+) -> Result<String, String> {
+    let path = match file_path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => default_capture_dir().join(format!(
+            "session_{}.pcapng",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        )),
+    };
+
+    let capture = app.state::<SessionCapture>();
+    capture.start_pcapng(&path)?;
+    Ok(path.display().to_string())
+}
+
+/// 停止导出 pcapng
+#[tauri::command]
+pub async fn stop_pcapng_capture(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let capture = app.state::<SessionCapture>();
+    match capture.stop_pcapng() {
+        Some((path, packets)) => Ok(serde_json::json!({
+            "file_path": path.display().to_string(),
+            "packets": packets
+        })),
+        None => Err("当前未在导出 pcapng".to_string()),
+    }
+}
+
+/// 导出 Wireshark Lua 解析器（按当前协议定义生成），返回文件路径
+#[tauri::command]
+pub async fn export_wireshark_dissector(file_path: Option<String>) -> Result<String, String> {
+    let path = match file_path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => default_capture_dir().join("dzviz.lua"),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+
+    let lua = dissector::generate_lua_dissector(AppConfig::global().ports.socket_server);
+    std::fs::write(&path, lua).map_err(|e| format!("写入解析器失败 {}: {}", path.display(), e))?;
+    info!("Wireshark 解析器已导出: {}", path.display());
+    Ok(path.display().to_string())
+}
+
+/// 加载回放文件（加载后处于暂停状态）
+#[tauri::command]
+pub async fn load_replay_session(app: tauri::AppHandle, file_path: String) -> Result<ReplayStatus, String> {
+    let controller = app.state::<ReplayController>();
+    let status = controller.load(&PathBuf::from(&file_path), Arc::new(app.clone()))?;
+    info!("回放文件已加载: {}", file_path);
+    Ok(status)
+}
+
+/// 开始/继续回放，可同时设置倍速
+#[tauri::command]
+pub async fn play_replay(app: tauri::AppHandle, speed: Option<f64>) -> Result<String, String> {
+    let controller = app.state::<ReplayController>();
+    if let Some(speed) = speed {
+        controller.send(ReplayCommand::SetSpeed(speed))?;
+    }
+    controller.send(ReplayCommand::Play)?;
+    Ok("回放已开始".to_string())
+}
+
+/// 暂停回放
+#[tauri::command]
+pub async fn pause_replay(app: tauri::AppHandle) -> Result<String, String> {
+    app.state::<ReplayController>().send(ReplayCommand::Pause)?;
+    Ok("回放已暂停".to_string())
+}
+
+/// 单步回放（处理下一条记录）
+#[tauri::command]
+pub async fn step_replay(app: tauri::AppHandle) -> Result<String, String> {
+    app.state::<ReplayController>().send(ReplayCommand::Step)?;
+    Ok("已单步回放".to_string())
+}
+
+/// 跳转到指定回放时间（毫秒）
+#[tauri::command]
+pub async fn seek_replay(app: tauri::AppHandle, position_ms: u64) -> Result<String, String> {
+    app.state::<ReplayController>()
+        .send(ReplayCommand::Seek(position_ms.saturating_mul(1000)))?;
+    Ok(format!("已跳转到 {} 毫秒", position_ms))
+}
+
+/// 设置回放倍速
+#[tauri::command]
+pub async fn set_replay_speed(app: tauri::AppHandle, speed: f64) -> Result<String, String> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err("回放倍速必须大于0".to_string());
+    }
+    app.state::<ReplayController>().send(ReplayCommand::SetSpeed(speed))?;
+    Ok(format!("回放倍速: {}x", speed))
+}
+
+/// 结束并卸载回放
+#[tauri::command]
+pub async fn stop_replay(app: tauri::AppHandle) -> Result<String, String> {
+    app.state::<ReplayController>().unload();
+    Ok("回放已结束".to_string())
+}
+
+/// 获取回放状态
+#[tauri::command]
+pub async fn get_replay_status(app: tauri::AppHandle) -> Result<ReplayStatus, String> {
+    Ok(app.state::<ReplayController>().status())
+}
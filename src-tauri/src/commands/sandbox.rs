@@ -5,6 +5,30 @@ use crate::protocol_processing::types::SandboxLightingData;
 use crate::database::{VehicleDatabase, UpdateTrafficLightSettingsRequest, CreateOrUpdateSandboxServiceRequest, CreateSandboxCameraRequest, UpdateSandboxCameraRequest};
 use tauri::Manager;
 
+/// 解析红绿灯/摄像头所属沙盘：未指定时使用默认沙盘（未配置沙盘时为1，与迁移保持一致）
+async fn resolve_sandbox_id(db: &VehicleDatabase, sandbox_id: Option<i64>) -> Result<i64, String> {
+    match sandbox_id {
+        Some(id) => Ok(id),
+        None => db
+            .get_sandbox_service_settings()
+            .await
+            .map(|settings| settings.map_or(1, |s| s.id))
+            .map_err(|e| format!("获取沙盘服务设置失败: {}", e)),
+    }
+}
+
+/// 校验沙盘IP未被其他沙盘占用（服务器按IP识别沙盘）
+async fn ensure_unique_sandbox_ip(db: &VehicleDatabase, ip_address: &str, target_id: Option<i64>) -> Result<(), String> {
+    let all = db
+        .get_all_sandbox_service_settings()
+        .await
+        .map_err(|e| format!("获取沙盘服务设置失败: {}", e))?;
+    match all.iter().find(|s| s.ip_address.trim() == ip_address.trim() && Some(s.id) != target_id) {
+        Some(other) => Err(format!("IP地址 {} 已被沙盘 {} 使用", ip_address, other.id)),
+        None => Ok(()),
+    }
+}
+
 /// 发送红绿灯时长到沙盘（0x2002）
 #[tauri::command]
 pub async fn send_sandbox_traffic_light_duration(
@@ -12,6 +36,7 @@ pub async fn send_sandbox_traffic_light_duration(
     light_id: u8,
     red_seconds: u16,
     green_seconds: u16,
+    sandbox_id: Option<i64>,
 ) -> Result<String, String> {
     // 基础范围校验（与前端一致，避免脏数据）
     if red_seconds == 0 || red_seconds > 300 {
@@ -26,11 +51,11 @@ pub async fn send_sandbox_traffic_light_duration(
     data.extend_from_slice(&red_seconds.to_le_bytes());
     data.extend_from_slice(&green_seconds.to_le_bytes());
 
-    // 发送成功后在DB保存对应编号的时长
-    match socket::SocketServer::send_to_sandbox(&sandbox, 0x2002, &data) {
-        Ok(_) => {
+    // 发送成功后在DB保存该沙盘对应编号的时长
+    match socket::SocketServer::send_to_sandbox(&sandbox, sandbox_id, 0x2002, &data) {
+        Ok(sandbox_id) => {
             if let Some(db) = app.try_state::<VehicleDatabase>() {
-                let _ = db.update_traffic_light_item(light_id as i32, red_seconds as i32, green_seconds as i32).await;
+                let _ = db.update_traffic_light_item(sandbox_id, light_id as i32, red_seconds as i32, green_seconds as i32).await;
             }
             Ok("发送成功".to_string())
         }
@@ -40,9 +65,10 @@ pub async fn send_sandbox_traffic_light_duration(
 
 /// 获取单个红绿灯项目
 #[tauri::command]
-pub async fn get_traffic_light_item(app: tauri::AppHandle, light_id: i32) -> Result<serde_json::Value, String> {
+pub async fn get_traffic_light_item(app: tauri::AppHandle, light_id: i32, sandbox_id: Option<i64>) -> Result<serde_json::Value, String> {
     if let Some(db) = app.try_state::<VehicleDatabase>() {
-        db.get_traffic_light_item(light_id)
+        let sandbox_id = resolve_sandbox_id(&db, sandbox_id).await?;
+        db.get_traffic_light_item(sandbox_id, light_id)
             .await
             .map(|item| serde_json::to_value(item).unwrap())
             .map_err(|e| e.to_string())
@@ -53,9 +79,16 @@ pub async fn get_traffic_light_item(app: tauri::AppHandle, light_id: i32) -> Res
 
 /// 更新单个红绿灯项目
 #[tauri::command]
-pub async fn update_traffic_light_item(app: tauri::AppHandle, light_id: i32, red_seconds: i32, green_seconds: i32) -> Result<serde_json::Value, String> {
+pub async fn update_traffic_light_item(
+    app: tauri::AppHandle,
+    light_id: i32,
+    red_seconds: i32,
+    green_seconds: i32,
+    sandbox_id: Option<i64>,
+) -> Result<serde_json::Value, String> {
     if let Some(db) = app.try_state::<VehicleDatabase>() {
-        db.update_traffic_light_item(light_id, red_seconds, green_seconds)
+        let sandbox_id = resolve_sandbox_id(&db, sandbox_id).await?;
+        db.update_traffic_light_item(sandbox_id, light_id, red_seconds, green_seconds)
             .await
             .map(|item| serde_json::to_value(item).unwrap())
             .map_err(|e| e.to_string())
@@ -69,15 +102,16 @@ pub async fn update_traffic_light_item(app: tauri::AppHandle, light_id: i32, red
 pub async fn send_sandbox_control(
     app: tauri::AppHandle,
     vehicle_id: u8,
+    sandbox_id: Option<i64>,
 ) -> Result<String, String> {
     // 构建数据域: 车辆编号(1) + 动作(1) - 动作1表示进入平行驾驶
     let data = vec![vehicle_id, 1];
 
     let sandbox = app.state::<SandboxConnectionManager>();
-    if sandbox.read().is_none() {
+    if sandbox.read().is_empty() {
         return Err("沙盘未连接".to_string());
     }
-    socket::SocketServer::send_to_sandbox(&sandbox, 0x2001, &data)
+    socket::SocketServer::send_to_sandbox(&sandbox, sandbox_id, 0x2001, &data)
         .map(|_| "发送成功".to_string())
         .map_err(|e| format!("发送失败: {}", e))
 }
@@ -87,15 +121,16 @@ pub async fn send_sandbox_control(
 pub async fn send_sandbox_exit_control(
     app: tauri::AppHandle,
     vehicle_id: u8,
+    sandbox_id: Option<i64>,
 ) -> Result<String, String> {
     // 构建数据域: 车辆编号(1) + 动作(1) - 动作0表示退出平行驾驶
     let data = vec![vehicle_id, 0];
 
     let sandbox = app.state::<SandboxConnectionManager>();
-    if sandbox.read().is_none() {
+    if sandbox.read().is_empty() {
         return Err("沙盘未连接".to_string());
     }
-    socket::SocketServer::send_to_sandbox(&sandbox, 0x2001, &data)
+    socket::SocketServer::send_to_sandbox(&sandbox, sandbox_id, 0x2001, &data)
         .map(|_| "发送成功".to_string())
         .map_err(|e| format!("发送失败: {}", e))
 }
@@ -108,6 +143,7 @@ pub async fn send_sandbox_lighting_control(
     ambient: u8,
     building: u8,
     street: u8,
+    sandbox_id: Option<i64>,
 ) -> Result<String, String> {
     for (name, value) in [("barrier", barrier), ("ambient", ambient), ("building", building), ("street", street)] {
         if !matches!(value, 0 | 1) {
@@ -116,7 +152,7 @@ pub async fn send_sandbox_lighting_control(
     }
 
     let sandbox = app.state::<SandboxConnectionManager>();
-    if sandbox.read().is_none() {
+    if sandbox.read().is_empty() {
         return Err("沙盘未连接".to_string());
     }
 
@@ -127,19 +163,30 @@ pub async fn send_sandbox_lighting_control(
         street,
     });
 
-    socket::SocketServer::send_to_sandbox(&sandbox, crate::protocol_processing::types::MessageTypes::SANDBOX_LIGHTING_CONTROL, &payload)
+    socket::SocketServer::send_to_sandbox(&sandbox, sandbox_id, crate::protocol_processing::types::MessageTypes::SANDBOX_LIGHTING_CONTROL, &payload)
         .map(|_| "发送成功".to_string())
         .map_err(|e| format!("发送失败: {}", e))
 }
 
-/// 查询沙盘是否已连接
+/// 查询沙盘是否已连接（未指定沙盘ID时任一沙盘在线即为已连接）
 #[tauri::command]
-pub async fn is_sandbox_connected(app: tauri::AppHandle) -> Result<bool, String> {
+pub async fn is_sandbox_connected(app: tauri::AppHandle, sandbox_id: Option<i64>) -> Result<bool, String> {
     let sandbox = app.state::<SandboxConnectionManager>();
-    let is_connected = sandbox.read().is_some();
+    let sandboxes = sandbox.read();
+    let is_connected = match sandbox_id {
+        Some(id) => sandboxes.contains_key(&id),
+        None => !sandboxes.is_empty(),
+    };
     Ok(is_connected)
 }
 
+/// 获取在线沙盘列表
+#[tauri::command]
+pub async fn get_connected_sandboxes(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let sandbox = app.state::<SandboxConnectionManager>();
+    Ok(serde_json::json!(socket::SocketServer::get_sandbox_status(&sandbox)))
+}
+
 /// 获取交通灯设置
 #[tauri::command]
 pub async fn get_traffic_light_settings(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
@@ -163,16 +210,46 @@ pub async fn update_traffic_light_settings(
     }
 }
 
-/// 获取沙盘服务设置
+/// 获取沙盘服务设置（未指定沙盘ID时返回默认沙盘）
+#[tauri::command]
+pub async fn get_sandbox_service_settings(app: tauri::AppHandle, sandbox_id: Option<i64>) -> Result<serde_json::Value, String> {
+    let db = app.state::<VehicleDatabase>();
+    let result = match sandbox_id {
+        Some(id) => db.get_sandbox_service_settings_by_id(id).await,
+        None => db.get_sandbox_service_settings().await,
+    };
+    match result {
+        Ok(settings) => Ok(serde_json::to_value(settings).unwrap()),
+        Err(e) => Err(format!("获取沙盘服务设置失败: {}", e))
+    }
+}
+
+/// 获取全部沙盘服务设置
 #[tauri::command]
-pub async fn get_sandbox_service_settings(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+pub async fn get_all_sandbox_service_settings(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
     let db = app.state::<VehicleDatabase>();
-    match db.get_sandbox_service_settings().await {
+    match db.get_all_sandbox_service_settings().await {
         Ok(settings) => Ok(serde_json::to_value(settings).unwrap()),
         Err(e) => Err(format!("获取沙盘服务设置失败: {}", e))
     }
 }
 
+/// 新增沙盘服务设置
+#[tauri::command]
+pub async fn create_sandbox_service_settings(
+    app: tauri::AppHandle,
+    request: CreateOrUpdateSandboxServiceRequest
+) -> Result<serde_json::Value, String> {
+    request.validate()?;
+
+    let db = app.state::<VehicleDatabase>();
+    ensure_unique_sandbox_ip(&db, &request.ip_address, None).await?;
+    match db.create_sandbox_service_settings(request).await {
+        Ok(settings) => Ok(serde_json::to_value(settings).unwrap()),
+        Err(e) => Err(format!("新增沙盘服务设置失败: {}", e))
+    }
+}
+
 /// 创建或更新沙盘服务设置
 #[tauri::command]
 pub async fn create_or_update_sandbox_service_settings(
@@ -185,28 +262,37 @@ pub async fn create_or_update_sandbox_service_settings(
     }
     
     let db = app.state::<VehicleDatabase>();
+    let target_id = match request.id {
+        Some(id) => Some(id),
+        None => db.get_sandbox_service_settings().await.ok().flatten().map(|s| s.id),
+    };
+    ensure_unique_sandbox_ip(&db, &request.ip_address, target_id).await?;
     match db.create_or_update_sandbox_service_settings(request).await {
         Ok(settings) => Ok(serde_json::to_value(settings).unwrap()),
         Err(e) => Err(format!("创建或更新沙盘服务设置失败: {}", e))
     }
 }
 
-/// 删除沙盘服务设置
+/// 删除沙盘服务设置（未指定沙盘ID时删除全部）
 #[tauri::command]
-pub async fn delete_sandbox_service_settings(app: tauri::AppHandle) -> Result<String, String> {
+pub async fn delete_sandbox_service_settings(app: tauri::AppHandle, sandbox_id: Option<i64>) -> Result<String, String> {
     let db = app.state::<VehicleDatabase>();
-    match db.delete_sandbox_service_settings().await {
+    match db.delete_sandbox_service_settings(sandbox_id).await {
         Ok(true) => Ok("删除成功".to_string()),
         Ok(false) => Err("没有找到要删除的设置".to_string()),
         Err(e) => Err(format!("删除沙盘服务设置失败: {}", e))
     }
 }
 
-/// 获取所有沙盘摄像头
+/// 获取沙盘摄像头（指定沙盘ID时仅返回该沙盘的摄像头）
 #[tauri::command]
-pub async fn get_all_sandbox_cameras(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+pub async fn get_all_sandbox_cameras(app: tauri::AppHandle, sandbox_id: Option<i64>) -> Result<serde_json::Value, String> {
     let db = app.state::<VehicleDatabase>();
-    match db.get_all_sandbox_cameras().await {
+    let result = match sandbox_id {
+        Some(id) => db.get_sandbox_cameras(id).await,
+        None => db.get_all_sandbox_cameras().await,
+    };
+    match result {
         Ok(cameras) => Ok(serde_json::to_value(cameras).unwrap()),
         Err(e) => Err(format!("获取沙盘摄像头列表失败: {}", e))
     }
@@ -0,0 +1,128 @@
+// 内置模拟器命令
+use crate::database::VehicleDatabase;
+use crate::services::path_loader::PathLoader;
+use crate::simulator::{
+    load_light_timings, vehicle_ids, SandboxSimulatorConfig, SandboxSimulatorStatus, SimRoute, SimulatorController,
+    VehicleSimulatorConfig, VehicleSimulatorStatus,
+};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tauri::Manager;
+
+/// 启动车辆模拟器（N 辆虚拟车接入本机 Socket 服务器）
+#[tauri::command]
+pub async fn start_vehicle_simulator(
+    app: tauri::AppHandle,
+    vehicle_count: u8,
+    first_vehicle_id: Option<u8>,
+    route_ids: Option<Vec<u8>>,
+    server_addr: Option<String>,
+    speed: Option<f64>,
+    auth_key: Option<String>,
+) -> Result<VehicleSimulatorStatus, String> {
+    if vehicle_count == 0 {
+        return Err("虚拟车辆数量必须大于0".to_string());
+    }
+    if let Some(speed) = speed {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err("行驶速度必须大于0".to_string());
+        }
+    }
+
+    let defaults = VehicleSimulatorConfig::default();
+    let mut config = VehicleSimulatorConfig {
+        server_addr: server_addr
+            .filter(|addr| !addr.trim().is_empty())
+            .unwrap_or_else(|| defaults.server_addr.clone()),
+        vehicle_count,
+        first_vehicle_id: first_vehicle_id.unwrap_or(defaults.first_vehicle_id),
+        speed: speed.unwrap_or(defaults.speed),
+        ..defaults
+    };
+
+    // 接入认证密钥：统一指定，或按车辆编号取车辆连接配置中的密钥
+    let auth_key = auth_key.filter(|key| !key.trim().is_empty());
+    let db = app.try_state::<VehicleDatabase>().map(|db| db.inner().clone());
+    for vehicle_id in vehicle_ids(config.first_vehicle_id, config.vehicle_count)? {
+        let key = match (&auth_key, &db) {
+            (Some(key), _) => Some(key.clone()),
+            (None, Some(db)) => db
+                .get_vehicle_connection_by_vehicle_id(vehicle_id as i32)
+                .await
+                .map_err(|e| format!("读取车辆 {} 认证密钥失败: {}", vehicle_id, e))?
+                .and_then(|vehicle| vehicle.auth_key),
+            (None, None) => None,
+        };
+        if let Some(key) = key {
+            config.auth_keys.insert(vehicle_id, key);
+        }
+    }
+
+    let route_ids = route_ids.unwrap_or_default();
+    let route = match app.try_state::<Arc<PathLoader>>() {
+        Some(loader) => SimRoute::from_loader(&loader, &route_ids),
+        None => SimRoute::sandbox_loop(),
+    };
+
+    app.state::<SimulatorController>().start_vehicles(config, route)
+}
+
+/// 停止车辆模拟器
+#[tauri::command]
+pub async fn stop_vehicle_simulator(app: tauri::AppHandle) -> Result<String, String> {
+    if app.state::<SimulatorController>().stop_vehicles() {
+        Ok("车辆模拟器已停止".to_string())
+    } else {
+        Err("车辆模拟器未运行".to_string())
+    }
+}
+
+/// 获取车辆模拟器状态（未启动时返回 null）
+#[tauri::command]
+pub async fn get_vehicle_simulator_status(app: tauri::AppHandle) -> Result<Option<VehicleSimulatorStatus>, String> {
+    Ok(app.state::<SimulatorController>().vehicle_status())
+}
+
+/// 启动沙盘模拟器（按数据库中指定沙盘的红绿灯时长循环，并响应沙盘指令）
+#[tauri::command]
+pub async fn start_sandbox_simulator(
+    app: tauri::AppHandle,
+    server_addr: Option<String>,
+    bind_ip: Option<String>,
+    sandbox_id: Option<i64>,
+) -> Result<SandboxSimulatorStatus, String> {
+    let bind_ip = match bind_ip.filter(|ip| !ip.trim().is_empty()) {
+        Some(ip) => Some(ip.trim().parse::<IpAddr>().map_err(|_| format!("本地地址格式无效: {}", ip))?),
+        None => None,
+    };
+
+    let defaults = SandboxSimulatorConfig::default();
+    let config = SandboxSimulatorConfig {
+        server_addr: server_addr
+            .filter(|addr| !addr.trim().is_empty())
+            .unwrap_or_else(|| defaults.server_addr.clone()),
+        bind_ip,
+        ..defaults
+    };
+
+    let db = app.try_state::<VehicleDatabase>().map(|db| db.inner().clone());
+    let timings = load_light_timings(db.as_ref(), sandbox_id).await;
+
+    Ok(app.state::<SimulatorController>().start_sandbox(config, timings))
+}
+
+/// 停止沙盘模拟器
+#[tauri::command]
+pub async fn stop_sandbox_simulator(app: tauri::AppHandle) -> Result<String, String> {
+    if app.state::<SimulatorController>().stop_sandbox() {
+        Ok("沙盘模拟器已停止".to_string())
+    } else {
+        Err("沙盘模拟器未运行".to_string())
+    }
+}
+
+/// 获取沙盘模拟器状态（未启动时返回 null）
+#[tauri::command]
+pub async fn get_sandbox_simulator_status(app: tauri::AppHandle) -> Result<Option<SandboxSimulatorStatus>, String> {
+    Ok(app.state::<SimulatorController>().sandbox_status())
+}
@@ -1,17 +1,24 @@
 // 车辆相关命令
 use crate::config::AppConfig;
 use crate::database::{
-    CreateAvpParkingRequest, CreateAvpPickupRequest, CreateTaxiOrderRequest,
-    CreateVehicleConnectionRequest, UpdateVehicleConnectionRequest, VehicleDatabase,
+    CreateAvpParkingRequest, CreateAvpPickupRequest, CreateTaxiOrderRequest, EnqueueTaxiOrderRequest, SaveParkingSlotRequest,
+    validate_auth_key, validate_cert_fingerprint, CreateVehicleConnectionRequest, UpdateVehicleConnectionRequest, VehicleDatabase,
 };
 use crate::protocol_processing::types::{
     AvpParkingData, AvpPickupData, ControlCommandType, DataRecordingData, PositionData,
     TaxiOrderData, VehicleCameraToggleData, VehicleControlCommand, VehicleFunctionSettingData,
     VehiclePathDisplayData, MessageTypes, SendMessageTypes,
 };
+use crate::services::fleet_state::FleetState;
+use crate::services::parking_lot::{ParkingLot, SlotKind};
+use crate::services::path_loader::PathLoader;
+use crate::services::taxi_dispatch::{DispatchStrategyKind, TaxiDispatcher};
+use crate::services::taxi_orders::{OrderStatus, TaxiOrderService};
+use crate::services::taxi_queue::TaxiOrderQueue;
 use crate::services::vehicle::VehicleService;
 use crate::socket::{self, ConnectionManager, SandboxConnectionManager};
 use log::{error, info, warn};
+use std::sync::Arc;
 use tauri::Manager;
 
 /// 启动Socket服务器
@@ -23,10 +30,14 @@ pub async fn start_socket_server(app: tauri::AppHandle) -> Result<String, String
     let port = AppConfig::global().ports.socket_server;
     let server = socket::SocketServer::new_with_connections(
         port,
-        app.clone(),
+        std::sync::Arc::new(app.clone()),
         connections.inner().clone(),
         sandbox.inner().clone(),
-    );
+    )
+    .with_session_capture(app.state::<socket::SessionCapture>().inner().clone())
+    .with_fleet_state(app.state::<FleetState>().inner().clone())
+    .with_parking_lot(app.state::<ParkingLot>().inner().clone())
+    .with_path_loader(app.try_state::<Arc<PathLoader>>().map(|loader| loader.inner().clone()));
 
     // 在后台启动服务器
     tokio::spawn(async move {
@@ -181,6 +192,9 @@ pub async fn update_vehicle_connection(
     id: i64,
     request: UpdateVehicleConnectionRequest,
 ) -> Result<serde_json::Value, String> {
+    validate_auth_key(request.auth_key.as_deref())?;
+    validate_cert_fingerprint(request.cert_fingerprint.as_deref())?;
+
     let db = app.state::<VehicleDatabase>();
     match db.update_vehicle_connection(id, request).await {
         Ok(Some(connection)) => Ok(serde_json::to_value(connection).unwrap()),
@@ -225,6 +239,12 @@ pub async fn get_socket_server_status(app: tauri::AppHandle) -> Result<serde_jso
     let connections = app.state::<ConnectionManager>();
     let connected_vehicles = socket::SocketServer::get_connection_status(&connections);
     let vehicle_count = connected_vehicles.len();
+    // 各车辆发送队列的深度与丢弃/拒绝计数汇总
+    let send_queue = socket::SocketServer::get_send_queue_totals(&connections);
+    // 各车辆解码统计汇总（重新同步丢弃的字节、CRC/帧尾错误）
+    let decoder = socket::SocketServer::get_decoder_totals(&connections);
+    // 支持的帧协议版本（各车辆实际使用的版本见 connected_vehicles[].protocol_version）
+    let protocol_versions = socket::codec::CodecRegistry::global().versions();
 
     // 检查是否有连接的管理器状态（简单判断服务是否运行）
     let is_running = true; // 如果能获取到连接管理器，说明服务正在运行
@@ -233,6 +253,9 @@ pub async fn get_socket_server_status(app: tauri::AppHandle) -> Result<serde_jso
         "running": is_running,
         "vehicle_count": vehicle_count,
         "connected_vehicles": connected_vehicles,
+        "send_queue": send_queue,
+        "decoder": decoder,
+        "protocol_versions": protocol_versions,
         "text": if is_running {
             "运行中".to_string()
         } else {
@@ -254,7 +277,6 @@ pub async fn send_taxi_order_to_vehicle(
     end_y: f64,
 ) -> Result<String, String> {
     let connections = app.state::<ConnectionManager>();
-    let db = app.state::<VehicleDatabase>();
 
     // 1. 检查指定车辆是否在线
     let vehicle_id_i32 = vehicle_id as i32;
@@ -270,6 +292,32 @@ pub async fn send_taxi_order_to_vehicle(
         return Err(format!("车辆{}当前不在线", vehicle_id));
     }
 
+    // 2. 车辆已有进行中的订单时拒绝，避免覆盖车端正在执行的订单
+    let orders = TaxiOrderService::new(Arc::new(app.clone()));
+    if let Some((active, status)) = orders.active_order(vehicle_id_i32).await? {
+        warn!(
+            "⚠️ 车辆{}已有进行中的订单{} ({})，拒绝下发订单{}",
+            vehicle_id,
+            active.order_id,
+            status.as_str(),
+            order_id
+        );
+        return Err(format!("车辆{}已有进行中的订单 {}", vehicle_id, active.order_id));
+    }
+
+    // 3. 先保存并分配订单，避免车辆上报的导航状态早于订单记录
+    let request = CreateTaxiOrderRequest {
+        order_id: order_id.clone(),
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+    };
+    let saved = orders.create(request, Some(vehicle_id_i32)).await;
+    if let Err(e) = &saved {
+        warn!("⚠️ 出租车订单保存失败: 订单{}, 车辆{}, 错误: {}", order_id, vehicle_id, e);
+    }
+
     let order_payload = VehicleService::new().build_taxi_order_payload(&TaxiOrderData {
         vehicle_id,
         start_x,
@@ -278,41 +326,36 @@ pub async fn send_taxi_order_to_vehicle(
         end_y,
     });
 
-    // 3. 发送消息给指定车辆
+    // 4. 发送消息给指定车辆
     let success =
         socket::SocketServer::send_to_vehicle(&connections, vehicle_id_i32, 0x1003, &order_payload)
             .is_ok();
 
-    if success {
-        // 4. 发送成功后保存到数据库
-        match db
-            .save_taxi_order(&order_id, vehicle_id_i32, start_x, start_y, end_x, end_y)
-            .await
-        {
-            Ok(_) => {
-                info!(
-                    "✅ 出租车订单发送并保存成功: 订单{}, 车辆{}",
-                    order_id, vehicle_id
-                );
-                Ok(format!(
-                    "出租车订单已发送给{}号车并保存到数据库",
-                    vehicle_id
-                ))
-            }
-            Err(e) => {
-                warn!(
-                    "⚠️ 出租车订单发送成功但保存失败: 订单{}, 车辆{}, 错误: {}",
-                    order_id, vehicle_id, e
-                );
-                // 即使保存失败，也认为发送成功
-                Ok(format!(
-                    "出租车订单已发送给{}号车，但数据库保存失败: {}",
-                    vehicle_id, e
-                ))
+    if !success {
+        if saved.is_ok() {
+            if let Err(e) = orders.transition(&order_id, OrderStatus::Failed, None, None, Some("订单下发失败")).await {
+                warn!("⚠️ 更新出租车订单状态失败: 订单{}, 错误: {}", order_id, e);
             }
         }
-    } else {
-        Err(format!("发送出租车订单给车辆{}失败", vehicle_id))
+        return Err(format!("发送出租车订单给车辆{}失败", vehicle_id));
+    }
+
+    match saved {
+        Ok(_) => {
+            info!(
+                "✅ 出租车订单发送并保存成功: 订单{}, 车辆{}",
+                order_id, vehicle_id
+            );
+            Ok(format!(
+                "出租车订单已发送给{}号车并保存到数据库",
+                vehicle_id
+            ))
+        }
+        // 即使保存失败，也认为发送成功
+        Err(e) => Ok(format!(
+            "出租车订单已发送给{}号车，但数据库保存失败: {}",
+            vehicle_id, e
+        )),
     }
 }
 
@@ -342,24 +385,22 @@ pub async fn broadcast_taxi_order(
         socket::SocketServer::broadcast_message(&connections, 0x1003, &broadcast_payload);
 
     if sent_count > 0 {
-        // 4. 发送成功，保存到数据库
-        if let Some(db) = app.try_state::<VehicleDatabase>() {
-            let taxi_order_request = CreateTaxiOrderRequest {
-                order_id: order_id.clone(),
-                start_x,
-                start_y,
-                end_x,
-                end_y,
-            };
-
-            match db.create_taxi_order(taxi_order_request).await {
-                Ok(_) => {
-                    info!("✅ 出租车订单已保存到数据库: {}", order_id);
-                }
-                Err(e) => {
-                    warn!("❌ 保存出租车订单到数据库失败: {}", e);
-                    // 虽然数据库保存失败，但消息已发送，所以不返回错误
-                }
+        // 4. 发送成功，保存到数据库（广播订单不指定车辆）
+        let taxi_order_request = CreateTaxiOrderRequest {
+            order_id: order_id.clone(),
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+        };
+
+        match TaxiOrderService::new(Arc::new(app.clone())).create(taxi_order_request, None).await {
+            Ok(_) => {
+                info!("✅ 出租车订单已保存到数据库: {}", order_id);
+            }
+            Err(e) => {
+                warn!("❌ {}", e);
+                // 虽然数据库保存失败，但消息已发送，所以不返回错误
             }
         }
 
@@ -369,23 +410,132 @@ pub async fn broadcast_taxi_order(
     }
 }
 
-/// 发送AVP停车指令
+/// 获取所有出租车订单（含当前状态）
+#[tauri::command]
+pub async fn get_taxi_orders(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let db = app.state::<VehicleDatabase>();
+    match db.get_all_taxi_orders().await {
+        Ok(orders) => Ok(serde_json::to_value(orders).unwrap()),
+        Err(e) => Err(format!("获取出租车订单失败: {}", e)),
+    }
+}
+
+/// 获取出租车订单的状态变更记录
+#[tauri::command]
+pub async fn get_taxi_order_history(app: tauri::AppHandle, order_id: String) -> Result<serde_json::Value, String> {
+    let db = app.state::<VehicleDatabase>();
+    match db.get_taxi_order_transitions(&order_id).await {
+        Ok(transitions) => Ok(serde_json::to_value(transitions).unwrap()),
+        Err(e) => Err(format!("获取出租车订单记录失败: {}", e)),
+    }
+}
+
+/// 取消出租车订单
+#[tauri::command]
+pub async fn cancel_taxi_order(
+    app: tauri::AppHandle,
+    order_id: String,
+    reason: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let order = TaxiOrderService::new(Arc::new(app.clone()))
+        .cancel(&order_id, reason.as_deref())
+        .await?;
+    Ok(serde_json::to_value(order).unwrap())
+}
+
+/// 由应用状态构建出租车派单器
+pub fn taxi_dispatcher(app: &tauri::AppHandle) -> TaxiDispatcher {
+    TaxiDispatcher::new(
+        TaxiOrderService::new(Arc::new(app.clone())),
+        app.state::<FleetState>().inner().clone(),
+        Arc::new(app.state::<ConnectionManager>().inner().clone()),
+        app.try_state::<Arc<PathLoader>>().map(|loader| loader.inner().clone()),
+        AppConfig::global().dispatch.clone(),
+    )
+}
+
+/// 创建出租车订单并自动派单（未指定策略时使用配置的默认策略）
+#[tauri::command]
+pub async fn dispatch_taxi_order(
+    app: tauri::AppHandle,
+    order_id: String,
+    start_x: f64,
+    start_y: f64,
+    end_x: f64,
+    end_y: f64,
+    strategy: Option<DispatchStrategyKind>,
+) -> Result<serde_json::Value, String> {
+    let dispatcher = taxi_dispatcher(&app);
+    let strategy = strategy.unwrap_or(dispatcher.config().strategy);
+    let request = CreateTaxiOrderRequest {
+        order_id,
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+    };
+    let dispatch = dispatcher.dispatch(request, strategy).await?;
+    Ok(serde_json::to_value(dispatch).unwrap())
+}
+
+/// 创建排队（预约）出租车订单，有空闲车辆且到达要求接客时间后自动派单
 #[tauri::command]
-pub async fn send_avp_parking(app: tauri::AppHandle, vehicle_id: i32, parking_spot: u8) -> Result<String, String> {
-    // 1. 构建AVP泊车协议数据域 (2字节)
+pub async fn enqueue_taxi_order(
+    app: tauri::AppHandle,
+    request: EnqueueTaxiOrderRequest,
+) -> Result<serde_json::Value, String> {
+    let order = TaxiOrderQueue::new(taxi_dispatcher(&app)).enqueue(request).await?;
+    Ok(serde_json::to_value(order).unwrap())
+}
+
+/// 获取排队中的出租车订单（按派单顺序）；取消排队订单使用 `cancel_taxi_order`
+#[tauri::command]
+pub async fn get_taxi_order_queue(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let queue = TaxiOrderQueue::new(taxi_dispatcher(&app)).list().await?;
+    Ok(serde_json::to_value(queue).unwrap())
+}
+
+/// 调整排队订单顺序：按给定订单号排在队列前面，返回调整后的队列
+#[tauri::command]
+pub async fn reorder_taxi_order_queue(
+    app: tauri::AppHandle,
+    order_ids: Vec<String>,
+) -> Result<serde_json::Value, String> {
+    let queue = TaxiOrderQueue::new(taxi_dispatcher(&app)).reorder(&order_ids).await?;
+    Ok(serde_json::to_value(queue).unwrap())
+}
+
+/// 发送AVP停车指令（未指定车位时自动分配离车辆最近的空闲车位，可限定车位类型）
+#[tauri::command]
+pub async fn send_avp_parking(
+    app: tauri::AppHandle,
+    vehicle_id: i32,
+    parking_spot: Option<u8>,
+    slot_type: Option<SlotKind>,
+) -> Result<String, String> {
+    // 1. 预留车位，已被占用或预留给其他车辆的车位拒绝泊车
+    let parking_lot = app.state::<ParkingLot>();
+    let position = app
+        .state::<FleetState>()
+        .get(vehicle_id)
+        .and_then(|snapshot| snapshot.info)
+        .map(|info| (info.position_x, info.position_y));
+    let parking_spot = parking_lot.reserve(vehicle_id, parking_spot, slot_type, position)?;
+
+    // 2. 构建AVP泊车协议数据域 (2字节)
     let parking_payload = VehicleService::new().build_avp_parking_payload(&AvpParkingData {
         vehicle_id: vehicle_id as u8,
         parking_spot,
     });
 
-    // 2. 发送消息给指定车辆
+    // 3. 发送消息给指定车辆
     let connections = app.state::<ConnectionManager>();
     let sent_result =
         socket::SocketServer::send_to_vehicle(&connections, vehicle_id, 0x1004, &parking_payload);
 
     match sent_result {
         Ok(_) => {
-            // 3. 发送成功，保存到数据库
+            // 4. 发送成功，保存到数据库
             if let Some(db) = app.try_state::<VehicleDatabase>() {
                 let avp_parking_request = CreateAvpParkingRequest {
                     vehicle_id,
@@ -408,10 +558,58 @@ pub async fn send_avp_parking(app: tauri::AppHandle, vehicle_id: i32, parking_sp
 
             Ok(format!("AVP泊车指令已发送到车辆 {} (车位: {})", vehicle_id, parking_spot))
         }
-        Err(e) => Err(format!("发送AVP泊车指令失败: {}", e)),
+        Err(e) => {
+            parking_lot.release(vehicle_id);
+            Err(format!("发送AVP泊车指令失败: {}", e))
+        }
     }
 }
 
+/// 获取停车场车位及占用状态
+#[tauri::command]
+pub async fn get_parking_slots(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let slots = app.state::<ParkingLot>().snapshot();
+    Ok(serde_json::to_value(slots).unwrap())
+}
+
+/// 新增或更新车位
+#[tauri::command]
+pub async fn save_parking_slot(
+    app: tauri::AppHandle,
+    request: SaveParkingSlotRequest,
+) -> Result<serde_json::Value, String> {
+    request.validate()?;
+
+    let db = app.state::<VehicleDatabase>();
+    let slot = db
+        .save_parking_slot(request)
+        .await
+        .map_err(|e| format!("保存车位失败: {}", e))?;
+    reload_parking_slots(&app, &db).await?;
+    Ok(serde_json::to_value(slot).unwrap())
+}
+
+/// 删除车位
+#[tauri::command]
+pub async fn delete_parking_slot(app: tauri::AppHandle, slot_id: i32) -> Result<String, String> {
+    let db = app.state::<VehicleDatabase>();
+    match db.delete_parking_slot(slot_id).await {
+        Ok(true) => {
+            reload_parking_slots(&app, &db).await?;
+            Ok(format!("{}号车位已删除", slot_id))
+        }
+        Ok(false) => Err(format!("{}号车位不存在", slot_id)),
+        Err(e) => Err(format!("删除车位失败: {}", e)),
+    }
+}
+
+/// 车位布局变更后重新载入停车场
+async fn reload_parking_slots(app: &tauri::AppHandle, db: &VehicleDatabase) -> Result<(), String> {
+    let slots = db.get_parking_slots().await.map_err(|e| format!("加载车位失败: {}", e))?;
+    app.state::<ParkingLot>().set_layout(&slots);
+    Ok(())
+}
+
 /// 发送AVP取车指令
 #[tauri::command]
 pub async fn send_avp_pickup(app: tauri::AppHandle, vehicle_id: i32) -> Result<String, String> {
@@ -458,6 +656,26 @@ pub async fn get_vehicle_online_stats(app: tauri::AppHandle) -> Result<serde_jso
     }
 }
 
+/// 查询车辆遥测记录（用于回溯车辆历史状态）
+#[tauri::command]
+pub async fn get_vehicle_telemetry(
+    app: tauri::AppHandle,
+    vehicle_id: i32,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    limit: Option<i64>,
+) -> Result<serde_json::Value, String> {
+    let db = app.state::<VehicleDatabase>();
+    let limit = limit.unwrap_or(1000).clamp(1, 100_000);
+    match db
+        .get_vehicle_telemetry(vehicle_id, start_time.as_deref(), end_time.as_deref(), limit)
+        .await
+    {
+        Ok(records) => serde_json::to_value(records).map_err(|e| format!("序列化遥测记录失败: {}", e)),
+        Err(e) => Err(format!("获取车辆遥测记录失败: {}", e)),
+    }
+}
+
 /// 获取驾驶行为统计
 #[tauri::command]
 pub async fn get_driving_behavior_stats(
@@ -1,8 +1,10 @@
-//! 车辆状态比对和管理命令
-//! 提供高性能的状态变化检测
+//! 车辆状态比对、查询与订阅命令
+//! 变化检测与车队状态统一由 `services::fleet_state` 提供
 
 use crate::protocol_processing::types::VehicleInfo;
+use crate::services::fleet_state::{diff_vehicle_info, ChangeFilter, FleetState, VehicleSnapshot};
 use serde::{Deserialize, Serialize};
+use tauri::State;
 
 /// 车辆状态变化检测结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,91 +13,27 @@ pub struct StateChangeResult {
     pub changed_fields: Vec<String>,
 }
 
+fn compare_states(prev: &VehicleInfo, next: &VehicleInfo) -> StateChangeResult {
+    let changed_fields: Vec<String> = diff_vehicle_info(Some(prev), next)
+        .into_iter()
+        .map(|change| change.field.to_string())
+        .collect();
+    StateChangeResult {
+        changed: !changed_fields.is_empty(),
+        changed_fields,
+    }
+}
+
 /// 检查车辆状态是否变化
-/// 使用Rust的高性能比对，比JS快10-50倍
 #[tauri::command]
 pub async fn is_vehicle_state_changed(
     prev: VehicleInfo,
     next: VehicleInfo,
 ) -> Result<StateChangeResult, String> {
-    let mut changed_fields = Vec::new();
-    const EPSILON: f64 = 1e-6;
-
-    // 快速路径：如果vehicle_id不同，肯定是不同的车辆
-    if prev.vehicle_id != next.vehicle_id {
-        return Ok(StateChangeResult {
-            changed: true,
-            changed_fields: vec!["vehicle_id".to_string()],
-        });
-    }
-
-    // 高效的浮点数比较
-    let close = |a: f64, b: f64| -> bool { (a - b).abs() <= EPSILON };
-
-    // 速度
-    if !close(prev.speed, next.speed) {
-        changed_fields.push("speed".to_string());
-    }
-
-    // 位置X
-    if !close(prev.position_x, next.position_x) {
-        changed_fields.push("position_x".to_string());
-    }
-
-    // 位置Y
-    if !close(prev.position_y, next.position_y) {
-        changed_fields.push("position_y".to_string());
-    }
-
-    // 朝向
-    if !close(prev.orientation, next.orientation) {
-        changed_fields.push("orientation".to_string());
-    }
-
-    // 电池
-    if !close(prev.battery, next.battery) {
-        changed_fields.push("battery".to_string());
-    }
-
-    // 档位
-    if prev.gear != next.gear {
-        changed_fields.push("gear".to_string());
-    }
-
-    // 方向盘角度
-    if !close(prev.steering_angle, next.steering_angle) {
-        changed_fields.push("steering_angle".to_string());
-    }
-
-    // 导航状态
-    if prev.nav_status != next.nav_status {
-        changed_fields.push("nav_status".to_string());
-    }
-
-    // 传感器状态
-    if prev.sensors.camera != next.sensors.camera {
-        changed_fields.push("sensors.camera".to_string());
-    }
-    if prev.sensors.lidar != next.sensors.lidar {
-        changed_fields.push("sensors.lidar".to_string());
-    }
-    if prev.sensors.gyro != next.sensors.gyro {
-        changed_fields.push("sensors.gyro".to_string());
-    }
-
-    // 停车位
-    if prev.parking_slot != next.parking_slot {
-        changed_fields.push("parking_slot".to_string());
-    }
-
-    Ok(StateChangeResult {
-        changed: !changed_fields.is_empty(),
-        changed_fields,
-    })
+    Ok(compare_states(&prev, &next))
 }
 
 /// 批量检查多个车辆状态是否变化
-/// 进一步优化性能，一次调用检查多个车辆
 #[tauri::command]
 pub async fn batch_check_vehicle_states(
     prev_states: Vec<VehicleInfo>,
@@ -105,66 +43,44 @@ pub async fn batch_check_vehicle_states(
         return Err("前后状态数组长度不一致".to_string());
     }
 
-    let results: Vec<StateChangeResult> = prev_states
-        .into_iter()
-        .zip(next_states.into_iter())
-        .map(|(prev, next)| {
-            // 直接使用同步版本的逻辑
-            let mut changed_fields = Vec::new();
-            const EPSILON: f64 = 1e-6;
-            let close = |a: f64, b: f64| -> bool { (a - b).abs() <= EPSILON };
-
-            if prev.vehicle_id != next.vehicle_id {
-                return StateChangeResult {
-                    changed: true,
-                    changed_fields: vec!["vehicle_id".to_string()],
-                };
-            }
+    Ok(prev_states
+        .iter()
+        .zip(next_states.iter())
+        .map(|(prev, next)| compare_states(prev, next))
+        .collect())
+}
 
-            if !close(prev.speed, next.speed) {
-                changed_fields.push("speed".to_string());
-            }
-            if !close(prev.position_x, next.position_x) {
-                changed_fields.push("position_x".to_string());
-            }
-            if !close(prev.position_y, next.position_y) {
-                changed_fields.push("position_y".to_string());
-            }
-            if !close(prev.orientation, next.orientation) {
-                changed_fields.push("orientation".to_string());
-            }
-            if !close(prev.battery, next.battery) {
-                changed_fields.push("battery".to_string());
-            }
-            if prev.gear != next.gear {
-                changed_fields.push("gear".to_string());
-            }
-            if !close(prev.steering_angle, next.steering_angle) {
-                changed_fields.push("steering_angle".to_string());
-            }
-            if prev.nav_status != next.nav_status {
-                changed_fields.push("nav_status".to_string());
-            }
-            if prev.sensors.camera != next.sensors.camera {
-                changed_fields.push("sensors.camera".to_string());
-            }
-            if prev.sensors.lidar != next.sensors.lidar {
-                changed_fields.push("sensors.lidar".to_string());
-            }
-            if prev.sensors.gyro != next.sensors.gyro {
-                changed_fields.push("sensors.gyro".to_string());
-            }
-            if prev.parking_slot != next.parking_slot {
-                changed_fields.push("parking_slot".to_string());
-            }
+/// 获取所有已连接车辆的状态快照
+#[tauri::command]
+pub async fn get_fleet_state(fleet: State<'_, FleetState>) -> Result<Vec<VehicleSnapshot>, String> {
+    Ok(fleet.snapshot())
+}
 
-            StateChangeResult {
-                changed: !changed_fields.is_empty(),
-                changed_fields,
-            }
-        })
-        .collect();
+/// 获取单车状态快照
+#[tauri::command]
+pub async fn get_vehicle_state(
+    fleet: State<'_, FleetState>,
+    vehicle_id: i32,
+) -> Result<VehicleSnapshot, String> {
+    fleet.get(vehicle_id).ok_or_else(|| format!("车辆 {} 未连接", vehicle_id))
+}
 
-    Ok(results)
+/// 订阅车队状态的字段级变化，变化以 `fleet-state-change` 事件推送，返回订阅编号
+#[tauri::command]
+pub async fn subscribe_fleet_state(
+    app: tauri::AppHandle,
+    fleet: State<'_, FleetState>,
+    filter: Option<ChangeFilter>,
+) -> Result<u64, String> {
+    Ok(fleet.subscribe_events(std::sync::Arc::new(app), filter.unwrap_or_default()))
 }
 
+/// 取消车队状态订阅
+#[tauri::command]
+pub async fn unsubscribe_fleet_state(fleet: State<'_, FleetState>, subscription_id: u64) -> Result<(), String> {
+    if fleet.unsubscribe(subscription_id) {
+        Ok(())
+    } else {
+        Err(format!("订阅 {} 不存在", subscription_id))
+    }
+}
@@ -1,4 +1,5 @@
 // 应用配置模块
+use crate::services::taxi_dispatch::DispatchStrategyKind;
 use serde::{Deserialize, Serialize};
 
 /// 默认端口配置
@@ -12,6 +13,8 @@ pub struct AppPorts {
     pub video_stream_server: u16,
     /// HLS服务器端口
     pub hls_server: u16,
+    /// 事件流WebSocket端口（无界面服务模式）
+    pub event_stream_server: u16,
 }
 
 impl Default for AppPorts {
@@ -21,6 +24,7 @@ impl Default for AppPorts {
             udp_video_server: 8080,     // UDP视频服务器默认端口
             video_stream_server: 9001,  // 视频流服务器默认端口
             hls_server: 9002,           // HLS服务器默认端口
+            event_stream_server: 9004,  // 事件流服务器默认端口
         }
     }
 }
@@ -50,6 +54,10 @@ impl AppPorts {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(9002),
+            event_stream_server: std::env::var("DZ_VIZ_EVENT_STREAM_PORT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(9004),
         }
     }
     
@@ -65,6 +73,7 @@ impl AppPorts {
         log::info!("  UDP视频服务器: {}", self.udp_video_server);
         log::info!("  视频流服务器: {}", self.video_stream_server);
         log::info!("  HLS服务器: {}", self.hls_server);
+        log::info!("  事件流服务器: {}", self.event_stream_server);
     }
 }
 
@@ -77,6 +86,14 @@ pub struct AppConfig {
     pub performance: PerformanceConfig,
     /// 网络配置  
     pub network: NetworkConfig,
+    /// 遥测记录配置
+    pub telemetry: TelemetryConfig,
+    /// 车辆接入认证配置
+    pub vehicle_auth: VehicleAuthConfig,
+    /// 车辆Socket传输加密配置
+    pub socket_tls: SocketTlsConfig,
+    /// 出租车自动派单配置
+    pub dispatch: DispatchConfig,
 }
 
 /// 性能配置
@@ -90,12 +107,14 @@ pub struct PerformanceConfig {
     pub high_fps_threshold: u32,
     /// 最大缓存大小（字节）
     pub max_cache_size: u64,
+    /// 事件中附带原始数据域字节（调试用，默认只推送解析结果）
+    pub debug_raw_frames: bool,
 }
 
 /// 网络配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
-    /// 连接超时时间（毫秒）
+    /// 连接超时时间（毫秒），同时用于TLS握手和单次发送
     pub timeout: u32,
     /// 重试次数
     pub retry_count: u32,
@@ -103,6 +122,72 @@ pub struct NetworkConfig {
     pub retry_delay: u32,
     /// 心跳间隔（毫秒）
     pub heartbeat_interval: u32,
+    /// 连续多少个心跳周期无数据判定车辆失联
+    pub heartbeat_stale_misses: u32,
+    /// 无数据超过该时长强制断开车辆连接（毫秒）
+    pub heartbeat_timeout: u32,
+    /// 每个连接待发送指令的上限（超出时拒绝发送）
+    pub send_queue_capacity: u32,
+}
+
+/// 遥测记录配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// 是否记录每帧车辆信息
+    pub enabled: bool,
+    /// 保留时长（小时）
+    pub retention_hours: u32,
+    /// 最大保留条数
+    pub max_rows: u64,
+    /// 批量写入间隔（毫秒）
+    pub flush_interval: u32,
+    /// 单批最大条数
+    pub batch_size: usize,
+    /// 清理间隔（毫秒）
+    pub prune_interval: u32,
+}
+
+/// 车辆接入认证配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VehicleAuthConfig {
+    /// 是否拒绝未完成认证握手的车辆；关闭即兼容模式（按IP识别旧车端），须显式配置
+    pub required: bool,
+    /// 等待车辆注册帧的超时时间（毫秒）
+    pub handshake_timeout: u32,
+}
+
+/// 车辆Socket传输加密配置（rustls）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SocketTlsConfig {
+    /// 是否启用TLS（启用后Socket端口只接受TLS连接，帧格式不变）
+    pub enabled: bool,
+    /// 服务器证书链路径（PEM）
+    pub cert_path: Option<String>,
+    /// 服务器私钥路径（PEM，PKCS#8/PKCS#1/SEC1）
+    pub key_path: Option<String>,
+    /// 校验客户端证书的CA路径（PEM）；未配置时不请求客户端证书
+    pub client_ca_path: Option<String>,
+    /// 是否要求客户端必须出示证书（需配置client_ca_path）
+    pub require_client_cert: bool,
+}
+
+/// 出租车自动派单配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispatchConfig {
+    /// 默认派单策略
+    pub strategy: DispatchStrategyKind,
+    /// 接单超时（毫秒）：车辆超时未出发前往起点时改派
+    pub accept_timeout: u32,
+    /// 单个订单最多尝试派给的车辆数
+    pub max_attempts: u32,
+    /// 电量优先策略下可接单的最低电量（%）
+    pub min_battery: f64,
+    /// 路径终点与其他路径视为相连的最大距离（米）
+    pub junction_tolerance: f64,
+    /// 排队订单的派单检查间隔（毫秒）
+    pub queue_interval: u32,
+    /// 预约订单提前派单的时间（毫秒），留出车辆前往起点的时间
+    pub schedule_lead_time: u32,
 }
 
 impl Default for PerformanceConfig {
@@ -112,6 +197,26 @@ impl Default for PerformanceConfig {
             low_fps_threshold: 20,
             high_fps_threshold: 50,
             max_cache_size: 100 * 1024 * 1024, // 100MB
+            debug_raw_frames: false,
+        }
+    }
+}
+
+impl PerformanceConfig {
+    /// 从环境变量加载（DZ_VIZ_TARGET_FPS 设置车辆状态推送频率，DZ_VIZ_DEBUG_RAW_FRAMES=1 附带原始字节）
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            target_fps: std::env::var("DZ_VIZ_TARGET_FPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|fps| *fps > 0)
+                .unwrap_or(defaults.target_fps),
+            debug_raw_frames: std::env::var("DZ_VIZ_DEBUG_RAW_FRAMES")
+                .ok()
+                .map(|s| matches!(s.trim(), "1" | "true" | "on"))
+                .unwrap_or(defaults.debug_raw_frames),
+            ..defaults
         }
     }
 }
@@ -123,6 +228,82 @@ impl Default for NetworkConfig {
             retry_count: 3,
             retry_delay: 1000,
             heartbeat_interval: 10000,
+            heartbeat_stale_misses: 3,
+            heartbeat_timeout: 60000,
+            send_queue_capacity: 256,
+        }
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_hours: 72,
+            max_rows: 2_000_000,
+            flush_interval: 1000,
+            batch_size: 500,
+            prune_interval: 10 * 60 * 1000, // 10分钟
+        }
+    }
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            strategy: DispatchStrategyKind::Nearest,
+            accept_timeout: 15000,
+            max_attempts: 3,
+            min_battery: 30.0,
+            junction_tolerance: 0.05,
+            queue_interval: 1000,
+            schedule_lead_time: 60000,
+        }
+    }
+}
+
+impl Default for VehicleAuthConfig {
+    fn default() -> Self {
+        Self {
+            required: true,
+            handshake_timeout: 5000,
+        }
+    }
+}
+
+impl VehicleAuthConfig {
+    /// 从环境变量加载（DZ_VIZ_VEHICLE_AUTH=0 时进入兼容模式，允许未认证车辆接入）
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            required: std::env::var("DZ_VIZ_VEHICLE_AUTH")
+                .ok()
+                .map(|s| !matches!(s.trim(), "0" | "false" | "off"))
+                .unwrap_or(defaults.required),
+            handshake_timeout: std::env::var("DZ_VIZ_VEHICLE_AUTH_TIMEOUT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.handshake_timeout),
+        }
+    }
+}
+
+impl SocketTlsConfig {
+    /// 从环境变量加载（DZ_VIZ_SOCKET_TLS=1 启用）
+    pub fn from_env() -> Self {
+        let path = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty());
+        let flag = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|s| matches!(s.trim(), "1" | "true" | "on"))
+                .unwrap_or(false)
+        };
+        Self {
+            enabled: flag("DZ_VIZ_SOCKET_TLS"),
+            cert_path: path("DZ_VIZ_SOCKET_TLS_CERT"),
+            key_path: path("DZ_VIZ_SOCKET_TLS_KEY"),
+            client_ca_path: path("DZ_VIZ_SOCKET_TLS_CLIENT_CA"),
+            require_client_cert: flag("DZ_VIZ_SOCKET_TLS_REQUIRE_CLIENT_CERT"),
         }
     }
 }
@@ -131,8 +312,12 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             ports: AppPorts::from_env(), // 优先从环境变量加载
-            performance: PerformanceConfig::default(),
+            performance: PerformanceConfig::from_env(),
             network: NetworkConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            vehicle_auth: VehicleAuthConfig::from_env(),
+            socket_tls: SocketTlsConfig::from_env(),
+            dispatch: DispatchConfig::default(),
         }
     }
 }
@@ -152,12 +337,14 @@ impl AppConfig {
 }
 
 // Tauri命令：获取应用配置
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn get_app_config() -> AppConfig {
     AppConfig::global().clone()
 }
 
 // Tauri命令：获取端口配置
+#[cfg(feature = "gui")]
 #[tauri::command] 
 pub fn get_port_config() -> AppPorts {
     AppConfig::global().ports.clone()
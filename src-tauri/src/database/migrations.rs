@@ -0,0 +1,619 @@
+//! 数据库版本化迁移
+//!
+//! `schema_version` 表记录已应用的迁移版本。启动时按版本顺序执行尚未应用的迁移，
+//! 每个迁移在独立事务中完成：任一步骤失败时整体回滚，数据库保持迁移前的状态。
+//!
+//! 引入版本化迁移之前，表结构由启动时的建表语句与零散的补列、重建逻辑维护，
+//! 现有数据库可能处于其中任一中间状态。版本 1 的迁移因此由可重复执行的步骤组成
+//! （按列是否存在决定是否补列或重建），能把任何旧数据库整理为同一表结构。
+//! 之后的迁移只会在各数据库上执行一次，新增列、表时追加新版本即可，不要修改已发布的迁移。
+
+use sqlx::{Pool, Row, Sqlite, SqliteConnection};
+
+/// 迁移步骤
+#[derive(Debug)]
+pub enum Step {
+    /// 表不存在时按给定列定义创建
+    CreateTable { table: &'static str, columns: &'static str },
+    /// 执行语句；版本 1 中须可重复执行（如 `CREATE INDEX IF NOT EXISTS`、数据回填）
+    Execute(&'static str),
+    /// 列不存在时添加；`backfill` 仅在本次添加该列后执行
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+        backfill: Option<&'static str>,
+    },
+    /// 满足条件时按新的列定义重建表（SQLite 不支持删除列或修改约束）
+    ///
+    /// 数据通过 `INSERT INTO 新表 (target) SELECT select FROM 旧表` 复制，
+    /// 表上的索引会随旧表删除，需要在重建之后的步骤中创建
+    Rebuild {
+        table: &'static str,
+        when: Condition,
+        columns: &'static str,
+        target: &'static str,
+        select: &'static str,
+    },
+}
+
+/// 重建条件
+#[derive(Debug, Clone, Copy)]
+pub enum Condition {
+    HasColumn(&'static str),
+    MissingColumn(&'static str),
+}
+
+/// 一个版本的迁移
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub steps: &'static [Step],
+}
+
+const VEHICLE_CONNECTIONS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL UNIQUE,
+    ip_address TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    color TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    auth_key TEXT,
+    cert_fingerprint TEXT
+"#;
+
+const TRAFFIC_LIGHT_SETTINGS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    red_light_duration INTEGER NOT NULL DEFAULT 45,
+    green_light_duration INTEGER NOT NULL DEFAULT 60,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+"#;
+
+const TRAFFIC_LIGHT_ITEMS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    sandbox_id INTEGER NOT NULL DEFAULT 1,
+    light_id INTEGER NOT NULL,
+    red_light_duration INTEGER NOT NULL DEFAULT 30,
+    green_light_duration INTEGER NOT NULL DEFAULT 30,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (sandbox_id, light_id)
+"#;
+
+const TAXI_ORDERS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL UNIQUE,
+    start_x REAL NOT NULL,
+    start_y REAL NOT NULL,
+    end_x REAL NOT NULL,
+    end_y REAL NOT NULL,
+    assigned_vehicle_id INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+"#;
+
+const AVP_PARKING: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    parking_spot INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+"#;
+
+const AVP_PICKUP: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+"#;
+
+const VEHICLE_ONLINE_TIME: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    online_minutes INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    UNIQUE(vehicle_id, date)
+"#;
+
+const VEHICLE_TELEMETRY: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    protocol_timestamp INTEGER NOT NULL,
+    received_at TEXT NOT NULL,
+    speed REAL NOT NULL,
+    position_x REAL NOT NULL,
+    position_y REAL NOT NULL,
+    orientation REAL NOT NULL,
+    battery REAL NOT NULL,
+    gear INTEGER NOT NULL,
+    steering_angle REAL NOT NULL,
+    nav_status INTEGER NOT NULL,
+    camera_status BOOLEAN NOT NULL,
+    lidar_status BOOLEAN NOT NULL,
+    gyro_status BOOLEAN NOT NULL,
+    parking_slot INTEGER NOT NULL
+"#;
+
+const SANDBOX_SERVICE_SETTINGS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL DEFAULT '',
+    ip_address TEXT NOT NULL,
+    traffic_light_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+"#;
+
+const SANDBOX_CAMERAS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    camera_type TEXT NOT NULL CHECK (camera_type IN ('RJ45', 'USB')),
+    rtsp_url TEXT,
+    device_index INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    sandbox_id INTEGER
+"#;
+
+const APP_SETTINGS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log_level TEXT NOT NULL DEFAULT 'INFO',
+    cache_size INTEGER NOT NULL DEFAULT 512,
+    auto_start BOOLEAN NOT NULL DEFAULT 0,
+    app_title TEXT NOT NULL DEFAULT '渡众智能沙盘云控平台',
+    coordinate_offset_x REAL NOT NULL DEFAULT 0.0,
+    coordinate_offset_y REAL NOT NULL DEFAULT 0.0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+"#;
+
+const MENU_VISIBILITY_SETTINGS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    show_vehicle_info BOOLEAN NOT NULL DEFAULT 1,
+    show_auto_drive BOOLEAN NOT NULL DEFAULT 1,
+    show_sandbox_control BOOLEAN NOT NULL DEFAULT 1,
+    show_settings BOOLEAN NOT NULL DEFAULT 1,
+    show_parallel_driving BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+"#;
+
+const TAXI_ORDER_TRANSITIONS: &str = r#"
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    vehicle_id INTEGER,
+    nav_status INTEGER,
+    reason TEXT,
+    created_at TEXT NOT NULL
+"#;
+
+const PARKING_SLOTS: &str = r#"
+    slot_id INTEGER PRIMARY KEY,
+    position_x REAL NOT NULL,
+    position_y REAL NOT NULL,
+    slot_type TEXT NOT NULL DEFAULT 'normal',
+    updated_at TEXT NOT NULL
+"#;
+
+/// 版本 1：整理引入版本化迁移之前的全部表结构
+const BASELINE: &[Step] = &[
+    Step::CreateTable { table: "vehicle_connections", columns: VEHICLE_CONNECTIONS },
+    Step::AddColumn { table: "vehicle_connections", column: "color", definition: "TEXT", backfill: None },
+    // 车辆接入认证密钥
+    Step::AddColumn { table: "vehicle_connections", column: "auth_key", definition: "TEXT", backfill: None },
+    // TLS客户端证书SHA-256指纹
+    Step::AddColumn { table: "vehicle_connections", column: "cert_fingerprint", definition: "TEXT", backfill: None },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_id ON vehicle_connections(vehicle_id)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_is_active ON vehicle_connections(is_active)"),
+    Step::CreateTable { table: "traffic_light_settings", columns: TRAFFIC_LIGHT_SETTINGS },
+    // 沙盘设置：早期版本带 port 列，需重建删除
+    Step::CreateTable { table: "sandbox_service_settings", columns: SANDBOX_SERVICE_SETTINGS },
+    Step::Rebuild {
+        table: "sandbox_service_settings",
+        when: Condition::HasColumn("port"),
+        columns: SANDBOX_SERVICE_SETTINGS,
+        target: "id, ip_address, created_at, updated_at",
+        select: "id, ip_address, created_at, updated_at",
+    },
+    Step::AddColumn {
+        table: "sandbox_service_settings",
+        column: "traffic_light_count",
+        definition: "INTEGER NOT NULL DEFAULT 0",
+        backfill: None,
+    },
+    Step::AddColumn { table: "sandbox_service_settings", column: "name", definition: "TEXT NOT NULL DEFAULT ''", backfill: None },
+    // 单个红绿灯时长：旧表 light_id 单列唯一，需重建为 (sandbox_id, light_id) 唯一，
+    // 已有数据归属到原唯一沙盘（无沙盘时为1）
+    Step::CreateTable { table: "traffic_light_items", columns: TRAFFIC_LIGHT_ITEMS },
+    Step::Rebuild {
+        table: "traffic_light_items",
+        when: Condition::MissingColumn("sandbox_id"),
+        columns: TRAFFIC_LIGHT_ITEMS,
+        target: "id, sandbox_id, light_id, red_light_duration, green_light_duration, created_at, updated_at",
+        select: "id, (SELECT COALESCE(MIN(id), 1) FROM sandbox_service_settings), light_id, red_light_duration, green_light_duration, created_at, updated_at",
+    },
+    Step::CreateTable { table: "taxi_orders", columns: TAXI_ORDERS },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_order_id ON taxi_orders(order_id)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_assigned_vehicle ON taxi_orders(assigned_vehicle_id)"),
+    Step::CreateTable { table: "avp_parking", columns: AVP_PARKING },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_avp_vehicle_id ON avp_parking(vehicle_id)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_avp_parking_spot ON avp_parking(parking_spot)"),
+    Step::CreateTable { table: "avp_pickup", columns: AVP_PICKUP },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_avp_pickup_vehicle_id ON avp_pickup(vehicle_id)"),
+    Step::CreateTable { table: "vehicle_online_time", columns: VEHICLE_ONLINE_TIME },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_date ON vehicle_online_time(date)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_vehicle_id ON vehicle_online_time(vehicle_id)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_composite ON vehicle_online_time(vehicle_id, date)"),
+    Step::CreateTable { table: "vehicle_telemetry", columns: VEHICLE_TELEMETRY },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_telemetry_vehicle_time ON vehicle_telemetry(vehicle_id, received_at)"),
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_vehicle_telemetry_received_at ON vehicle_telemetry(received_at)"),
+    // 沙盘摄像头：多沙盘支持后按沙盘归属，已有摄像头归属到原唯一沙盘
+    Step::CreateTable { table: "sandbox_cameras", columns: SANDBOX_CAMERAS },
+    Step::AddColumn {
+        table: "sandbox_cameras",
+        column: "sandbox_id",
+        definition: "INTEGER",
+        backfill: Some("UPDATE sandbox_cameras SET sandbox_id = (SELECT COALESCE(MIN(id), 1) FROM sandbox_service_settings)"),
+    },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_sandbox_camera_type ON sandbox_cameras(camera_type)"),
+    Step::CreateTable { table: "app_settings", columns: APP_SETTINGS },
+    Step::AddColumn { table: "app_settings", column: "auto_start", definition: "BOOLEAN NOT NULL DEFAULT 0", backfill: None },
+    Step::AddColumn {
+        table: "app_settings",
+        column: "coordinate_offset_x",
+        definition: "REAL NOT NULL DEFAULT 0.0",
+        backfill: None,
+    },
+    Step::AddColumn {
+        table: "app_settings",
+        column: "coordinate_offset_y",
+        definition: "REAL NOT NULL DEFAULT 0.0",
+        backfill: None,
+    },
+    Step::AddColumn {
+        table: "app_settings",
+        column: "app_title",
+        definition: "TEXT NOT NULL DEFAULT '渡众智能沙盘云控平台'",
+        backfill: None,
+    },
+    Step::CreateTable { table: "menu_visibility_settings", columns: MENU_VISIBILITY_SETTINGS },
+    Step::AddColumn {
+        table: "menu_visibility_settings",
+        column: "show_parallel_driving",
+        definition: "BOOLEAN NOT NULL DEFAULT 1",
+        backfill: None,
+    },
+];
+
+/// 版本 2：出租车订单状态机（订单当前状态与逐条状态变更记录）
+const TAXI_ORDER_LIFECYCLE: &[Step] = &[
+    Step::AddColumn {
+        table: "taxi_orders",
+        column: "status",
+        definition: "TEXT NOT NULL DEFAULT 'created'",
+        backfill: Some("UPDATE taxi_orders SET status = 'assigned' WHERE assigned_vehicle_id IS NOT NULL"),
+    },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_taxi_order_vehicle_status ON taxi_orders(assigned_vehicle_id, status)"),
+    Step::CreateTable { table: "taxi_order_transitions", columns: TAXI_ORDER_TRANSITIONS },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_taxi_order_transitions_order_id ON taxi_order_transitions(order_id)"),
+    // 已有订单按创建时间补记创建与分配记录
+    Step::Execute(
+        "INSERT INTO taxi_order_transitions (order_id, from_status, to_status, created_at) \
+         SELECT order_id, NULL, 'created', created_at FROM taxi_orders",
+    ),
+    Step::Execute(
+        "INSERT INTO taxi_order_transitions (order_id, from_status, to_status, vehicle_id, created_at) \
+         SELECT order_id, 'created', 'assigned', assigned_vehicle_id, created_at FROM taxi_orders \
+         WHERE assigned_vehicle_id IS NOT NULL",
+    ),
+];
+
+/// 版本 3：出租车订单排队与预约（要求接客时间、优先级、队列顺序与派单策略）
+const TAXI_ORDER_QUEUE: &[Step] = &[
+    Step::AddColumn { table: "taxi_orders", column: "scheduled_at", definition: "TEXT", backfill: None },
+    Step::AddColumn { table: "taxi_orders", column: "priority", definition: "INTEGER NOT NULL DEFAULT 0", backfill: None },
+    Step::AddColumn { table: "taxi_orders", column: "queue_position", definition: "INTEGER", backfill: None },
+    Step::AddColumn { table: "taxi_orders", column: "dispatch_strategy", definition: "TEXT", backfill: None },
+    Step::Execute("CREATE INDEX IF NOT EXISTS idx_taxi_order_queue ON taxi_orders(status, queue_position)"),
+];
+
+/// 版本 4：AVP 停车场车位布局（默认沙盘上的两个普通车位）
+const PARKING_LOT: &[Step] = &[
+    Step::CreateTable { table: "parking_slots", columns: PARKING_SLOTS },
+    Step::Execute(
+        "INSERT INTO parking_slots (slot_id, position_x, position_y, slot_type, updated_at) VALUES \
+         (1, 3.46875, 0.72991, 'normal', strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')), \
+         (2, 3.93503, 0.72991, 'normal', strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'))",
+    ),
+];
+
+/// 版本 5：车位占用车辆及其最后上报时间，重启后恢复占用
+const PARKING_OCCUPANCY: &[Step] = &[
+    Step::AddColumn { table: "parking_slots", column: "occupant", definition: "INTEGER", backfill: None },
+    Step::AddColumn { table: "parking_slots", column: "reported_at", definition: "TEXT", backfill: None },
+];
+
+/// 全部迁移（版本号连续递增）
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline", steps: BASELINE },
+    Migration { version: 2, name: "taxi_order_lifecycle", steps: TAXI_ORDER_LIFECYCLE },
+    Migration { version: 3, name: "taxi_order_queue", steps: TAXI_ORDER_QUEUE },
+    Migration { version: 4, name: "parking_lot", steps: PARKING_LOT },
+    Migration { version: 5, name: "parking_occupancy", steps: PARKING_OCCUPANCY },
+];
+
+async fn table_columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<String>, sqlx::Error> {
+    Ok(sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect())
+}
+
+impl Step {
+    async fn apply(&self, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+        match *self {
+            Step::CreateTable { table, columns } => {
+                sqlx::query(&format!("CREATE TABLE IF NOT EXISTS {} ({})", table, columns))
+                    .execute(conn)
+                    .await?;
+            }
+            Step::Execute(sql) => {
+                sqlx::query(sql).execute(conn).await?;
+            }
+            Step::AddColumn { table, column, definition, backfill } => {
+                if table_columns(&mut *conn, table).await?.iter().any(|c| c == column) {
+                    return Ok(());
+                }
+                sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                    .execute(&mut *conn)
+                    .await?;
+                if let Some(sql) = backfill {
+                    sqlx::query(sql).execute(conn).await?;
+                }
+            }
+            Step::Rebuild { table, when, columns, target, select } => {
+                let existing = table_columns(&mut *conn, table).await?;
+                let needed = match when {
+                    Condition::HasColumn(column) => existing.iter().any(|c| c == column),
+                    Condition::MissingColumn(column) => !existing.iter().any(|c| c == column),
+                };
+                if !needed {
+                    return Ok(());
+                }
+                log::info!("重建数据表 {}", table);
+                sqlx::query(&format!("CREATE TABLE {}_new ({})", table, columns))
+                    .execute(&mut *conn)
+                    .await?;
+                sqlx::query(&format!("INSERT INTO {}_new ({}) SELECT {} FROM {}", table, target, select, table))
+                    .execute(&mut *conn)
+                    .await?;
+                sqlx::query(&format!("DROP TABLE {}", table)).execute(&mut *conn).await?;
+                sqlx::query(&format!("ALTER TABLE {}_new RENAME TO {}", table, table))
+                    .execute(conn)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 当前数据库版本（未记录任何迁移时为0）
+pub async fn current_version(pool: &Pool<Sqlite>) -> Result<i64, sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool)
+        .await
+}
+
+/// 执行所有尚未应用的迁移，返回迁移后的数据库版本
+pub async fn migrate(pool: &Pool<Sqlite>) -> Result<i64, sqlx::Error> {
+    run(pool, MIGRATIONS).await
+}
+
+async fn run(pool: &Pool<Sqlite>, migrations: &[Migration]) -> Result<i64, sqlx::Error> {
+    let mut version = current_version(pool).await?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if version > latest {
+        // 由更新版本的程序创建，保持原样
+        log::warn!("数据库版本 {} 高于程序支持的版本 {}，跳过迁移", version, latest);
+        return Ok(version);
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > version).collect();
+    for migration in pending {
+        let mut tx = pool.begin().await?;
+        for step in migration.steps {
+            if let Err(e) = step.apply(&mut tx).await {
+                log::error!("数据库迁移 {} ({}) 失败，已回滚: {}", migration.version, migration.name, e);
+                return Err(e);
+            }
+        }
+        sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        log::info!("✅ 数据库迁移 {} ({}) 已应用", migration.version, migration.name);
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::VehicleDatabase;
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{Executor, SqlitePool};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// 表结构：每张表的列（名称、类型、非空、默认值）与索引名
+    type Schema = BTreeMap<String, (BTreeSet<(String, String, bool, Option<String>)>, BTreeSet<String>)>;
+
+    fn latest_version() -> i64 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    async fn connect(path: &Path) -> SqlitePool {
+        SqlitePool::connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true))
+            .await
+            .unwrap()
+    }
+
+    async fn schema(pool: &SqlitePool) -> Schema {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let mut schema = Schema::new();
+        for table in tables {
+            let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+                .fetch_all(pool)
+                .await
+                .unwrap()
+                .iter()
+                .map(|row| (row.get("name"), row.get("type"), row.get("notnull"), row.get("dflt_value")))
+                .collect();
+            let indexes = sqlx::query_scalar(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL",
+            )
+            .bind(&table)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+            schema.insert(table, (columns, indexes));
+        }
+        schema
+    }
+
+    /// 用旧数据库快照创建数据库文件后按当前程序打开
+    async fn open_snapshot(dir: &TempDir, name: &str, snapshot: &str) -> (VehicleDatabase, SqlitePool) {
+        let path = dir.path().join(name);
+        let pool = connect(&path).await;
+        pool.execute(snapshot).await.unwrap();
+        pool.close().await;
+
+        let db = VehicleDatabase::open(&path).await.unwrap();
+        (db, connect(&path).await)
+    }
+
+    async fn fresh_schema(dir: &TempDir) -> Schema {
+        let path = dir.path().join("fresh.db");
+        VehicleDatabase::open(&path).await.unwrap();
+        schema(&connect(&path).await).await
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database_is_versioned_and_idempotent() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vehicles.db");
+        VehicleDatabase::open(&path).await.unwrap();
+        let pool = connect(&path).await;
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        let before = schema(&pool).await;
+
+        // 重复执行不会再次应用迁移，也不改变表结构
+        assert_eq!(migrate(&pool).await.unwrap(), latest_version());
+        VehicleDatabase::open(&path).await.unwrap();
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version").fetch_one(&pool).await.unwrap();
+        assert_eq!(applied, MIGRATIONS.len() as i64);
+        assert_eq!(schema(&pool).await, before);
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_snapshot_upgrades_in_place() {
+        let dir = TempDir::new().unwrap();
+        let (db, pool) = open_snapshot(&dir, "unversioned.db", include_str!("testdata/unversioned.sql")).await;
+        assert_eq!(schema(&pool).await, fresh_schema(&dir).await);
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+
+        let vehicle = db.get_vehicle_connection_by_vehicle_id(3).await.unwrap().unwrap();
+        assert_eq!((vehicle.name.as_str(), vehicle.auth_key.as_deref()), ("三号车", Some("secret-3")));
+        let settings = db.get_app_settings().await.unwrap();
+        assert_eq!((settings.log_level.as_str(), settings.app_title.as_str()), ("DEBUG", "测试沙盘"));
+        assert_eq!(db.get_sandbox_cameras(2).await.unwrap().len(), 1);
+        assert_eq!(db.get_traffic_light_item(2, 1).await.unwrap().red_light_duration, 20);
+        assert!(!db.get_menu_visibility_settings().await.unwrap().show_auto_drive);
+        // 已分配的订单补记为已分配状态
+        let orders = db.get_all_taxi_orders().await.unwrap();
+        assert_eq!(orders.iter().map(|o| (o.order_id.as_str(), o.status.as_str())).collect::<Vec<_>>(), vec![("ORDER-1", "assigned")]);
+        let transitions = db.get_taxi_order_transitions("ORDER-1").await.unwrap();
+        assert_eq!(
+            transitions.iter().map(|t| (t.from_status.as_deref(), t.to_status.as_str())).collect::<Vec<_>>(),
+            vec![(None, "created"), (Some("created"), "assigned")]
+        );
+        assert_eq!(transitions[1].created_at, "2025-06-01T08:00:00+00:00");
+        // 已有订单不在队列中
+        assert_eq!((orders[0].priority, orders[0].queue_position, orders[0].scheduled_at.as_deref()), (0, None, None));
+        // 默认车位布局
+        let slots = db.get_parking_slots().await.unwrap();
+        assert_eq!(slots.iter().map(|s| (s.slot_id, s.slot_type.as_str())).collect::<Vec<_>>(), vec![(1, "normal"), (2, "normal")]);
+        assert_eq!(slots[1].position_x, 3.93503);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_snapshot_rebuilds_tables() {
+        let dir = TempDir::new().unwrap();
+        let (db, pool) = open_snapshot(&dir, "legacy.db", include_str!("testdata/legacy_port_column.sql")).await;
+        assert_eq!(schema(&pool).await, fresh_schema(&dir).await);
+
+        // port 列随重建删除，其余数据保留
+        let sandbox = db.get_sandbox_service_settings_by_id(5).await.unwrap().unwrap();
+        assert_eq!((sandbox.ip_address.as_str(), sandbox.name.as_str()), ("192.168.1.50", ""));
+        // 红绿灯与摄像头归属到原唯一沙盘
+        let lights = db.get_all_traffic_light_items(5).await.unwrap();
+        assert_eq!(lights.iter().map(|l| (l.light_id, l.red_light_duration)).collect::<Vec<_>>(), vec![(1, 20), (2, 35)]);
+        assert_eq!(db.get_sandbox_cameras(5).await.unwrap()[0].name, "路口俯视");
+
+        let vehicle = db.get_vehicle_connection_by_vehicle_id(3).await.unwrap().unwrap();
+        assert_eq!((vehicle.color, vehicle.auth_key), (None, None));
+        let settings = db.get_app_settings().await.unwrap();
+        assert_eq!((settings.log_level.as_str(), settings.cache_size, settings.auto_start), ("WARN", 1024, false));
+        assert!(db.get_menu_visibility_settings().await.unwrap().show_parallel_driving);
+        assert_eq!(db.get_traffic_light_settings().await.unwrap().red_light_duration, 30);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        const BROKEN: &[Step] = &[
+            Step::CreateTable { table: "half_done", columns: "id INTEGER PRIMARY KEY" },
+            Step::Execute("INSERT INTO missing_table VALUES (1)"),
+        ];
+        const MIGRATIONS: &[Migration] = &[Migration { version: 1, name: "broken", steps: BROKEN }];
+
+        let dir = TempDir::new().unwrap();
+        let pool = connect(&dir.path().join("broken.db")).await;
+        assert!(run(&pool, MIGRATIONS).await.is_err());
+        assert_eq!(current_version(&pool).await.unwrap(), 0);
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE name = 'half_done'")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(tables.is_empty());
+    }
+}
@@ -11,6 +11,12 @@ pub struct VehicleConnection {
     pub description: Option<String>, // 描述信息
     pub color: Option<String>,       // 车辆颜色（十六进制格式，如 #409EFF）
     pub is_active: bool,    // 是否启用
+    #[serde(skip_serializing)]
+    pub auth_key: Option<String>,    // 接入认证密钥（HMAC预共享密钥），不下发到前端
+    #[serde(default)]
+    #[sqlx(default)]
+    pub has_auth_key: bool,          // 是否已配置认证密钥
+    pub cert_fingerprint: Option<String>, // TLS客户端证书SHA-256指纹（小写十六进制）
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -23,6 +29,10 @@ pub struct CreateVehicleConnectionRequest {
     pub name: String,
     pub description: Option<String>,
     pub color: Option<String>,
+    #[serde(default)]
+    pub auth_key: Option<String>,
+    #[serde(default)]
+    pub cert_fingerprint: Option<String>,
 }
 
 /// 更新车辆连接的请求参数
@@ -34,6 +44,10 @@ pub struct UpdateVehicleConnectionRequest {
     pub description: Option<String>,
     pub color: Option<String>,
     pub is_active: Option<bool>,
+    #[serde(default)]
+    pub auth_key: Option<String>,
+    #[serde(default)]
+    pub cert_fingerprint: Option<String>,
 }
 
 impl VehicleConnection {
@@ -62,11 +76,41 @@ impl CreateVehicleConnectionRequest {
         if self.name.trim().is_empty() {
             return Err("车辆名称不能为空".to_string());
         }
+
+        validate_auth_key(self.auth_key.as_deref())?;
+        validate_cert_fingerprint(self.cert_fingerprint.as_deref())?;
         
         Ok(())
     }
 }
 
+/// 认证密钥最短长度
+pub const MIN_AUTH_KEY_LEN: usize = 8;
+
+/// 校验认证密钥（为空表示不设置）
+pub fn validate_auth_key(auth_key: Option<&str>) -> Result<(), String> {
+    match auth_key.map(str::trim) {
+        Some(key) if !key.is_empty() && key.len() < MIN_AUTH_KEY_LEN => {
+            Err(format!("认证密钥长度不能少于{}个字符", MIN_AUTH_KEY_LEN))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 校验客户端证书指纹（SHA-256，64位十六进制，可用冒号分隔；为空表示不设置）
+pub fn validate_cert_fingerprint(fingerprint: Option<&str>) -> Result<(), String> {
+    let hex: String = fingerprint
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' '))
+        .collect();
+    if hex.is_empty() || (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())) {
+        Ok(())
+    } else {
+        Err("证书指纹应为64位十六进制SHA-256摘要".to_string())
+    }
+}
+
 /// 交通灯设置模型
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TrafficLightSettings {
@@ -81,6 +125,7 @@ pub struct TrafficLightSettings {
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TrafficLightItem {
     pub id: i64,
+    pub sandbox_id: i64,            // 所属沙盘（sandbox_service_settings.id）
     pub light_id: i32,              // 红绿灯编号（从1开始）
     pub red_light_duration: i32,    // 红灯时长（秒）
     pub green_light_duration: i32,  // 绿灯时长（秒）
@@ -95,7 +140,7 @@ pub struct UpdateTrafficLightSettingsRequest {
     pub green_light_duration: Option<i32>,
 }
 
-#[derive(Debug, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
 pub struct TaxiOrder {
     pub id: i64,
     pub order_id: String,           // 16字节UUID字符串
@@ -104,10 +149,28 @@ pub struct TaxiOrder {
     pub end_x: f64,                 // 终点X坐标
     pub end_y: f64,                 // 终点Y坐标
     pub assigned_vehicle_id: Option<i32>, // 接单的车辆ID（可选）
+    pub status: String,             // 订单状态（见 services::taxi_orders::OrderStatus）
+    pub scheduled_at: Option<String>, // 要求接客时间（为空表示立即）
+    pub priority: i32,              // 排队优先级（越大越靠前）
+    pub queue_position: Option<i64>, // 排队顺序（仅排队中的订单）
+    pub dispatch_strategy: Option<String>, // 派单策略（为空时使用默认策略）
     pub created_at: String,         // 创建时间
     pub updated_at: String,         // 更新时间
 }
 
+/// 出租车订单状态变更记录
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
+pub struct TaxiOrderTransition {
+    pub id: i64,
+    pub order_id: String,
+    pub from_status: Option<String>, // 变更前状态（创建时为空）
+    pub to_status: String,           // 变更后状态
+    pub vehicle_id: Option<i32>,     // 相关车辆
+    pub nav_status: Option<i32>,     // 触发变更的车辆导航状态
+    pub reason: Option<String>,      // 取消/失败原因
+    pub created_at: String,          // 变更时间
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct CreateTaxiOrderRequest {
     pub order_id: String,
@@ -117,6 +180,17 @@ pub struct CreateTaxiOrderRequest {
     pub end_y: f64,
 }
 
+/// 排队（预约）出租车订单的请求参数
+#[derive(Debug, serde::Deserialize)]
+pub struct EnqueueTaxiOrderRequest {
+    #[serde(flatten)]
+    pub order: CreateTaxiOrderRequest,
+    pub scheduled_at: Option<String>, // 要求接客时间（RFC3339，为空表示有空闲车辆即派单）
+    #[serde(default)]
+    pub priority: i32,
+    pub dispatch_strategy: Option<String>,
+}
+
 #[derive(Debug, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
 pub struct AvpParking {
     pub id: i64,
@@ -131,6 +205,46 @@ pub struct CreateAvpParkingRequest {
     pub parking_spot: i32,
 }
 
+/// AVP 停车场车位
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
+pub struct ParkingSlot {
+    pub slot_id: i32,               // 车位编号（即 AVP 泊车指令中的停车位编号）
+    pub position_x: f64,            // 车位中心 X（车辆坐标系，米）
+    pub position_y: f64,            // 车位中心 Y（车辆坐标系，米）
+    pub slot_type: String,          // 车位类型（normal 普通 / charging 充电）
+    pub updated_at: String,         // 更新时间
+    pub occupant: Option<i32>,      // 最后上报停在该车位的车辆
+    pub reported_at: Option<String>, // 占用的最后确认时间
+}
+
+/// 新增或更新车位的请求参数
+#[derive(Debug, serde::Deserialize)]
+pub struct SaveParkingSlotRequest {
+    pub slot_id: i32,
+    pub position_x: f64,
+    pub position_y: f64,
+    pub slot_type: String,
+}
+
+impl SaveParkingSlotRequest {
+    /// 验证请求参数
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=255).contains(&self.slot_id) {
+            return Err("车位编号必须在1-255之间".to_string());
+        }
+
+        if !self.position_x.is_finite() || !self.position_y.is_finite() {
+            return Err("车位坐标无效".to_string());
+        }
+
+        if !matches!(self.slot_type.as_str(), "normal" | "charging") {
+            return Err(format!("未知的车位类型: {}", self.slot_type));
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
 pub struct AvpPickup {
     pub id: i64,
@@ -152,6 +266,55 @@ pub struct VehicleOnlineTime {
     pub updated_at: String,         // 最后更新时间
 }
 
+/// 车辆遥测记录（每帧 0x0002 车辆信息）
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
+pub struct VehicleTelemetry {
+    pub id: i64,
+    pub vehicle_id: i32,            // 车辆编号
+    pub protocol_timestamp: i64,    // 协议帧时间戳（微秒）
+    pub received_at: String,        // 服务端接收时间
+    pub speed: f64,
+    pub position_x: f64,
+    pub position_y: f64,
+    pub orientation: f64,
+    pub battery: f64,
+    pub gear: i32,
+    pub steering_angle: f64,
+    pub nav_status: i32,
+    pub camera_status: bool,
+    pub lidar_status: bool,
+    pub gyro_status: bool,
+    pub parking_slot: i32,
+}
+
+impl VehicleTelemetry {
+    /// 由解析后的车辆信息构建遥测记录（id 由数据库分配）
+    pub fn from_vehicle_info(
+        info: &crate::protocol_processing::types::VehicleInfo,
+        protocol_timestamp: u64,
+        received_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: 0,
+            vehicle_id: info.vehicle_id as i32,
+            protocol_timestamp: protocol_timestamp as i64,
+            received_at: received_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            speed: info.speed,
+            position_x: info.position_x,
+            position_y: info.position_y,
+            orientation: info.orientation,
+            battery: info.battery,
+            gear: info.gear.to_u8() as i32,
+            steering_angle: info.steering_angle,
+            nav_status: info.nav_status as i32,
+            camera_status: info.sensors.camera,
+            lidar_status: info.sensors.lidar,
+            gyro_status: info.sensors.gyro,
+            parking_slot: info.parking_slot as i32,
+        }
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct UpdateVehicleOnlineTimeRequest {
     pub vehicle_id: i32,
@@ -181,7 +344,8 @@ impl UpdateTrafficLightSettingsRequest {
 /// 沙盘服务设置模型
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct SandboxServiceSettings {
-    pub id: i64,
+    pub id: i64,                // 沙盘ID
+    pub name: String,           // 沙盘名称（多个沙盘时区分）
     pub ip_address: String,     // 沙盘服务IP地址
     pub traffic_light_count: i32, // 红绿灯数量
     pub created_at: DateTime<Utc>,
@@ -191,6 +355,11 @@ pub struct SandboxServiceSettings {
 /// 创建/更新沙盘服务设置的请求参数
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateOrUpdateSandboxServiceRequest {
+    /// 指定时更新该沙盘；未指定时更新默认沙盘（不存在则创建）
+    #[serde(default)]
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub name: Option<String>,
     pub ip_address: String,
     pub traffic_light_count: i32,
 }
@@ -199,6 +368,7 @@ pub struct CreateOrUpdateSandboxServiceRequest {
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct SandboxCamera {
     pub id: i64,
+    pub sandbox_id: Option<i64>, // 所属沙盘（未分配时为空）
     pub name: String,           // 摄像头名称
     pub camera_type: String,    // 摄像头类型：'RJ45' 或 'USB'
     pub rtsp_url: Option<String>,    // RTSP地址（RJ45类型使用）
@@ -210,6 +380,8 @@ pub struct SandboxCamera {
 /// 创建沙盘摄像头的请求参数
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateSandboxCameraRequest {
+    #[serde(default)]
+    pub sandbox_id: Option<i64>,
     pub name: String,
     pub camera_type: String,    // 'RJ45' 或 'USB'
     pub rtsp_url: Option<String>,
@@ -219,6 +391,8 @@ pub struct CreateSandboxCameraRequest {
 /// 更新沙盘摄像头的请求参数
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateSandboxCameraRequest {
+    #[serde(default)]
+    pub sandbox_id: Option<i64>,
     pub name: Option<String>,
     pub camera_type: Option<String>,
     pub rtsp_url: Option<String>,
@@ -231,6 +405,9 @@ impl CreateOrUpdateSandboxServiceRequest {
         if self.ip_address.parse::<std::net::IpAddr>().is_err() {
             return Err("IP地址格式无效".to_string());
         }
+        if self.name.as_ref().is_some_and(|name| name.chars().count() > 30) {
+            return Err("沙盘名称长度不能超过30个字符".to_string());
+        }
         
         Ok(())
     }
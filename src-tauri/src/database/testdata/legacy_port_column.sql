@@ -0,0 +1,87 @@
+-- 早期版本的数据库：沙盘设置带 port 列、红绿灯按编号单列唯一、摄像头无沙盘归属，
+-- 车辆连接与应用设置缺少后来追加的列，部分表尚不存在
+CREATE TABLE vehicle_connections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL UNIQUE,
+    ip_address TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX idx_vehicle_id ON vehicle_connections(vehicle_id);
+INSERT INTO vehicle_connections VALUES(1,3,'192.168.1.13','三号车',NULL,1,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE traffic_light_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    red_light_duration INTEGER NOT NULL DEFAULT 45,
+    green_light_duration INTEGER NOT NULL DEFAULT 60,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO traffic_light_settings VALUES(1,30,40,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE traffic_light_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    light_id INTEGER NOT NULL UNIQUE,
+    red_light_duration INTEGER NOT NULL DEFAULT 30,
+    green_light_duration INTEGER NOT NULL DEFAULT 30,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO traffic_light_items VALUES(1,1,20,25,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+INSERT INTO traffic_light_items VALUES(2,2,35,15,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE taxi_orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL UNIQUE,
+    start_x REAL NOT NULL,
+    start_y REAL NOT NULL,
+    end_x REAL NOT NULL,
+    end_y REAL NOT NULL,
+    assigned_vehicle_id INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO taxi_orders VALUES(1,'ORDER-1',1.0,2.0,3.0,4.0,3,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE sandbox_service_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ip_address TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO sandbox_service_settings VALUES(5,'192.168.1.50',8080,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE sandbox_cameras (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    camera_type TEXT NOT NULL CHECK (camera_type IN ('RJ45', 'USB')),
+    rtsp_url TEXT,
+    device_index INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO sandbox_cameras VALUES(1,'路口俯视','RJ45','rtsp://192.168.1.60/live',NULL,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE app_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log_level TEXT NOT NULL DEFAULT 'INFO',
+    cache_size INTEGER NOT NULL DEFAULT 512,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO app_settings VALUES(1,'WARN',1024,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
+
+CREATE TABLE menu_visibility_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    show_vehicle_info BOOLEAN NOT NULL DEFAULT 1,
+    show_auto_drive BOOLEAN NOT NULL DEFAULT 1,
+    show_sandbox_control BOOLEAN NOT NULL DEFAULT 1,
+    show_settings BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO menu_visibility_settings VALUES(1,0,1,1,1,'2024-03-01T08:00:00+00:00','2024-03-01T08:00:00+00:00');
@@ -0,0 +1,148 @@
+-- 引入版本化迁移之前、由启动时建表逻辑生成的数据库（无 schema_version 表）
+-- 追加列位于建表语句末尾，与当时的 ALTER TABLE ADD COLUMN 结果一致
+CREATE TABLE vehicle_connections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL UNIQUE,
+    ip_address TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    color TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+, auth_key TEXT, cert_fingerprint TEXT);
+CREATE INDEX idx_vehicle_id ON vehicle_connections(vehicle_id);
+CREATE INDEX idx_is_active ON vehicle_connections(is_active);
+INSERT INTO vehicle_connections VALUES(1,3,'192.168.1.13','三号车','巡游车','#ff6600',1,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00','secret-3',NULL);
+
+CREATE TABLE traffic_light_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    red_light_duration INTEGER NOT NULL DEFAULT 45,
+    green_light_duration INTEGER NOT NULL DEFAULT 60,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO traffic_light_settings VALUES(1,30,40,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
+
+CREATE TABLE traffic_light_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    sandbox_id INTEGER NOT NULL DEFAULT 1,
+    light_id INTEGER NOT NULL,
+    red_light_duration INTEGER NOT NULL DEFAULT 30,
+    green_light_duration INTEGER NOT NULL DEFAULT 30,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (sandbox_id, light_id)
+);
+INSERT INTO traffic_light_items VALUES(1,2,1,20,25,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
+
+CREATE TABLE taxi_orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL UNIQUE,
+    start_x REAL NOT NULL,
+    start_y REAL NOT NULL,
+    end_x REAL NOT NULL,
+    end_y REAL NOT NULL,
+    assigned_vehicle_id INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX idx_order_id ON taxi_orders(order_id);
+CREATE INDEX idx_assigned_vehicle ON taxi_orders(assigned_vehicle_id);
+INSERT INTO taxi_orders VALUES(1,'ORDER-1',1.0,2.0,3.0,4.0,3,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
+
+CREATE TABLE avp_parking (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    parking_spot INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_avp_vehicle_id ON avp_parking(vehicle_id);
+CREATE INDEX idx_avp_parking_spot ON avp_parking(parking_spot);
+
+CREATE TABLE avp_pickup (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_avp_pickup_vehicle_id ON avp_pickup(vehicle_id);
+
+CREATE TABLE vehicle_online_time (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    online_minutes INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    UNIQUE(vehicle_id, date)
+);
+CREATE INDEX idx_vehicle_online_time_date ON vehicle_online_time(date);
+CREATE INDEX idx_vehicle_online_time_vehicle_id ON vehicle_online_time(vehicle_id);
+CREATE INDEX idx_vehicle_online_time_composite ON vehicle_online_time(vehicle_id, date);
+
+CREATE TABLE vehicle_telemetry (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id INTEGER NOT NULL,
+    protocol_timestamp INTEGER NOT NULL,
+    received_at TEXT NOT NULL,
+    speed REAL NOT NULL,
+    position_x REAL NOT NULL,
+    position_y REAL NOT NULL,
+    orientation REAL NOT NULL,
+    battery REAL NOT NULL,
+    gear INTEGER NOT NULL,
+    steering_angle REAL NOT NULL,
+    nav_status INTEGER NOT NULL,
+    camera_status BOOLEAN NOT NULL,
+    lidar_status BOOLEAN NOT NULL,
+    gyro_status BOOLEAN NOT NULL,
+    parking_slot INTEGER NOT NULL
+);
+CREATE INDEX idx_vehicle_telemetry_vehicle_time ON vehicle_telemetry(vehicle_id, received_at);
+CREATE INDEX idx_vehicle_telemetry_received_at ON vehicle_telemetry(received_at);
+
+CREATE TABLE sandbox_service_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL DEFAULT '',
+    ip_address TEXT NOT NULL,
+    traffic_light_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO sandbox_service_settings VALUES(2,'东区沙盘','192.168.1.50',1,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
+
+CREATE TABLE sandbox_cameras (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    camera_type TEXT NOT NULL CHECK (camera_type IN ('RJ45', 'USB')),
+    rtsp_url TEXT,
+    device_index INTEGER,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+, sandbox_id INTEGER);
+CREATE INDEX idx_sandbox_camera_type ON sandbox_cameras(camera_type);
+INSERT INTO sandbox_cameras VALUES(1,'路口俯视','RJ45','rtsp://192.168.1.60/live',NULL,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00',2);
+
+CREATE TABLE app_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log_level TEXT NOT NULL DEFAULT 'INFO',
+    cache_size INTEGER NOT NULL DEFAULT 512,
+    auto_start BOOLEAN NOT NULL DEFAULT 0,
+    app_title TEXT NOT NULL DEFAULT '渡众智能沙盘云控平台',
+    coordinate_offset_x REAL NOT NULL DEFAULT 0.0,
+    coordinate_offset_y REAL NOT NULL DEFAULT 0.0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO app_settings VALUES(1,'DEBUG',256,1,'测试沙盘',1.5,-2.5,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
+
+CREATE TABLE menu_visibility_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    show_vehicle_info BOOLEAN NOT NULL DEFAULT 1,
+    show_auto_drive BOOLEAN NOT NULL DEFAULT 1,
+    show_sandbox_control BOOLEAN NOT NULL DEFAULT 1,
+    show_settings BOOLEAN NOT NULL DEFAULT 1,
+    show_parallel_driving BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO menu_visibility_settings VALUES(1,1,0,1,1,0,'2025-06-01T08:00:00+00:00','2025-06-01T08:00:00+00:00');
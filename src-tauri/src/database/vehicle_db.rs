@@ -1,4 +1,6 @@
 use sqlx::{Pool, Sqlite, SqlitePool, Row};
+use sqlx::sqlite::SqliteRow;
+use std::collections::HashMap;
 use chrono::Utc;
 use crate::database::models::*;
 
@@ -32,13 +34,18 @@ impl VehicleDatabase {
         }
         
         let db_path = data_dir.join("vehicles.db");
+        Self::open(&db_path).await
+    }
+
+    /// 打开指定路径的数据库（不存在时创建）
+    pub async fn open(db_path: &std::path::Path) -> Result<Self, sqlx::Error> {
         let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
         
         log::debug!("📁 数据库路径: {}", database_url);
         
         // 创建连接池，优化配置以提升性能和稳定性
         let options = sqlx::sqlite::SqliteConnectOptions::new()
-            .filename(&db_path)
+            .filename(db_path)
             .create_if_missing(true)
             .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
             .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
@@ -56,297 +63,18 @@ impl VehicleDatabase {
         Ok(db)
     }
     
-    /// 初始化数据库表结构
+    /// 初始化数据库表结构：执行版本化迁移后写入默认设置
     async fn init_tables(&self) -> Result<(), sqlx::Error> {
-        // 只有在表不存在时才创建，保留现有数据
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vehicle_connections (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vehicle_id INTEGER NOT NULL UNIQUE,
-                ip_address TEXT NOT NULL,
-                name TEXT NOT NULL,
-                description TEXT,
-                color TEXT,
-                is_active BOOLEAN NOT NULL DEFAULT true,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-        
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_vehicle_id ON vehicle_connections(vehicle_id)")
-            .execute(&self.pool).await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_is_active ON vehicle_connections(is_active)")
-            .execute(&self.pool).await?;
-        
-        // 迁移：添加 color 列（如果不存在）
-        // 使用简单的方式：尝试添加列，如果失败则忽略（说明列已存在）
-        let _ = sqlx::query("ALTER TABLE vehicle_connections ADD COLUMN color TEXT")
-            .execute(&self.pool)
-            .await;
-        // 忽略错误，因为如果列已存在会报错，但这是正常的
+        let version = super::migrations::migrate(&self.pool).await?;
 
-        // 创建交通灯设置表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS traffic_light_settings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                red_light_duration INTEGER NOT NULL DEFAULT 45,
-                green_light_duration INTEGER NOT NULL DEFAULT 60,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 初始化默认交通灯设置
         self.init_default_traffic_light_settings().await?;
-
-        // 创建单个红绿灯时长表（按编号独立保存）
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS traffic_light_items (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                light_id INTEGER NOT NULL UNIQUE,
-                red_light_duration INTEGER NOT NULL DEFAULT 30,
-                green_light_duration INTEGER NOT NULL DEFAULT 30,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建出租车订单表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS taxi_orders (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                order_id TEXT NOT NULL UNIQUE,
-                start_x REAL NOT NULL,
-                start_y REAL NOT NULL,
-                end_x REAL NOT NULL,
-                end_y REAL NOT NULL,
-                assigned_vehicle_id INTEGER,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_order_id ON taxi_orders(order_id)")
-            .execute(&self.pool).await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_assigned_vehicle ON taxi_orders(assigned_vehicle_id)")
-            .execute(&self.pool).await?;
-
-        // 创建AVP泊车表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS avp_parking (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vehicle_id INTEGER NOT NULL,
-                parking_spot INTEGER NOT NULL,
-                created_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_avp_vehicle_id ON avp_parking(vehicle_id)")
-            .execute(&self.pool).await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_avp_parking_spot ON avp_parking(parking_spot)")
-            .execute(&self.pool).await?;
-
-        // 创建AVP取车表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS avp_pickup (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vehicle_id INTEGER NOT NULL,
-                created_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_avp_pickup_vehicle_id ON avp_pickup(vehicle_id)")
-            .execute(&self.pool).await?;
-
-        // 创建车辆在线时长统计表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vehicle_online_time (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vehicle_id INTEGER NOT NULL,
-                date TEXT NOT NULL,
-                online_minutes INTEGER NOT NULL DEFAULT 0,
-                updated_at TEXT NOT NULL,
-                UNIQUE(vehicle_id, date)
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_date ON vehicle_online_time(date)")
-            .execute(&self.pool).await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_vehicle_id ON vehicle_online_time(vehicle_id)")
-            .execute(&self.pool).await?;
-        // 添加复合索引以优化按车辆和日期查询的性能
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_vehicle_online_time_composite ON vehicle_online_time(vehicle_id, date)")
-            .execute(&self.pool).await?;
-
-        // 创建沙盘服务设置表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sandbox_service_settings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                ip_address TEXT NOT NULL,
-                traffic_light_count INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 迁移：如果存在port列，则删除它
-        let cols = sqlx::query("PRAGMA table_info(sandbox_service_settings)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .map(|row| row.get::<String, _>("name"))
-            .collect::<Vec<_>>();
-
-        if cols.iter().any(|n| n == "port") {
-            // SQLite不支持直接删除列，需要重建表
-            sqlx::query(
-                r#"
-                CREATE TABLE sandbox_service_settings_new (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    ip_address TEXT NOT NULL,
-                    traffic_light_count INTEGER NOT NULL DEFAULT 0,
-                    created_at TEXT NOT NULL,
-                    updated_at TEXT NOT NULL
-                )
-                "#
-            ).execute(&self.pool).await?;
-
-            // 复制数据（不包括port列）
-            sqlx::query(
-                r#"
-                INSERT INTO sandbox_service_settings_new (id, ip_address, traffic_light_count, created_at, updated_at)
-                SELECT id, ip_address, 0 as traffic_light_count, created_at, updated_at FROM sandbox_service_settings
-                "#
-            ).execute(&self.pool).await?;
-
-            // 删除旧表
-            sqlx::query("DROP TABLE sandbox_service_settings").execute(&self.pool).await?;
-
-            // 重命名新表
-            sqlx::query("ALTER TABLE sandbox_service_settings_new RENAME TO sandbox_service_settings")
-                .execute(&self.pool).await?;
-        }
-
-        // 如果缺少 traffic_light_count 列则添加
-        if !cols.iter().any(|n| n == "traffic_light_count") {
-            sqlx::query("ALTER TABLE sandbox_service_settings ADD COLUMN traffic_light_count INTEGER NOT NULL DEFAULT 0")
-                .execute(&self.pool)
-                .await?;
-        }
-
-        // 创建沙盘摄像头设置表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sandbox_cameras (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                camera_type TEXT NOT NULL CHECK (camera_type IN ('RJ45', 'USB')),
-                rtsp_url TEXT,
-                device_index INTEGER,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-
-        // 创建索引（如果不存在）
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_sandbox_camera_type ON sandbox_cameras(camera_type)")
-            .execute(&self.pool).await?;
-
-        // （已移除应用主题设置表）
-        
-        // 创建应用基本设置表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS app_settings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                log_level TEXT NOT NULL DEFAULT 'INFO',
-                cache_size INTEGER NOT NULL DEFAULT 512,
-                auto_start BOOLEAN NOT NULL DEFAULT 0,
-                app_title TEXT NOT NULL DEFAULT '渡众智能沙盘云控平台',
-                coordinate_offset_x REAL NOT NULL DEFAULT 0.0,
-                coordinate_offset_y REAL NOT NULL DEFAULT 0.0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-        
-        // 为现有表添加auto_start字段（如果不存在）
-        let _ = sqlx::query("ALTER TABLE app_settings ADD COLUMN auto_start BOOLEAN NOT NULL DEFAULT 0")
-            .execute(&self.pool).await; // 忽略错误，字段可能已存在
-        
-        // 为现有表添加coordinate_offset_x字段（如果不存在）
-        let _ = sqlx::query("ALTER TABLE app_settings ADD COLUMN coordinate_offset_x REAL NOT NULL DEFAULT 0.0")
-            .execute(&self.pool).await; // 忽略错误，字段可能已存在
-        
-        // 为现有表添加coordinate_offset_y字段（如果不存在）
-        let _ = sqlx::query("ALTER TABLE app_settings ADD COLUMN coordinate_offset_y REAL NOT NULL DEFAULT 0.0")
-            .execute(&self.pool).await; // 忽略错误，字段可能已存在
-        
-        // 为现有表添加app_title字段（如果不存在）
-        let _ = sqlx::query("ALTER TABLE app_settings ADD COLUMN app_title TEXT NOT NULL DEFAULT '渡众智能沙盘云控平台'")
-            .execute(&self.pool).await; // 忽略错误，字段可能已存在
-        
-        // 初始化默认应用设置
-        let cnt: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM app_settings").fetch_one(&self.pool).await?;
-        if cnt == 0 {
-            let now = Utc::now().to_rfc3339();
-            sqlx::query(
-                r#"INSERT INTO app_settings (log_level, cache_size, auto_start, app_title, coordinate_offset_x, coordinate_offset_y, created_at, updated_at) VALUES ('INFO', 512, 0, '渡众智能沙盘云控平台', 0.0, 0.0, ?, ?)"#
-            ).bind(&now).bind(&now).execute(&self.pool).await?;
-        }
-
-        // 创建菜单可见性设置表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS menu_visibility_settings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                show_vehicle_info BOOLEAN NOT NULL DEFAULT 1,
-                show_auto_drive BOOLEAN NOT NULL DEFAULT 1,
-                show_sandbox_control BOOLEAN NOT NULL DEFAULT 1,
-                show_settings BOOLEAN NOT NULL DEFAULT 1,
-                show_parallel_driving BOOLEAN NOT NULL DEFAULT 1,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#
-        ).execute(&self.pool).await?;
-        
-        // 为现有表添加 show_parallel_driving 列（兼容旧数据库）
-        let _ = sqlx::query(
-            "ALTER TABLE menu_visibility_settings ADD COLUMN show_parallel_driving BOOLEAN NOT NULL DEFAULT 1"
-        ).execute(&self.pool).await;
-
-        // 初始化默认菜单可见性设置
+        self.init_default_app_settings().await?;
         self.init_default_menu_visibility_settings().await?;
-        
-        log::info!("数据库表结构检查完成");
+
+        log::info!("数据库表结构检查完成（版本 {}）", version);
         Ok(())
     }
-    
-    
+
     /// 创建车辆连接
     pub async fn create_vehicle_connection(
         &self, 
@@ -357,8 +85,8 @@ impl VehicleDatabase {
         sqlx::query(
             r#"
             INSERT INTO vehicle_connections 
-            (vehicle_id, ip_address, name, description, color, is_active, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, true, ?, ?)
+            (vehicle_id, ip_address, name, description, color, is_active, auth_key, cert_fingerprint, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, true, ?, ?, ?, ?)
             "#
         )
         .bind(request.vehicle_id)
@@ -366,6 +94,8 @@ impl VehicleDatabase {
         .bind(&request.name)
         .bind(&request.description)
         .bind(&request.color)
+        .bind(normalize_auth_key(request.auth_key))
+        .bind(normalize_cert_fingerprint(request.cert_fingerprint))
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
         .execute(&self.pool)
@@ -377,17 +107,7 @@ impl VehicleDatabase {
             .fetch_one(&self.pool)
             .await?;
         
-        Ok(VehicleConnection {
-            id: row.get("id"),
-            vehicle_id: row.get("vehicle_id"),
-            ip_address: row.get("ip_address"),
-            name: row.get("name"),
-            description: row.get("description"),
-            color: row.get("color"),
-            is_active: row.get("is_active"),
-            created_at: row.get::<String, _>("created_at").parse().unwrap(),
-            updated_at: row.get::<String, _>("updated_at").parse().unwrap(),
-        })
+        Ok(vehicle_connection_from_row(&row))
     }
 
     // ============ 应用基本设置 ==========
@@ -445,28 +165,13 @@ impl VehicleDatabase {
     /// 获取所有车辆连接
     pub async fn get_all_vehicle_connections(&self) -> Result<Vec<VehicleConnection>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT id, vehicle_id, ip_address, name, description, color, is_active, created_at, updated_at 
+            "SELECT id, vehicle_id, ip_address, name, description, color, is_active, auth_key, cert_fingerprint, created_at, updated_at 
              FROM vehicle_connections ORDER BY created_at DESC"
         )
             .fetch_all(&self.pool)
             .await?;
         
-        let mut connections = Vec::new();
-        for row in rows {
-            connections.push(VehicleConnection {
-                id: row.get("id"),
-                vehicle_id: row.get("vehicle_id"),
-                ip_address: row.get("ip_address"),
-                name: row.get("name"),
-                description: row.get("description"),
-                color: row.get("color"),
-                is_active: row.get("is_active"),
-                created_at: row.get::<String, _>("created_at").parse().unwrap(),
-                updated_at: row.get::<String, _>("updated_at").parse().unwrap(),
-            });
-        }
-        
-        Ok(connections)
+        Ok(rows.iter().map(vehicle_connection_from_row).collect())
     }
     
     /// 根据ID获取车辆连接
@@ -476,21 +181,7 @@ impl VehicleDatabase {
             .fetch_optional(&self.pool)
             .await?;
         
-        if let Some(row) = row {
-            Ok(Some(VehicleConnection {
-                id: row.get("id"),
-                vehicle_id: row.get("vehicle_id"),
-                ip_address: row.get("ip_address"),
-                name: row.get("name"),
-                description: row.get("description"),
-                color: row.get("color"),
-                is_active: row.get("is_active"),
-                created_at: row.get::<String, _>("created_at").parse().unwrap(),
-                updated_at: row.get::<String, _>("updated_at").parse().unwrap(),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.as_ref().map(vehicle_connection_from_row))
     }
     
     /// 更新车辆连接
@@ -514,12 +205,21 @@ impl VehicleDatabase {
         let description = request.description.or(existing.description);
         let color = request.color.or(existing.color);
         let is_active = request.is_active.unwrap_or(existing.is_active);
+        // 传入空字符串表示清除密钥
+        let auth_key = match request.auth_key {
+            Some(key) => normalize_auth_key(Some(key)),
+            None => existing.auth_key,
+        };
+        let cert_fingerprint = match request.cert_fingerprint {
+            Some(fingerprint) => normalize_cert_fingerprint(Some(fingerprint)),
+            None => existing.cert_fingerprint,
+        };
         
         // 执行更新
         sqlx::query(
             r#"
             UPDATE vehicle_connections 
-            SET vehicle_id = ?, ip_address = ?, name = ?, description = ?, color = ?, is_active = ?, updated_at = ?
+            SET vehicle_id = ?, ip_address = ?, name = ?, description = ?, color = ?, is_active = ?, auth_key = ?, cert_fingerprint = ?, updated_at = ?
             WHERE id = ?
             "#
         )
@@ -529,6 +229,8 @@ impl VehicleDatabase {
         .bind(&description)
         .bind(&color)
         .bind(is_active)
+        .bind(&auth_key)
+        .bind(&cert_fingerprint)
         .bind(now.to_rfc3339())
         .bind(id)
         .execute(&self.pool)
@@ -548,28 +250,36 @@ impl VehicleDatabase {
         Ok(result.rows_affected() > 0)
     }
     
+    /// 按车辆编号获取车辆连接
+    pub async fn get_vehicle_connection_by_vehicle_id(&self, vehicle_id: i32) -> Result<Option<VehicleConnection>, sqlx::Error> {
+        let row = sqlx::query("SELECT * FROM vehicle_connections WHERE vehicle_id = ?")
+            .bind(vehicle_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(vehicle_connection_from_row))
+    }
+    
+    /// 按客户端证书指纹获取车辆连接
+    pub async fn get_vehicle_connection_by_cert_fingerprint(&self, fingerprint: &str) -> Result<Option<VehicleConnection>, sqlx::Error> {
+        let Some(fingerprint) = normalize_cert_fingerprint(Some(fingerprint.to_string())) else {
+            return Ok(None);
+        };
+        let row = sqlx::query("SELECT * FROM vehicle_connections WHERE cert_fingerprint = ?")
+            .bind(&fingerprint)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(vehicle_connection_from_row))
+    }
+    
     /// 获取活跃的车辆连接
     pub async fn get_active_vehicle_connections(&self) -> Result<Vec<VehicleConnection>, sqlx::Error> {
         let rows = sqlx::query("SELECT * FROM vehicle_connections WHERE is_active = true ORDER BY name")
             .fetch_all(&self.pool)
             .await?;
         
-        let mut connections = Vec::new();
-        for row in rows {
-            connections.push(VehicleConnection {
-                id: row.get("id"),
-                vehicle_id: row.get("vehicle_id"),
-                ip_address: row.get("ip_address"),
-                name: row.get("name"),
-                description: row.get("description"),
-                color: row.get("color"),
-                is_active: row.get("is_active"),
-                created_at: row.get::<String, _>("created_at").parse().unwrap(),
-                updated_at: row.get::<String, _>("updated_at").parse().unwrap(),
-            });
-        }
-        
-        Ok(connections)
+        Ok(rows.iter().map(vehicle_connection_from_row).collect())
     }
 
     /// 初始化默认交通灯设置（如果不存在）
@@ -657,15 +367,36 @@ impl VehicleDatabase {
         self.get_traffic_light_settings().await
     }
 
-    /// 创建出租车订单
-    pub async fn create_taxi_order(&self, request: CreateTaxiOrderRequest) -> Result<TaxiOrder, sqlx::Error> {
+    /// 创建出租车订单，同时记录创建状态
+    pub async fn create_taxi_order(&self, request: CreateTaxiOrderRequest) -> Result<(TaxiOrder, TaxiOrderTransition), sqlx::Error> {
+        self.insert_taxi_order(&request, None, 0, None).await
+    }
+
+    /// 创建待排队的出租车订单（带要求接客时间、优先级与派单策略），状态仍为已创建
+    pub async fn create_scheduled_taxi_order(&self, request: &EnqueueTaxiOrderRequest) -> Result<(TaxiOrder, TaxiOrderTransition), sqlx::Error> {
+        self.insert_taxi_order(
+            &request.order,
+            request.scheduled_at.as_deref(),
+            request.priority,
+            request.dispatch_strategy.as_deref(),
+        )
+        .await
+    }
+
+    async fn insert_taxi_order(
+        &self,
+        request: &CreateTaxiOrderRequest,
+        scheduled_at: Option<&str>,
+        priority: i32,
+        dispatch_strategy: Option<&str>,
+    ) -> Result<(TaxiOrder, TaxiOrderTransition), sqlx::Error> {
         let now = Utc::now().to_rfc3339();
-        
-        let row = sqlx::query(
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
             r#"
-            INSERT INTO taxi_orders (order_id, start_x, start_y, end_x, end_y, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            RETURNING id, order_id, start_x, start_y, end_x, end_y, assigned_vehicle_id, created_at, updated_at
+            INSERT INTO taxi_orders (order_id, start_x, start_y, end_x, end_y, status, scheduled_at, priority, dispatch_strategy, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, 'created', ?, ?, ?, ?, ?)
             "#
         )
         .bind(&request.order_id)
@@ -673,83 +404,245 @@ impl VehicleDatabase {
         .bind(request.start_y)
         .bind(request.end_x)
         .bind(request.end_y)
+        .bind(scheduled_at)
+        .bind(priority)
+        .bind(dispatch_strategy)
         .bind(&now)
         .bind(&now)
-        .fetch_one(&self.pool)
+        .execute(&mut tx)
         .await?;
 
-        Ok(TaxiOrder {
-            id: row.get("id"),
-            order_id: row.get("order_id"),
-            start_x: row.get("start_x"),
-            start_y: row.get("start_y"),
-            end_x: row.get("end_x"),
-            end_y: row.get("end_y"),
-            assigned_vehicle_id: row.get("assigned_vehicle_id"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        // RETURNING 返回未按列类型转换的存储值（整数值的坐标会以 INTEGER 返回），因此写入后重新查询
+        let row = sqlx::query(&format!("SELECT {} FROM taxi_orders WHERE order_id = ?", TAXI_ORDER_COLUMNS))
+            .bind(&request.order_id)
+            .fetch_one(&mut tx)
+            .await?;
+        let order = taxi_order_from_row(&row);
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO taxi_order_transitions (order_id, from_status, to_status, created_at)
+            VALUES (?, NULL, ?, ?)
+            RETURNING {}
+            "#,
+            TAXI_ORDER_TRANSITION_COLUMNS
+        ))
+        .bind(&order.order_id)
+        .bind(&order.status)
+        .bind(&now)
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((order, taxi_order_transition_from_row(&row)))
     }
 
-    /// 保存出租车订单（包含分配的车辆ID）
-    pub async fn save_taxi_order(&self, order_id: &str, assigned_vehicle_id: i32, start_x: f64, start_y: f64, end_x: f64, end_y: f64) -> Result<TaxiOrder, sqlx::Error> {
+    /// 按订单号获取出租车订单
+    pub async fn get_taxi_order(&self, order_id: &str) -> Result<Option<TaxiOrder>, sqlx::Error> {
+        let row = sqlx::query(&format!("SELECT {} FROM taxi_orders WHERE order_id = ?", TAXI_ORDER_COLUMNS))
+            .bind(order_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(taxi_order_from_row))
+    }
+
+    /// 获取车辆处于给定状态之一的最近一个订单
+    pub async fn get_vehicle_taxi_order(&self, vehicle_id: i32, statuses: &[&str]) -> Result<Option<TaxiOrder>, sqlx::Error> {
+        if statuses.is_empty() {
+            return Ok(None);
+        }
+
+        let sql = format!(
+            "SELECT {} FROM taxi_orders WHERE assigned_vehicle_id = ? AND status IN ({}) ORDER BY id DESC LIMIT 1",
+            TAXI_ORDER_COLUMNS,
+            vec!["?"; statuses.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql).bind(vehicle_id);
+        for status in statuses {
+            query = query.bind(*status);
+        }
+        let row = query.fetch_optional(&self.pool).await?;
+
+        Ok(row.as_ref().map(taxi_order_from_row))
+    }
+
+    /// 变更出租车订单状态并记录变更
+    ///
+    /// 仅当订单当前状态仍为 `from_status` 时更新（并发变更时后到者不生效），返回 None 表示未更新。
+    /// `vehicle` 非空时同时更新接单车辆；其中的状态列表非空时，该车辆已有处于这些状态的其他订单则不更新。
+    /// 进入排队状态时清除接单车辆并插入队列：
+    /// 新订单排在同优先级订单之后，重新排队的订单排在同优先级订单之前；离开排队状态时移出队列。
+    pub async fn update_taxi_order_status(
+        &self,
+        order_id: &str,
+        from_status: &str,
+        to_status: &str,
+        vehicle: Option<(i32, &[&str])>,
+        nav_status: Option<i32>,
+        reason: Option<&str>,
+    ) -> Result<Option<(TaxiOrder, TaxiOrderTransition)>, sqlx::Error> {
+        let vehicle_id = vehicle.map(|(vehicle_id, _)| vehicle_id);
+        let busy_statuses = vehicle.map_or(&[][..], |(_, busy_statuses)| busy_statuses);
         let now = Utc::now().to_rfc3339();
-        
-        let row = sqlx::query(
+        let mut tx = self.pool.begin().await?;
+
+        let queue_position: Option<i64> = if to_status == "queued" {
+            let Some(priority) = sqlx::query_scalar::<_, i32>("SELECT priority FROM taxi_orders WHERE order_id = ?")
+                .bind(order_id)
+                .fetch_optional(&mut tx)
+                .await?
+            else {
+                return Ok(None);
+            };
+            let comparison = if from_status == "created" { ">=" } else { ">" };
+            let position: i64 = sqlx::query_scalar(&format!(
+                "SELECT COALESCE(MAX(queue_position), -1) + 1 FROM taxi_orders WHERE status = 'queued' AND priority {} ?",
+                comparison
+            ))
+            .bind(priority)
+            .fetch_one(&mut tx)
+            .await?;
+            sqlx::query("UPDATE taxi_orders SET queue_position = queue_position + 1 WHERE status = 'queued' AND queue_position >= ?")
+                .bind(position)
+                .execute(&mut tx)
+                .await?;
+            Some(position)
+        } else {
+            None
+        };
+
+        // 占用检查与更新在同一条语句中完成，并发分配同一辆车时只有一个生效
+        let busy_guard = if busy_statuses.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " AND NOT EXISTS (SELECT 1 FROM taxi_orders busy WHERE busy.assigned_vehicle_id = ? AND busy.order_id <> taxi_orders.order_id AND busy.status IN ({}))",
+                vec!["?"; busy_statuses.len()].join(", ")
+            )
+        };
+        let sql = format!(
             r#"
-            INSERT INTO taxi_orders (order_id, start_x, start_y, end_x, end_y, assigned_vehicle_id, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-            RETURNING id, order_id, start_x, start_y, end_x, end_y, assigned_vehicle_id, created_at, updated_at
-            "#
-        )
+            UPDATE taxi_orders
+            SET status = ?,
+                assigned_vehicle_id = CASE WHEN ? IS NULL THEN COALESCE(?, assigned_vehicle_id) END,
+                queue_position = ?,
+                updated_at = ?
+            WHERE order_id = ? AND status = ?{}
+            "#,
+            busy_guard
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(to_status)
+            .bind(queue_position)
+            .bind(vehicle_id)
+            .bind(queue_position)
+            .bind(&now)
+            .bind(order_id)
+            .bind(from_status);
+        if !busy_guard.is_empty() {
+            query = query.bind(vehicle_id);
+            for status in busy_statuses {
+                query = query.bind(*status);
+            }
+        }
+        let updated = query.execute(&mut tx).await?.rows_affected();
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(&format!("SELECT {} FROM taxi_orders WHERE order_id = ?", TAXI_ORDER_COLUMNS))
+            .bind(order_id)
+            .fetch_one(&mut tx)
+            .await?;
+        let order = taxi_order_from_row(&row);
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO taxi_order_transitions (order_id, from_status, to_status, vehicle_id, nav_status, reason, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            TAXI_ORDER_TRANSITION_COLUMNS
+        ))
         .bind(order_id)
-        .bind(start_x)
-        .bind(start_y)
-        .bind(end_x)
-        .bind(end_y)
-        .bind(assigned_vehicle_id)
-        .bind(&now)
+        .bind(from_status)
+        .bind(to_status)
+        .bind(order.assigned_vehicle_id)
+        .bind(nav_status)
+        .bind(reason)
         .bind(&now)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut tx)
         .await?;
 
-        Ok(TaxiOrder {
-            id: row.get("id"),
-            order_id: row.get("order_id"),
-            start_x: row.get("start_x"),
-            start_y: row.get("start_y"),
-            end_x: row.get("end_x"),
-            end_y: row.get("end_y"),
-            assigned_vehicle_id: row.get("assigned_vehicle_id"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        tx.commit().await?;
+        Ok(Some((order, taxi_order_transition_from_row(&row))))
     }
 
-    /// 获取所有出租车订单
-    pub async fn get_all_taxi_orders(&self) -> Result<Vec<TaxiOrder>, sqlx::Error> {
+    /// 获取排队中的出租车订单（按队列顺序）
+    pub async fn get_queued_taxi_orders(&self) -> Result<Vec<TaxiOrder>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM taxi_orders WHERE status = 'queued' ORDER BY queue_position, id",
+            TAXI_ORDER_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(taxi_order_from_row).collect())
+    }
+
+    /// 按给定顺序重排排队中的订单（不在排队中的订单号忽略）
+    pub async fn reorder_taxi_order_queue(&self, order_ids: &[String]) -> Result<Vec<TaxiOrder>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (position, order_id) in order_ids.iter().enumerate() {
+            sqlx::query("UPDATE taxi_orders SET queue_position = ? WHERE order_id = ? AND status = 'queued'")
+                .bind(position as i64)
+                .bind(order_id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        self.get_queued_taxi_orders().await
+    }
+
+    /// 获取出租车订单的状态变更记录（按时间顺序）
+    pub async fn get_taxi_order_transitions(&self, order_id: &str) -> Result<Vec<TaxiOrderTransition>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM taxi_order_transitions WHERE order_id = ? ORDER BY id",
+            TAXI_ORDER_TRANSITION_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(taxi_order_transition_from_row).collect())
+    }
+
+    /// 统计各车辆自 `since`（RFC3339）起进入 `to_status` 状态的订单数
+    pub async fn count_taxi_order_transitions_by_vehicle(&self, to_status: &str, since: &str) -> Result<HashMap<i32, i64>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT id, order_id, start_x, start_y, end_x, end_y, assigned_vehicle_id, created_at, updated_at FROM taxi_orders ORDER BY created_at DESC"
+            r#"
+            SELECT vehicle_id, COUNT(*) AS count FROM taxi_order_transitions
+            WHERE to_status = ? AND created_at >= ? AND vehicle_id IS NOT NULL
+            GROUP BY vehicle_id
+            "#
         )
+        .bind(to_status)
+        .bind(since)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut orders = Vec::new();
-        for row in rows {
-            orders.push(TaxiOrder {
-                id: row.get("id"),
-                order_id: row.get("order_id"),
-                start_x: row.get("start_x"),
-                start_y: row.get("start_y"),
-                end_x: row.get("end_x"),
-                end_y: row.get("end_y"),
-                assigned_vehicle_id: row.get("assigned_vehicle_id"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            });
-        }
+        Ok(rows.iter().map(|row| (row.get("vehicle_id"), row.get("count"))).collect())
+    }
 
-        Ok(orders)
+    /// 获取所有出租车订单
+    pub async fn get_all_taxi_orders(&self) -> Result<Vec<TaxiOrder>, sqlx::Error> {
+        let rows = sqlx::query(&format!("SELECT {} FROM taxi_orders ORDER BY created_at DESC", TAXI_ORDER_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(taxi_order_from_row).collect())
     }
 
     /// 创建AVP泊车记录
@@ -841,6 +734,72 @@ impl VehicleDatabase {
         Ok(pickup_records)
     }
 
+    /// 获取停车场全部车位（按编号排序）
+    pub async fn get_parking_slots(&self) -> Result<Vec<ParkingSlot>, sqlx::Error> {
+        sqlx::query_as::<_, ParkingSlot>(
+            "SELECT slot_id, position_x, position_y, slot_type, updated_at, occupant, reported_at FROM parking_slots ORDER BY slot_id"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// 新增或更新车位
+    pub async fn save_parking_slot(&self, request: SaveParkingSlotRequest) -> Result<ParkingSlot, sqlx::Error> {
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            INSERT INTO parking_slots (slot_id, position_x, position_y, slot_type, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(slot_id) DO UPDATE SET
+                position_x = excluded.position_x,
+                position_y = excluded.position_y,
+                slot_type = excluded.slot_type,
+                updated_at = excluded.updated_at
+            "#
+        )
+        .bind(request.slot_id)
+        .bind(request.position_x)
+        .bind(request.position_y)
+        .bind(&request.slot_type)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query_as::<_, ParkingSlot>(
+            "SELECT slot_id, position_x, position_y, slot_type, updated_at, occupant, reported_at FROM parking_slots WHERE slot_id = ?"
+        )
+        .bind(request.slot_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// 保存车位占用：`occupants` 为 (车位编号, 车辆, 最后确认时间)，其余车位清除占用
+    pub async fn save_parking_occupants(&self, occupants: &[(i32, i32, String)]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE parking_slots SET occupant = NULL, reported_at = NULL WHERE occupant IS NOT NULL")
+            .execute(&mut tx)
+            .await?;
+        for (slot_id, vehicle_id, reported_at) in occupants {
+            sqlx::query("UPDATE parking_slots SET occupant = ?, reported_at = ? WHERE slot_id = ?")
+                .bind(vehicle_id)
+                .bind(reported_at)
+                .bind(slot_id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await
+    }
+
+    /// 删除车位，返回是否存在
+    pub async fn delete_parking_slot(&self, slot_id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM parking_slots WHERE slot_id = ?")
+            .bind(slot_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// 更新车辆在线时长
     pub async fn update_vehicle_online_time(&self, vehicle_id: i32, minutes: i32) -> Result<(), sqlx::Error> {
         let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
@@ -908,6 +867,131 @@ impl VehicleDatabase {
         Ok(records)
     }
 
+    // ============ 车辆遥测记录相关方法 ============
+
+    /// 批量写入车辆遥测记录
+    pub async fn insert_vehicle_telemetry_batch(&self, records: &[VehicleTelemetry]) -> Result<u64, sqlx::Error> {
+        if records.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO vehicle_telemetry (
+                    vehicle_id, protocol_timestamp, received_at, speed, position_x, position_y,
+                    orientation, battery, gear, steering_angle, nav_status,
+                    camera_status, lidar_status, gyro_status, parking_slot
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(record.vehicle_id)
+            .bind(record.protocol_timestamp)
+            .bind(&record.received_at)
+            .bind(record.speed)
+            .bind(record.position_x)
+            .bind(record.position_y)
+            .bind(record.orientation)
+            .bind(record.battery)
+            .bind(record.gear)
+            .bind(record.steering_angle)
+            .bind(record.nav_status)
+            .bind(record.camera_status)
+            .bind(record.lidar_status)
+            .bind(record.gyro_status)
+            .bind(record.parking_slot)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(records.len() as u64)
+    }
+
+    /// 查询车辆遥测记录（按接收时间升序，时间范围为 RFC3339 字符串）
+    pub async fn get_vehicle_telemetry(
+        &self,
+        vehicle_id: i32,
+        start_time: Option<&str>,
+        end_time: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<VehicleTelemetry>, sqlx::Error> {
+        // received_at 按字符串比较，时间范围需换算成与存储一致的 UTC 毫秒格式
+        let start_time = start_time.map(normalize_telemetry_time).transpose()?;
+        let end_time = end_time.map(normalize_telemetry_time).transpose()?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM vehicle_telemetry
+            WHERE vehicle_id = ?
+              AND (? IS NULL OR received_at >= ?)
+              AND (? IS NULL OR received_at <= ?)
+            ORDER BY received_at ASC, id ASC
+            LIMIT ?
+            "#
+        )
+        .bind(vehicle_id)
+        .bind(&start_time)
+        .bind(&start_time)
+        .bind(&end_time)
+        .bind(&end_time)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(VehicleTelemetry {
+                id: row.get("id"),
+                vehicle_id: row.get("vehicle_id"),
+                protocol_timestamp: row.get("protocol_timestamp"),
+                received_at: row.get("received_at"),
+                speed: row.get("speed"),
+                position_x: row.get("position_x"),
+                position_y: row.get("position_y"),
+                orientation: row.get("orientation"),
+                battery: row.get("battery"),
+                gear: row.get("gear"),
+                steering_angle: row.get("steering_angle"),
+                nav_status: row.get("nav_status"),
+                camera_status: row.get("camera_status"),
+                lidar_status: row.get("lidar_status"),
+                gyro_status: row.get("gyro_status"),
+                parking_slot: row.get("parking_slot"),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// 按保留策略清理车辆遥测记录，返回删除条数
+    pub async fn prune_vehicle_telemetry(&self, retention_hours: u32, max_rows: u64) -> Result<u64, sqlx::Error> {
+        let cutoff = Utc::now() - chrono::Duration::hours(retention_hours as i64);
+        let cutoff_str = cutoff.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let expired = sqlx::query("DELETE FROM vehicle_telemetry WHERE received_at < ?")
+            .bind(&cutoff_str)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        // 超出最大条数时删除最旧的记录
+        let overflow = sqlx::query(
+            r#"
+            DELETE FROM vehicle_telemetry
+            WHERE id <= (SELECT id FROM vehicle_telemetry ORDER BY id DESC LIMIT 1 OFFSET ?)
+            "#
+        )
+        .bind(max_rows as i64)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(expired + overflow)
+    }
+
     /// 获取自动驾驶行为统计
     pub async fn get_driving_behavior_stats(&self) -> Result<serde_json::Value, sqlx::Error> {
         // 统计出租车订单数量
@@ -936,30 +1020,50 @@ impl VehicleDatabase {
 
     // ============ 沙盘设置相关方法 ============
 
-    /// 获取沙盘服务设置
+    fn map_sandbox_service_settings(row: &sqlx::sqlite::SqliteRow) -> SandboxServiceSettings {
+        SandboxServiceSettings {
+            id: row.get("id"),
+            name: row.get("name"),
+            ip_address: row.get("ip_address"),
+            traffic_light_count: row.get("traffic_light_count"),
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))
+                .unwrap_or_default()
+                .with_timezone(&chrono::Utc),
+            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))
+                .unwrap_or_default()
+                .with_timezone(&chrono::Utc),
+        }
+    }
+
+    /// 获取默认沙盘服务设置（ID最小的沙盘）
     pub async fn get_sandbox_service_settings(&self) -> Result<Option<SandboxServiceSettings>, sqlx::Error> {
-        let row = sqlx::query("SELECT * FROM sandbox_service_settings ORDER BY id DESC LIMIT 1")
+        let row = sqlx::query("SELECT * FROM sandbox_service_settings ORDER BY id LIMIT 1")
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            Ok(Some(SandboxServiceSettings {
-                id: row.get("id"),
-                ip_address: row.get("ip_address"),
-                traffic_light_count: row.get("traffic_light_count"),
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))
-                    .unwrap_or_default()
-                    .with_timezone(&chrono::Utc),
-                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))
-                    .unwrap_or_default()
-                    .with_timezone(&chrono::Utc),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.as_ref().map(Self::map_sandbox_service_settings))
+    }
+
+    /// 获取指定沙盘服务设置
+    pub async fn get_sandbox_service_settings_by_id(&self, id: i64) -> Result<Option<SandboxServiceSettings>, sqlx::Error> {
+        let row = sqlx::query("SELECT * FROM sandbox_service_settings WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(Self::map_sandbox_service_settings))
+    }
+
+    /// 获取全部沙盘服务设置
+    pub async fn get_all_sandbox_service_settings(&self) -> Result<Vec<SandboxServiceSettings>, sqlx::Error> {
+        let rows = sqlx::query("SELECT * FROM sandbox_service_settings ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::map_sandbox_service_settings).collect())
     }
 
-    /// 创建或更新沙盘服务设置（仅保存一条数据）
+    /// 创建或更新沙盘服务设置：指定ID时更新该沙盘，否则更新默认沙盘（不存在则创建）
     pub async fn create_or_update_sandbox_service_settings(
         &self,
         request: CreateOrUpdateSandboxServiceRequest,
@@ -967,17 +1071,23 @@ impl VehicleDatabase {
         let now = Utc::now();
 
         // 检查是否已存在记录
-        let existing = self.get_sandbox_service_settings().await?;
+        let existing = match request.id {
+            Some(id) => Some(self.get_sandbox_service_settings_by_id(id).await?.ok_or(sqlx::Error::RowNotFound)?),
+            None => self.get_sandbox_service_settings().await?,
+        };
 
         if let Some(existing_settings) = existing {
+            let name = request.name.unwrap_or(existing_settings.name);
+
             // 更新现有记录
             sqlx::query(
                 r#"
                 UPDATE sandbox_service_settings 
-                SET ip_address = ?, traffic_light_count = ?, updated_at = ?
+                SET name = ?, ip_address = ?, traffic_light_count = ?, updated_at = ?
                 WHERE id = ?
                 "#
             )
+            .bind(&name)
             .bind(&request.ip_address)
             .bind(request.traffic_light_count)
             .bind(now.to_rfc3339())
@@ -988,64 +1098,97 @@ impl VehicleDatabase {
             // 返回更新后的记录
             let updated = SandboxServiceSettings {
                 id: existing_settings.id,
+                name,
                 ip_address: request.ip_address,
                 traffic_light_count: request.traffic_light_count,
                 created_at: existing_settings.created_at,
                 updated_at: now,
             };
             // 确保单灯时长表具有对应数量的记录（默认30/30）
-            self.ensure_traffic_light_items(updated.traffic_light_count).await?;
+            self.ensure_traffic_light_items(updated.id, updated.traffic_light_count).await?;
             Ok(updated)
         } else {
-            // 创建新记录
-            let result = sqlx::query(
-                r#"
-                INSERT INTO sandbox_service_settings (ip_address, traffic_light_count, created_at, updated_at)
-                VALUES (?, ?, ?, ?)
-                "#
-            )
-            .bind(&request.ip_address)
-            .bind(request.traffic_light_count)
-            .bind(now.to_rfc3339())
-            .bind(now.to_rfc3339())
-            .execute(&self.pool)
-            .await?;
-
-            let created = SandboxServiceSettings {
-                id: result.last_insert_rowid(),
-                ip_address: request.ip_address,
-                traffic_light_count: request.traffic_light_count,
-                created_at: now,
-                updated_at: now,
-            };
-            // 初始化对应数量的单灯记录
-            self.ensure_traffic_light_items(created.traffic_light_count).await?;
-            Ok(created)
+            self.create_sandbox_service_settings(request).await
         }
     }
 
-    /// 删除沙盘服务设置
-    pub async fn delete_sandbox_service_settings(&self) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query("DELETE FROM sandbox_service_settings")
+    /// 新增沙盘服务设置
+    pub async fn create_sandbox_service_settings(
+        &self,
+        request: CreateOrUpdateSandboxServiceRequest,
+    ) -> Result<SandboxServiceSettings, sqlx::Error> {
+        let now = Utc::now();
+        let name = request.name.unwrap_or_default();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO sandbox_service_settings (name, ip_address, traffic_light_count, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&name)
+        .bind(&request.ip_address)
+        .bind(request.traffic_light_count)
+        .bind(now.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        let created = SandboxServiceSettings {
+            id: result.last_insert_rowid(),
+            name,
+            ip_address: request.ip_address,
+            traffic_light_count: request.traffic_light_count,
+            created_at: now,
+            updated_at: now,
+        };
+        // 初始化对应数量的单灯记录
+        self.ensure_traffic_light_items(created.id, created.traffic_light_count).await?;
+        Ok(created)
+    }
+
+    /// 删除沙盘服务设置：指定ID时删除该沙盘及其红绿灯时长（摄像头改为未分配），否则全部删除
+    pub async fn delete_sandbox_service_settings(&self, id: Option<i64>) -> Result<bool, sqlx::Error> {
+        let Some(id) = id else {
+            let result = sqlx::query("DELETE FROM sandbox_service_settings")
+                .execute(&self.pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        };
+
+        let result = sqlx::query("DELETE FROM sandbox_service_settings WHERE id = ?")
+            .bind(id)
             .execute(&self.pool)
             .await?;
-
-        Ok(result.rows_affected() > 0)
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM traffic_light_items WHERE sandbox_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("UPDATE sandbox_cameras SET sandbox_id = NULL WHERE sandbox_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(true)
     }
 
-    // ============ 每个红绿灯时长（按编号） ============
-    async fn ensure_traffic_light_items(&self, count: i32) -> Result<(), sqlx::Error> {
+    // ============ 每个红绿灯时长（按沙盘与编号） ============
+    async fn ensure_traffic_light_items(&self, sandbox_id: i64, count: i32) -> Result<(), sqlx::Error> {
         if count <= 0 { return Ok(()); }
         for i in 1..=count {
-            let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM traffic_light_items WHERE light_id = ?")
+            let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM traffic_light_items WHERE sandbox_id = ? AND light_id = ?")
+                .bind(sandbox_id)
                 .bind(i)
                 .fetch_optional(&self.pool)
                 .await?;
             if exists.is_none() {
                 let now = Utc::now().to_rfc3339();
                 sqlx::query(
-                    "INSERT INTO traffic_light_items (light_id, red_light_duration, green_light_duration, created_at, updated_at) VALUES (?, 30, 30, ?, ?)"
+                    "INSERT INTO traffic_light_items (sandbox_id, light_id, red_light_duration, green_light_duration, created_at, updated_at) VALUES (?, ?, 30, 30, ?, ?)"
                 )
+                .bind(sandbox_id)
                 .bind(i)
                 .bind(&now)
                 .bind(&now)
@@ -1056,34 +1199,51 @@ impl VehicleDatabase {
         Ok(())
     }
 
-    pub async fn get_traffic_light_item(&self, light_id: i32) -> Result<TrafficLightItem, sqlx::Error> {
-        // 如果不存在该编号记录则创建默认
-        self.ensure_traffic_light_items(light_id).await?;
-        let row = sqlx::query("SELECT * FROM traffic_light_items WHERE light_id = ?")
-            .bind(light_id)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(TrafficLightItem {
+    fn map_traffic_light_item(row: &sqlx::sqlite::SqliteRow) -> TrafficLightItem {
+        TrafficLightItem {
             id: row.get("id"),
+            sandbox_id: row.get("sandbox_id"),
             light_id: row.get("light_id"),
             red_light_duration: row.get("red_light_duration"),
             green_light_duration: row.get("green_light_duration"),
             created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at")).unwrap_or_default().with_timezone(&chrono::Utc),
             updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at")).unwrap_or_default().with_timezone(&chrono::Utc),
-        })
+        }
+    }
+
+    pub async fn get_traffic_light_item(&self, sandbox_id: i64, light_id: i32) -> Result<TrafficLightItem, sqlx::Error> {
+        // 如果不存在该编号记录则创建默认
+        self.ensure_traffic_light_items(sandbox_id, light_id).await?;
+        let row = sqlx::query("SELECT * FROM traffic_light_items WHERE sandbox_id = ? AND light_id = ?")
+            .bind(sandbox_id)
+            .bind(light_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(Self::map_traffic_light_item(&row))
+    }
+
+    /// 获取沙盘的全部红绿灯时长（按编号排序）
+    pub async fn get_all_traffic_light_items(&self, sandbox_id: i64) -> Result<Vec<TrafficLightItem>, sqlx::Error> {
+        let rows = sqlx::query("SELECT * FROM traffic_light_items WHERE sandbox_id = ? ORDER BY light_id")
+            .bind(sandbox_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(Self::map_traffic_light_item).collect())
     }
 
-    pub async fn update_traffic_light_item(&self, light_id: i32, red_seconds: i32, green_seconds: i32) -> Result<TrafficLightItem, sqlx::Error> {
+    pub async fn update_traffic_light_item(&self, sandbox_id: i64, light_id: i32, red_seconds: i32, green_seconds: i32) -> Result<TrafficLightItem, sqlx::Error> {
         let now = Utc::now().to_rfc3339();
-        let updated = sqlx::query("UPDATE traffic_light_items SET red_light_duration = ?, green_light_duration = ?, updated_at = ? WHERE light_id = ?")
+        let updated = sqlx::query("UPDATE traffic_light_items SET red_light_duration = ?, green_light_duration = ?, updated_at = ? WHERE sandbox_id = ? AND light_id = ?")
             .bind(red_seconds)
             .bind(green_seconds)
             .bind(&now)
+            .bind(sandbox_id)
             .bind(light_id)
             .execute(&self.pool)
             .await?;
         if updated.rows_affected() == 0 {
-            sqlx::query("INSERT INTO traffic_light_items (light_id, red_light_duration, green_light_duration, created_at, updated_at) VALUES (?, ?, ?, ?, ?)")
+            sqlx::query("INSERT INTO traffic_light_items (sandbox_id, light_id, red_light_duration, green_light_duration, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)")
+                .bind(sandbox_id)
                 .bind(light_id)
                 .bind(red_seconds)
                 .bind(green_seconds)
@@ -1092,7 +1252,24 @@ impl VehicleDatabase {
                 .execute(&self.pool)
                 .await?;
         }
-        self.get_traffic_light_item(light_id).await
+        self.get_traffic_light_item(sandbox_id, light_id).await
+    }
+
+    fn map_sandbox_camera(row: &sqlx::sqlite::SqliteRow) -> SandboxCamera {
+        SandboxCamera {
+            id: row.get("id"),
+            sandbox_id: row.get("sandbox_id"),
+            name: row.get("name"),
+            camera_type: row.get("camera_type"),
+            rtsp_url: row.get("rtsp_url"),
+            device_index: row.get("device_index"),
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))
+                .unwrap_or_default()
+                .with_timezone(&chrono::Utc),
+            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))
+                .unwrap_or_default()
+                .with_timezone(&chrono::Utc),
+        }
     }
 
     /// 获取所有沙盘摄像头
@@ -1101,24 +1278,17 @@ impl VehicleDatabase {
             .fetch_all(&self.pool)
             .await?;
 
-        let mut cameras = Vec::new();
-        for row in rows {
-            cameras.push(SandboxCamera {
-                id: row.get("id"),
-                name: row.get("name"),
-                camera_type: row.get("camera_type"),
-                rtsp_url: row.get("rtsp_url"),
-                device_index: row.get("device_index"),
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))
-                    .unwrap_or_default()
-                    .with_timezone(&chrono::Utc),
-                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))
-                    .unwrap_or_default()
-                    .with_timezone(&chrono::Utc),
-            });
-        }
+        Ok(rows.iter().map(Self::map_sandbox_camera).collect())
+    }
 
-        Ok(cameras)
+    /// 获取指定沙盘的摄像头
+    pub async fn get_sandbox_cameras(&self, sandbox_id: i64) -> Result<Vec<SandboxCamera>, sqlx::Error> {
+        let rows = sqlx::query("SELECT * FROM sandbox_cameras WHERE sandbox_id = ? ORDER BY created_at DESC")
+            .bind(sandbox_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::map_sandbox_camera).collect())
     }
 
     /// 创建沙盘摄像头
@@ -1130,10 +1300,11 @@ impl VehicleDatabase {
 
         let result = sqlx::query(
             r#"
-            INSERT INTO sandbox_cameras (name, camera_type, rtsp_url, device_index, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO sandbox_cameras (sandbox_id, name, camera_type, rtsp_url, device_index, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             "#
         )
+        .bind(request.sandbox_id)
         .bind(&request.name)
         .bind(&request.camera_type)
         .bind(&request.rtsp_url)
@@ -1145,6 +1316,7 @@ impl VehicleDatabase {
 
         Ok(SandboxCamera {
             id: result.last_insert_rowid(),
+            sandbox_id: request.sandbox_id,
             name: request.name,
             camera_type: request.camera_type,
             rtsp_url: request.rtsp_url,
@@ -1169,6 +1341,7 @@ impl VehicleDatabase {
             .await?;
 
         if let Some(existing) = existing_row {
+            let sandbox_id = request.sandbox_id.or_else(|| existing.get("sandbox_id"));
             let name = request.name.unwrap_or_else(|| existing.get("name"));
             let camera_type = request.camera_type.unwrap_or_else(|| existing.get("camera_type"));
             let rtsp_url = request.rtsp_url.or_else(|| existing.get("rtsp_url"));
@@ -1177,10 +1350,11 @@ impl VehicleDatabase {
             sqlx::query(
                 r#"
                 UPDATE sandbox_cameras 
-                SET name = ?, camera_type = ?, rtsp_url = ?, device_index = ?, updated_at = ?
+                SET sandbox_id = ?, name = ?, camera_type = ?, rtsp_url = ?, device_index = ?, updated_at = ?
                 WHERE id = ?
                 "#
             )
+            .bind(sandbox_id)
             .bind(&name)
             .bind(&camera_type)
             .bind(&rtsp_url)
@@ -1192,6 +1366,7 @@ impl VehicleDatabase {
 
             Ok(Some(SandboxCamera {
                 id,
+                sandbox_id,
                 name,
                 camera_type,
                 rtsp_url,
@@ -1286,6 +1461,18 @@ impl VehicleDatabase {
         })
     }
 
+    /// 初始化默认应用设置（如果不存在）
+    async fn init_default_app_settings(&self) -> Result<(), sqlx::Error> {
+        let cnt: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM app_settings").fetch_one(&self.pool).await?;
+        if cnt == 0 {
+            let now = Utc::now().to_rfc3339();
+            sqlx::query(
+                r#"INSERT INTO app_settings (log_level, cache_size, auto_start, app_title, coordinate_offset_x, coordinate_offset_y, created_at, updated_at) VALUES ('INFO', 512, 0, '渡众智能沙盘云控平台', 0.0, 0.0, ?, ?)"#
+            ).bind(&now).bind(&now).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
     /// 初始化默认菜单可见性设置
     async fn init_default_menu_visibility_settings(&self) -> Result<(), sqlx::Error> {
         let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM menu_visibility_settings")
@@ -1317,3 +1504,85 @@ impl VehicleDatabase {
         Ok(())
     }
 }
+
+const TAXI_ORDER_COLUMNS: &str = "id, order_id, start_x, start_y, end_x, end_y, assigned_vehicle_id, status, \
+     scheduled_at, priority, queue_position, dispatch_strategy, created_at, updated_at";
+
+const TAXI_ORDER_TRANSITION_COLUMNS: &str =
+    "id, order_id, from_status, to_status, vehicle_id, nav_status, reason, created_at";
+
+fn vehicle_connection_from_row(row: &SqliteRow) -> VehicleConnection {
+    let auth_key: Option<String> = row.get("auth_key");
+    VehicleConnection {
+        id: row.get("id"),
+        vehicle_id: row.get("vehicle_id"),
+        ip_address: row.get("ip_address"),
+        name: row.get("name"),
+        description: row.get("description"),
+        color: row.get("color"),
+        is_active: row.get("is_active"),
+        has_auth_key: auth_key.is_some(),
+        auth_key,
+        cert_fingerprint: row.get("cert_fingerprint"),
+        created_at: row.get::<String, _>("created_at").parse().unwrap(),
+        updated_at: row.get::<String, _>("updated_at").parse().unwrap(),
+    }
+}
+
+fn taxi_order_from_row(row: &SqliteRow) -> TaxiOrder {
+    TaxiOrder {
+        id: row.get("id"),
+        order_id: row.get("order_id"),
+        start_x: row.get("start_x"),
+        start_y: row.get("start_y"),
+        end_x: row.get("end_x"),
+        end_y: row.get("end_y"),
+        assigned_vehicle_id: row.get("assigned_vehicle_id"),
+        status: row.get("status"),
+        scheduled_at: row.get("scheduled_at"),
+        priority: row.get("priority"),
+        queue_position: row.get("queue_position"),
+        dispatch_strategy: row.get("dispatch_strategy"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn taxi_order_transition_from_row(row: &SqliteRow) -> TaxiOrderTransition {
+    TaxiOrderTransition {
+        id: row.get("id"),
+        order_id: row.get("order_id"),
+        from_status: row.get("from_status"),
+        to_status: row.get("to_status"),
+        vehicle_id: row.get("vehicle_id"),
+        nav_status: row.get("nav_status"),
+        reason: row.get("reason"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// 去除认证密钥首尾空白，空字符串视为未设置
+fn normalize_auth_key(auth_key: Option<String>) -> Option<String> {
+    auth_key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// 证书指纹统一为小写十六进制（允许冒号/空格分隔），空字符串视为未设置
+fn normalize_cert_fingerprint(fingerprint: Option<String>) -> Option<String> {
+    fingerprint
+        .map(|fp| {
+            fp.chars()
+                .filter(|c| !matches!(c, ':' | ' '))
+                .collect::<String>()
+                .to_ascii_lowercase()
+        })
+        .filter(|fp| !fp.is_empty())
+}
+
+/// 将 RFC3339 时间换算为遥测表 received_at 的存储格式（UTC、毫秒精度）
+fn normalize_telemetry_time(value: &str) -> Result<String, sqlx::Error> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc).to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .map_err(|e| sqlx::Error::Protocol(format!("无效的时间: {} ({})", value, e)))
+}
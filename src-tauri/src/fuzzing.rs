@@ -0,0 +1,148 @@
+//! 协议解码器模糊测试入口（`fuzzing` feature 或单元测试下编译）
+//!
+//! 每个函数接收任意字节并驱动一个处理网络输入的解码器，供 `fuzz/fuzz_targets` 下的
+//! cargo-fuzz 目标调用。除不崩溃外，还断言解码结果重新编码后能得到相同的内容。
+
+use crate::protocol_processing::converter::{DataConverter, StreamProcessor, VehicleInfoRaw};
+use crate::protocol_processing::{ProtocolParser as PayloadParser, ProtocolSchema};
+use crate::socket::protocol::{encode_message, ProtocolParser as FrameParser};
+use crate::udp_video::protocol::{FrameAssembler, VideoPacket};
+use std::mem;
+use zerocopy::little_endian::U64;
+
+/// 车辆 Socket 帧流：首字节决定每次读取的块大小，覆盖跨读取边界的重新同步
+pub fn socket_stream(data: &[u8]) {
+    let Some((&chunk, stream)) = data.split_first() else {
+        return;
+    };
+    let mut parser = FrameParser::new();
+    for piece in stream.chunks(usize::from(chunk.max(1))) {
+        parser.feed_data(piece);
+        while let Some(message) = parser.next_message() {
+            let frame = encode_message(message.version, message.message_type, &message.data, message.timestamp);
+            let mut again = FrameParser::new();
+            again.feed_data(&frame);
+            let decoded = again.next_message().expect("重新编码的帧无法解析");
+            assert_eq!(
+                (decoded.version, decoded.message_type, decoded.timestamp, decoded.data),
+                (message.version, message.message_type, message.timestamp, message.data)
+            );
+        }
+    }
+    assert!(parser.counters().snapshot().bytes_discarded <= stream.len() as u64);
+}
+
+/// 数据域解析：首字节选择协议定义，次字节决定是否严格验证，其余为数据域
+///
+/// 能解析为结构体的数据域，按同一定义重新构建后必须解析出相同的结构体
+pub fn message_payload(data: &[u8]) {
+    let [selector, flags, payload @ ..] = data else {
+        return;
+    };
+    let schema = ProtocolSchema::global();
+    let message = &schema.messages()[usize::from(*selector) % schema.messages().len()];
+
+    if let Some(message_type) = message.receive {
+        let mut parser = PayloadParser::with_version(flags & 1 != 0, message.version);
+        let result = parser.parse_protocol(message_type, payload);
+        assert_eq!(result.success, result.data.is_some());
+    }
+
+    let Some(parsed) = message.decode(payload).ok().and_then(|record| message.to_parsed(record).ok()) else {
+        return;
+    };
+    let (rebuilt_schema, record) = schema.parsed_record(&parsed, message.version).expect("解析结果无法转回原始值");
+    assert_eq!(rebuilt_schema.name, message.name);
+    let bytes = rebuilt_schema.encode(&record).expect("解析结果无法重新构建");
+    let reparsed = message
+        .decode(&bytes)
+        .and_then(|record| message.to_parsed(record))
+        .expect("重新构建的数据域无法解析");
+    assert_eq!(serde_json::to_value(&parsed).ok(), serde_json::to_value(&reparsed).ok());
+}
+
+/// UDP 视频包：解析成功的包重新序列化后内容不变，单片帧可直接重组
+pub fn udp_packet(data: &[u8]) {
+    let Ok(packet) = VideoPacket::from_udp_packet(data) else {
+        return;
+    };
+    assert!(packet.header.fragment_index < packet.header.total_fragments);
+    let again = VideoPacket::from_udp_packet(&packet.to_udp_packet()).expect("重新序列化的包无法解析");
+    assert_eq!(again.data, packet.data);
+    assert_eq!(
+        (again.header.frame_id, again.header.fragment_index, again.header.total_fragments, again.header.timestamp),
+        (packet.header.frame_id, packet.header.fragment_index, packet.header.total_fragments, packet.header.timestamp)
+    );
+
+    let mut assembler = FrameAssembler::new(&packet.header);
+    if assembler.add_fragment(&packet.header, packet.data.clone()) {
+        assert_eq!(assembler.assemble_frame(), Some(packet.data));
+    }
+}
+
+/// 零拷贝转换：任意长度与偏移的输入只能得到长度错误或与逐字节解码一致的结果
+pub fn zero_copy_convert(data: &[u8]) {
+    let mut converter = DataConverter::new();
+    if let Ok(raw) = converter.zero_copy_convert::<VehicleInfoRaw>(data) {
+        let raw = *raw;
+        assert_eq!(raw.vehicle_id, data[0]);
+        assert_eq!(raw.parking_slot, data[mem::size_of::<VehicleInfoRaw>() - 1]);
+    }
+    if let Ok(info) = converter.safe_convert_vehicle_info(data) {
+        let info = converter.convert_raw_to_vehicle_info(&info);
+        assert!(info.speed.is_finite() && info.battery.is_finite() && info.steering_angle.is_finite());
+    }
+
+    // 视图不要求对齐：任意偏移都应与逐字节解码一致
+    for offset in 0..data.len().min(mem::size_of::<u64>()) {
+        let bytes = &data[offset..];
+        if let Ok(value) = converter.zero_copy_convert::<U64>(bytes) {
+            let expected = u64::from_le_bytes(bytes[..mem::size_of::<u64>()].try_into().expect("长度已校验"));
+            assert_eq!(value.get(), expected);
+        }
+        if let Ok(info) = converter.convert_vehicle_info(bytes) {
+            assert_eq!(info.vehicle_id, bytes[0]);
+        }
+    }
+
+    let count = data.len() / mem::size_of::<VehicleInfoRaw>();
+    if let Ok(records) = converter.memory_map_convert::<VehicleInfoRaw>(data, count) {
+        assert_eq!(records.len(), count);
+    }
+    assert!(converter.memory_map_convert::<VehicleInfoRaw>(data, usize::MAX).is_err());
+
+    let mut processor = StreamProcessor::new(data.len());
+    let processed = processor.process_stream::<VehicleInfoRaw, _>(data, |_| Ok(()));
+    assert_eq!(processed.ok(), Some(count));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::ProtocolBuilder;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_unknown_gear_round_trips() {
+        // 档位原始值 0 解析为未知档位，重新构建后不能变成空挡
+        let schema = ProtocolSchema::global();
+        let selector = schema.messages().iter().position(|m| m.name == "VEHICLE_INFO").unwrap() as u8;
+        let mut info = ProtocolBuilder::new().build_vehicle_info(&serde_json::from_value(serde_json::json!({
+            "vehicle_id": 1, "speed": 0.5, "position_x": 0.0, "position_y": 0.0, "orientation": 0.0,
+            "battery": 50.0, "gear": "Park", "steering_angle": 0.0, "nav_status": 1,
+            "sensors": { "camera": true, "lidar": true, "gyro": true }, "parking_slot": 0
+        })).unwrap());
+        info[crate::protocol_processing::ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET] = 0;
+        message_payload(&[&[selector, 0][..], &info].concat());
+    }
+
+    proptest! {
+        #[test]
+        fn test_entry_points_never_panic(data in prop::collection::vec(any::<u8>(), 0..256)) {
+            socket_stream(&data);
+            message_payload(&data);
+            udp_packet(&data);
+            zero_copy_convert(&data);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! 无界面服务模式
+//!
+//! 不创建 Tauri 窗口，直接按配置启动车辆 Socket 服务器、UDP 视频服务器和 HLS 服务器，
+//! 事件通过事件流 WebSocket 推送，图形界面可作为其中一个订阅者接入。
+
+use crate::config::AppConfig;
+use crate::database::VehicleDatabase;
+use crate::rtsp_converter::HLSServer;
+use crate::socket::{event_stream, BroadcastEventSink, SocketServer};
+use crate::udp_video::UdpVideoManager;
+use log::{error, info, warn, LevelFilter, Log, Metadata, Record};
+use std::sync::Arc;
+
+/// 事件广播通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 标准输出日志（无界面模式下替代 tauri-plugin-log）
+struct StdoutLogger;
+
+impl Log for StdoutLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // 与界面模式一致，压低 sqlx 查询日志
+        !(metadata.target().starts_with("sqlx::query") && metadata.level() > log::Level::Warn)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            println!(
+                "{} [{} {}] {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StdoutLogger = StdoutLogger;
+
+/// 将数据库中的日志级别映射为 LevelFilter
+fn parse_log_level(level: &str) -> LevelFilter {
+    match level.to_uppercase().as_str() {
+        "TRACE" => LevelFilter::Trace,
+        "DEBUG" => LevelFilter::Debug,
+        "INFO" => LevelFilter::Info,
+        "WARN" | "WARNING" => LevelFilter::Warn,
+        "ERROR" => LevelFilter::Error,
+        _ => LevelFilter::Info,
+    }
+}
+
+/// 安装标准输出日志（模拟器等命令行入口共用）
+pub(crate) fn install_stdout_logger(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/// 启动无界面服务（阻塞直到 Socket 服务器退出）
+pub fn run() {
+    let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("创建异步运行时失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    runtime.block_on(serve());
+}
+
+async fn serve() {
+    let database = VehicleDatabase::new().await;
+
+    let level = match &database {
+        Ok(db) => match db.get_app_settings().await {
+            Ok(settings) => parse_log_level(&settings.log_level),
+            Err(_) => LevelFilter::Info,
+        },
+        Err(_) => LevelFilter::Info,
+    };
+    install_stdout_logger(level);
+
+    info!("🚀 dz-viz-server 以无界面模式启动");
+
+    let database = match database {
+        Ok(db) => {
+            info!("✅ 数据库初始化成功");
+            Some(db)
+        }
+        Err(e) => {
+            error!("❌ 数据库初始化失败，车辆识别与在线时长统计不可用: {}", e);
+            None
+        }
+    };
+
+    let config = AppConfig::global();
+    config.ports.log_config();
+
+    let sink = Arc::new(BroadcastEventSink::new(EVENT_CHANNEL_CAPACITY, database));
+
+    // 事件流服务
+    if let Err(e) =
+        event_stream::start_event_stream_server(config.ports.event_stream_server, sink.clone()).await
+    {
+        warn!("事件流服务未启动，事件将不会对外推送: {}", e);
+    }
+
+    // HLS服务
+    let hls_server = HLSServer::new(config.ports.hls_server, std::env::temp_dir().join("dz_viz_hls"));
+    tokio::spawn(async move {
+        if let Err(e) = hls_server.start().await {
+            error!("❌ HLS服务器启动失败: {}", e);
+        }
+    });
+
+    // UDP视频服务（无界面时不推送视频帧事件）
+    let mut udp_video_manager = UdpVideoManager::new();
+    let udp_bind_addr = config.ports.udp_video_bind_addr();
+    if let Err(e) = udp_video_manager.start_server(&udp_bind_addr, None).await {
+        error!("❌ UDP视频服务器启动失败 {}: {}", udp_bind_addr, e);
+    }
+
+    // 车辆Socket服务（前台运行）
+    let server = SocketServer::new(config.ports.socket_server, sink);
+    if let Err(e) = server.start().await {
+        error!("❌ Socket服务器错误: {}", e);
+    }
+
+    udp_video_manager.stop_server().await;
+}
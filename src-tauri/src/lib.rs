@@ -1,30 +1,66 @@
+// 无界面构建（--no-default-features）不编译前端命令，仅供命令使用的数据库接口和模型会成为死代码
+#![cfg_attr(not(feature = "gui"), allow(dead_code, unused_imports))]
+
+#[cfg(feature = "gui")]
 use local_ip_address::local_ip;
+#[cfg(feature = "gui")]
 use log::{debug, error, info, warn};
+#[cfg(feature = "gui")]
 use tauri::Manager;
+#[cfg(feature = "gui")]
 use tauri_plugin_log;
 
+#[cfg(feature = "gui")]
 mod commands;
 mod config;
 mod database;
 mod error;
+mod headless;
 mod protocol_processing;
 mod rtsp_converter;
 mod rtsp_stream;
 mod services;
+mod simulator;
 mod socket;
 mod udp_video;
 mod video_processing;
 mod mse_streamer;
 mod utils;
 
+#[cfg(test)]
+mod test_support;
+
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod fuzzing;
+
+/// 基准测试（benches/）使用的协议处理接口，不属于公开 API
+#[doc(hidden)]
+pub mod bench_support {
+    pub use crate::protocol_processing::converter::{DataConverter, StreamProcessor};
+    pub use crate::protocol_processing::types::{GearPosition, MessageTypes, ProtocolConstants, SensorStatus, VehicleInfo};
+    pub use crate::protocol_processing::wire::VehicleInfoRaw;
+    pub use crate::protocol_processing::{ProtocolBuilder, ProtocolParser};
+}
+
+pub use headless::run as run_headless;
+pub use simulator::run as run_simulator;
+
+#[cfg(feature = "gui")]
 use commands::protocol_processing::ProtocolProcessorState;
+#[cfg(feature = "gui")]
 use commands::*;
+#[cfg(feature = "gui")]
 use commands::protocol_config::*;
+#[cfg(feature = "gui")]
 use config::{get_app_config, get_port_config};
+#[cfg(feature = "gui")]
 use database::VehicleDatabase;
+#[cfg(feature = "gui")]
 use std::sync::Arc;
 
 /// 获取网络状态信息
+#[cfg(feature = "gui")]
 #[tauri::command]
 async fn get_network_status() -> Result<serde_json::Value, String> {
     match local_ip() {
@@ -55,6 +91,7 @@ async fn get_network_status() -> Result<serde_json::Value, String> {
 }
 
 /// 判断是否为私有IP地址
+#[cfg(feature = "gui")]
 fn is_private_ip(ip: &str) -> bool {
     if let Ok(addr) = ip.parse::<std::net::Ipv4Addr>() {
         let octets = addr.octets();
@@ -93,6 +130,7 @@ fn is_private_ip(ip: &str) -> bool {
 
 // 应用设置命令已移动到 commands/settings.rs
 
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // 预读取数据库中的应用设置，用于在日志插件初始化之前配置日志级别、最大文件大小和开机启动
@@ -171,7 +209,12 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(socket::ConnectionManager::default())
-        .manage(Arc::new(parking_lot::RwLock::new(None)) as socket::SandboxConnectionManager)
+        .manage(Arc::new(parking_lot::RwLock::new(std::collections::HashMap::new())) as socket::SandboxConnectionManager)
+        .manage(socket::SessionCapture::default())
+        .manage(services::fleet_state::FleetState::default())
+        .manage(services::parking_lot::ParkingLot::default())
+        .manage(socket::ReplayController::default())
+        .manage(simulator::SimulatorController::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             open_folder,
@@ -203,11 +246,25 @@ pub fn run() {
             update_traffic_light_settings,
             broadcast_taxi_order,
             send_taxi_order_to_vehicle,
+            get_taxi_orders,
+            get_taxi_order_history,
+            cancel_taxi_order,
+            dispatch_taxi_order,
+            enqueue_taxi_order,
+            get_taxi_order_queue,
+            reorder_taxi_order_queue,
+            get_parking_slots,
+            save_parking_slot,
+            delete_parking_slot,
             send_avp_parking,
             send_avp_pickup,
             get_vehicle_online_stats,
+            get_vehicle_telemetry,
             get_driving_behavior_stats,
             get_sandbox_service_settings,
+            get_all_sandbox_service_settings,
+            create_sandbox_service_settings,
+            get_connected_sandboxes,
             create_or_update_sandbox_service_settings,
             delete_sandbox_service_settings,
             get_all_sandbox_cameras,
@@ -271,13 +328,38 @@ pub fn run() {
             // 车辆状态管理命令
             is_vehicle_state_changed,
             batch_check_vehicle_states,
+            get_fleet_state,
+            get_vehicle_state,
+            subscribe_fleet_state,
+            unsubscribe_fleet_state,
             // 批量操作命令
             batch_send_to_vehicles,
             batch_broadcast_to_vehicles,
             // 路径数据命令
             get_merged_path_data,
             get_loaded_paths_info,
-            reload_all_paths
+            reload_all_paths,
+            // 会话抓包与回放命令
+            start_session_capture,
+            stop_session_capture,
+            start_pcapng_capture,
+            stop_pcapng_capture,
+            export_wireshark_dissector,
+            load_replay_session,
+            play_replay,
+            pause_replay,
+            step_replay,
+            seek_replay,
+            set_replay_speed,
+            stop_replay,
+            get_replay_status,
+            // 内置模拟器命令
+            start_vehicle_simulator,
+            stop_vehicle_simulator,
+            get_vehicle_simulator_status,
+            start_sandbox_simulator,
+            stop_sandbox_simulator,
+            get_sandbox_simulator_status
         ])
         .setup(move |app| {
             info!("应用启动: {}", env!("CARGO_PKG_NAME"));
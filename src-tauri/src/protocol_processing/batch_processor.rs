@@ -35,6 +35,10 @@ pub struct BatchProcessingStats {
     pub total_time_us: u64,
     /// 平均处理时间（微秒）
     pub average_time_us: f64,
+    /// 平均延迟（微秒，提交到处理完成，含排队等待）
+    pub average_latency_us: f64,
+    /// 最大延迟（微秒）
+    pub max_latency_us: u64,
     /// 吞吐量（任务/秒）
     pub throughput: f64,
     /// 并行效率
@@ -63,6 +67,19 @@ pub enum TaskPriority {
     Critical = 3,
 }
 
+impl TaskPriority {
+    /// 实时链路中各上行消息类型的处理优先级
+    pub fn for_message(message_type: u16) -> Self {
+        match message_type {
+            // 指令确认决定是否重发控制指令，最先处理
+            MessageTypes::COMMAND_ACK => TaskPriority::Critical,
+            MessageTypes::VEHICLE_INFO => TaskPriority::High,
+            MessageTypes::HEARTBEAT => TaskPriority::Low,
+            _ => TaskPriority::Normal,
+        }
+    }
+}
+
 /// 批处理结果
 #[derive(Debug, Clone)]
 pub struct BatchTaskResult {
@@ -98,6 +115,8 @@ impl BatchProcessor {
                 failed_tasks: 0,
                 total_time_us: 0,
                 average_time_us: 0.0,
+                average_latency_us: 0.0,
+                max_latency_us: 0,
                 throughput: 0.0,
                 parallel_efficiency: 0.0,
             })),
@@ -459,6 +478,8 @@ impl BatchProcessor {
             failed_tasks: 0,
             total_time_us: 0,
             average_time_us: 0.0,
+            average_latency_us: 0.0,
+            max_latency_us: 0,
             throughput: 0.0,
             parallel_efficiency: 0.0,
         };
@@ -1,8 +1,11 @@
 //! 协议构建器
 //! 
-//! 提供高效的二进制协议构建功能，支持零拷贝协议数据生成
+//! 按 `messages.toml` 的协议定义生成各类型数据域，另提供车辆控制指令的零拷贝构建
 
+use crate::protocol_processing::schema::ProtocolSchema;
 use crate::protocol_processing::types::*;
+use crate::protocol_processing::wire::{VehicleControlRaw, VehicleControlWithPositionRaw, WireLayout};
+use serde::Serialize;
 
 /// 协议构建器
 pub struct ProtocolBuilder {
@@ -36,198 +39,126 @@ impl ProtocolBuilder {
         }
     }
     
-    /// 构建车辆控制协议
-    pub fn build_vehicle_control(&mut self, command: &VehicleControlCommand) -> Vec<u8> {
+    /// 按协议定义构建数据域
+    pub fn build_message<T: Serialize>(&mut self, name: &str, version: u8, value: &T) -> Result<Vec<u8>, ProtocolError> {
         let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        // 写入车辆ID
-        self.buffer.push(command.vehicle_id);
-        
-        // 写入控制指令
-        self.buffer.push(command.command.to_u8());
-        
-        // 如果有位置数据，写入位置信息
-        if let Some(position) = &command.position_data {
-            self.write_f64_le(position.x);
-            self.write_f64_le(position.y);
-            self.write_f64_le(position.orientation);
-        }
-        
+        let schema = ProtocolSchema::global()
+            .by_name(name, version)
+            .ok_or_else(|| ProtocolError::FieldError(format!("未定义的消息: {}", name)))?;
+        self.buffer = schema.encode(&schema.record_from(value)?)?;
         self.update_stats(start_time);
-        self.buffer.clone()
+        Ok(self.buffer.clone())
+    }
+
+    /// 构建解析结果对应的数据域
+    pub fn build_parsed(&mut self, data: &ParsedProtocolData) -> Result<Vec<u8>, ProtocolError> {
+        let start_time = current_timestamp_us();
+        let (schema, record) = ProtocolSchema::global().parsed_record(data, ProtocolConstants::PROTOCOL_VERSION_V10)?;
+        self.buffer = schema.encode(&record)?;
+        self.update_stats(start_time);
+        Ok(self.buffer.clone())
+    }
+
+    /// 构建结构体与协议定义一致的内置消息（schema 测试覆盖，失败时记录错误并返回空数据域）
+    fn build_builtin<T: Serialize>(&mut self, name: &str, version: u8, value: &T) -> Vec<u8> {
+        self.build_message(name, version, value).unwrap_or_else(|e| {
+            log::error!("构建 {} 数据域失败: {}", name, e);
+            Vec::new()
+        })
+    }
+
+    /// 构建车辆控制协议
+    pub fn build_vehicle_control(&mut self, command: &VehicleControlCommand) -> Vec<u8> {
+        self.build_builtin("VEHICLE_CONTROL", ProtocolConstants::PROTOCOL_VERSION_V10, command)
     }
     
+    /// 构建车辆信息协议（车端上报，供模拟器使用）
+    pub fn build_vehicle_info(&mut self, info: &VehicleInfo) -> Vec<u8> {
+        self.build_builtin("VEHICLE_INFO", ProtocolConstants::PROTOCOL_VERSION_V10, info)
+    }
+
     /// 构建出租车订单协议
     pub fn build_taxi_order(&mut self, order: &TaxiOrderData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        self.buffer.push(order.vehicle_id);
-        self.write_f64_le(order.start_x);
-        self.write_f64_le(order.start_y);
-        self.write_f64_le(order.end_x);
-        self.write_f64_le(order.end_y);
-        
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("TAXI_ORDER", ProtocolConstants::PROTOCOL_VERSION_V10, order)
     }
     
     /// 构建AVP泊车协议
     pub fn build_avp_parking(&mut self, parking: &AvpParkingData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        self.buffer.push(parking.vehicle_id);
-        self.buffer.push(parking.parking_spot);
-        
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("AVP_PARKING", ProtocolConstants::PROTOCOL_VERSION_V10, parking)
     }
     
     /// 构建AVP取车协议
     pub fn build_avp_pickup(&mut self, pickup: &AvpPickupData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        self.buffer.push(pickup.vehicle_id);
-        
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("AVP_PICKUP", ProtocolConstants::PROTOCOL_VERSION_V10, pickup)
     }
     
     /// 构建数据记录协议
     pub fn build_data_recording(&mut self, recording: &DataRecordingData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        self.buffer.push(recording.vehicle_id);
-        self.buffer.push(recording.action);
-        
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("DATA_RECORDING", ProtocolConstants::PROTOCOL_VERSION_V10, recording)
     }
     
     /// 构建施工标记协议
     pub fn build_construction_marker(&mut self, marker: &ConstructionMarkerData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-        
-        self.buffer.push(marker.marker_id);
-        self.buffer.push(marker.action);
-        self.write_f64_le(marker.x);
-        self.write_f64_le(marker.y);
-        
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("CONSTRUCTION_MARKER", ProtocolConstants::PROTOCOL_VERSION_V10, marker)
     }
 
     /// 构建车辆功能设置协议
     pub fn build_vehicle_function_setting(&mut self, setting: &VehicleFunctionSettingData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-
-        self.buffer.push(setting.vehicle_id);
-        self.buffer.push(setting.function_id);
-        self.buffer.push(setting.enable_status);
-
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("VEHICLE_FUNCTION_SETTING", ProtocolConstants::PROTOCOL_VERSION_V10, setting)
     }
 
     /// 构建车辆路径显示协议
     pub fn build_vehicle_path_display(&mut self, path: &VehiclePathDisplayData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-
-        self.buffer.push(path.vehicle_id);
-        self.buffer.push(path.display_path);
-
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("VEHICLE_PATH_DISPLAY", ProtocolConstants::PROTOCOL_VERSION_V10, path)
     }
 
     /// 构建车辆摄像头开关协议
     pub fn build_vehicle_camera_toggle(&mut self, toggle: &VehicleCameraToggleData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-
-        self.buffer.push(toggle.vehicle_id);
-        self.buffer.push(toggle.enabled);
-
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("VEHICLE_CAMERA_TOGGLE", ProtocolConstants::PROTOCOL_VERSION_V10, toggle)
     }
 
     /// 构建沙盘灯光控制协议（4字节：停车抬杆、环境灯、建筑灯、路灯）
     pub fn build_sandbox_lighting(&mut self, lighting: &SandboxLightingData) -> Vec<u8> {
-        let start_time = current_timestamp_us();
-        self.buffer.clear();
-
-        self.buffer.push(lighting.barrier);   // 停车抬杆
-        self.buffer.push(lighting.ambient);   // 环境灯
-        self.buffer.push(lighting.building);  // 建筑灯
-        self.buffer.push(lighting.street);    // 路灯
-
-        self.update_stats(start_time);
-        self.buffer.clone()
+        self.build_builtin("SANDBOX_LIGHTING_CONTROL", ProtocolConstants::PROTOCOL_VERSION_V10, lighting)
     }
  
-    /// 批量构建协议
+    /// 批量构建协议（只构建服务器下发的消息）
     pub fn batch_build(&mut self, commands: &[ParsedProtocolData]) -> Vec<Vec<u8>> {
         let start_time = current_timestamp_us();
         let mut results = Vec::with_capacity(commands.len());
  
         for command in commands {
-            let data = match command {
-                ParsedProtocolData::VehicleControl(cmd) => self.build_vehicle_control(cmd),
-                ParsedProtocolData::TaxiOrder(order) => self.build_taxi_order(order),
-                ParsedProtocolData::AvpParking(parking) => self.build_avp_parking(parking),
-                ParsedProtocolData::AvpPickup(pickup) => self.build_avp_pickup(pickup),
-                ParsedProtocolData::DataRecording(recording) => self.build_data_recording(recording),
-                ParsedProtocolData::ConstructionMarker(marker) => self.build_construction_marker(marker),
-                ParsedProtocolData::VehicleFunctionSetting(setting) => self.build_vehicle_function_setting(setting),
-                ParsedProtocolData::VehiclePathDisplay(path) => self.build_vehicle_path_display(path),
-                ParsedProtocolData::VehicleCameraToggle(toggle) => self.build_vehicle_camera_toggle(toggle),
-                ParsedProtocolData::SandboxLighting(lighting) => self.build_sandbox_lighting(lighting),
-                _ => continue,
-            };
-            results.push(data);
+            let built = ProtocolSchema::global()
+                .parsed_record(command, ProtocolConstants::PROTOCOL_VERSION_V10)
+                .and_then(|(schema, record)| match schema.send {
+                    Some(_) => schema.encode(&record).map(Some),
+                    None => Ok(None),
+                });
+            match built {
+                Ok(Some(data)) => {
+                    self.stats.protocols_built += 1;
+                    self.stats.total_bytes += data.len() as u64;
+                    results.push(data);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("批量构建跳过无效数据: {}", e),
+            }
         }
  
         self.stats.total_time_us += current_timestamp_us() - start_time;
         results
     }
     
-    /// 零拷贝构建（直接写入目标缓冲区）
+    /// 零拷贝构建（按布局视图直接写入目标缓冲区）
     pub fn zero_copy_build_vehicle_control(&mut self, command: &VehicleControlCommand, target: &mut [u8]) -> Result<usize, ProtocolError> {
-        let required_size = if command.position_data.is_some() {
-            ProtocolConstants::VEHICLE_CONTROL_TOTAL_SIZE_WITH_POSITION
-        } else {
-            ProtocolConstants::VEHICLE_CONTROL_BASE_SIZE
-        };
-        
-        if target.len() < required_size {
-            return Err(ProtocolError::InsufficientData {
-                required: required_size,
-                actual: target.len(),
-            });
-        }
-        
         let start_time = current_timestamp_us();
+        let vehicle_id = command.vehicle_id;
+        let code = command.command.to_u8();
         
-        // 直接写入目标缓冲区
-        target[0] = command.vehicle_id;
-        target[1] = command.command.to_u8();
-        
-        let mut written = 2;
-        
-        if let Some(position) = &command.position_data {
-            self.write_f64_le_to_slice(&mut target[2..10], position.x);
-            self.write_f64_le_to_slice(&mut target[10..18], position.y);
-            self.write_f64_le_to_slice(&mut target[18..26], position.orientation);
-            written = 26;
-        }
+        let written = match &command.position_data {
+            Some(position) => write_view(&VehicleControlWithPositionRaw::new(vehicle_id, code, position), target)?,
+            None => write_view(&VehicleControlRaw { vehicle_id, command: code }, target)?,
+        };
         
         self.stats.protocols_built += 1;
         self.stats.total_time_us += current_timestamp_us() - start_time;
@@ -241,17 +172,6 @@ impl ProtocolBuilder {
         PreAllocatedBuilder::new(capacity)
     }
     
-    /// 写入小端序f64到缓冲区
-    fn write_f64_le(&mut self, value: f64) {
-        self.buffer.extend_from_slice(&value.to_le_bytes());
-    }
-    
-    /// 写入小端序f64到指定切片
-    fn write_f64_le_to_slice(&self, target: &mut [u8], value: f64) {
-        let bytes = value.to_le_bytes();
-        target.copy_from_slice(&bytes);
-    }
-    
     /// 更新统计信息
     fn update_stats(&mut self, start_time: u64) {
         self.stats.protocols_built += 1;
@@ -283,6 +203,16 @@ impl ProtocolBuilder {
     }
 }
 
+/// 把布局视图写入目标缓冲区前缀，返回写入的字节数
+fn write_view<T: WireLayout>(view: &T, target: &mut [u8]) -> Result<usize, ProtocolError> {
+    let actual = target.len();
+    view.write_to_prefix(target).map_err(|_| ProtocolError::InsufficientData {
+        required: std::mem::size_of::<T>(),
+        actual,
+    })?;
+    Ok(std::mem::size_of::<T>())
+}
+
 impl Default for ProtocolBuilder {
     fn default() -> Self {
         Self::new()
@@ -425,6 +355,37 @@ mod tests {
         assert_eq!(data.len(), 26);
     }
     
+    #[test]
+    fn test_build_vehicle_info() {
+        let mut builder = ProtocolBuilder::new();
+
+        let info = VehicleInfo {
+            vehicle_id: 3,
+            speed: 0.25,
+            position_x: 1.5,
+            position_y: 2.5,
+            orientation: 0.0,
+            battery: 85.0,
+            gear: GearPosition::DriveLevel(1),
+            steering_angle: 0.0,
+            nav_status: 7,
+            sensors: SensorStatus {
+                camera: true,
+                lidar: false,
+                gyro: true,
+            },
+            parking_slot: 4,
+        };
+
+        let data = builder.build_vehicle_info(&info);
+        assert_eq!(data.len(), ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE);
+        assert_eq!(data[ProtocolConstants::VEHICLE_INFO_VEHICLE_ID_OFFSET], 3);
+        assert_eq!(data[ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET], 4);
+        assert_eq!(data[ProtocolConstants::VEHICLE_INFO_NAV_STATUS_OFFSET], 7);
+        assert_eq!(data[ProtocolConstants::VEHICLE_INFO_LIDAR_STATUS_OFFSET], 0);
+        assert_eq!(data[ProtocolConstants::VEHICLE_INFO_PARKING_SLOT_OFFSET], 4);
+    }
+
     #[test]
     fn test_zero_copy_build() {
         let mut builder = ProtocolBuilder::new();
@@ -440,6 +401,16 @@ mod tests {
         assert_eq!(written, 2);
         assert_eq!(target[0], 1);
         assert_eq!(target[1], 1);
+
+        // 带位姿的布局视图与按协议定义构建的数据域一致
+        let init = VehicleControlCommand {
+            vehicle_id: 2,
+            command: ControlCommandType::InitPose,
+            position_data: Some(PositionData { x: 1.5, y: -2.0, orientation: 90.0 }),
+        };
+        let written = builder.zero_copy_build_vehicle_control(&init, &mut target).unwrap();
+        assert_eq!(&target[..written], &builder.build_vehicle_control(&init)[..]);
+        assert!(builder.zero_copy_build_vehicle_control(&init, &mut [0u8; 25]).is_err());
     }
     
     #[test]
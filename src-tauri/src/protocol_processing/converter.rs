@@ -1,15 +1,15 @@
 //! 零拷贝数据转换器
 //! 
-//! 提供高效的数据转换功能，最大化利用零拷贝技术减少内存分配和拷贝操作
+//! 提供高效的数据转换功能，最大化利用零拷贝技术减少内存分配和拷贝操作。
+//! 转换基于 `wire` 模块的小端布局视图：直接借用输入缓冲区，不要求内存对齐，也不使用 unsafe。
 
 use crate::protocol_processing::types::*;
+pub use crate::protocol_processing::wire::VehicleInfoRaw;
 use std::mem;
-use std::slice;
+use zerocopy::{FromBytes, Immutable, KnownLayout, Unaligned};
 
 /// 零拷贝数据转换器
 pub struct DataConverter {
-    /// 内存对齐缓冲区
-    aligned_buffer: Vec<u8>,
     /// 转换统计
     stats: ConversionStats,
 }
@@ -19,7 +19,7 @@ pub struct DataConverter {
 pub struct ConversionStats {
     /// 零拷贝转换次数
     pub zero_copy_conversions: u64,
-    /// 内存拷贝转换次数
+    /// 内存拷贝转换次数（布局视图无对齐要求，不再因地址未对齐而拷贝）
     pub memory_copy_conversions: u64,
     /// 节省的内存拷贝字节数
     pub saved_bytes: u64,
@@ -31,7 +31,6 @@ impl DataConverter {
     /// 创建新的数据转换器
     pub fn new() -> Self {
         Self {
-            aligned_buffer: Vec::with_capacity(4096), // 预分配4KB缓冲区
             stats: ConversionStats {
                 zero_copy_conversions: 0,
                 memory_copy_conversions: 0,
@@ -43,116 +42,52 @@ impl DataConverter {
     
     /// 零拷贝转换字节数组到结构化数据
     /// 
-    /// # Safety
-    /// 此函数使用unsafe代码进行零拷贝转换，调用者需要确保：
-    /// - 输入数据的内存布局与目标类型兼容
-    /// - 数据长度足够
-    /// - 内存对齐正确
-    pub fn zero_copy_convert<T>(&mut self, data: &[u8]) -> Result<&T, ProtocolError> 
+    /// 目标类型须由 `zerocopy` 证明任意字节合法且无对齐要求（见 `wire` 模块），
+    /// 返回的视图直接借用 `data` 的前缀；长度不足时返回错误。
+    pub fn zero_copy_convert<'a, T>(&mut self, data: &'a [u8]) -> Result<&'a T, ProtocolError>
     where
-        T: Sized,
+        T: FromBytes + KnownLayout + Immutable + Unaligned,
     {
-        let start_time = current_timestamp_us();
+        // 单次借用只是长度检查，不计时（计时开销远大于转换本身）；批量接口统计总耗时
+        let (view, _) = T::ref_from_prefix(data).map_err(|_| ProtocolError::InsufficientData {
+            required: mem::size_of::<T>(),
+            actual: data.len(),
+        })?;
         
-        if data.len() < mem::size_of::<T>() {
-            return Err(ProtocolError::InsufficientData {
-                required: mem::size_of::<T>(),
-                actual: data.len(),
-            });
-        }
-        
-        let ptr = data.as_ptr();
-        let alignment = mem::align_of::<T>();
-        
-        // 检查内存对齐
-        if ptr as usize % alignment != 0 {
-            // 如果没有对齐，需要进行内存拷贝到对齐的缓冲区
-            self.aligned_buffer.clear();
-            self.aligned_buffer.extend_from_slice(&data[..mem::size_of::<T>()]);
-            
-            // 确保缓冲区对齐
-            let buffer_ptr = self.aligned_buffer.as_ptr();
-            if buffer_ptr as usize % alignment != 0 {
-                return Err(ProtocolError::AlignmentError {
-                    address: buffer_ptr,
-                    alignment,
-                });
-            }
-            
-            self.stats.memory_copy_conversions += 1;
-            unsafe {
-                let result = &*(buffer_ptr as *const T);
-                self.stats.total_time_us += current_timestamp_us() - start_time;
-                Ok(result)
-            }
-        } else {
-            // 内存已对齐，可以进行零拷贝转换
-            self.stats.zero_copy_conversions += 1;
-            self.stats.saved_bytes += mem::size_of::<T>() as u64;
-            
-            unsafe {
-                let result = &*(ptr as *const T);
-                self.stats.total_time_us += current_timestamp_us() - start_time;
-                Ok(result)
-            }
-        }
+        self.stats.zero_copy_conversions += 1;
+        self.stats.saved_bytes += mem::size_of::<T>() as u64;
+        Ok(view)
     }
     
     /// 批量零拷贝转换
-    pub fn batch_zero_copy_convert<T>(&mut self, data_chunks: &[&[u8]]) -> Result<Vec<*const T>, ProtocolError>
+    pub fn batch_zero_copy_convert<'a, T>(&mut self, data_chunks: &[&'a [u8]]) -> Result<Vec<&'a T>, ProtocolError>
     where
-        T: Sized,
+        T: FromBytes + KnownLayout + Immutable + Unaligned,
     {
-        let start_time = current_timestamp_us();
-        let mut results = Vec::with_capacity(data_chunks.len());
-        
-        for chunk in data_chunks {
-            let converted = self.zero_copy_convert::<T>(chunk)?;
-            results.push(converted as *const T);
-        }
-        
-        self.stats.total_time_us += current_timestamp_us() - start_time;
-        Ok(results)
+        data_chunks
+            .iter()
+            .map(|chunk| self.zero_copy_convert::<T>(chunk))
+            .collect()
     }
     
     /// 安全的数据转换（带验证）
     pub fn safe_convert_vehicle_info(&mut self, data: &[u8]) -> Result<VehicleInfoRaw, ProtocolError> {
-        if data.len() < mem::size_of::<VehicleInfoRaw>() {
-            return Err(ProtocolError::InsufficientData {
-                required: mem::size_of::<VehicleInfoRaw>(),
-                actual: data.len(),
-            });
-        }
-        
-        // 使用零拷贝转换
-        let raw_data = self.zero_copy_convert::<VehicleInfoRaw>(data)?;
-        let raw_data_copy = *raw_data;
+        let raw_data = *self.zero_copy_convert::<VehicleInfoRaw>(data)?;
         
         // 验证数据有效性
-        self.validate_vehicle_info_raw(&raw_data_copy)?;
+        self.validate_vehicle_info_raw(&raw_data)?;
         
-        Ok(raw_data_copy)
+        Ok(raw_data)
     }
     
     /// 转换原始数据到高级结构
     pub fn convert_raw_to_vehicle_info(&self, raw: &VehicleInfoRaw) -> VehicleInfo {
-        VehicleInfo {
-            vehicle_id: raw.vehicle_id,
-            speed: f64::from_le_bytes(raw.speed),
-            position_x: f64::from_le_bytes(raw.position_x),
-            position_y: f64::from_le_bytes(raw.position_y),
-            orientation: f64::from_le_bytes(raw.orientation),
-            battery: f64::from_le_bytes(raw.battery),
-            gear: GearPosition::from_u8(raw.gear),
-            steering_angle: f64::from_le_bytes(raw.steering_angle),
-            nav_status: raw.nav_status,
-            sensors: SensorStatus {
-                camera: raw.camera_status != 0,
-                lidar: raw.lidar_status != 0,
-                gyro: raw.gyro_status != 0,
-            },
-            parking_slot: raw.parking_slot,
-        }
+        raw.to_vehicle_info()
+    }
+    
+    /// 经布局视图直接转换车辆信息
+    pub fn convert_vehicle_info(&mut self, data: &[u8]) -> Result<VehicleInfo, ProtocolError> {
+        Ok(self.zero_copy_convert::<VehicleInfoRaw>(data)?.to_vehicle_info())
     }
     
     /// 批量转换车辆信息
@@ -170,46 +105,31 @@ impl DataConverter {
         Ok(results)
     }
     
-    /// 内存映射转换（用于大数据块）
-    pub fn memory_map_convert<T>(&mut self, data: &[u8], count: usize) -> Result<&[T], ProtocolError>
+    /// 内存映射转换（用于大数据块）：把前 `count` 个元素借用为切片
+    pub fn memory_map_convert<'a, T>(&mut self, data: &'a [u8], count: usize) -> Result<&'a [T], ProtocolError>
     where
-        T: Sized,
+        T: FromBytes + KnownLayout + Immutable + Unaligned,
     {
-        let element_size = mem::size_of::<T>();
-        let required_size = element_size * count;
-        
-        if data.len() < required_size {
-            return Err(ProtocolError::InsufficientData {
+        let required_size = mem::size_of::<T>().saturating_mul(count);
+        let (elements, _) = <[T]>::ref_from_prefix_with_elems(data, count).map_err(|_| {
+            ProtocolError::InsufficientData {
                 required: required_size,
                 actual: data.len(),
-            });
-        }
-        
-        let ptr = data.as_ptr();
-        let alignment = mem::align_of::<T>();
-        
-        if ptr as usize % alignment != 0 {
-            return Err(ProtocolError::AlignmentError {
-                address: ptr,
-                alignment,
-            });
-        }
+            }
+        })?;
         
         self.stats.zero_copy_conversions += 1;
         self.stats.saved_bytes += required_size as u64;
-        
-        unsafe {
-            Ok(slice::from_raw_parts(ptr as *const T, count))
-        }
+        Ok(elements)
     }
     
-    /// 验证原始车辆信息数据
+    /// 验证原始车辆信息数据（NaN 视为越界）
     fn validate_vehicle_info_raw(&self, raw: &VehicleInfoRaw) -> Result<(), ProtocolError> {
-        let speed = f64::from_le_bytes(raw.speed);
-        let battery = f64::from_le_bytes(raw.battery);
-        let steering_angle = f64::from_le_bytes(raw.steering_angle);
+        let speed = raw.speed.get();
+        let battery = raw.battery.get();
+        let steering_angle = raw.steering_angle.get();
         
-        if speed < ProtocolConstants::MIN_SPEED || speed > ProtocolConstants::MAX_SPEED {
+        if !(ProtocolConstants::MIN_SPEED..=ProtocolConstants::MAX_SPEED).contains(&speed) {
             return Err(ProtocolError::ValidationError {
                 field: "speed".to_string(),
                 value: speed,
@@ -218,7 +138,7 @@ impl DataConverter {
             });
         }
         
-        if battery < ProtocolConstants::MIN_BATTERY || battery > ProtocolConstants::MAX_BATTERY {
+        if !(ProtocolConstants::MIN_BATTERY..=ProtocolConstants::MAX_BATTERY).contains(&battery) {
             return Err(ProtocolError::ValidationError {
                 field: "battery".to_string(),
                 value: battery,
@@ -227,7 +147,7 @@ impl DataConverter {
             });
         }
         
-        if steering_angle < ProtocolConstants::MIN_STEERING_ANGLE || steering_angle > ProtocolConstants::MAX_STEERING_ANGLE {
+        if !(ProtocolConstants::MIN_STEERING_ANGLE..=ProtocolConstants::MAX_STEERING_ANGLE).contains(&steering_angle) {
             return Err(ProtocolError::ValidationError {
                 field: "steering_angle".to_string(),
                 value: steering_angle,
@@ -271,25 +191,6 @@ impl Default for DataConverter {
     }
 }
 
-/// 原始车辆信息数据结构（用于零拷贝转换）
-#[repr(C, packed)]
-#[derive(Debug, Clone, Copy)]
-pub struct VehicleInfoRaw {
-    pub vehicle_id: u8,
-    pub speed: [u8; 8],        // f64 as bytes
-    pub position_x: [u8; 8],   // f64 as bytes
-    pub position_y: [u8; 8],   // f64 as bytes
-    pub orientation: [u8; 8],  // f64 as bytes
-    pub battery: [u8; 8],      // f64 as bytes
-    pub gear: u8,
-    pub steering_angle: [u8; 8], // f64 as bytes
-    pub nav_status: u8,
-    pub camera_status: u8,
-    pub lidar_status: u8,
-    pub gyro_status: u8,
-    pub parking_slot: u8,
-}
-
 /// 高效的数据流处理器
 pub struct StreamProcessor {
     converter: DataConverter,
@@ -306,22 +207,20 @@ impl StreamProcessor {
         }
     }
     
-    /// 流式处理数据
+    /// 流式处理数据（按元素大小切分，末尾不足一个元素的字节不处理）
     pub fn process_stream<T, F>(&mut self, data: &[u8], mut processor: F) -> Result<usize, ProtocolError>
     where
-        T: Sized,
+        T: FromBytes + KnownLayout + Immutable + Unaligned,
         F: FnMut(&T) -> Result<(), ProtocolError>,
     {
         let element_size = mem::size_of::<T>();
-        let mut processed_count = 0;
-        let mut offset = 0;
+        let Some(count) = data.len().checked_div(element_size) else {
+            return Ok(0);
+        };
         
-        while offset + element_size <= data.len() {
-            let chunk = &data[offset..offset + element_size];
-            let converted = self.converter.zero_copy_convert::<T>(chunk)?;
-            processor(converted)?;
-            
-            offset += element_size;
+        let mut processed_count = 0;
+        for element in self.converter.memory_map_convert::<T>(data, count)? {
+            processor(element)?;
             processed_count += 1;
             self.processed_bytes += element_size;
         }
@@ -343,6 +242,8 @@ impl StreamProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol_processing::{ProtocolBuilder, ProtocolParser};
+    use proptest::prelude::*;
     
     #[test]
     fn test_zero_copy_conversion() {
@@ -379,4 +280,53 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 3);
     }
+
+    #[test]
+    fn test_nan_rejected_and_oversized_count() {
+        let mut converter = DataConverter::new();
+        let mut data = vec![0u8; mem::size_of::<VehicleInfoRaw>()];
+        data[1..9].copy_from_slice(&f64::NAN.to_le_bytes());
+        assert!(converter.safe_convert_vehicle_info(&data).is_err());
+        assert!(converter.memory_map_convert::<VehicleInfoRaw>(&data, usize::MAX).is_err());
+    }
+
+    proptest! {
+        /// 构建器按 0x10 布局写出的车辆信息经零拷贝转换还原
+        #[test]
+        fn test_built_vehicle_info_converts_back(
+            vehicle_id in any::<u8>(),
+            speed in ProtocolConstants::MIN_SPEED..=ProtocolConstants::MAX_SPEED,
+            position in (-1.0e6f64..1.0e6, -1.0e6f64..1.0e6, -360.0f64..360.0),
+            battery in ProtocolConstants::MIN_BATTERY..=ProtocolConstants::MAX_BATTERY,
+            gear in 1u8..=9,
+            steering_angle in ProtocolConstants::MIN_STEERING_ANGLE..=ProtocolConstants::MAX_STEERING_ANGLE,
+            flags in any::<(u8, bool, bool, bool, u8)>(),
+        ) {
+            let info = VehicleInfo {
+                vehicle_id,
+                speed,
+                position_x: position.0,
+                position_y: position.1,
+                orientation: position.2,
+                battery,
+                gear: GearPosition::from_u8(gear),
+                steering_angle,
+                nav_status: flags.0,
+                sensors: SensorStatus { camera: flags.1, lidar: flags.2, gyro: flags.3 },
+                parking_slot: flags.4,
+            };
+            let bytes = ProtocolBuilder::new().build_vehicle_info(&info);
+            prop_assert_eq!(bytes.len(), mem::size_of::<VehicleInfoRaw>());
+
+            let mut converter = DataConverter::new();
+            let raw = converter.safe_convert_vehicle_info(&bytes).unwrap();
+            let converted = converter.convert_raw_to_vehicle_info(&raw);
+            prop_assert_eq!(serde_json::to_value(converted).unwrap(), serde_json::to_value(&info).unwrap());
+
+            // 实时处理的布局视图转换与按协议定义解码结果一致
+            let viewed = ParsedProtocolData::VehicleInfo(converter.convert_vehicle_info(&bytes).unwrap());
+            let decoded = ProtocolParser::new(false).parse_protocol(MessageTypes::VEHICLE_INFO, &bytes).data.unwrap();
+            prop_assert_eq!(serde_json::to_value(viewed).unwrap(), serde_json::to_value(decoded).unwrap());
+        }
+    }
 }
@@ -1,6 +1,8 @@
 //! 消息类型配置导出模块
-//! 提供统一的消息类型定义，并可导出为JSON供前端使用
+//! 提供统一的消息类型定义，并可导出为JSON供前端使用（消息布局由 `messages.toml` 生成）
 
+use super::schema::{MessageSchema, ProtocolSchema};
+use super::types::ProtocolConstants;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -13,28 +15,8 @@ pub struct MessageTypesConfig {
     pub send_message_types: HashMap<String, u16>,
     /// 协议常量
     pub protocol_constants: ProtocolConstantsConfig,
-    /// 车辆信息协议定义
-    pub vehicle_info_protocol: VehicleInfoProtocolConfig,
-    /// 车辆控制协议定义
-    pub vehicle_control_protocol: VehicleControlProtocolConfig,
-    /// 数据记录协议定义
-    pub data_recording_protocol: DataRecordingProtocolConfig,
-    /// 出租车订单协议定义
-    pub taxi_order_protocol: TaxiOrderProtocolConfig,
-    /// AVP停车协议定义
-    pub avp_parking_protocol: AvpParkingProtocolConfig,
-    /// AVP取车协议定义
-    pub avp_pickup_protocol: AvpPickupProtocolConfig,
-    /// 车辆功能设置协议定义
-    pub vehicle_function_setting_protocol: VehicleFunctionSettingProtocolConfig,
-    /// 车辆路径显示协议定义
-    pub vehicle_path_display_protocol: VehiclePathDisplayProtocolConfig,
-    /// 车辆摄像头协议定义
-    pub vehicle_camera_protocol: VehicleCameraProtocolConfig,
-    /// 施工标记协议定义
-    pub construction_marker_protocol: ConstructionMarkerProtocolConfig,
-    /// 沙盘灯光控制协议定义
-    pub sandbox_lighting_protocol: SandboxLightingProtocolConfig,
+    /// 各消息数据域布局（字段偏移、类型、取值范围与含义）
+    pub messages: Vec<MessageSchema>,
     /// 导航状态文本映射
     pub nav_status_texts: HashMap<String, String>,
 }
@@ -55,188 +37,30 @@ pub struct ProtocolConstantsConfig {
     pub min_packet_size: usize,
 }
 
-/// 车辆信息协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VehicleInfoProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub speed_offset: usize,
-    pub position_x_offset: usize,
-    pub position_y_offset: usize,
-    pub orientation_offset: usize,
-    pub battery_offset: usize,
-    pub gear_offset: usize,
-    pub steering_angle_offset: usize,
-    pub nav_status_offset: usize,
-    pub camera_status_offset: usize,
-    pub lidar_status_offset: usize,
-    pub gyro_status_offset: usize,
-    pub parking_slot_offset: usize,
-    pub total_size: usize,
-    pub min_speed: f64,
-    pub max_speed: f64,
-}
-
-/// 车辆控制协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VehicleControlProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub control_command_offset: usize,
-    pub position_x_offset: usize,
-    pub position_y_offset: usize,
-    pub orientation_offset: usize,
-    pub base_size: usize,
-    pub position_data_size: usize,
-    pub total_size_with_position: usize,
-    pub total_size_without_position: usize,
-    pub command_start: u8,
-    pub command_stop: u8,
-    pub command_emergency_brake: u8,
-    pub command_init_pose: u8,
-    pub command_names: HashMap<String, String>,
-}
-
-/// 数据记录协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DataRecordingProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub recording_status_offset: usize,
-    pub total_size: usize,
-    pub recording_off: u8,
-    pub recording_on: u8,
-    pub status_names: HashMap<String, String>,
-}
-
-/// 出租车订单协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TaxiOrderProtocolConfig {
-    pub order_id_offset: usize,
-    pub start_x_offset: usize,
-    pub start_y_offset: usize,
-    pub end_x_offset: usize,
-    pub end_y_offset: usize,
-    pub total_size: usize,
-    pub default_start_x: f64,
-    pub default_start_y: f64,
-    pub default_end_x: f64,
-    pub default_end_y: f64,
-}
-
-/// AVP停车协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AvpParkingProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub parking_spot_offset: usize,
-    pub total_size: usize,
-    pub default_parking_spot: u8,
-}
-
-/// AVP取车协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AvpPickupProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub total_size: usize,
-}
-
-/// 车辆功能设置协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VehicleFunctionSettingProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub function_id_offset: usize,
-    pub enable_status_offset: usize,
-    pub total_size: usize,
-    pub function_all: u8,
-    pub function_sensors: u8,
-    pub function_mapping: u8,
-    pub function_recording: u8,
-    pub function_positioning: u8,
-    pub function_autonomous_nav: u8,
-    pub function_image_recognition: u8,
-    pub function_target_shooting: u8,
-    pub status_disable: u8,
-    pub status_enable: u8,
-}
-
-/// 车辆路径显示协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VehiclePathDisplayProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub display_path_offset: usize,
-    pub total_size: usize,
-    pub path_disable: u8,
-    pub path_enable: u8,
-}
-
-/// 车辆摄像头协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VehicleCameraProtocolConfig {
-    pub vehicle_id_offset: usize,
-    pub enable_status_offset: usize,
-    pub total_size: usize,
-    pub status_off: u8,
-    pub status_on: u8,
-}
-
-/// 施工标记协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConstructionMarkerProtocolConfig {
-    pub id_offset: usize,
-    pub position_x_offset: usize,
-    pub position_y_offset: usize,
-    pub action_offset: usize,
-    pub total_size: usize,
-    pub action_cancel: u8,
-    pub action_set: u8,
-    pub action_names: HashMap<String, String>,
-}
-
-/// 沙盘灯光控制协议配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SandboxLightingProtocolConfig {
-    pub ambient_offset: usize,
-    pub building_offset: usize,
-    pub street_offset: usize,
-    pub total_size: usize,
-    pub status_off: u8,
-    pub status_on: u8,
-}
-
 /// 获取完整的消息类型配置
 pub fn get_message_types_config() -> MessageTypesConfig {
-    use super::types::MessageTypes;
-    
-    let mut receive_types = HashMap::new();
-    receive_types.insert("HEARTBEAT".to_string(), MessageTypes::HEARTBEAT);
-    receive_types.insert("VEHICLE_INFO".to_string(), MessageTypes::VEHICLE_INFO);
-    receive_types.insert("SANDBOX_TRAFFIC_LIGHT_STATUS".to_string(), MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS);
+    let schema = ProtocolSchema::global();
+    let base = schema
+        .messages()
+        .iter()
+        .filter(|m| m.version == ProtocolConstants::PROTOCOL_VERSION_V10);
 
+    let mut receive_types = HashMap::new();
     let mut send_types = HashMap::new();
-    send_types.insert("VEHICLE_CONTROL".to_string(), 0x1001u16);
-    send_types.insert("DATA_RECORDING".to_string(), 0x1002u16);
-    send_types.insert("TAXI_ORDER".to_string(), 0x1003u16);
-    send_types.insert("AVP_PARKING".to_string(), 0x1004u16);
-    send_types.insert("AVP_PICKUP".to_string(), 0x1005u16);
-    send_types.insert("VEHICLE_FUNCTION_SETTING".to_string(), 0x1006u16);
-    send_types.insert("VEHICLE_PATH_DISPLAY".to_string(), 0x1007u16);
-    send_types.insert("CONSTRUCTION_MARKER".to_string(), 0x1008u16);
-    send_types.insert("VEHICLE_CAMERA_TOGGLE".to_string(), 0x1009u16);
-    send_types.insert("SANDBOX_LIGHTING_CONTROL".to_string(), 0x2003u16);
+    for message in base {
+        if let Some(id) = message.receive {
+            receive_types.insert(message.name.clone(), id);
+        }
+        if let Some(id) = message.send {
+            send_types.insert(message.name.clone(), id);
+        }
+    }
 
-    let mut nav_status_texts = HashMap::new();
-    nav_status_texts.insert("1".to_string(), "正常行驶中（空载模式不倒车入库）".to_string());
-    nav_status_texts.insert("2".to_string(), "正常行驶中（空载模式倒车入库）".to_string());
-    nav_status_texts.insert("3".to_string(), "接客模式，去起点接客".to_string());
-    nav_status_texts.insert("4".to_string(), "接客模式，去终点送客".to_string());
-    nav_status_texts.insert("5".to_string(), "去往充电车位".to_string());
-    nav_status_texts.insert("6".to_string(), "充电中".to_string());
-    nav_status_texts.insert("7".to_string(), "去往停车位路上".to_string());
-    nav_status_texts.insert("8".to_string(), "车位停车中".to_string());
-    nav_status_texts.insert("9".to_string(), "到达接客起点".to_string());
-    nav_status_texts.insert("10".to_string(), "到达接客终点".to_string());
-    nav_status_texts.insert("11".to_string(), "正在倒车入库".to_string());
-    nav_status_texts.insert("12".to_string(), "正在出库中".to_string());
-    nav_status_texts.insert("13".to_string(), "正在倒车入库".to_string());
-    nav_status_texts.insert("14".to_string(), "出库完成".to_string());
-    nav_status_texts.insert("15".to_string(), "平行驾驶模式".to_string());
+    let nav_status_texts = schema
+        .by_name("VEHICLE_INFO", ProtocolConstants::PROTOCOL_VERSION_V10)
+        .and_then(|m| m.fields.iter().find(|f| f.name == "nav_status"))
+        .map(|f| f.labels.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
 
     MessageTypesConfig {
         receive_message_types: receive_types,
@@ -256,147 +80,32 @@ pub fn get_message_types_config() -> MessageTypesConfig {
             min_packet_size: 25,
         },
 
-        vehicle_info_protocol: VehicleInfoProtocolConfig {
-            vehicle_id_offset: 0,
-            speed_offset: 1,
-            position_x_offset: 9,
-            position_y_offset: 17,
-            orientation_offset: 25,
-            battery_offset: 33,
-            gear_offset: 41,
-            steering_angle_offset: 42,
-            nav_status_offset: 50,
-            camera_status_offset: 51,
-            lidar_status_offset: 52,
-            gyro_status_offset: 53,
-            parking_slot_offset: 54,
-            total_size: 55,
-            min_speed: 0.0,
-            max_speed: 1.0,
-        },
-
-        vehicle_control_protocol: VehicleControlProtocolConfig {
-            vehicle_id_offset: 0,
-            control_command_offset: 1,
-            position_x_offset: 2,
-            position_y_offset: 10,
-            orientation_offset: 18,
-            base_size: 2,
-            position_data_size: 24,
-            total_size_with_position: 26,
-            total_size_without_position: 2,
-            command_start: 1,
-            command_stop: 2,
-            command_emergency_brake: 3,
-            command_init_pose: 4,
-            command_names: {
-                let mut map = HashMap::new();
-                map.insert("1".to_string(), "启动".to_string());
-                map.insert("2".to_string(), "停止".to_string());
-                map.insert("3".to_string(), "紧急制动".to_string());
-                map.insert("4".to_string(), "初始化位姿".to_string());
-                map
-            },
-        },
-
-        data_recording_protocol: DataRecordingProtocolConfig {
-            vehicle_id_offset: 0,
-            recording_status_offset: 1,
-            total_size: 2,
-            recording_off: 0,
-            recording_on: 1,
-            status_names: {
-                let mut map = HashMap::new();
-                map.insert("0".to_string(), "关闭".to_string());
-                map.insert("1".to_string(), "开启".to_string());
-                map
-            },
-        },
-
-        taxi_order_protocol: TaxiOrderProtocolConfig {
-            order_id_offset: 0,
-            start_x_offset: 16,
-            start_y_offset: 24,
-            end_x_offset: 32,
-            end_y_offset: 40,
-            total_size: 48,
-            default_start_x: 116.4,
-            default_start_y: 39.9,
-            default_end_x: 118.5,
-            default_end_y: 41.2,
-        },
-
-        avp_parking_protocol: AvpParkingProtocolConfig {
-            vehicle_id_offset: 0,
-            parking_spot_offset: 1,
-            total_size: 2,
-            default_parking_spot: 1,
-        },
-
-        avp_pickup_protocol: AvpPickupProtocolConfig {
-            vehicle_id_offset: 0,
-            total_size: 1,
-        },
-
-        vehicle_function_setting_protocol: VehicleFunctionSettingProtocolConfig {
-            vehicle_id_offset: 0,
-            function_id_offset: 1,
-            enable_status_offset: 2,
-            total_size: 3,
-            function_all: 0,
-            function_sensors: 1,
-            function_mapping: 2,
-            function_recording: 3,
-            function_positioning: 4,
-            function_autonomous_nav: 5,
-            function_image_recognition: 6,
-            function_target_shooting: 7,
-            status_disable: 0,
-            status_enable: 1,
-        },
-
-        vehicle_path_display_protocol: VehiclePathDisplayProtocolConfig {
-            vehicle_id_offset: 0,
-            display_path_offset: 1,
-            total_size: 2,
-            path_disable: 0,
-            path_enable: 1,
-        },
-
-        vehicle_camera_protocol: VehicleCameraProtocolConfig {
-            vehicle_id_offset: 0,
-            enable_status_offset: 1,
-            total_size: 2,
-            status_off: 0,
-            status_on: 1,
-        },
-
-        construction_marker_protocol: ConstructionMarkerProtocolConfig {
-            id_offset: 0,
-            position_x_offset: 1,
-            position_y_offset: 9,
-            action_offset: 17,
-            total_size: 18,
-            action_cancel: 0,
-            action_set: 1,
-            action_names: {
-                let mut map = HashMap::new();
-                map.insert("0".to_string(), "取消".to_string());
-                map.insert("1".to_string(), "设置".to_string());
-                map
-            },
-        },
-
-        sandbox_lighting_protocol: SandboxLightingProtocolConfig {
-            ambient_offset: 0,
-            building_offset: 1,
-            street_offset: 2,
-            total_size: 3,
-            status_off: 0,
-            status_on: 1,
-        },
+        messages: schema.messages().to_vec(),
 
         nav_status_texts,
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_derived_from_schema() {
+        let config = get_message_types_config();
+        assert_eq!(config.send_message_types["SANDBOX_LIGHTING_CONTROL"], 0x2003);
+        assert_eq!(config.receive_message_types["SANDBOX_TRAFFIC_LIGHT_STATUS"], 0x3001);
+        assert_eq!(config.nav_status_texts["15"], "平行驾驶模式");
+
+        let json = serde_json::to_value(&config).unwrap();
+        let marker = json["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["name"] == "CONSTRUCTION_MARKER")
+            .unwrap();
+        assert_eq!(marker["total_size"], 18);
+        assert_eq!(marker["fields"][1]["name"], "action");
+        assert_eq!(marker["fields"][1]["offset"], 1);
+    }
+}
@@ -3,21 +3,28 @@
 //! 提供高性能的二进制协议解析功能，包括：
 //! - 车辆信息协议解析
 //! - 控制指令协议构建
-//! - 零拷贝数据转换
+//! - 零拷贝数据转换（小端布局视图，无 unsafe）
 //! - 批量协议处理
+//! - 实时处理阶段（所有连接共享，按优先级限制并发）
 //! - 协议验证和校验
+//! - 声明式协议定义（messages.toml）
 
 pub mod types;
+pub mod schema;
 pub mod parser;
 pub mod builder;
 pub mod validator;
+pub mod wire;
 pub mod converter;
 pub mod batch_processor;
+pub mod pipeline;
 pub mod message_types_config;
 
 pub use types::*;
+pub use schema::ProtocolSchema;
 pub use parser::ProtocolParser;
 pub use builder::ProtocolBuilder;
 pub use validator::ProtocolValidator;
 pub use converter::DataConverter;
 pub use batch_processor::BatchProcessor;
+pub use pipeline::ProcessingPipeline;
@@ -1,23 +1,31 @@
 //! 高性能二进制协议解析器
 //! 
-//! 提供零拷贝的协议解析功能，支持多种协议类型的高效解析
+//! 按 `messages.toml` 的协议定义解析各类型数据域
 
+use crate::protocol_processing::schema::{MessageSchema, ProtocolSchema};
 use crate::protocol_processing::types::*;
-use crate::utils::byte_utils;
 
 /// 高性能协议解析器
 pub struct ProtocolParser {
     /// 启用严格验证模式
     strict_validation: bool,
+    /// 帧协议版本（决定数据域布局）
+    version: u8,
     /// 性能统计
     stats: ProtocolProcessingStats,
 }
 
 impl ProtocolParser {
-    /// 创建新的协议解析器
+    /// 创建新的协议解析器（基线版本 0x10 的数据域布局）
     pub fn new(strict_validation: bool) -> Self {
+        Self::with_version(strict_validation, ProtocolConstants::PROTOCOL_VERSION_V10)
+    }
+
+    /// 按帧协议版本创建解析器
+    pub fn with_version(strict_validation: bool, version: u8) -> Self {
         Self {
             strict_validation,
+            version,
             stats: ProtocolProcessingStats {
                 parsing_time_us: 0,
                 validation_time_us: 0,
@@ -51,16 +59,12 @@ impl ProtocolParser {
         }
         
         let parsing_start = current_timestamp_us();
-        let result = match message_type {
-            MessageTypes::VEHICLE_INFO => self.parse_vehicle_info(data),
-            MessageTypes::PATH_FILE_SELECTION => self.parse_path_file_selection(data),
-            MessageTypes::TAXI_ORDER => self.parse_taxi_order(data),
-            MessageTypes::AVP_PARKING => self.parse_avp_parking(data),
-            MessageTypes::AVP_PICKUP => self.parse_avp_pickup(data),
-            MessageTypes::DATA_RECORDING => self.parse_data_recording(data),
-            MessageTypes::CONSTRUCTION_MARKER => self.parse_construction_marker(data),
-            MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS => self.parse_sandbox_traffic_light_status(data),
-            _ => {
+        let schema = ProtocolSchema::global()
+            .by_receive(self.version, message_type)
+            .filter(|schema| schema.variant.is_some());
+        let result = match schema {
+            Some(schema) => self.parse_payload(schema, data),
+            None => {
                 // 记录未知消息类型到日志（不发送到前端）
                 log::warn!(
                     "收到未知消息类型: 0x{:04X}, 数据长度: {} 字节",
@@ -90,305 +94,20 @@ impl ProtocolParser {
         }
     }
     
-    /// 解析车辆信息协议
-    fn parse_vehicle_info(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
+    /// 按协议定义解码数据域（严格模式下同时校验取值范围）
+    fn parse_payload(&mut self, schema: &MessageSchema, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
+        let record = schema.decode(data)?;
+
         let validation_start = current_timestamp_us();
-        
-        // 零拷贝解析 - 直接从字节数组读取数据
-        let vehicle_id = data[ProtocolConstants::VEHICLE_INFO_VEHICLE_ID_OFFSET];
-        
-        let speed = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_SPEED_OFFSET)?;
-        let position_x = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_POSITION_X_OFFSET)?;
-        let position_y = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_POSITION_Y_OFFSET)?;
-        let orientation = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_ORIENTATION_OFFSET)?;
-        let battery = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_BATTERY_OFFSET)?;
-        let gear_raw = data[ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET];
-        let gear = GearPosition::from_u8(gear_raw);
-        let steering_angle = self.read_f64_le(data, ProtocolConstants::VEHICLE_INFO_STEERING_ANGLE_OFFSET)?;
-        let nav_status = data[ProtocolConstants::VEHICLE_INFO_NAV_STATUS_OFFSET];
-        
-        // 传感器状态解析
-        let camera_status = data[ProtocolConstants::VEHICLE_INFO_CAMERA_STATUS_OFFSET] != 0;
-        let lidar_status = data[ProtocolConstants::VEHICLE_INFO_LIDAR_STATUS_OFFSET] != 0;
-        let gyro_status = data[ProtocolConstants::VEHICLE_INFO_GYRO_STATUS_OFFSET] != 0;
-        let parking_slot = data[ProtocolConstants::VEHICLE_INFO_PARKING_SLOT_OFFSET];
-        
-        // 数据验证
         if self.strict_validation {
-            self.validate_speed(speed)?;
-            self.validate_battery(battery)?;
-            self.validate_steering_angle(steering_angle)?;
-        } else {
-            // 非严格模式下进行范围限制
-            let _speed = speed.clamp(ProtocolConstants::MIN_SPEED, ProtocolConstants::MAX_SPEED);
-            let _battery = battery.clamp(ProtocolConstants::MIN_BATTERY, ProtocolConstants::MAX_BATTERY);
-            let _steering_angle = steering_angle.clamp(
-                ProtocolConstants::MIN_STEERING_ANGLE, 
-                ProtocolConstants::MAX_STEERING_ANGLE
-            );
+            schema.validate(&record)?;
         }
-        
         self.stats.validation_time_us = current_timestamp_us() - validation_start;
-        
+
         let conversion_start = current_timestamp_us();
-        
-        let vehicle_info = VehicleInfo {
-            vehicle_id,
-            speed,
-            position_x,
-            position_y,
-            orientation,
-            battery,
-            gear,
-            steering_angle,
-            nav_status,
-            sensors: SensorStatus {
-                camera: camera_status,
-                lidar: lidar_status,
-                gyro: gyro_status,
-            },
-            parking_slot,
-        };
-        
+        let parsed = schema.to_parsed(record)?;
         self.stats.conversion_time_us = current_timestamp_us() - conversion_start;
-        
-        Ok(ParsedProtocolData::VehicleInfo(vehicle_info))
-    }
-    
-    /// 解析路径文件选择协议（0x0003）
-    /// 数据格式：车辆编号(1字节) + N个路径文件编号(每个1字节)
-    fn parse_path_file_selection(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        // 至少需要1个字节（车辆编号）
-        if data.is_empty() {
-            return Err(ProtocolError::InsufficientData {
-                required: 1,
-                actual: data.len(),
-            });
-        }
-        
-        // 第一个字节是车辆编号
-        let vehicle_id = data[0];
-        
-        // 剩余的字节都是路径文件编号
-        let path_file_ids: Vec<u8> = data[1..].to_vec();
-        
-        let path_selection = PathFileSelectionData {
-            vehicle_id,
-            path_file_ids: path_file_ids.clone(),
-        };
-        
-        log::info!(
-            "解析路径文件选择 - 车辆ID: {}, 路径文件数: {}, 路径编号: {:?}",
-            vehicle_id,
-            path_selection.path_file_ids.len(),
-            path_selection.path_file_ids
-        );
-        
-        Ok(ParsedProtocolData::PathFileSelection(path_selection))
-    }
-    
-    /// 解析出租车订单协议
-    fn parse_taxi_order(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::TAXI_ORDER_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::TAXI_ORDER_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
-        let vehicle_id = data[ProtocolConstants::TAXI_ORDER_VEHICLE_ID_OFFSET];
-        let start_x = self.read_f64_le(data, ProtocolConstants::TAXI_ORDER_START_X_OFFSET)?;
-        let start_y = self.read_f64_le(data, ProtocolConstants::TAXI_ORDER_START_Y_OFFSET)?;
-        let end_x = self.read_f64_le(data, ProtocolConstants::TAXI_ORDER_END_X_OFFSET)?;
-        let end_y = self.read_f64_le(data, ProtocolConstants::TAXI_ORDER_END_Y_OFFSET)?;
-        
-        let taxi_order = TaxiOrderData {
-            vehicle_id,
-            start_x,
-            start_y,
-            end_x,
-            end_y,
-        };
-        
-        Ok(ParsedProtocolData::TaxiOrder(taxi_order))
-    }
-    
-    /// 解析AVP泊车协议
-    fn parse_avp_parking(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::AVP_PARKING_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::AVP_PARKING_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
-        let vehicle_id = data[ProtocolConstants::AVP_PARKING_VEHICLE_ID_OFFSET];
-        let parking_spot = data[ProtocolConstants::AVP_PARKING_SPOT_OFFSET];
-        
-        let avp_parking = AvpParkingData {
-            vehicle_id,
-            parking_spot,
-        };
-        
-        Ok(ParsedProtocolData::AvpParking(avp_parking))
-    }
-    
-    /// 解析AVP取车协议
-    fn parse_avp_pickup(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::AVP_PICKUP_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::AVP_PICKUP_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
-        let vehicle_id = data[ProtocolConstants::AVP_PICKUP_VEHICLE_ID_OFFSET];
-        
-        let avp_pickup = AvpPickupData { vehicle_id };
-        
-        Ok(ParsedProtocolData::AvpPickup(avp_pickup))
-    }
-    
-    /// 解析数据记录协议
-    fn parse_data_recording(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::DATA_RECORDING_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::DATA_RECORDING_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
-        let vehicle_id = data[ProtocolConstants::DATA_RECORDING_VEHICLE_ID_OFFSET];
-        let action = data[ProtocolConstants::DATA_RECORDING_ACTION_OFFSET];
-        
-        let data_recording = DataRecordingData {
-            vehicle_id,
-            action,
-        };
-        
-        Ok(ParsedProtocolData::DataRecording(data_recording))
-    }
-    
-    /// 解析施工标记协议
-    fn parse_construction_marker(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        if data.len() < ProtocolConstants::CONSTRUCTION_MARKER_TOTAL_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: ProtocolConstants::CONSTRUCTION_MARKER_TOTAL_SIZE,
-                actual: data.len(),
-            });
-        }
-        
-        let marker_id = data[ProtocolConstants::CONSTRUCTION_MARKER_ID_OFFSET];
-        let action = data[ProtocolConstants::CONSTRUCTION_MARKER_ACTION_OFFSET];
-        let x = self.read_f64_le(data, ProtocolConstants::CONSTRUCTION_MARKER_X_OFFSET)?;
-        let y = self.read_f64_le(data, ProtocolConstants::CONSTRUCTION_MARKER_Y_OFFSET)?;
-        
-        let construction_marker = ConstructionMarkerData {
-            marker_id,
-            action,
-            x,
-            y,
-        };
-        
-        Ok(ParsedProtocolData::ConstructionMarker(construction_marker))
-    }
-
-    /// 解析沙盘红绿灯状态协议
-    fn parse_sandbox_traffic_light_status(&mut self, data: &[u8]) -> Result<ParsedProtocolData, ProtocolError> {
-        // 协议格式：每组2字节（1字节颜色 + 1字节倒计时），2组共4字节
-        const BYTES_PER_GROUP: usize = 2;
-        const NUM_GROUPS: usize = 2;
-        const EXPECTED_SIZE: usize = BYTES_PER_GROUP * NUM_GROUPS;
-
-        if data.len() < EXPECTED_SIZE {
-            return Err(ProtocolError::InsufficientData {
-                required: EXPECTED_SIZE,
-                actual: data.len(),
-            });
-        }
-
-        let mut lights = Vec::with_capacity(NUM_GROUPS);
-        
-        // 解析每组数据
-        for i in 0..NUM_GROUPS {
-            let offset = i * BYTES_PER_GROUP;
-            let color = data[offset];
-            let remaining = data[offset + 1];  // 1字节倒计时
-            
-            lights.push(SandboxTrafficLightState {
-                index: i as u8,  // 0 = 1组(6个红绿灯), 1 = 2组(2个红绿灯)
-                color,
-                remaining,
-            });
-        }
-
-        log::info!(
-            "解析红绿灯状态: 组0(1组-6个灯)={}/{}, 组1(2组-2个灯)={}/{}",
-            lights[0].color,
-            lights[0].remaining,
-            lights[1].color,
-            lights[1].remaining
-        );
-
-        Ok(ParsedProtocolData::SandboxTrafficLightStatus(SandboxTrafficLightStatusData { lights }))
-    }
-    
-    /// 读取小端序f64（使用统一工具函数）
-    fn read_f64_le(&self, data: &[u8], offset: usize) -> Result<f64, ProtocolError> {
-        byte_utils::read_f64_le(data, offset)
-            .map_err(|_| ProtocolError::InsufficientData {
-                required: offset + 8,
-                actual: data.len(),
-            })
-    }
-    
-    /// 验证速度范围
-    fn validate_speed(&self, speed: f64) -> Result<(), ProtocolError> {
-        if speed < ProtocolConstants::MIN_SPEED || speed > ProtocolConstants::MAX_SPEED {
-            Err(ProtocolError::ValidationError {
-                field: "speed".to_string(),
-                value: speed,
-                min: ProtocolConstants::MIN_SPEED,
-                max: ProtocolConstants::MAX_SPEED,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证电池电量范围
-    fn validate_battery(&self, battery: f64) -> Result<(), ProtocolError> {
-        if battery < ProtocolConstants::MIN_BATTERY || battery > ProtocolConstants::MAX_BATTERY {
-            Err(ProtocolError::ValidationError {
-                field: "battery".to_string(),
-                value: battery,
-                min: ProtocolConstants::MIN_BATTERY,
-                max: ProtocolConstants::MAX_BATTERY,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证方向盘角度范围
-    fn validate_steering_angle(&self, angle: f64) -> Result<(), ProtocolError> {
-        if angle < ProtocolConstants::MIN_STEERING_ANGLE || angle > ProtocolConstants::MAX_STEERING_ANGLE {
-            Err(ProtocolError::ValidationError {
-                field: "steering_angle".to_string(),
-                value: angle,
-                min: ProtocolConstants::MIN_STEERING_ANGLE,
-                max: ProtocolConstants::MAX_STEERING_ANGLE,
-            })
-        } else {
-            Ok(())
-        }
+        Ok(parsed)
     }
     
     /// 获取性能统计
@@ -0,0 +1,446 @@
+//! 实时协议处理阶段
+//!
+//! 所有 Socket 连接（车辆、沙盘、会话回放）收到的消息都经由此阶段完成解析、验证和推送。
+//! 阶段持有固定数量的处理槽，每个槽复用按协议版本缓存的解析器和一个验证器，
+//! 车辆信息（占现场流量绝大部分）经布局视图直接转换，不走按协议定义的通用解码；
+//! 只有数据域结构无法解码的消息判为失败，超出协议取值范围的字段作为告警随数据推送；
+//! 同时处理的消息数不超过槽数；槽位占满时，等待者按 `TaskPriority` 由高到低、
+//! 同优先级按提交顺序获得槽位。
+//!
+//! 处理在提交方的异步任务内执行，连接任务等待本条消息推送完成后再提交下一条，
+//! 因此同一连接内的消息顺序不变，车辆编号纠正等结果也能直接返回给连接任务。
+
+use crate::protocol_processing::batch_processor::{BatchProcessingStats, TaskPriority};
+use crate::protocol_processing::converter::DataConverter;
+use crate::protocol_processing::parser::ProtocolParser;
+use crate::protocol_processing::schema::ProtocolSchema;
+use crate::protocol_processing::types::*;
+use crate::protocol_processing::validator::{ProtocolValidator, ValidationConfig};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// 单条消息的处理结果：`None` 表示该消息类型没有数据域定义（如心跳），无需解析
+pub type ProcessOutcome = Option<Result<ParsedProtocolData, String>>;
+
+static GLOBAL_PIPELINE: Lazy<ProcessingPipeline> = Lazy::new(ProcessingPipeline::default);
+
+/// 处理槽：复用解析器、转换器与验证器
+struct Slot {
+    parsers: HashMap<u8, ProtocolParser>,
+    converter: DataConverter,
+    validator: ProtocolValidator,
+}
+
+impl Slot {
+    fn new() -> Self {
+        Self {
+            parsers: HashMap::new(),
+            converter: DataConverter::new(),
+            // 取值范围单独检查为告警；停车打方向等逻辑规则不适用于现场数据
+            validator: ProtocolValidator::new(ValidationConfig {
+                strict_mode: false,
+                range_check: false,
+                logic_check: false,
+                performance_check: false,
+                max_validation_time_us: 0,
+            }),
+        }
+    }
+
+    fn process(&mut self, version: u8, message_type: u16, data: &[u8]) -> ProcessOutcome {
+        let schema = ProtocolSchema::global()
+            .by_receive(version, message_type)
+            .filter(|schema| schema.variant.is_some())?;
+
+        // 布局视图与 0x10 协议定义的一致性由 wire 模块测试保证
+        if message_type == MessageTypes::VEHICLE_INFO && schema.version == ProtocolConstants::PROTOCOL_VERSION_V10 {
+            let parsed = self
+                .converter
+                .convert_vehicle_info(data)
+                .map(ParsedProtocolData::VehicleInfo)
+                .map_err(|e| e.to_string());
+            return Some(parsed.and_then(|parsed| self.validate(parsed)));
+        }
+
+        let parser = self
+            .parsers
+            .entry(version)
+            .or_insert_with(|| ProtocolParser::with_version(false, version));
+        let result = parser.parse_protocol(message_type, data);
+        let Some(parsed) = result.data else {
+            return Some(Err(result.error.unwrap_or_else(|| "解析失败".to_string())));
+        };
+        Some(self.validate(parsed))
+    }
+
+    fn validate(&mut self, parsed: ParsedProtocolData) -> Result<ParsedProtocolData, String> {
+        self.validator
+            .validate(&parsed)
+            .map(|_| parsed)
+            .map_err(|e| format!("验证失败: {}", e))
+    }
+
+    /// 超出协议取值范围的字段（告警文本）
+    fn range_warnings(outcome: &ProcessOutcome) -> Vec<String> {
+        let Some(Ok(parsed)) = outcome else {
+            return Vec::new();
+        };
+        match ProtocolValidator::range_violations(parsed) {
+            Ok(violations) => violations.iter().map(ToString::to_string).collect(),
+            Err(e) => vec![format!("取值范围检查失败: {}", e)],
+        }
+    }
+}
+
+/// 等待槽位的提交者
+struct Waiter {
+    priority: TaskPriority,
+    sequence: u64,
+    sender: oneshot::Sender<Slot>,
+}
+
+impl Ord for Waiter {
+    /// 优先级高者先出队，同优先级先提交者先出队
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+#[derive(Default)]
+struct SlotQueue {
+    idle: Vec<Slot>,
+    waiting: BinaryHeap<Waiter>,
+    next_sequence: u64,
+}
+
+/// 累计统计；吞吐量与并行效率在读取时按统计起点计算
+struct PipelineCounters {
+    stats: BatchProcessingStats,
+    total_latency_us: u64,
+    since: Instant,
+}
+
+impl PipelineCounters {
+    fn new() -> Self {
+        Self {
+            stats: BatchProcessingStats {
+                total_tasks: 0,
+                successful_tasks: 0,
+                failed_tasks: 0,
+                total_time_us: 0,
+                average_time_us: 0.0,
+                average_latency_us: 0.0,
+                max_latency_us: 0,
+                throughput: 0.0,
+                parallel_efficiency: 0.0,
+            },
+            total_latency_us: 0,
+            since: Instant::now(),
+        }
+    }
+}
+
+/// 实时协议处理阶段
+pub struct ProcessingPipeline {
+    concurrency: usize,
+    slots: Mutex<SlotQueue>,
+    counters: Mutex<PipelineCounters>,
+}
+
+impl ProcessingPipeline {
+    /// 创建最多同时处理 `concurrency` 条消息的处理阶段
+    pub fn new(concurrency: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        Self {
+            concurrency,
+            slots: Mutex::new(SlotQueue {
+                idle: (0..concurrency).map(|_| Slot::new()).collect(),
+                ..Default::default()
+            }),
+            counters: Mutex::new(PipelineCounters::new()),
+        }
+    }
+
+    /// 所有连接共享的处理阶段
+    pub fn global() -> &'static Self {
+        &GLOBAL_PIPELINE
+    }
+
+    /// 解析、验证一条消息并交给 `emit` 推送，返回 `emit` 的结果
+    ///
+    /// `emit` 的第二个参数为超出协议取值范围的字段告警，数据照常推送。
+    /// 槽位占满时按 `priority` 排队；`emit` 在占用槽位期间执行，计入处理耗时
+    pub async fn process<R>(
+        &self,
+        priority: TaskPriority,
+        version: u8,
+        message_type: u16,
+        data: &[u8],
+        emit: impl FnOnce(ProcessOutcome, Vec<String>) -> R,
+    ) -> R {
+        let submitted = Instant::now();
+        let mut guard = self.acquire(priority).await;
+        let started = Instant::now();
+
+        let outcome = guard.slot().process(version, message_type, data);
+        let success = !matches!(outcome, Some(Err(_)));
+        let warnings = Slot::range_warnings(&outcome);
+        let output = emit(outcome, warnings);
+        drop(guard);
+
+        self.record(started.elapsed(), submitted.elapsed(), success);
+        output
+    }
+
+    async fn acquire(&self, priority: TaskPriority) -> SlotGuard<'_> {
+        let receiver = {
+            let mut queue = self.slots.lock();
+            if let Some(slot) = queue.idle.pop() {
+                return SlotGuard { pipeline: self, slot: Some(slot) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let sequence = queue.next_sequence;
+            queue.next_sequence += 1;
+            queue.waiting.push(Waiter { priority, sequence, sender });
+            receiver
+        };
+
+        let mut pending = PendingSlot { pipeline: self, receiver };
+        // 等待者的发送端只在交出槽位时取出，不会未发送就被丢弃
+        let slot = (&mut pending.receiver).await.expect("处理槽交接失败");
+        SlotGuard { pipeline: self, slot: Some(slot) }
+    }
+
+    /// 归还槽位：优先交给等待中优先级最高的提交者
+    fn release(&self, mut slot: Slot) {
+        let mut queue = self.slots.lock();
+        while let Some(waiter) = queue.waiting.pop() {
+            match waiter.sender.send(slot) {
+                Ok(()) => return,
+                // 提交者已取消等待，交给下一位
+                Err(returned) => slot = returned,
+            }
+        }
+        queue.idle.push(slot);
+    }
+
+    fn record(&self, processing: Duration, latency: Duration, success: bool) {
+        let processing_us = processing.as_micros() as u64;
+        let latency_us = latency.as_micros() as u64;
+
+        let mut counters = self.counters.lock();
+        counters.total_latency_us += latency_us;
+        let stats = &mut counters.stats;
+        stats.total_tasks += 1;
+        if success {
+            stats.successful_tasks += 1;
+        } else {
+            stats.failed_tasks += 1;
+        }
+        stats.total_time_us += processing_us;
+        stats.max_latency_us = stats.max_latency_us.max(latency_us);
+    }
+
+    /// 获取处理统计（吞吐量为统计起点以来每秒处理的消息数，并行效率为槽位平均占用率）
+    pub fn get_stats(&self) -> BatchProcessingStats {
+        let counters = self.counters.lock();
+        let mut stats = counters.stats.clone();
+        let elapsed_us = counters.since.elapsed().as_micros() as f64;
+        if stats.total_tasks > 0 {
+            stats.average_time_us = stats.total_time_us as f64 / stats.total_tasks as f64;
+            stats.average_latency_us = counters.total_latency_us as f64 / stats.total_tasks as f64;
+        }
+        if elapsed_us > 0.0 {
+            stats.throughput = stats.total_tasks as f64 / (elapsed_us / 1_000_000.0);
+            stats.parallel_efficiency = stats.total_time_us as f64 / (elapsed_us * self.concurrency as f64);
+        }
+        stats
+    }
+
+    /// 重置统计
+    pub fn reset_stats(&self) {
+        *self.counters.lock() = PipelineCounters::new();
+    }
+
+    /// 最大并发处理数
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+}
+
+impl Default for ProcessingPipeline {
+    fn default() -> Self {
+        let concurrency = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+        Self::new(concurrency)
+    }
+}
+
+/// 占用中的槽位，离开作用域时归还（处理中途 panic 也不会丢失槽位）
+struct SlotGuard<'a> {
+    pipeline: &'a ProcessingPipeline,
+    slot: Option<Slot>,
+}
+
+impl SlotGuard<'_> {
+    fn slot(&mut self) -> &mut Slot {
+        self.slot.as_mut().expect("槽位已归还")
+    }
+}
+
+impl Drop for SlotGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            self.pipeline.release(slot);
+        }
+    }
+}
+
+/// 排队中的提交者；等待被取消时，若槽位已交接则转交给下一位
+struct PendingSlot<'a> {
+    pipeline: &'a ProcessingPipeline,
+    receiver: oneshot::Receiver<Slot>,
+}
+
+impl Drop for PendingSlot<'_> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if let Ok(slot) = self.receiver.try_recv() {
+            self.pipeline.release(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::ProtocolBuilder;
+    use std::sync::Arc;
+
+    fn vehicle_info(battery: f64) -> Vec<u8> {
+        ProtocolBuilder::new().build_vehicle_info(&VehicleInfo {
+            vehicle_id: 1,
+            speed: 0.5,
+            position_x: 1.0,
+            position_y: 2.0,
+            orientation: 90.0,
+            battery,
+            gear: GearPosition::DriveLevel(1),
+            steering_angle: 0.0,
+            nav_status: 1,
+            sensors: SensorStatus { camera: true, lidar: true, gyro: true },
+            parking_slot: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_parse_validate_and_stats() {
+        let pipeline = ProcessingPipeline::new(2);
+        let v10 = ProtocolConstants::PROTOCOL_VERSION_V10;
+        let run = |message_type: u16, data: Vec<u8>| {
+            let pipeline = &pipeline;
+            async move {
+                pipeline
+                    .process(TaskPriority::Normal, v10, message_type, &data, |outcome, warnings| (outcome, warnings))
+                    .await
+            }
+        };
+
+        assert!(run(MessageTypes::HEARTBEAT, vec![]).await.0.is_none());
+        match run(MessageTypes::VEHICLE_INFO, vehicle_info(80.0)).await {
+            (Some(Ok(ParsedProtocolData::VehicleInfo(info))), warnings) => {
+                assert_eq!(info.battery, 80.0);
+                assert!(warnings.is_empty());
+            }
+            other => panic!("车辆信息处理失败: {:?}", other),
+        }
+        // 超出取值范围的数据照常返回，附带告警
+        match run(MessageTypes::VEHICLE_INFO, vehicle_info(150.0)).await {
+            (Some(Ok(ParsedProtocolData::VehicleInfo(info))), warnings) => {
+                assert_eq!(info.battery, 150.0);
+                assert_eq!(warnings.len(), 1);
+                assert!(warnings[0].contains("battery"));
+            }
+            other => panic!("越界车辆信息应照常返回: {:?}", other),
+        }
+        assert!(run(MessageTypes::VEHICLE_INFO, vec![1, 2, 3]).await.0.unwrap().is_err());
+
+        let stats = pipeline.get_stats();
+        assert_eq!((stats.total_tasks, stats.successful_tasks, stats.failed_tasks), (4, 3, 1));
+        assert!(stats.average_latency_us >= stats.average_time_us);
+        assert!(stats.throughput > 0.0);
+
+        pipeline.reset_stats();
+        assert_eq!(pipeline.get_stats().total_tasks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_waiters_served_by_priority() {
+        let pipeline = Arc::new(ProcessingPipeline::new(1));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let busy = pipeline.acquire(TaskPriority::Normal).await;
+
+        let mut handles = Vec::new();
+        for priority in [TaskPriority::Low, TaskPriority::Normal, TaskPriority::Critical, TaskPriority::High] {
+            let (submitter, order) = (pipeline.clone(), order.clone());
+            handles.push(tokio::spawn(async move {
+                submitter
+                    .process(priority, 0x10, MessageTypes::HEARTBEAT, &[], |_, _| order.lock().push(priority))
+                    .await
+            }));
+            // 保证各提交者按此顺序入队
+            while pipeline.slots.lock().waiting.len() < handles.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        drop(busy);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock(),
+            [TaskPriority::Critical, TaskPriority::High, TaskPriority::Normal, TaskPriority::Low]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_does_not_leak_slot() {
+        let pipeline = ProcessingPipeline::new(1);
+        let busy = pipeline.acquire(TaskPriority::Normal).await;
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(10),
+            pipeline.process(TaskPriority::High, 0x10, MessageTypes::HEARTBEAT, &[], |_, _| ()),
+        )
+        .await;
+        assert!(cancelled.is_err());
+
+        drop(busy);
+        pipeline
+            .process(TaskPriority::Low, 0x10, MessageTypes::HEARTBEAT, &[], |_, _| ())
+            .await;
+        assert_eq!(pipeline.slots.lock().idle.len(), 1);
+    }
+}
@@ -0,0 +1,689 @@
+//! 声明式协议定义
+//!
+//! `messages.toml` 描述每种消息数据域的字段、类型、取值范围和取值含义，解析器、构建器、
+//! 验证器和前端消息类型配置都从这里派生。数据域先解码为线上原始值组成的记录
+//! （`Record`），经字段 codec 转换后反序列化为 `ParsedProtocolData`；构建与验证走相反方向。
+
+use super::types::{ControlCommandType, GearPosition, ParsedProtocolData, ProtocolConstants, ProtocolError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// 线上原始值记录（`a.b` 字段展开为嵌套对象）
+pub type Record = Map<String, Value>;
+
+static SCHEMA: Lazy<ProtocolSchema> =
+    Lazy::new(|| ProtocolSchema::from_toml(include_str!("messages.toml")).expect("messages.toml 协议定义无效"));
+
+/// 字段线上类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    /// 占用剩余全部字节
+    Bytes,
+    /// 重复组
+    Group,
+}
+
+impl FieldType {
+    /// 定长标量的字节数（bytes / group 为 0）
+    fn scalar_size(self) -> usize {
+        match self {
+            Self::U8 | Self::Bool => 1,
+            Self::U16 => 2,
+            Self::U32 | Self::F32 => 4,
+            Self::U64 | Self::F64 => 8,
+            Self::Bytes | Self::Group => 0,
+        }
+    }
+}
+
+/// 线上原始值与 Rust 枚举的转换
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldCodec {
+    Gear,
+    ControlCommand,
+}
+
+impl FieldCodec {
+    fn decode(self, raw: &Value) -> Result<Value, ProtocolError> {
+        let code = raw
+            .as_u64()
+            .and_then(|v| u8::try_from(v).ok())
+            .ok_or_else(|| ProtocolError::FieldError(format!("{:?} 原始值无效: {}", self, raw)))?;
+        let typed = match self {
+            Self::Gear => serde_json::to_value(GearPosition::from_u8(code)),
+            Self::ControlCommand => serde_json::to_value(ControlCommandType::from_u8(code)?),
+        };
+        typed.map_err(|e| ProtocolError::FieldError(e.to_string()))
+    }
+
+    fn encode(self, typed: &Value) -> Result<Value, ProtocolError> {
+        let code = match self {
+            Self::Gear => serde_json::from_value::<GearPosition>(typed.clone()).map(GearPosition::to_u8),
+            Self::ControlCommand => serde_json::from_value::<ControlCommandType>(typed.clone()).map(|c| c.to_u8()),
+        };
+        code.map(Value::from).map_err(|e| ProtocolError::FieldError(e.to_string()))
+    }
+}
+
+/// 字段定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: FieldType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// 取值含义（键为原始值）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing)]
+    pub codec: Option<FieldCodec>,
+    #[serde(default)]
+    pub optional: bool,
+    /// 组内字段
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldSchema>,
+    /// 重复组次数
+    #[serde(default)]
+    pub count: usize,
+    /// 相对数据域（组内字段相对组元素）的偏移
+    #[serde(skip_deserializing)]
+    pub offset: usize,
+    /// 占用字节数（bytes 为 0，group 为全部元素之和）
+    #[serde(skip_deserializing)]
+    pub size: usize,
+}
+
+impl FieldSchema {
+    /// 按定义的取值范围检查数值（`path` 为报错时的字段路径）
+    pub fn check_range(&self, path: &str, value: f64) -> Result<(), ProtocolError> {
+        let min = self.min.unwrap_or(f64::MIN);
+        let max = self.max.unwrap_or(f64::MAX);
+        if (min..=max).contains(&value) {
+            return Ok(());
+        }
+        Err(ProtocolError::ValidationError {
+            field: path.to_string(),
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+/// 消息定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageSchema {
+    pub name: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    #[serde(default)]
+    pub receive: Option<u16>,
+    #[serde(default)]
+    pub send: Option<u16>,
+    #[serde(default = "base_version")]
+    pub version: u8,
+    #[serde(default)]
+    pub fields: Vec<FieldSchema>,
+    /// 最短数据域长度（不含可选字段）
+    #[serde(skip_deserializing)]
+    pub min_size: usize,
+    /// 定长数据域的完整长度（含 bytes 字段时为空）
+    #[serde(skip_deserializing)]
+    pub total_size: Option<usize>,
+}
+
+fn base_version() -> u8 {
+    ProtocolConstants::PROTOCOL_VERSION_V10
+}
+
+/// 全部消息定义
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtocolSchema {
+    #[serde(rename = "message")]
+    messages: Vec<MessageSchema>,
+}
+
+impl ProtocolSchema {
+    /// 内置协议定义（`messages.toml`）
+    pub fn global() -> &'static ProtocolSchema {
+        &SCHEMA
+    }
+
+    /// 解析协议定义并计算各字段偏移
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let mut schema: Self = toml::from_str(source).map_err(|e| format!("协议定义解析失败: {}", e))?;
+        for (index, message) in schema.messages.iter().enumerate() {
+            if schema.messages[..index]
+                .iter()
+                .any(|m| m.name == message.name && m.version == message.version)
+            {
+                return Err(format!("消息 {} 在版本 0x{:02X} 中重复定义", message.name, message.version));
+            }
+        }
+        for message in &mut schema.messages {
+            message.layout().map_err(|e| format!("消息 {}: {}", message.name, e))?;
+        }
+        Ok(schema)
+    }
+
+    pub fn messages(&self) -> &[MessageSchema] {
+        &self.messages
+    }
+
+    /// 按版本查找：新版本未登记的消息沿用基线版本的定义
+    fn find(&self, version: u8, matches: impl Fn(&MessageSchema) -> bool) -> Option<&MessageSchema> {
+        self.messages
+            .iter()
+            .find(|m| m.version == version && matches(m))
+            .or_else(|| self.messages.iter().find(|m| m.version == base_version() && matches(m)))
+    }
+
+    pub fn by_name(&self, name: &str, version: u8) -> Option<&MessageSchema> {
+        self.find(version, |m| m.name == name)
+    }
+
+    pub fn by_receive(&self, version: u8, message_type: u16) -> Option<&MessageSchema> {
+        self.find(version, |m| m.receive == Some(message_type))
+    }
+
+    pub fn by_variant(&self, variant: &str, version: u8) -> Option<&MessageSchema> {
+        self.find(version, |m| m.variant.as_deref() == Some(variant))
+    }
+
+    /// 把解析结果还原为线上原始值记录
+    pub fn parsed_record(&self, data: &ParsedProtocolData, version: u8) -> Result<(&MessageSchema, Record), ProtocolError> {
+        let tagged = serde_json::to_value(data).map_err(|e| ProtocolError::FieldError(e.to_string()))?;
+        let (variant, value) = tagged
+            .as_object()
+            .and_then(|map| map.iter().next())
+            .ok_or_else(|| ProtocolError::FieldError("解析结果格式无效".to_string()))?;
+        let message = self
+            .by_variant(variant, version)
+            .ok_or_else(|| ProtocolError::FieldError(format!("{} 没有协议定义", variant)))?;
+        Ok((message, message.record_from_value(value.clone())?))
+    }
+}
+
+impl MessageSchema {
+    fn layout(&mut self) -> Result<(), String> {
+        let count = self.fields.len();
+        let mut offset = 0;
+        let mut optional_seen = false;
+        let mut variable = false;
+        for (index, field) in self.fields.iter_mut().enumerate() {
+            if optional_seen && !field.optional {
+                return Err(format!("可选字段之后不能再有必选字段 {}", field.name));
+            }
+            field.offset = offset;
+            field.size = match field.ty {
+                FieldType::Bytes if index + 1 != count || field.optional => {
+                    return Err(format!("bytes 字段 {} 必须是最后一个必选字段", field.name));
+                }
+                FieldType::Bytes => {
+                    variable = true;
+                    0
+                }
+                FieldType::Group if field.count == 0 || field.fields.is_empty() => {
+                    return Err(format!("group 字段 {} 需要 count 与 fields", field.name));
+                }
+                FieldType::Group => layout_group(&mut field.fields)? * field.count,
+                ty => ty.scalar_size(),
+            };
+            check_codec(field)?;
+            offset += field.size;
+            if field.optional {
+                optional_seen = true;
+            } else {
+                self.min_size = offset;
+            }
+        }
+        self.total_size = (!variable).then_some(offset);
+        Ok(())
+    }
+
+    /// 解码数据域为线上原始值记录
+    pub fn decode(&self, data: &[u8]) -> Result<Record, ProtocolError> {
+        if data.len() < self.min_size {
+            return Err(ProtocolError::InsufficientData {
+                required: self.min_size,
+                actual: data.len(),
+            });
+        }
+        // 可选字段整体出现或整体省略
+        let with_optional = self.total_size.is_some_and(|total| data.len() >= total);
+        let mut record = Record::new();
+        for field in self.fields.iter().filter(|f| with_optional || !f.optional) {
+            let value = match field.ty {
+                FieldType::Bytes => Value::from(data[field.offset..].to_vec()),
+                FieldType::Group => {
+                    let element_size = field.size / field.count;
+                    let elements = (0..field.count)
+                        .map(|index| {
+                            let start = field.offset + index * element_size;
+                            let mut element = Record::new();
+                            element.insert("index".to_string(), Value::from(index));
+                            for inner in &field.fields {
+                                insert_path(&mut element, &inner.name, read_scalar(inner.ty, &data[start + inner.offset..]));
+                            }
+                            Value::Object(element)
+                        })
+                        .collect();
+                    Value::Array(elements)
+                }
+                ty => read_scalar(ty, &data[field.offset..]),
+            };
+            insert_path(&mut record, &field.name, value);
+        }
+        Ok(record)
+    }
+
+    /// 按字段顺序编码线上原始值记录
+    pub fn encode(&self, record: &Record) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = Vec::with_capacity(self.total_size.unwrap_or(self.min_size));
+        encode_fields(&self.fields, record, &mut out)?;
+        Ok(out)
+    }
+
+    /// 按字段取值范围验证线上原始值记录
+    pub fn validate(&self, record: &Record) -> Result<(), ProtocolError> {
+        self.violations(record).into_iter().next().map_or(Ok(()), Err)
+    }
+
+    /// 超出取值范围的全部字段
+    pub fn violations(&self, record: &Record) -> Vec<ProtocolError> {
+        let mut violations = Vec::new();
+        validate_fields(&self.fields, record, "", &mut violations);
+        violations
+    }
+
+    /// 原始值记录转为解析结果
+    pub fn to_parsed(&self, mut record: Record) -> Result<ParsedProtocolData, ProtocolError> {
+        let variant = self
+            .variant
+            .as_deref()
+            .ok_or_else(|| ProtocolError::FieldError(format!("消息 {} 没有数据结构", self.name)))?;
+        convert_codecs(&self.fields, &mut record, FieldCodec::decode)?;
+        let mut tagged = Map::new();
+        tagged.insert(variant.to_string(), Value::Object(record));
+        serde_json::from_value(Value::Object(tagged)).map_err(|e| ProtocolError::FieldError(e.to_string()))
+    }
+
+    /// 结构体（serde 形式）转为原始值记录
+    pub fn record_from_value(&self, value: Value) -> Result<Record, ProtocolError> {
+        let Value::Object(mut record) = value else {
+            return Err(ProtocolError::FieldError(format!("{} 数据必须是对象", self.label)));
+        };
+        convert_codecs(&self.fields, &mut record, FieldCodec::encode)?;
+        Ok(record)
+    }
+
+    /// 结构体转为原始值记录
+    pub fn record_from<T: Serialize>(&self, value: &T) -> Result<Record, ProtocolError> {
+        let value = serde_json::to_value(value).map_err(|e| ProtocolError::FieldError(e.to_string()))?;
+        self.record_from_value(value)
+    }
+}
+
+fn layout_group(fields: &mut [FieldSchema]) -> Result<usize, String> {
+    let mut offset = 0;
+    for field in fields {
+        if field.ty.scalar_size() == 0 || field.optional {
+            return Err(format!("组内字段 {} 必须是必选的定长标量", field.name));
+        }
+        check_codec(field)?;
+        field.offset = offset;
+        field.size = field.ty.scalar_size();
+        offset += field.size;
+    }
+    Ok(offset)
+}
+
+fn check_codec(field: &FieldSchema) -> Result<(), String> {
+    if field.codec.is_some() && field.ty != FieldType::U8 {
+        return Err(format!("codec 字段 {} 必须是 u8", field.name));
+    }
+    Ok(())
+}
+
+fn read_scalar(ty: FieldType, bytes: &[u8]) -> Value {
+    let mut buf = [0u8; 8];
+    buf[..ty.scalar_size()].copy_from_slice(&bytes[..ty.scalar_size()]);
+    match ty {
+        FieldType::U8 => Value::from(buf[0]),
+        FieldType::Bool => Value::from(buf[0] != 0),
+        FieldType::U16 => Value::from(u16::from_le_bytes([buf[0], buf[1]])),
+        FieldType::U32 => Value::from(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])),
+        FieldType::U64 => Value::from(u64::from_le_bytes(buf)),
+        FieldType::F32 => Value::from(f64::from(f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))),
+        FieldType::F64 => Value::from(f64::from_le_bytes(buf)),
+        FieldType::Bytes | FieldType::Group => Value::Null,
+    }
+}
+
+fn write_scalar(field: &FieldSchema, value: &Value, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+    let invalid = || ProtocolError::FieldError(format!("字段 {} 的值无效: {}", field.name, value));
+    let unsigned = |max: u64| value.as_u64().filter(|v| *v <= max).ok_or_else(invalid);
+    match field.ty {
+        FieldType::U8 => out.push(unsigned(u8::MAX as u64)? as u8),
+        FieldType::Bool => out.push(value.as_bool().ok_or_else(invalid)? as u8),
+        FieldType::U16 => out.extend_from_slice(&(unsigned(u16::MAX as u64)? as u16).to_le_bytes()),
+        FieldType::U32 => out.extend_from_slice(&(unsigned(u32::MAX as u64)? as u32).to_le_bytes()),
+        FieldType::U64 => out.extend_from_slice(&unsigned(u64::MAX)?.to_le_bytes()),
+        FieldType::F32 => out.extend_from_slice(&(value.as_f64().ok_or_else(invalid)? as f32).to_le_bytes()),
+        FieldType::F64 => out.extend_from_slice(&value.as_f64().ok_or_else(invalid)?.to_le_bytes()),
+        FieldType::Bytes | FieldType::Group => return Err(invalid()),
+    }
+    Ok(())
+}
+
+fn encode_fields(fields: &[FieldSchema], record: &Record, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+    for field in fields {
+        let Some(value) = get_path(record, &field.name).filter(|v| !v.is_null()) else {
+            if field.optional {
+                // 可选字段整体省略
+                break;
+            }
+            return Err(ProtocolError::FieldError(format!("缺少字段 {}", field.name)));
+        };
+        match field.ty {
+            FieldType::Bytes => {
+                for byte in value.as_array().into_iter().flatten() {
+                    let byte = byte
+                        .as_u64()
+                        .and_then(|b| u8::try_from(b).ok())
+                        .ok_or_else(|| ProtocolError::FieldError(format!("字段 {} 的值无效: {}", field.name, byte)))?;
+                    out.push(byte);
+                }
+            }
+            FieldType::Group => {
+                let elements = value.as_array().filter(|a| a.len() == field.count).ok_or_else(|| {
+                    ProtocolError::FieldError(format!("字段 {} 需要 {} 个元素", field.name, field.count))
+                })?;
+                for element in elements {
+                    let element = element
+                        .as_object()
+                        .ok_or_else(|| ProtocolError::FieldError(format!("字段 {} 的元素必须是对象", field.name)))?;
+                    encode_fields(&field.fields, element, out)?;
+                }
+            }
+            _ => write_scalar(field, value, out)?,
+        }
+    }
+    Ok(())
+}
+
+fn validate_fields(fields: &[FieldSchema], record: &Record, prefix: &str, violations: &mut Vec<ProtocolError>) {
+    for field in fields {
+        let Some(value) = get_path(record, &field.name) else {
+            continue;
+        };
+        let name = if prefix.is_empty() {
+            field.name.clone()
+        } else {
+            format!("{}.{}", prefix, field.name)
+        };
+        if field.ty == FieldType::Group {
+            for (index, element) in value.as_array().into_iter().flatten().enumerate() {
+                if let Some(element) = element.as_object() {
+                    validate_fields(&field.fields, element, &format!("{}[{}]", name, index), violations);
+                }
+            }
+            continue;
+        }
+        if let Some(Err(e)) = value.as_f64().map(|number| field.check_range(&name, number)) {
+            violations.push(e);
+        }
+    }
+}
+
+fn convert_codecs(
+    fields: &[FieldSchema],
+    record: &mut Record,
+    convert: fn(FieldCodec, &Value) -> Result<Value, ProtocolError>,
+) -> Result<(), ProtocolError> {
+    for field in fields {
+        let Some(value) = get_path_mut(record, &field.name).filter(|v| !v.is_null()) else {
+            continue;
+        };
+        if let Some(codec) = field.codec {
+            *value = convert(codec, value)?;
+        } else if field.ty == FieldType::Group {
+            for element in value.as_array_mut().into_iter().flatten() {
+                if let Some(element) = element.as_object_mut() {
+                    convert_codecs(&field.fields, element, convert)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn insert_path(record: &mut Record, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            if let Value::Object(child) = record.entry(head).or_insert_with(|| Value::Object(Map::new())) {
+                insert_path(child, rest, value);
+            }
+        }
+        None => {
+            record.insert(path.to_string(), value);
+        }
+    }
+}
+
+fn get_path<'a>(record: &'a Record, path: &str) -> Option<&'a Value> {
+    match path.split_once('.') {
+        Some((head, rest)) => get_path(record.get(head)?.as_object()?, rest),
+        None => record.get(path),
+    }
+}
+
+fn get_path_mut<'a>(record: &'a mut Record, path: &str) -> Option<&'a mut Value> {
+    match path.split_once('.') {
+        Some((head, rest)) => get_path_mut(record.get_mut(head)?.as_object_mut()?, rest),
+        None => record.get_mut(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::types::{MessageTypes, SendMessageTypes};
+    use crate::protocol_processing::{ProtocolBuilder, ProtocolParser};
+    use proptest::prelude::*;
+
+    fn offsets(name: &str, version: u8) -> Vec<(String, usize)> {
+        ProtocolSchema::global()
+            .by_name(name, version)
+            .unwrap()
+            .fields
+            .iter()
+            .map(|f| (f.name.clone(), f.offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_matches_protocol_constants() {
+        let schema = ProtocolSchema::global();
+        let v10 = ProtocolConstants::PROTOCOL_VERSION_V10;
+
+        let info = offsets("VEHICLE_INFO", v10);
+        assert_eq!(info[6], ("gear".to_string(), ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET));
+        assert_eq!(info[12], ("parking_slot".to_string(), ProtocolConstants::VEHICLE_INFO_PARKING_SLOT_OFFSET));
+
+        let sizes = [
+            ("VEHICLE_INFO", v10, ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE),
+            ("VEHICLE_CONTROL", v10, ProtocolConstants::VEHICLE_CONTROL_TOTAL_SIZE_WITH_POSITION),
+            ("TAXI_ORDER", v10, ProtocolConstants::TAXI_ORDER_TOTAL_SIZE),
+            ("AVP_PARKING", v10, ProtocolConstants::AVP_PARKING_TOTAL_SIZE),
+            ("AVP_PICKUP", v10, ProtocolConstants::AVP_PICKUP_TOTAL_SIZE),
+            ("DATA_RECORDING", v10, ProtocolConstants::DATA_RECORDING_TOTAL_SIZE),
+            ("CONSTRUCTION_MARKER", v10, ProtocolConstants::CONSTRUCTION_MARKER_TOTAL_SIZE),
+        ];
+        for (name, version, size) in sizes {
+            assert_eq!(schema.by_name(name, version).unwrap().total_size, Some(size), "{}", name);
+        }
+        let control = schema.by_name("VEHICLE_CONTROL", v10).unwrap();
+        assert_eq!(control.min_size, ProtocolConstants::VEHICLE_CONTROL_BASE_SIZE);
+        assert_eq!(offsets("CONSTRUCTION_MARKER", v10)[2].1, ProtocolConstants::CONSTRUCTION_MARKER_X_OFFSET);
+
+        // 接收/发送编号与消息类型常量一致
+        assert_eq!(schema.by_receive(v10, MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS).unwrap().name, "SANDBOX_TRAFFIC_LIGHT_STATUS");
+        assert_eq!(schema.by_name("SANDBOX_LIGHTING_CONTROL", v10).unwrap().send, Some(SendMessageTypes::SANDBOX_LIGHTING_CONTROL));
+        assert_eq!(schema.by_receive(v10, MessageTypes::COMMAND_ACK).unwrap().total_size, Some(crate::socket::ack::ACK_PAYLOAD_LEN));
+        assert_eq!(schema.by_receive(v10, MessageTypes::VEHICLE_REGISTER).unwrap().min_size, 1);
+        assert_eq!(schema.by_name("AUTH_RESULT", v10).unwrap().send, Some(SendMessageTypes::AUTH_RESULT));
+        assert_eq!(schema.by_name("TAXI_ORDER_CANCEL", v10).unwrap().send, Some(SendMessageTypes::TAXI_ORDER_CANCEL));
+        // 新版本未登记的消息沿用 0x10 定义
+        assert_eq!(schema.by_receive(0x11, MessageTypes::TAXI_ORDER).unwrap().version, v10);
+    }
+
+    #[test]
+    fn test_optional_fields_and_groups() {
+        let schema = ProtocolSchema::global();
+        let control = schema.by_name("VEHICLE_CONTROL", 0x10).unwrap();
+        let short = control.decode(&[1, 2]).unwrap();
+        assert!(short.get("position_data").is_none());
+        assert!(matches!(
+            control.to_parsed(short).unwrap(),
+            ParsedProtocolData::VehicleControl(cmd) if cmd.position_data.is_none()
+        ));
+
+        let lights = schema.by_receive(0x10, MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS).unwrap();
+        let record = lights.decode(&[1, 30, 2, 200]).unwrap();
+        assert_eq!(record["lights"][1]["index"], 1);
+        match lights.validate(&record) {
+            Err(ProtocolError::ValidationError { field, .. }) => assert_eq!(field, "lights[1].remaining"),
+            other => panic!("应超出倒计时范围: {:?}", other),
+        }
+        assert_eq!(lights.encode(&record).unwrap(), vec![1, 30, 2, 200]);
+    }
+
+    /// 按字段定义生成取值范围内的原始值（带 codec 的字段只取登记过的编码）
+    fn field_value(field: &FieldSchema) -> BoxedStrategy<Value> {
+        let int = |max: u64| {
+            let lo = field.min.map_or(0, |m| m as u64);
+            let hi = field.max.map_or(max, |m| m as u64);
+            (lo..=hi).prop_map(Value::from).boxed()
+        };
+        let float = |lo: f64, hi: f64| field.min.unwrap_or(lo)..=field.max.unwrap_or(hi);
+        match field.ty {
+            _ if field.codec.is_some() => {
+                let codes: Vec<u64> = field.labels.keys().filter_map(|k| k.parse().ok()).collect();
+                prop::sample::select(codes).prop_map(Value::from).boxed()
+            }
+            FieldType::U8 => int(u8::MAX.into()),
+            FieldType::U16 => int(u16::MAX.into()),
+            FieldType::U32 => int(u32::MAX.into()),
+            FieldType::U64 => int(u64::MAX),
+            FieldType::Bool => any::<bool>().prop_map(Value::from).boxed(),
+            FieldType::F32 => float(-1.0e6, 1.0e6).prop_map(|v| Value::from(f64::from(v as f32))).boxed(),
+            FieldType::F64 => float(-1.0e9, 1.0e9).prop_map(Value::from).boxed(),
+            FieldType::Bytes => prop::collection::vec(any::<u8>(), 0..32).prop_map(Value::from).boxed(),
+            FieldType::Group => {
+                let elements: Vec<_> = (0..field.count).map(|_| record(&field.fields)).collect();
+                elements
+                    .prop_map(|elements| {
+                        let elements = elements.into_iter().enumerate().map(|(index, mut element)| {
+                            element.insert("index".to_string(), Value::from(index));
+                            Value::Object(element)
+                        });
+                        Value::Array(elements.collect())
+                    })
+                    .boxed()
+            }
+        }
+    }
+
+    /// 按字段定义生成原始值记录（可选字段整体出现或整体省略）
+    fn record(fields: &[FieldSchema]) -> BoxedStrategy<Record> {
+        let names: Vec<(String, bool)> = fields.iter().map(|f| (f.name.clone(), f.optional)).collect();
+        let values: Vec<_> = fields.iter().map(field_value).collect();
+        (values, any::<bool>())
+            .prop_map(move |(values, with_optional)| {
+                let mut record = Record::new();
+                for ((name, optional), value) in names.iter().zip(values) {
+                    if with_optional || !optional {
+                        insert_path(&mut record, name, value);
+                    }
+                }
+                record
+            })
+            .boxed()
+    }
+
+    /// 任取一个消息定义（含各版本）及其原始值记录
+    fn message_record() -> impl Strategy<Value = (MessageSchema, Record)> {
+        prop::sample::select(ProtocolSchema::global().messages().to_vec())
+            .prop_flat_map(|message| (record(&message.fields), Just(message)))
+            .prop_map(|(record, message)| (message, record))
+    }
+
+    proptest! {
+        #[test]
+        fn test_every_message_round_trips((message, record) in message_record()) {
+            let bytes = message.encode(&record).unwrap();
+            prop_assert!(bytes.len() >= message.min_size);
+            prop_assert!(message.validate(&record).is_ok());
+            prop_assert_eq!(message.decode(&bytes).unwrap(), record);
+        }
+
+        /// 构建器按结构体构建的数据域，经解析器（严格模式）还原为同一结构体
+        #[test]
+        fn test_builder_to_parser_round_trip(
+            (message, record) in message_record().prop_filter("需要结构体", |(m, _)| m.variant.is_some())
+        ) {
+            let typed = serde_json::to_value(message.to_parsed(record).unwrap()).unwrap();
+            let inner = typed.as_object().and_then(|m| m.values().next()).unwrap();
+            let built = ProtocolBuilder::new().build_message(&message.name, message.version, inner).unwrap();
+            let reparsed = match message.receive {
+                Some(message_type) => {
+                    let result = ProtocolParser::with_version(true, message.version).parse_protocol(message_type, &built);
+                    result.data.ok_or_else(|| TestCaseError::fail(format!("{:?}", result.error)))?
+                }
+                None => message.to_parsed(message.decode(&built).unwrap()).unwrap(),
+            };
+            prop_assert_eq!(serde_json::to_value(reparsed).unwrap(), typed);
+        }
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected() {
+        let trailing = r#"
+            [[message]]
+            name = "BAD"
+            label = "错误"
+            fields = [
+                { name = "tail", type = "bytes" },
+                { name = "id", type = "u8" },
+            ]
+        "#;
+        assert!(ProtocolSchema::from_toml(trailing).is_err());
+
+        let optional = r#"
+            [[message]]
+            name = "BAD"
+            label = "错误"
+            fields = [
+                { name = "x", type = "f64", optional = true },
+                { name = "id", type = "u8" },
+            ]
+        "#;
+        assert!(ProtocolSchema::from_toml(optional).is_err());
+    }
+}
@@ -54,7 +54,9 @@ impl GearPosition {
             GearPosition::Park => 1,
             GearPosition::Reverse => 2,
             GearPosition::Neutral => 3,
-            GearPosition::DriveLevel(level) => level.saturating_add(3),
+            GearPosition::DriveLevel(level @ 1..=6) => level + 3,
+            // 未知档位按原始值 0 写出，与 from_u8 往返一致（不与空挡混淆）
+            GearPosition::DriveLevel(_) => 0,
         }
     }
 
@@ -337,6 +339,7 @@ pub fn nav_status_text(status: u8) -> &'static str {
     }
 }
 
+/// 协议偏移量常量（与 `messages.toml` 一致，由 schema 测试校验）
 pub struct ProtocolConstants;
 
 impl ProtocolConstants {
@@ -355,6 +358,9 @@ impl ProtocolConstants {
     pub const VEHICLE_INFO_GYRO_STATUS_OFFSET: usize = 53;
     pub const VEHICLE_INFO_PARKING_SLOT_OFFSET: usize = 54;
     pub const VEHICLE_INFO_TOTAL_SIZE: usize = 55;
+
+    // 帧协议版本
+    pub const PROTOCOL_VERSION_V10: u8 = 0x10;
     
     /// 车辆控制协议偏移量
     pub const VEHICLE_CONTROL_VEHICLE_ID_OFFSET: usize = 0;
@@ -427,6 +433,9 @@ pub enum ProtocolError {
     #[error("数据长度无效: {length}")]
     InvalidPayloadLength { length: usize },
 
+    #[error("协议字段错误: {0}")]
+    FieldError(String),
+
     #[error("零拷贝转换失败: {0}")]
     ZeroCopyError(String),
     
@@ -450,6 +459,8 @@ impl MessageTypes {
     pub const AVP_PICKUP: u16 = 0x0006;
     pub const DATA_RECORDING: u16 = 0x0007;
     pub const CONSTRUCTION_MARKER: u16 = 0x0008;
+    pub const VEHICLE_REGISTER: u16 = 0x0009;     // 车辆注册（车辆编号 + 对服务器挑战的HMAC）
+    pub const COMMAND_ACK: u16 = 0x000A;          // 指令确认（序号 + 原消息类型 + 结果码，非0为NACK）
     pub const VEHICLE_CAMERA_TOGGLE: u16 = 0x1009;
     pub const SANDBOX_LIGHTING_CONTROL: u16 = 0x2003;
     pub const SANDBOX_TRAFFIC_LIGHT_STATUS: u16 = 0x3001;
@@ -465,6 +476,8 @@ impl MessageTypes {
             Self::AVP_PICKUP => "AVP取车",
             Self::DATA_RECORDING => "数据记录",
             Self::CONSTRUCTION_MARKER => "施工标记",
+            Self::VEHICLE_REGISTER => "车辆注册",
+            Self::COMMAND_ACK => "指令确认",
             Self::VEHICLE_CAMERA_TOGGLE => "车辆摄像头开关",
             Self::SANDBOX_LIGHTING_CONTROL => "沙盘灯光控制",
             Self::SANDBOX_TRAFFIC_LIGHT_STATUS => "沙盘红绿灯状态",
@@ -484,6 +497,8 @@ impl MessageTypes {
                 | Self::AVP_PICKUP
                 | Self::DATA_RECORDING
                 | Self::CONSTRUCTION_MARKER
+                | Self::VEHICLE_REGISTER
+                | Self::COMMAND_ACK
                 | Self::VEHICLE_CAMERA_TOGGLE
                 | Self::SANDBOX_LIGHTING_CONTROL
                 | Self::SANDBOX_TRAFFIC_LIGHT_STATUS
@@ -495,6 +510,7 @@ impl MessageTypes {
 pub struct SendMessageTypes;
 
 impl SendMessageTypes {
+    pub const HEARTBEAT: u16 = 0x0001;                 // 服务器心跳（空数据域）
     pub const VEHICLE_CONTROL: u16 = 0x1001;           // 车辆控制指令
     pub const DATA_RECORDING: u16 = 0x1002;            // 数据记录控制
     pub const TAXI_ORDER: u16 = 0x1003;                // 出租车订单
@@ -504,13 +520,16 @@ impl SendMessageTypes {
     pub const VEHICLE_PATH_DISPLAY: u16 = 0x1007;      // 车辆路径显示控制
     pub const CONSTRUCTION_MARKER: u16 = 0x1008;       // 施工标记
     pub const VEHICLE_CAMERA_TOGGLE: u16 = 0x1009;     // 车载摄像头开关
+    pub const AUTH_CHALLENGE: u16 = 0x100A;            // 接入认证挑战（16字节随机数）
+    pub const AUTH_RESULT: u16 = 0x100B;               // 接入认证结果
+    pub const TAXI_ORDER_CANCEL: u16 = 0x100C;         // 出租车订单取消（车辆放弃已下发的订单）
     pub const SANDBOX_LIGHTING_CONTROL: u16 = 0x2003;  // 沙盘灯光控制
 }
 
-/// 获取当前时间戳（微秒）
+/// 获取单调时钟时间戳（微秒，自进程首次调用起计）
+///
+/// 仅用于耗时统计；不随系统时间回拨而倒退，相减不会下溢
 pub fn current_timestamp_us() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_micros() as u64)
-        .unwrap_or(0)
+    static EPOCH: once_cell::sync::Lazy<std::time::Instant> = once_cell::sync::Lazy::new(std::time::Instant::now);
+    EPOCH.elapsed().as_micros() as u64
 }
@@ -2,9 +2,18 @@
 //! 
 //! 提供全面的协议数据验证功能，确保数据完整性和有效性
 
+use crate::protocol_processing::schema::{MessageSchema, ProtocolSchema};
 use crate::protocol_processing::types::*;
+use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
+/// 车辆信息的协议定义（只取字段取值范围）
+static VEHICLE_INFO_SCHEMA: Lazy<&'static MessageSchema> = Lazy::new(|| {
+    ProtocolSchema::global()
+        .by_variant("VehicleInfo", ProtocolConstants::PROTOCOL_VERSION_V10)
+        .expect("messages.toml 缺少车辆信息定义")
+});
+
 /// 协议验证器
 pub struct ProtocolValidator {
     /// 验证规则配置
@@ -93,151 +102,49 @@ impl ProtocolValidator {
         result
     }
     
-    /// 执行验证逻辑
+    /// 执行验证逻辑：取值范围来自 `messages.toml`，跨字段的逻辑约束在此单独检查
     fn perform_validation(&self, data: &ParsedProtocolData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        match data {
-            ParsedProtocolData::VehicleInfo(info) => self.validate_vehicle_info(info, start_time, timeout),
-            ParsedProtocolData::PathFileSelection(selection) => self.validate_path_file_selection(selection, start_time, timeout),
-            ParsedProtocolData::VehicleControl(cmd) => self.validate_vehicle_control(cmd, start_time, timeout),
-            ParsedProtocolData::TaxiOrder(order) => self.validate_taxi_order(order, start_time, timeout),
-            ParsedProtocolData::AvpParking(parking) => self.validate_avp_parking(parking, start_time, timeout),
-            ParsedProtocolData::AvpPickup(pickup) => self.validate_avp_pickup(pickup, start_time, timeout),
-            ParsedProtocolData::DataRecording(recording) => self.validate_data_recording(recording, start_time, timeout),
-            ParsedProtocolData::ConstructionMarker(marker) => self.validate_construction_marker(marker, start_time, timeout),
-            ParsedProtocolData::VehicleFunctionSetting(_) | ParsedProtocolData::VehiclePathDisplay(_) => Ok(()),
-            ParsedProtocolData::VehicleCameraToggle(toggle) => self.validate_vehicle_camera_toggle(toggle),
-            ParsedProtocolData::SandboxLighting(lighting) => self.validate_sandbox_lighting(lighting),
-            ParsedProtocolData::SandboxTrafficLightStatus(status) => self.validate_sandbox_traffic_light_status(status),
-        }
-    }
-
-    fn validate_vehicle_camera_toggle(&self, toggle: &VehicleCameraToggleData) -> Result<(), ProtocolError> {
-        if toggle.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".into(),
-                value: toggle.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        if !matches!(toggle.enabled, 0 | 1) {
-            return Err(ProtocolError::ValidationError {
-                field: "enabled".into(),
-                value: toggle.enabled as f64,
-                min: 0.0,
-                max: 1.0,
-            });
-        }
-        Ok(())
-    }
+        self.check_timeout(start_time, timeout)?;
 
-    fn validate_sandbox_lighting(&self, lighting: &SandboxLightingData) -> Result<(), ProtocolError> {
-        for (field, value) in [
-            ("barrier", lighting.barrier),
-            ("ambient", lighting.ambient),
-            ("building", lighting.building),
-            ("street", lighting.street),
-        ] {
-            if !matches!(value, 0 | 1) {
-                return Err(ProtocolError::ValidationError {
-                    field: field.to_string(),
-                    value: value as f64,
-                    min: 0.0,
-                    max: 1.0,
-                });
+        if self.config.range_check {
+            if let Some(violation) = Self::range_violations(data)?.into_iter().next() {
+                return Err(violation);
             }
         }
-        Ok(())
-    }
 
-    fn validate_sandbox_traffic_light_status(&self, status: &SandboxTrafficLightStatusData) -> Result<(), ProtocolError> {
-        for light in &status.lights {
-            if !matches!(light.color, 1 | 2 | 3) {
-                return Err(ProtocolError::ValidationError {
-                    field: format!("light_color_{}", light.index),
-                    value: light.color as f64,
-                    min: 1.0,
-                    max: 3.0,
-                });
-            }
-            if light.remaining > 120 {
-                return Err(ProtocolError::ValidationError {
-                    field: format!("light_remaining_{}", light.index),
-                    value: light.remaining as f64,
-                    min: 0.0,
-                    max: 120.0,
-                });
+        match data {
+            ParsedProtocolData::VehicleInfo(info) => {
+                // 逻辑验证
+                if self.config.logic_check {
+                    self.validate_vehicle_logic(info)?;
+                }
+                // 性能验证
+                if self.config.performance_check {
+                    self.validate_vehicle_performance(info)?;
+                }
+                Ok(())
             }
+            ParsedProtocolData::VehicleControl(cmd) => self.validate_vehicle_control(cmd),
+            ParsedProtocolData::TaxiOrder(order) if self.config.logic_check => self.validate_taxi_order(order),
+            _ => Ok(()),
         }
-        Ok(())
     }
     
-    /// 验证车辆信息
-    fn validate_vehicle_info(&self, info: &VehicleInfo, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        // 基本范围验证
-        if self.config.range_check {
-            self.validate_speed_range(info.speed)?;
-            self.validate_battery_range(info.battery)?;
-            self.validate_steering_angle_range(info.steering_angle)?;
-            self.validate_gear_range(info.gear)?;
-            self.validate_nav_status_range(info.nav_status)?;
-        }
-        
-        // 逻辑验证
-        if self.config.logic_check {
-            self.validate_vehicle_logic(info)?;
-        }
-        
-        // 性能验证
-        if self.config.performance_check {
-            self.validate_vehicle_performance(info)?;
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证路径文件选择
-    fn validate_path_file_selection(&self, selection: &PathFileSelectionData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        // 验证车辆ID
-        if selection.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".into(),
-                value: selection.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
+    /// 按协议定义的取值范围检查，返回全部越界字段（不计入验证统计）
+    pub fn range_violations(data: &ParsedProtocolData) -> Result<Vec<ProtocolError>, ProtocolError> {
+        match data {
+            // 车辆信息逐帧到达，直接按字段检查，不经原始值记录
+            ParsedProtocolData::VehicleInfo(info) => Ok(vehicle_info_violations(info)),
+            _ => {
+                let (schema, record) =
+                    ProtocolSchema::global().parsed_record(data, ProtocolConstants::PROTOCOL_VERSION_V10)?;
+                Ok(schema.violations(&record))
+            }
         }
-        
-        // 路径文件ID列表可以为空，所以不需要验证数量
-        // 每个路径文件ID范围为 0-255，已经由 u8 类型保证
-        
-        Ok(())
     }
-    
-    /// 验证车辆控制指令
-    fn validate_vehicle_control(&self, cmd: &VehicleControlCommand, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        // 验证车辆ID
-        if cmd.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".to_string(),
-                value: cmd.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        // 验证位置数据（如果存在）
-        if let Some(position) = &cmd.position_data {
-            self.validate_position_data(position)?;
-        }
-        
-        // 逻辑验证：初始化位姿命令必须包含位置数据
+
+    /// 验证车辆控制指令：初始化位姿命令必须包含位置数据
+    fn validate_vehicle_control(&self, cmd: &VehicleControlCommand) -> Result<(), ProtocolError> {
         if matches!(cmd.command, ControlCommandType::InitPose) && cmd.position_data.is_none() {
             return Err(ProtocolError::ValidationError {
                 field: "position_data".to_string(),
@@ -250,207 +157,15 @@ impl ProtocolValidator {
         Ok(())
     }
     
-    /// 验证出租车订单
-    fn validate_taxi_order(&self, order: &TaxiOrderData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        if order.vehicle_id == 0 {
+    /// 验证出租车订单：起点和终点不能相同
+    fn validate_taxi_order(&self, order: &TaxiOrderData) -> Result<(), ProtocolError> {
+        let distance = ((order.end_x - order.start_x).powi(2) + (order.end_y - order.start_y).powi(2)).sqrt();
+        if distance < 0.1 {
             return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".to_string(),
-                value: order.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        // 验证起点和终点不能相同
-        if self.config.logic_check {
-            let distance = ((order.end_x - order.start_x).powi(2) + (order.end_y - order.start_y).powi(2)).sqrt();
-            if distance < 0.1 {
-                return Err(ProtocolError::ValidationError {
-                    field: "distance".to_string(),
-                    value: distance,
-                    min: 0.1,
-                    max: f64::MAX,
-                });
-            }
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证AVP泊车
-    fn validate_avp_parking(&self, parking: &AvpParkingData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        if parking.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".to_string(),
-                value: parking.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        if parking.parking_spot == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "parking_spot".to_string(),
-                value: parking.parking_spot as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证AVP取车
-    fn validate_avp_pickup(&self, pickup: &AvpPickupData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        if pickup.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".to_string(),
-                value: pickup.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证数据记录
-    fn validate_data_recording(&self, recording: &DataRecordingData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        if recording.vehicle_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "vehicle_id".to_string(),
-                value: recording.vehicle_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        // 验证动作类型
-        if recording.action > 2 {
-            return Err(ProtocolError::ValidationError {
-                field: "action".to_string(),
-                value: recording.action as f64,
-                min: 0.0,
-                max: 2.0,
-            });
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证施工标记
-    fn validate_construction_marker(&self, marker: &ConstructionMarkerData, start_time: u64, timeout: u64) -> Result<(), ProtocolError> {
-        self.check_timeout(start_time, timeout)?;
-        
-        if marker.marker_id == 0 {
-            return Err(ProtocolError::ValidationError {
-                field: "marker_id".to_string(),
-                value: marker.marker_id as f64,
-                min: 1.0,
-                max: 255.0,
-            });
-        }
-        
-        // 验证动作类型
-        if marker.action > 1 {
-            return Err(ProtocolError::ValidationError {
-                field: "action".to_string(),
-                value: marker.action as f64,
-                min: 0.0,
-                max: 1.0,
-            });
-        }
-        
-        Ok(())
-    }
-    
-    /// 验证速度范围
-    fn validate_speed_range(&self, speed: f64) -> Result<(), ProtocolError> {
-        if speed < ProtocolConstants::MIN_SPEED || speed > ProtocolConstants::MAX_SPEED {
-            Err(ProtocolError::ValidationError {
-                field: "speed".to_string(),
-                value: speed,
-                min: ProtocolConstants::MIN_SPEED,
-                max: ProtocolConstants::MAX_SPEED,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证电池电量范围
-    fn validate_battery_range(&self, battery: f64) -> Result<(), ProtocolError> {
-        if battery < ProtocolConstants::MIN_BATTERY || battery > ProtocolConstants::MAX_BATTERY {
-            Err(ProtocolError::ValidationError {
-                field: "battery".to_string(),
-                value: battery,
-                min: ProtocolConstants::MIN_BATTERY,
-                max: ProtocolConstants::MAX_BATTERY,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证方向盘角度范围
-    fn validate_steering_angle_range(&self, angle: f64) -> Result<(), ProtocolError> {
-        if angle < ProtocolConstants::MIN_STEERING_ANGLE || angle > ProtocolConstants::MAX_STEERING_ANGLE {
-            Err(ProtocolError::ValidationError {
-                field: "steering_angle".to_string(),
-                value: angle,
-                min: ProtocolConstants::MIN_STEERING_ANGLE,
-                max: ProtocolConstants::MAX_STEERING_ANGLE,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证档位范围
-    fn validate_gear_range(&self, gear: GearPosition) -> Result<(), ProtocolError> {
-        match gear {
-            GearPosition::Park | GearPosition::Reverse | GearPosition::Neutral => Ok(()),
-            GearPosition::DriveLevel(level) if (1..=6).contains(&level) => Ok(()),
-            _ => Err(ProtocolError::ValidationError {
-                field: "gear".to_string(),
-                value: gear.to_u8() as f64,
-                min: 1.0,
-                max: 9.0,
-            }),
-        }
-    }
-    
-    /// 验证导航状态范围
-    fn validate_nav_status_range(&self, status: u8) -> Result<(), ProtocolError> {
-        if status > 3 { // 0: 停止, 1: 导航中, 2: 到达, 3: 错误
-            Err(ProtocolError::ValidationError {
-                field: "nav_status".to_string(),
-                value: status as f64,
-                min: 0.0,
-                max: 3.0,
-            })
-        } else {
-            Ok(())
-        }
-    }
-    
-    /// 验证位置数据
-    fn validate_position_data(&self, position: &PositionData) -> Result<(), ProtocolError> {
-        // 验证角度范围
-        if position.orientation < -180.0 || position.orientation > 180.0 {
-            return Err(ProtocolError::ValidationError {
-                field: "orientation".to_string(),
-                value: position.orientation,
-                min: -180.0,
-                max: 180.0,
+                field: "distance".to_string(),
+                value: distance,
+                min: 0.1,
+                max: f64::MAX,
             });
         }
         
@@ -573,6 +288,31 @@ impl ProtocolValidator {
     }
 }
 
+/// 按协议定义的取值范围检查车辆信息各数值字段
+fn vehicle_info_violations(info: &VehicleInfo) -> Vec<ProtocolError> {
+    let mut violations = Vec::new();
+    for field in &VEHICLE_INFO_SCHEMA.fields {
+        let value = match field.name.as_str() {
+            "vehicle_id" => f64::from(info.vehicle_id),
+            "speed" => info.speed,
+            "position_x" => info.position_x,
+            "position_y" => info.position_y,
+            "orientation" => info.orientation,
+            "battery" => info.battery,
+            "gear" => f64::from(info.gear.to_u8()),
+            "steering_angle" => info.steering_angle,
+            "nav_status" => f64::from(info.nav_status),
+            "parking_slot" => f64::from(info.parking_slot),
+            // 传感器状态为布尔值，没有取值范围
+            _ => continue,
+        };
+        if let Err(e) = field.check_range(&field.name, value) {
+            violations.push(e);
+        }
+    }
+    violations
+}
+
 impl Default for ProtocolValidator {
     fn default() -> Self {
         Self::new(ValidationConfig {
@@ -640,7 +380,7 @@ mod tests {
         
         let data = ParsedProtocolData::VehicleInfo(info);
         let result = validator.validate(&data);
-        assert!(result.is_err());
+        assert!(matches!(result, Err(ProtocolError::ValidationError { ref field, .. }) if field == "speed"));
     }
     
     #[test]
@@ -0,0 +1,155 @@
+//! 定长数据域的线上布局视图
+//!
+//! 字段使用小端字节序类型声明，由 `zerocopy` 在编译期证明：任意字节都是合法取值、
+//! 结构体无填充且无对齐要求。因此可直接从网络缓冲区的任意偏移借用视图，无需 unsafe。
+//! 各视图与 `messages.toml` 中对应消息的长度和字段偏移由测试逐一校验。
+
+use crate::protocol_processing::types::*;
+use zerocopy::little_endian::F64;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+/// 已与协议定义核对过布局的线上定长结构
+pub trait WireLayout: FromBytes + IntoBytes + KnownLayout + Immutable + Unaligned {}
+
+/// 车辆信息（0x10 布局）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+pub struct VehicleInfoRaw {
+    pub vehicle_id: u8,
+    pub speed: F64,
+    pub position_x: F64,
+    pub position_y: F64,
+    pub orientation: F64,
+    pub battery: F64,
+    pub gear: u8,
+    pub steering_angle: F64,
+    pub nav_status: u8,
+    pub camera_status: u8,
+    pub lidar_status: u8,
+    pub gyro_status: u8,
+    pub parking_slot: u8,
+}
+
+/// 车辆控制指令（不带位姿）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+pub struct VehicleControlRaw {
+    pub vehicle_id: u8,
+    pub command: u8,
+}
+
+/// 车辆控制指令（初始化位姿时携带位置数据）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+pub struct VehicleControlWithPositionRaw {
+    pub vehicle_id: u8,
+    pub command: u8,
+    pub x: F64,
+    pub y: F64,
+    pub orientation: F64,
+}
+
+impl WireLayout for VehicleInfoRaw {}
+
+impl WireLayout for VehicleControlRaw {}
+
+impl WireLayout for VehicleControlWithPositionRaw {}
+
+impl VehicleInfoRaw {
+    pub fn to_vehicle_info(self) -> VehicleInfo {
+        VehicleInfo {
+            vehicle_id: self.vehicle_id,
+            speed: self.speed.get(),
+            position_x: self.position_x.get(),
+            position_y: self.position_y.get(),
+            orientation: self.orientation.get(),
+            battery: self.battery.get(),
+            gear: GearPosition::from_u8(self.gear),
+            steering_angle: self.steering_angle.get(),
+            nav_status: self.nav_status,
+            sensors: SensorStatus {
+                camera: self.camera_status != 0,
+                lidar: self.lidar_status != 0,
+                gyro: self.gyro_status != 0,
+            },
+            parking_slot: self.parking_slot,
+        }
+    }
+}
+
+impl VehicleControlWithPositionRaw {
+    pub fn new(vehicle_id: u8, command: u8, position: &PositionData) -> Self {
+        Self {
+            vehicle_id,
+            command,
+            x: F64::new(position.x),
+            y: F64::new(position.y),
+            orientation: F64::new(position.orientation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::schema::ProtocolSchema;
+    use std::mem::{offset_of, size_of};
+    use zerocopy::FromZeros;
+
+    /// 视图的长度与字段偏移必须与协议定义一致
+    fn assert_layout<T: WireLayout>(name: &str, version: u8, offsets: &[(&str, usize)]) {
+        let message = ProtocolSchema::global()
+            .by_name(name, version)
+            .unwrap_or_else(|| panic!("{} 0x{:02X} 未定义", name, version));
+        let fields: Vec<(&str, usize)> = message
+            .fields
+            .iter()
+            .take(offsets.len())
+            .map(|f| (f.name.as_str(), f.offset))
+            .collect();
+        assert_eq!(fields, offsets, "{} 0x{:02X}", name, version);
+
+        let expected = if offsets.len() == message.fields.len() {
+            message.total_size
+        } else {
+            Some(message.min_size)
+        };
+        assert_eq!(Some(size_of::<T>()), expected, "{} 0x{:02X}", name, version);
+    }
+
+    macro_rules! offsets {
+        ($ty:ty, $name:literal, $version:expr, { $($schema:literal => $field:ident),* $(,)? }) => {
+            assert_layout::<$ty>($name, $version, &[$(($schema, offset_of!($ty, $field))),*])
+        };
+    }
+
+    #[test]
+    fn test_layouts_match_schema() {
+        let v10 = ProtocolConstants::PROTOCOL_VERSION_V10;
+        offsets!(VehicleInfoRaw, "VEHICLE_INFO", v10, {
+            "vehicle_id" => vehicle_id, "speed" => speed, "position_x" => position_x,
+            "position_y" => position_y, "orientation" => orientation, "battery" => battery,
+            "gear" => gear, "steering_angle" => steering_angle, "nav_status" => nav_status,
+            "sensors.camera" => camera_status, "sensors.lidar" => lidar_status,
+            "sensors.gyro" => gyro_status, "parking_slot" => parking_slot,
+        });
+        offsets!(VehicleControlRaw, "VEHICLE_CONTROL", v10, { "vehicle_id" => vehicle_id, "command" => command });
+        offsets!(VehicleControlWithPositionRaw, "VEHICLE_CONTROL", v10, {
+            "vehicle_id" => vehicle_id, "command" => command, "position_data.x" => x,
+            "position_data.y" => y, "position_data.orientation" => orientation,
+        });
+    }
+
+    #[test]
+    fn test_view_at_unaligned_offset() {
+        let mut raw = VehicleInfoRaw::new_zeroed();
+        raw.vehicle_id = 3;
+        raw.speed = F64::new(0.5);
+        // 前置一个字节，使浮点字段落在奇数地址
+        let buffer = [&[0xAA][..], raw.as_bytes()].concat();
+        let (view, rest) = VehicleInfoRaw::ref_from_prefix(&buffer[1..]).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!((view.vehicle_id, view.speed.get()), (3, 0.5));
+        assert!(VehicleInfoRaw::ref_from_prefix(&buffer[2..]).is_err());
+    }
+}
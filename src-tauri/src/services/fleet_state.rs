@@ -0,0 +1,498 @@
+//! 车队状态服务
+//!
+//! 集中保存每辆已连接车辆的最新 `VehicleInfo`、连接信息、最近活动时间与派生字段：
+//! Socket 服务负责写入，命令层查询快照，状态变化以字段级差异广播给订阅者。
+//! 车辆信息的变化检测（合并推送去重、状态比对命令）统一使用 [`diff_vehicle_info`]。
+
+use crate::protocol_processing::types::VehicleInfo;
+use crate::socket::event_sink::SharedEventSink;
+use log::{debug, warn};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// 浮点字段视为未变化的最大差值
+pub const FLOAT_EPSILON: f64 = 1e-6;
+/// 车速超过该值视为行驶中（m/s）
+const MOVING_SPEED: f64 = 0.01;
+/// 电量低于该值视为低电量（%）
+const LOW_BATTERY: f64 = 20.0;
+/// 变化广播通道容量（订阅者处理过慢时丢弃最旧的变化）
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// 单个字段的变化
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    /// 首次上报时没有旧值
+    pub old: Option<serde_json::Value>,
+    pub new: serde_json::Value,
+}
+
+enum FieldValue {
+    Float(f64),
+    Exact(serde_json::Value),
+}
+
+impl FieldValue {
+    fn same_as(&self, other: &FieldValue) -> bool {
+        match (self, other) {
+            (FieldValue::Float(a), FieldValue::Float(b)) => (a - b).abs() <= FLOAT_EPSILON,
+            (FieldValue::Exact(a), FieldValue::Exact(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            FieldValue::Float(value) => serde_json::json!(value),
+            FieldValue::Exact(value) => value.clone(),
+        }
+    }
+}
+
+/// 参与比对的字段（名称与 `is_vehicle_state_changed` 返回的字段名一致）
+fn vehicle_fields(info: &VehicleInfo) -> [(&'static str, FieldValue); 12] {
+    [
+        ("speed", FieldValue::Float(info.speed)),
+        ("position_x", FieldValue::Float(info.position_x)),
+        ("position_y", FieldValue::Float(info.position_y)),
+        ("orientation", FieldValue::Float(info.orientation)),
+        ("battery", FieldValue::Float(info.battery)),
+        ("gear", FieldValue::Exact(serde_json::json!(info.gear))),
+        ("steering_angle", FieldValue::Float(info.steering_angle)),
+        ("nav_status", FieldValue::Exact(serde_json::json!(info.nav_status))),
+        ("sensors.camera", FieldValue::Exact(serde_json::json!(info.sensors.camera))),
+        ("sensors.lidar", FieldValue::Exact(serde_json::json!(info.sensors.lidar))),
+        ("sensors.gyro", FieldValue::Exact(serde_json::json!(info.sensors.gyro))),
+        ("parking_slot", FieldValue::Exact(serde_json::json!(info.parking_slot))),
+    ]
+}
+
+/// 比对两次车辆信息，返回变化的字段
+///
+/// 没有旧值时所有字段都视为变化；车辆编号不同时只返回 `vehicle_id`（不同车辆的状态不可比）
+pub fn diff_vehicle_info(prev: Option<&VehicleInfo>, next: &VehicleInfo) -> Vec<FieldChange> {
+    let Some(prev) = prev else {
+        return vehicle_fields(next)
+            .into_iter()
+            .map(|(field, value)| FieldChange { field, old: None, new: value.to_json() })
+            .collect();
+    };
+    if prev.vehicle_id != next.vehicle_id {
+        return vec![FieldChange {
+            field: "vehicle_id",
+            old: Some(serde_json::json!(prev.vehicle_id)),
+            new: serde_json::json!(next.vehicle_id),
+        }];
+    }
+    vehicle_fields(prev)
+        .into_iter()
+        .zip(vehicle_fields(next))
+        .filter(|((_, old), (_, new))| !old.same_as(new))
+        .map(|((field, old), (_, new))| FieldChange { field, old: Some(old.to_json()), new: new.to_json() })
+        .collect()
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// 车辆连接信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub addr: String,
+    pub authenticated: bool,
+    /// 连接建立时间（Unix 毫秒）
+    pub connected_at: u64,
+}
+
+/// 由车辆信息与活动时间推导的字段
+#[derive(Debug, Clone, Serialize)]
+pub struct DerivedState {
+    pub online: bool,
+    pub moving: bool,
+    pub low_battery: bool,
+    /// 摄像头、激光雷达、陀螺仪均正常
+    pub sensors_ok: bool,
+    /// 距最近一次收到该车辆消息的毫秒数
+    pub idle_ms: u64,
+}
+
+/// 单车状态快照
+#[derive(Debug, Clone, Serialize)]
+pub struct VehicleSnapshot {
+    pub vehicle_id: i32,
+    pub vehicle_name: String,
+    pub connection: Option<ConnectionInfo>,
+    pub info: Option<VehicleInfo>,
+    /// 最新车辆信息帧的时间戳
+    pub info_timestamp: Option<u64>,
+    /// 最近一次收到该车辆消息的时间（Unix 毫秒）
+    pub last_seen: u64,
+    pub derived: DerivedState,
+}
+
+/// 状态变化类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Connected,
+    Updated,
+    /// 连接编号按车辆上报的编号纠正
+    Rekeyed,
+    Disconnected,
+}
+
+/// 状态变化通知
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetStateChange {
+    pub vehicle_id: i32,
+    pub vehicle_name: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_id: Option<i32>,
+    pub changes: Vec<FieldChange>,
+    /// 变化时间（Unix 毫秒）
+    pub timestamp: u64,
+}
+
+/// 订阅过滤条件（为空表示不过滤）
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChangeFilter {
+    pub vehicle_ids: Option<Vec<i32>>,
+    /// 字段名或字段前缀（如 `sensors` 匹配所有传感器字段）
+    pub fields: Option<Vec<String>>,
+}
+
+impl ChangeFilter {
+    fn matches_field(&self, field: &str) -> bool {
+        self.fields.as_ref().map_or(true, |fields| {
+            fields.iter().any(|f| field == f || field.strip_prefix(f.as_str()).is_some_and(|rest| rest.starts_with('.')))
+        })
+    }
+
+    /// 按条件裁剪变化；状态更新在过滤字段后没有剩余变化时丢弃，连接类变化总是保留
+    fn apply(&self, change: &FleetStateChange) -> Option<FleetStateChange> {
+        let vehicle_matches = self.vehicle_ids.as_ref().map_or(true, |ids| {
+            ids.contains(&change.vehicle_id) || change.previous_id.is_some_and(|id| ids.contains(&id))
+        });
+        if !vehicle_matches {
+            return None;
+        }
+        let mut change = change.clone();
+        change.changes.retain(|c| self.matches_field(c.field));
+        (change.kind != ChangeKind::Updated || !change.changes.is_empty()).then_some(change)
+    }
+}
+
+struct VehicleRecord {
+    vehicle_name: String,
+    connection: Option<ConnectionInfo>,
+    info: Option<VehicleInfo>,
+    info_timestamp: Option<u64>,
+    last_seen: u64,
+}
+
+impl VehicleRecord {
+    fn new(vehicle_name: &str) -> Self {
+        Self {
+            vehicle_name: vehicle_name.to_string(),
+            connection: None,
+            info: None,
+            info_timestamp: None,
+            last_seen: now_millis(),
+        }
+    }
+
+    fn snapshot(&self, vehicle_id: i32, now: u64) -> VehicleSnapshot {
+        let info = self.info.as_ref();
+        VehicleSnapshot {
+            vehicle_id,
+            vehicle_name: self.vehicle_name.clone(),
+            connection: self.connection.clone(),
+            info: self.info.clone(),
+            info_timestamp: self.info_timestamp,
+            last_seen: self.last_seen,
+            derived: DerivedState {
+                online: self.connection.is_some(),
+                moving: info.is_some_and(|i| i.speed.abs() > MOVING_SPEED),
+                low_battery: info.is_some_and(|i| i.battery < LOW_BATTERY),
+                sensors_ok: info.is_some_and(|i| i.sensors.camera && i.sensors.lidar && i.sensors.gyro),
+                idle_ms: now.saturating_sub(self.last_seen),
+            },
+        }
+    }
+}
+
+struct FleetInner {
+    vehicles: RwLock<BTreeMap<i32, VehicleRecord>>,
+    changes: broadcast::Sender<FleetStateChange>,
+    subscriptions: Mutex<HashMap<u64, JoinHandle<()>>>,
+    next_subscription: AtomicU64,
+}
+
+/// 车队状态（克隆共享同一份状态）
+#[derive(Clone)]
+pub struct FleetState {
+    inner: Arc<FleetInner>,
+}
+
+impl Default for FleetState {
+    fn default() -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            inner: Arc::new(FleetInner {
+                vehicles: RwLock::new(BTreeMap::new()),
+                changes,
+                subscriptions: Mutex::new(HashMap::new()),
+                next_subscription: AtomicU64::new(1),
+            }),
+        }
+    }
+}
+
+impl FleetState {
+    /// 持有写锁时发布，保证订阅者收到的变化顺序与写入顺序一致
+    fn publish(&self, vehicle_id: i32, record: &VehicleRecord, kind: ChangeKind, previous_id: Option<i32>, changes: Vec<FieldChange>) {
+        // 没有订阅者时发送失败属于正常情况
+        let _ = self.inner.changes.send(FleetStateChange {
+            vehicle_id,
+            vehicle_name: record.vehicle_name.clone(),
+            kind,
+            previous_id,
+            changes,
+            timestamp: now_millis(),
+        });
+    }
+
+    /// 车辆连接建立
+    pub fn connect(&self, vehicle_id: i32, vehicle_name: &str, addr: String, authenticated: bool) {
+        let mut vehicles = self.inner.vehicles.write();
+        let record = vehicles.entry(vehicle_id).or_insert_with(|| VehicleRecord::new(vehicle_name));
+        record.vehicle_name = vehicle_name.to_string();
+        record.connection = Some(ConnectionInfo { addr, authenticated, connected_at: now_millis() });
+        record.last_seen = now_millis();
+        self.publish(vehicle_id, record, ChangeKind::Connected, None, Vec::new());
+    }
+
+    /// 连接编号纠正：状态随连接迁移到新编号
+    pub fn rekey(&self, old_id: i32, new_id: i32, vehicle_name: &str) {
+        let mut vehicles = self.inner.vehicles.write();
+        let mut record = vehicles.remove(&old_id).unwrap_or_else(|| VehicleRecord::new(vehicle_name));
+        record.vehicle_name = vehicle_name.to_string();
+        self.publish(new_id, &record, ChangeKind::Rekeyed, Some(old_id), Vec::new());
+        vehicles.insert(new_id, record);
+    }
+
+    /// 记录车辆活动（心跳等不携带状态的消息）
+    pub fn touch(&self, vehicle_id: i32) {
+        if let Some(record) = self.inner.vehicles.write().get_mut(&vehicle_id) {
+            record.last_seen = now_millis();
+        }
+    }
+
+    /// 写入一帧车辆信息，返回相对上一帧变化的字段（为空表示内容未变化）
+    pub fn update_info(&self, vehicle_id: i32, vehicle_name: &str, info: &VehicleInfo, timestamp: u64) -> Vec<FieldChange> {
+        let mut vehicles = self.inner.vehicles.write();
+        let record = vehicles.entry(vehicle_id).or_insert_with(|| VehicleRecord::new(vehicle_name));
+        record.last_seen = now_millis();
+        record.info_timestamp = Some(timestamp);
+
+        let changes = diff_vehicle_info(record.info.as_ref(), info);
+        if !changes.is_empty() {
+            record.info = Some(info.clone());
+            self.publish(vehicle_id, record, ChangeKind::Updated, None, changes.clone());
+        }
+        changes
+    }
+
+    /// 车辆断开：移除其全部状态，返回是否已移除
+    ///
+    /// `addr` 为断开连接的对端地址；同一车辆已从新连接重新接入时，旧连接的断开不影响新连接
+    pub fn disconnect(&self, vehicle_id: i32, addr: &str) -> bool {
+        let mut vehicles = self.inner.vehicles.write();
+        let owned = vehicles
+            .get(&vehicle_id)
+            .is_some_and(|record| record.connection.as_ref().map_or(true, |conn| conn.addr == addr));
+        if !owned {
+            return false;
+        }
+        if let Some(record) = vehicles.remove(&vehicle_id) {
+            self.publish(vehicle_id, &record, ChangeKind::Disconnected, None, Vec::new());
+        }
+        true
+    }
+
+    /// 清除所有车辆（回放跳转时使用）
+    pub fn clear(&self) {
+        let mut vehicles = self.inner.vehicles.write();
+        for (vehicle_id, record) in std::mem::take(&mut *vehicles) {
+            self.publish(vehicle_id, &record, ChangeKind::Disconnected, None, Vec::new());
+        }
+    }
+
+    /// 所有车辆的状态快照（按车辆编号排序）
+    pub fn snapshot(&self) -> Vec<VehicleSnapshot> {
+        let now = now_millis();
+        self.inner
+            .vehicles
+            .read()
+            .iter()
+            .map(|(vehicle_id, record)| record.snapshot(*vehicle_id, now))
+            .collect()
+    }
+
+    /// 单车状态快照
+    pub fn get(&self, vehicle_id: i32) -> Option<VehicleSnapshot> {
+        self.inner.vehicles.read().get(&vehicle_id).map(|record| record.snapshot(vehicle_id, now_millis()))
+    }
+
+    /// 订阅全部状态变化
+    pub fn subscribe(&self) -> broadcast::Receiver<FleetStateChange> {
+        self.inner.changes.subscribe()
+    }
+
+    /// 按条件订阅状态变化，以 `fleet-state-change` 事件推送到事件出口，返回订阅编号
+    pub fn subscribe_events(&self, sink: SharedEventSink, filter: ChangeFilter) -> u64 {
+        let id = self.inner.next_subscription.fetch_add(1, Ordering::Relaxed);
+        let mut changes = self.subscribe();
+        let task = tokio::spawn(async move {
+            loop {
+                match changes.recv().await {
+                    Ok(change) => {
+                        let Some(change) = filter.apply(&change) else {
+                            continue;
+                        };
+                        let mut payload = serde_json::json!(change);
+                        payload["subscription_id"] = serde_json::json!(id);
+                        sink.emit("fleet-state-change", payload);
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("车队状态订阅 {} 处理过慢，跳过 {} 条变化", id, skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        self.inner.subscriptions.lock().insert(id, task);
+        debug!("新增车队状态订阅: {}", id);
+        id
+    }
+
+    /// 取消订阅，订阅不存在时返回 false
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        match self.inner.subscriptions.lock().remove(&id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::types::SensorStatus;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support;
+    use std::time::Duration;
+
+    fn sample_info(vehicle_id: u8, speed: f64, battery: f64) -> VehicleInfo {
+        let base = test_support::vehicle_info(vehicle_id);
+        let sensors = SensorStatus { lidar: false, ..base.sensors };
+        VehicleInfo { speed, position_x: 1.0, position_y: 2.0, battery, sensors, ..base }
+    }
+
+    #[test]
+    fn test_diff_reports_changed_fields() {
+        let prev = sample_info(1, 0.5, 80.0);
+        let mut next = sample_info(1, 0.5 + FLOAT_EPSILON / 2.0, 79.0);
+        next.sensors.lidar = true;
+
+        let changes = diff_vehicle_info(Some(&prev), &next);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, vec!["battery", "sensors.lidar"]);
+        assert_eq!((changes[0].old.clone(), changes[0].new.clone()), (Some(serde_json::json!(80.0)), serde_json::json!(79.0)));
+
+        assert_eq!(diff_vehicle_info(None, &next).len(), 12);
+        let other = diff_vehicle_info(Some(&prev), &sample_info(2, 0.5, 80.0));
+        assert_eq!(other.iter().map(|c| c.field).collect::<Vec<_>>(), vec!["vehicle_id"]);
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_and_snapshot() {
+        let state = FleetState::default();
+        let mut changes = state.subscribe();
+
+        state.connect(0, "未知车辆", "127.0.0.1:6000".to_string(), false);
+        state.rekey(0, 3, "车3");
+        assert!(!state.update_info(3, "车3", &sample_info(3, 0.5, 15.0), 10).is_empty());
+        assert!(state.update_info(3, "车3", &sample_info(3, 0.5, 15.0), 11).is_empty());
+
+        let snapshot = state.get(3).unwrap();
+        assert_eq!(snapshot.vehicle_name, "车3");
+        assert_eq!(snapshot.info_timestamp, Some(11));
+        assert!(snapshot.derived.online && snapshot.derived.moving && snapshot.derived.low_battery);
+        assert!(!snapshot.derived.sensors_ok);
+        assert!(state.get(0).is_none());
+
+        // 同一车辆从新连接重新接入后，旧连接的断开被忽略
+        state.connect(3, "车3", "127.0.0.1:6001".to_string(), false);
+        assert!(!state.disconnect(3, "127.0.0.1:6000"));
+        assert!(state.get(3).is_some());
+        assert!(state.disconnect(3, "127.0.0.1:6001"));
+        assert!(state.snapshot().is_empty());
+
+        let kinds: Vec<(ChangeKind, i32)> =
+            std::iter::from_fn(|| changes.try_recv().ok()).map(|c| (c.kind, c.vehicle_id)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ChangeKind::Connected, 0),
+                (ChangeKind::Rekeyed, 3),
+                (ChangeKind::Updated, 3),
+                (ChangeKind::Connected, 3),
+                (ChangeKind::Disconnected, 3)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription_events() {
+        let state = FleetState::default();
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let filter = ChangeFilter { vehicle_ids: Some(vec![1]), fields: Some(vec!["sensors".to_string()]) };
+        let id = state.subscribe_events(sink.clone(), filter);
+
+        state.update_info(2, "车2", &sample_info(2, 0.0, 80.0), 1);
+        state.update_info(1, "车1", &sample_info(1, 0.0, 80.0), 1);
+        // 只有电量变化，被字段过滤丢弃
+        state.update_info(1, "车1", &sample_info(1, 0.0, 70.0), 2);
+        let mut next = sample_info(1, 0.0, 70.0);
+        next.sensors.gyro = false;
+        state.update_info(1, "车1", &next, 3);
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+            received.push(event.payload);
+        }
+        assert_eq!(received[0]["subscription_id"], id);
+        assert_eq!(received[0]["changes"].as_array().unwrap().len(), 3);
+        assert_eq!(received[1]["changes"], serde_json::json!([{ "field": "sensors.gyro", "old": true, "new": false }]));
+
+        assert!(state.unsubscribe(id));
+        assert!(!state.unsubscribe(id));
+    }
+}
@@ -5,4 +5,8 @@
 pub mod vehicle;
 pub mod sandbox;
 pub mod path_loader;
-
+pub mod fleet_state;
+pub mod taxi_orders;
+pub mod taxi_dispatch;
+pub mod taxi_queue;
+pub mod parking_lot;
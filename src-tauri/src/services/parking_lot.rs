@@ -0,0 +1,589 @@
+//! AVP 停车场车位管理
+//!
+//! 车位布局（编号、位置、类型）保存在 `parking_slots` 表中，占用情况由车辆信息帧的
+//! `parking_slot` 字段维护：车辆上报停在某车位时占用该车位，上报 0 时释放；车辆断开后
+//! 保留最后上报的占用（车辆仍停在车位上）。占用车辆与最后确认时间同步保存到 `parking_slots` 表，
+//! 重启后恢复；离线车辆的占用超过 [`OCCUPANT_TTL`] 未再确认时释放（车辆可能已被移走或删除）。
+//!
+//! 下发 AVP 泊车指令前为车辆预留目标车位，车辆上报停入车位、断开连接或预留超时后解除预留。
+//! 已被占用或预留给其他车辆的车位拒绝泊车请求；未指定车位时自动分配离车辆最近的空闲车位。
+
+use crate::database::{ParkingSlot, VehicleDatabase};
+use crate::services::fleet_state::{ChangeKind, FleetState, FleetStateChange};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// 车辆在该时间内未停入预留车位时解除预留
+const RESERVATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 离线车辆的占用在最后确认后保留的时长
+pub const OCCUPANT_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// 检查离线车辆占用是否过期的间隔
+const OCCUPANT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 车位类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotKind {
+    Normal,
+    Charging,
+}
+
+impl SlotKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(SlotKind::Normal),
+            "charging" => Some(SlotKind::Charging),
+            _ => None,
+        }
+    }
+}
+
+/// 车位状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotState {
+    Free,
+    Reserved,
+    Occupied,
+}
+
+/// 车位及其当前状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotStatus {
+    pub slot_id: u8,
+    pub position_x: f64,
+    pub position_y: f64,
+    pub slot_type: SlotKind,
+    pub state: SlotState,
+    /// 占用或预留该车位的车辆
+    pub occupant: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SlotLayout {
+    position_x: f64,
+    position_y: f64,
+    kind: SlotKind,
+}
+
+struct Reservation {
+    vehicle_id: i32,
+    reserved_at: Instant,
+}
+
+struct Occupant {
+    vehicle_id: i32,
+    /// 最后一次上报或断开的时间
+    reported_at: DateTime<Utc>,
+    online: bool,
+}
+
+struct LotInner {
+    slots: BTreeMap<u8, SlotLayout>,
+    /// 车位 -> 上报停在该车位的车辆
+    occupants: HashMap<u8, Occupant>,
+    /// 车位 -> 预留给的车辆
+    reservations: HashMap<u8, Reservation>,
+    reservation_timeout: Duration,
+    occupant_ttl: Duration,
+    /// 占用有变化尚未保存
+    dirty: bool,
+}
+
+impl LotInner {
+    fn expire_occupants(&mut self) {
+        let now = Utc::now();
+        let ttl = self.occupant_ttl;
+        let before = self.occupants.len();
+        self.occupants.retain(|slot_id, occupant| {
+            let stale = !occupant.online && (now - occupant.reported_at).to_std().is_ok_and(|age| age >= ttl);
+            if stale {
+                info!("车辆 {} 离线后 {} 秒内未再上报，释放 {} 号车位", occupant.vehicle_id, ttl.as_secs(), slot_id);
+            }
+            !stale
+        });
+        self.dirty |= self.occupants.len() != before;
+    }
+
+    fn expire_reservations(&mut self) {
+        let timeout = self.reservation_timeout;
+        self.reservations.retain(|slot_id, reservation| {
+            let alive = reservation.reserved_at.elapsed() < timeout;
+            if !alive {
+                debug!("车辆 {} 未在预留时间内停入 {} 号车位，解除预留", reservation.vehicle_id, slot_id);
+            }
+            alive
+        });
+    }
+
+    /// 车位的占用或预留车辆
+    fn holder(&self, slot_id: u8) -> Option<(SlotState, i32)> {
+        self.occupants
+            .get(&slot_id)
+            .map(|occupant| (SlotState::Occupied, occupant.vehicle_id))
+            .or_else(|| self.reservations.get(&slot_id).map(|r| (SlotState::Reserved, r.vehicle_id)))
+    }
+
+    fn check_available(&self, slot_id: u8, vehicle_id: i32) -> Result<(), String> {
+        if !self.slots.contains_key(&slot_id) {
+            return Err(format!("{}号车位不存在", slot_id));
+        }
+        match self.holder(slot_id) {
+            Some((SlotState::Occupied, other)) if other != vehicle_id => {
+                Err(format!("{}号车位已被车辆 {} 占用", slot_id, other))
+            }
+            Some((_, other)) if other != vehicle_id => Err(format!("{}号车位已预留给车辆 {}", slot_id, other)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 选择空闲车位：指定类型时只在该类型中选择，否则优先普通车位；其次离 `from` 最近、编号最小
+    fn allocate(&self, vehicle_id: i32, kind: Option<SlotKind>, from: Option<(f64, f64)>) -> Option<u8> {
+        let distance = |slot: &SlotLayout| {
+            from.map_or(0.0, |(x, y)| (slot.position_x - x).hypot(slot.position_y - y))
+        };
+        self.slots
+            .iter()
+            .filter(|(slot_id, slot)| {
+                kind.map_or(true, |kind| slot.kind == kind) && self.check_available(**slot_id, vehicle_id).is_ok()
+            })
+            .min_by(|(a_id, a), (b_id, b)| {
+                (a.kind != SlotKind::Normal)
+                    .cmp(&(b.kind != SlotKind::Normal))
+                    .then_with(|| distance(a).total_cmp(&distance(b)))
+                    .then_with(|| a_id.cmp(b_id))
+            })
+            .map(|(slot_id, _)| *slot_id)
+    }
+}
+
+/// 停车场：车位布局、占用与预留
+#[derive(Clone)]
+pub struct ParkingLot {
+    inner: Arc<Mutex<LotInner>>,
+}
+
+impl Default for ParkingLot {
+    fn default() -> Self {
+        Self::new(RESERVATION_TIMEOUT, OCCUPANT_TTL)
+    }
+}
+
+impl ParkingLot {
+    pub fn new(reservation_timeout: Duration, occupant_ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LotInner {
+                slots: BTreeMap::new(),
+                occupants: HashMap::new(),
+                reservations: HashMap::new(),
+                reservation_timeout,
+                occupant_ttl,
+                dirty: false,
+            })),
+        }
+    }
+
+    /// 启动时载入车位布局并恢复保存的占用（车辆视为离线，已上报的占用保持不变）
+    pub fn load(&self, slots: &[ParkingSlot]) {
+        self.set_layout(slots);
+        let mut inner = self.inner.lock();
+        for slot in slots {
+            let (Some(vehicle_id), Some(reported_at)) = (slot.occupant, slot.reported_at.as_deref()) else {
+                continue;
+            };
+            let Some(slot_id) = u8::try_from(slot.slot_id).ok().filter(|id| *id > 0) else {
+                continue;
+            };
+            let reported_at = match DateTime::parse_from_rfc3339(reported_at) {
+                Ok(reported_at) => reported_at.with_timezone(&Utc),
+                Err(e) => {
+                    warn!("忽略 {} 号车位保存的占用: 上报时间无效 ({})", slot_id, e);
+                    continue;
+                }
+            };
+            if inner.occupants.values().any(|occupant| occupant.vehicle_id == vehicle_id) {
+                continue;
+            }
+            inner.occupants.entry(slot_id).or_insert(Occupant { vehicle_id, reported_at, online: false });
+        }
+        inner.expire_occupants();
+    }
+
+    /// 载入车位布局（编号或类型无效的车位忽略）；占用与预留保持不变
+    pub fn set_layout(&self, slots: &[ParkingSlot]) {
+        let layout = slots
+            .iter()
+            .filter_map(|slot| {
+                let slot_id = u8::try_from(slot.slot_id).ok().filter(|id| *id > 0);
+                let kind = SlotKind::parse(&slot.slot_type);
+                if slot_id.is_none() || kind.is_none() {
+                    warn!("忽略无效车位: 编号 {}, 类型 {}", slot.slot_id, slot.slot_type);
+                }
+                Some((slot_id?, SlotLayout { position_x: slot.position_x, position_y: slot.position_y, kind: kind? }))
+            })
+            .collect();
+        self.inner.lock().slots = layout;
+    }
+
+    /// 车辆上报的停车位（0 表示未停在车位上）
+    pub fn report(&self, vehicle_id: i32, parking_slot: u8) {
+        let mut inner = self.inner.lock();
+        let before = inner.occupants.len();
+        inner.occupants.retain(|_, occupant| occupant.vehicle_id != vehicle_id);
+        if parking_slot == 0 {
+            inner.dirty |= inner.occupants.len() != before;
+            return;
+        }
+
+        inner.dirty = true;
+        let occupant = Occupant { vehicle_id, reported_at: Utc::now(), online: true };
+        if let Some(other) = inner.occupants.insert(parking_slot, occupant) {
+            warn!("{} 号车位同时被车辆 {} 与 {} 上报占用，以最新上报为准", parking_slot, other.vehicle_id, vehicle_id);
+        }
+        // 车辆已停入车位，其预留以及其他车辆对该车位的预留均解除
+        inner.reservations.retain(|slot_id, reservation| {
+            if *slot_id == parking_slot && reservation.vehicle_id != vehicle_id {
+                warn!("{} 号车位已被车辆 {} 占用，解除车辆 {} 的预留", slot_id, vehicle_id, reservation.vehicle_id);
+            }
+            reservation.vehicle_id != vehicle_id && *slot_id != parking_slot
+        });
+    }
+
+    /// 车辆连接：其保留的占用恢复为在线，不再过期
+    pub fn connect(&self, vehicle_id: i32) {
+        let mut inner = self.inner.lock();
+        for occupant in inner.occupants.values_mut().filter(|occupant| occupant.vehicle_id == vehicle_id) {
+            occupant.online = true;
+        }
+    }
+
+    /// 车辆断开：解除其预留，保留最后上报的占用并从此时开始计算过期
+    pub fn disconnect(&self, vehicle_id: i32) {
+        let mut inner = self.inner.lock();
+        inner.reservations.retain(|_, reservation| reservation.vehicle_id != vehicle_id);
+        let now = Utc::now();
+        let mut changed = false;
+        for occupant in inner.occupants.values_mut().filter(|occupant| occupant.vehicle_id == vehicle_id) {
+            occupant.online = false;
+            occupant.reported_at = now;
+            changed = true;
+        }
+        inner.dirty |= changed;
+    }
+
+    /// 车辆编号纠正：占用与预留随车辆迁移
+    pub fn rekey(&self, old_id: i32, new_id: i32) {
+        let mut inner = self.inner.lock();
+        let mut changed = false;
+        for occupant in inner.occupants.values_mut().filter(|occupant| occupant.vehicle_id == old_id) {
+            occupant.vehicle_id = new_id;
+            changed = true;
+        }
+        inner.dirty |= changed;
+        for reservation in inner.reservations.values_mut().filter(|r| r.vehicle_id == old_id) {
+            reservation.vehicle_id = new_id;
+        }
+    }
+
+    /// 为车辆预留车位：指定车位时须空闲，否则自动分配离 `from` 最近的空闲车位；返回车位编号
+    ///
+    /// 车辆原有的预留随之解除
+    pub fn reserve(
+        &self,
+        vehicle_id: i32,
+        slot_id: Option<u8>,
+        kind: Option<SlotKind>,
+        from: Option<(f64, f64)>,
+    ) -> Result<u8, String> {
+        let mut inner = self.inner.lock();
+        inner.expire_reservations();
+        inner.expire_occupants();
+        let slot_id = match slot_id {
+            Some(slot_id) => {
+                inner.check_available(slot_id, vehicle_id)?;
+                slot_id
+            }
+            None => inner.allocate(vehicle_id, kind, from).ok_or_else(|| "没有空闲车位".to_string())?,
+        };
+
+        inner.reservations.retain(|_, reservation| reservation.vehicle_id != vehicle_id);
+        inner.reservations.insert(slot_id, Reservation { vehicle_id, reserved_at: Instant::now() });
+        Ok(slot_id)
+    }
+
+    /// 解除车辆的预留（泊车指令下发失败等）
+    pub fn release(&self, vehicle_id: i32) {
+        self.inner.lock().reservations.retain(|_, reservation| reservation.vehicle_id != vehicle_id);
+    }
+
+    /// 全部车位及其状态（按编号排序）
+    pub fn snapshot(&self) -> Vec<SlotStatus> {
+        let mut inner = self.inner.lock();
+        inner.expire_reservations();
+        inner.expire_occupants();
+        inner
+            .slots
+            .iter()
+            .map(|(slot_id, slot)| {
+                let holder = inner.holder(*slot_id);
+                SlotStatus {
+                    slot_id: *slot_id,
+                    position_x: slot.position_x,
+                    position_y: slot.position_y,
+                    slot_type: slot.kind,
+                    state: holder.map_or(SlotState::Free, |(state, _)| state),
+                    occupant: holder.map(|(_, vehicle_id)| vehicle_id),
+                }
+            })
+            .collect()
+    }
+
+    /// 按车队状态快照重新核对占用（启动时与变化通知丢失后）
+    pub fn sync(&self, fleet: &FleetState) {
+        for snapshot in fleet.snapshot() {
+            if let Some(info) = snapshot.info.as_ref() {
+                self.report(snapshot.vehicle_id, info.parking_slot);
+            }
+        }
+    }
+
+    /// 离线车辆的占用过期检查
+    pub fn expire_occupants(&self) {
+        self.inner.lock().expire_occupants();
+    }
+
+    /// 取出自上次保存以来有变化的占用：(车位编号, 车辆, 最后确认时间)，没有变化时返回 None
+    pub fn take_changed_occupants(&self) -> Option<Vec<(i32, i32, String)>> {
+        let mut inner = self.inner.lock();
+        if !std::mem::take(&mut inner.dirty) {
+            return None;
+        }
+        let mut occupants: Vec<_> = inner
+            .occupants
+            .iter()
+            .map(|(slot_id, occupant)| (i32::from(*slot_id), occupant.vehicle_id, occupant.reported_at.to_rfc3339()))
+            .collect();
+        occupants.sort();
+        Some(occupants)
+    }
+}
+
+/// 车位占用跟踪：订阅车队状态变化更新停车场并保存占用，释放时停止
+pub struct ParkingLotTracker {
+    task: JoinHandle<()>,
+}
+
+impl ParkingLotTracker {
+    /// `db` 为空时只在内存中跟踪占用
+    pub fn start(lot: ParkingLot, fleet: &FleetState, db: Option<VehicleDatabase>) -> Self {
+        let mut changes = fleet.subscribe();
+        lot.sync(fleet);
+        let fleet = fleet.clone();
+        let task = tokio::spawn(async move {
+            let mut expiry = tokio::time::interval(OCCUPANT_CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    change = changes.recv() => match change {
+                        Ok(change) => Self::handle(&lot, &change),
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("车位占用跟踪处理过慢，跳过 {} 条车队状态变化，按当前状态重新核对", skipped);
+                            lot.sync(&fleet);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = expiry.tick() => lot.expire_occupants(),
+                }
+                if let Some(db) = db.as_ref() {
+                    if let Some(occupants) = lot.take_changed_occupants() {
+                        if let Err(e) = db.save_parking_occupants(&occupants).await {
+                            warn!("保存车位占用失败，下次变化时重试: {}", e);
+                            lot.inner.lock().dirty = true;
+                        }
+                    }
+                }
+            }
+        });
+        debug!("车位占用跟踪已启动");
+        Self { task }
+    }
+
+    fn handle(lot: &ParkingLot, change: &FleetStateChange) {
+        match change.kind {
+            ChangeKind::Updated => {
+                let parking_slot = change
+                    .changes
+                    .iter()
+                    .find(|c| c.field == "parking_slot")
+                    .and_then(|c| c.new.as_u64())
+                    .and_then(|slot| u8::try_from(slot).ok());
+                if let Some(parking_slot) = parking_slot {
+                    lot.report(change.vehicle_id, parking_slot);
+                }
+            }
+            ChangeKind::Rekeyed => {
+                if let Some(previous_id) = change.previous_id {
+                    lot.rekey(previous_id, change.vehicle_id);
+                }
+            }
+            ChangeKind::Disconnected => lot.disconnect(change.vehicle_id),
+            ChangeKind::Connected => lot.connect(change.vehicle_id),
+        }
+    }
+}
+
+impl Drop for ParkingLotTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::SaveParkingSlotRequest;
+    use crate::protocol_processing::types::VehicleInfo;
+    use crate::test_support::{self, eventually};
+    use tempfile::TempDir;
+
+    fn slot(slot_id: i32, position_x: f64, slot_type: &str) -> ParkingSlot {
+        ParkingSlot {
+            slot_id,
+            position_x,
+            position_y: 0.0,
+            slot_type: slot_type.to_string(),
+            updated_at: String::new(),
+            occupant: None,
+            reported_at: None,
+        }
+    }
+
+    fn occupied(slot_id: i32, vehicle_id: i32, reported_at: &str) -> ParkingSlot {
+        ParkingSlot { occupant: Some(vehicle_id), reported_at: Some(reported_at.to_string()), ..slot(slot_id, 0.0, "normal") }
+    }
+
+    fn sample_lot(reservation_timeout: Duration) -> ParkingLot {
+        let lot = ParkingLot::new(reservation_timeout, OCCUPANT_TTL);
+        lot.set_layout(&[slot(1, 1.0, "normal"), slot(2, 2.0, "normal"), slot(3, 3.0, "charging"), slot(0, 0.0, "normal")]);
+        lot
+    }
+
+    fn states(lot: &ParkingLot) -> Vec<(u8, SlotState, Option<i32>)> {
+        lot.snapshot().iter().map(|s| (s.slot_id, s.state, s.occupant)).collect()
+    }
+
+    fn sample_info(vehicle_id: u8, parking_slot: u8) -> VehicleInfo {
+        VehicleInfo { nav_status: 8, parking_slot, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    #[test]
+    fn test_reserve_rejects_taken_slots_and_allocates_nearest() {
+        let lot = sample_lot(RESERVATION_TIMEOUT);
+        lot.report(7, 1);
+
+        assert_eq!(lot.reserve(8, Some(1), None, None).unwrap_err(), "1号车位已被车辆 7 占用");
+        assert_eq!(lot.reserve(8, Some(9), None, None).unwrap_err(), "9号车位不存在");
+        assert_eq!(lot.reserve(8, Some(2), None, None), Ok(2));
+        assert_eq!(lot.reserve(9, Some(2), None, None).unwrap_err(), "2号车位已预留给车辆 8");
+
+        // 普通车位用完后才分配充电车位；指定类型时只在该类型中选择
+        assert_eq!(lot.reserve(9, None, None, Some((0.0, 0.0))), Ok(3));
+        assert_eq!(lot.reserve(9, None, Some(SlotKind::Normal), None).unwrap_err(), "没有空闲车位");
+        // 车辆重新预留时原预留解除
+        assert_eq!(lot.reserve(8, None, Some(SlotKind::Normal), Some((0.0, 0.0))), Ok(2));
+        lot.release(9);
+        assert_eq!(
+            states(&lot),
+            vec![(1, SlotState::Occupied, Some(7)), (2, SlotState::Reserved, Some(8)), (3, SlotState::Free, None)]
+        );
+
+        // 预留超时后车位恢复空闲
+        let lot = sample_lot(Duration::ZERO);
+        lot.reserve(8, Some(2), None, None).unwrap();
+        assert_eq!(states(&lot)[1], (2, SlotState::Free, None));
+    }
+
+    #[tokio::test]
+    async fn test_occupancy_follows_vehicle_reports() {
+        let lot = sample_lot(RESERVATION_TIMEOUT);
+        let fleet = FleetState::default();
+        fleet.update_info(4, "车4", &sample_info(4, 2), 1);
+        let _tracker = ParkingLotTracker::start(lot.clone(), &fleet, None);
+        // 启动时按已有状态核对
+        assert_eq!(states(&lot)[1], (2, SlotState::Occupied, Some(4)));
+
+        lot.reserve(5, Some(1), None, None).unwrap();
+        fleet.update_info(4, "车4", &sample_info(4, 0), 2);
+        fleet.update_info(5, "车5", &sample_info(5, 1), 2);
+        fleet.update_info(6, "车6", &sample_info(6, 3), 2);
+        fleet.disconnect(6, "127.0.0.1:6000");
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while states(&lot)
+                != vec![(1, SlotState::Occupied, Some(5)), (2, SlotState::Free, None), (3, SlotState::Occupied, Some(6))]
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_saved_occupants_expire_while_offline() {
+        let lot = ParkingLot::new(RESERVATION_TIMEOUT, Duration::from_secs(3600));
+        let recent = (Utc::now() - chrono::Duration::minutes(10)).to_rfc3339();
+        let stale = (Utc::now() - chrono::Duration::hours(2)).to_rfc3339();
+        lot.load(&[occupied(1, 7, &recent), occupied(2, 8, &stale), occupied(3, 9, "昨天")]);
+        // 超过保留时长的离线占用与时间无效的占用不恢复
+        assert_eq!(
+            states(&lot),
+            vec![(1, SlotState::Occupied, Some(7)), (2, SlotState::Free, None), (3, SlotState::Free, None)]
+        );
+        let saved = lot.take_changed_occupants().unwrap();
+        assert_eq!(saved.iter().map(|(slot_id, vehicle_id, _)| (*slot_id, *vehicle_id)).collect::<Vec<_>>(), vec![(1, 7)]);
+        assert!(lot.take_changed_occupants().is_none());
+
+        // 在线车辆的占用不过期，断开后开始计时
+        let lot = ParkingLot::new(RESERVATION_TIMEOUT, Duration::ZERO);
+        lot.set_layout(&[slot(1, 1.0, "normal")]);
+        lot.report(5, 1);
+        assert_eq!(states(&lot), vec![(1, SlotState::Occupied, Some(5))]);
+        lot.disconnect(5);
+        assert_eq!(states(&lot), vec![(1, SlotState::Free, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_occupancy_survives_restart() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("parking.db")).await.unwrap();
+        let lot = ParkingLot::default();
+        lot.load(&db.get_parking_slots().await.unwrap());
+        let fleet = FleetState::default();
+        let tracker = ParkingLotTracker::start(lot, &fleet, Some(db.clone()));
+
+        fleet.connect(4, "车4", "127.0.0.1:4000".to_string(), true);
+        fleet.update_info(4, "车4", &sample_info(4, 2), 1);
+        let occupant = |slots: Vec<ParkingSlot>| slots.iter().find(|s| s.slot_id == 2).and_then(|s| s.occupant);
+        eventually("保存 2 号车位占用", || async { occupant(db.get_parking_slots().await.unwrap()) == Some(4) }).await;
+        drop(tracker);
+
+        // 修改车位布局不影响占用；重启后离线车辆仍占用车位
+        let request = SaveParkingSlotRequest { slot_id: 2, position_x: 4.0, position_y: 0.7, slot_type: "charging".to_string() };
+        db.save_parking_slot(request).await.unwrap();
+        let slots = db.get_parking_slots().await.unwrap();
+        assert!(slots[1].reported_at.is_some());
+        let restarted = ParkingLot::default();
+        restarted.load(&slots);
+        assert_eq!(states(&restarted), vec![(1, SlotState::Free, None), (2, SlotState::Occupied, Some(4))]);
+        assert_eq!(restarted.reserve(5, Some(2), None, None).unwrap_err(), "2号车位已被车辆 4 占用");
+    }
+}
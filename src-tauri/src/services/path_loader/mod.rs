@@ -1,6 +1,9 @@
+pub mod network;
+
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use log::{info, warn, error};
 use serde::{Serialize, Deserialize};
@@ -23,6 +26,8 @@ pub struct PathData {
 pub struct PathLoader {
     /// 路径数据缓存: path_id -> PathData
     paths: RwLock<HashMap<u8, PathData>>,
+    /// 路径数据版本，每次重新加载后递增
+    generation: AtomicU64,
     /// 路径文件目录
     routes_dir: PathBuf,
 }
@@ -32,6 +37,7 @@ impl PathLoader {
     pub fn new(routes_dir: PathBuf) -> Arc<Self> {
         Arc::new(Self {
             paths: RwLock::new(HashMap::new()),
+            generation: AtomicU64::new(0),
             routes_dir,
         })
     }
@@ -105,6 +111,7 @@ impl PathLoader {
         {
             let mut paths = self.paths.write().unwrap();
             *paths = paths_map;
+            self.generation.fetch_add(1, Ordering::Release);
         }
 
         info!("路径文件预加载完成: {} 个文件", loaded_count);
@@ -203,6 +210,11 @@ impl PathLoader {
         Ok(merged_points)
     }
 
+    /// 当前路径数据版本；版本不变时已加载的路径数据不变，可用于缓存派生数据
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
     /// 获取已加载的路径数量
     pub fn get_loaded_count(&self) -> usize {
         let paths = self.paths.read().unwrap();
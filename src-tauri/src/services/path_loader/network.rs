@@ -0,0 +1,173 @@
+//! 路径网络
+//!
+//! 将已加载的路径文件连成有向图：同一路径的相邻点按行驶方向相连，路径终点与其他路径上
+//! 距离在容差内的点相连（路径衔接或汇入）。用于按实际行驶路线估算两点之间的距离。
+
+use super::{PathData, PathLoader, PathPoint};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// 路径网络
+pub struct RouteNetwork {
+    points: Vec<PathPoint>,
+    /// 入边：终点下标 -> [(起点下标, 长度)]，按目标点反向搜索
+    incoming: Vec<Vec<(usize, f64)>>,
+}
+
+/// 网络中各点到同一目标点的行驶距离
+pub struct DistanceField<'a> {
+    network: &'a RouteNetwork,
+    distances: Vec<f64>,
+    /// 目标点到最近路径点的直线距离
+    target_offset: f64,
+}
+
+#[derive(PartialEq)]
+struct Visit {
+    cost: f64,
+    node: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    // 反向比较，使 BinaryHeap 成为最小堆
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn distance(a: &PathPoint, b: &PathPoint) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+impl RouteNetwork {
+    /// 由路径构建网络；没有有效路径点时返回 None
+    pub fn build(paths: &[PathData], junction_tolerance: f64) -> Option<Self> {
+        let mut points = Vec::new();
+        let mut ranges = Vec::new();
+        for path in paths {
+            let start = points.len();
+            points.extend(path.points.iter().filter(|p| p.x.is_finite() && p.y.is_finite()).cloned());
+            if points.len() > start {
+                ranges.push(start..points.len());
+            }
+        }
+        if points.is_empty() {
+            return None;
+        }
+
+        let mut incoming = vec![Vec::new(); points.len()];
+        for range in &ranges {
+            for from in range.start..range.end - 1 {
+                incoming[from + 1].push((from, distance(&points[from], &points[from + 1])));
+            }
+            // 路径终点衔接其他路径上的邻近点
+            let end = range.end - 1;
+            for other in ranges.iter().filter(|other| *other != range) {
+                for to in other.clone() {
+                    let gap = distance(&points[end], &points[to]);
+                    if gap <= junction_tolerance {
+                        incoming[to].push((end, gap));
+                    }
+                }
+            }
+        }
+
+        Some(Self { points, incoming })
+    }
+
+    /// 由路径加载器中已加载的全部路径构建网络
+    pub fn from_loader(loader: &PathLoader, junction_tolerance: f64) -> Option<Self> {
+        let paths: Vec<PathData> = loader
+            .get_loaded_path_ids()
+            .into_iter()
+            .filter_map(|path_id| loader.get_path(path_id))
+            .collect();
+        Self::build(&paths, junction_tolerance)
+    }
+
+    /// 离给定坐标最近的路径点及其直线距离
+    fn nearest(&self, x: f64, y: f64) -> (usize, f64) {
+        let target = PathPoint { x, y };
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| (index, distance(point, &target)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, f64::INFINITY))
+    }
+
+    /// 计算各点沿路径行驶到 (x, y) 的距离
+    pub fn distances_to(&self, x: f64, y: f64) -> DistanceField<'_> {
+        let (target, target_offset) = self.nearest(x, y);
+        let mut distances = vec![f64::INFINITY; self.points.len()];
+        let mut queue = BinaryHeap::new();
+        distances[target] = 0.0;
+        queue.push(Visit { cost: 0.0, node: target });
+
+        while let Some(Visit { cost, node }) = queue.pop() {
+            if cost > distances[node] {
+                continue;
+            }
+            for &(from, length) in &self.incoming[node] {
+                let next = cost + length;
+                if next < distances[from] {
+                    distances[from] = next;
+                    queue.push(Visit { cost: next, node: from });
+                }
+            }
+        }
+
+        DistanceField { network: self, distances, target_offset }
+    }
+}
+
+impl DistanceField<'_> {
+    /// 从 (x, y) 出发沿路径到目标点的距离（含两端到路径的直线距离）；不可达时返回 None
+    pub fn from(&self, x: f64, y: f64) -> Option<f64> {
+        let (start, start_offset) = self.network.nearest(x, y);
+        let distance = self.distances[start];
+        distance.is_finite().then_some(start_offset + distance + self.target_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path_id: u8, points: &[(f64, f64)]) -> PathData {
+        PathData {
+            path_id,
+            points: points.iter().map(|&(x, y)| PathPoint { x, y }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_distance_follows_driving_direction() {
+        // 两条路径首尾相接组成逆时针环线
+        let paths = [
+            path(1, &[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]),
+            path(2, &[(2.01, 0.0), (2.0, 1.0), (0.0, 1.0), (0.0, 0.01)]),
+        ];
+        let network = RouteNetwork::build(&paths, 0.05).unwrap();
+        let field = network.distances_to(0.0, 1.0);
+
+        let along = field.from(1.0, 0.0).unwrap();
+        assert!((along - (1.0 + 0.01 + 1.0 + 2.0)).abs() < 1e-3, "{}", along);
+        // 不能逆行：从 (0, 0) 出发也要绕行一圈
+        let around = field.from(0.0, 0.1).unwrap();
+        assert!(around > 4.0, "{}", around);
+
+        // 终点未衔接时不可达
+        let network = RouteNetwork::build(&paths[..1], 0.05).unwrap();
+        assert!(network.distances_to(0.0, 0.0).from(2.0, 0.0).is_none());
+        assert!(RouteNetwork::build(&[], 0.05).is_none());
+    }
+}
@@ -0,0 +1,545 @@
+//! 出租车自动派单
+//!
+//! 订单创建后从在线车辆中选择接单车辆：候选车辆须处于空载导航状态（1/2）且没有进行中的订单，
+//! 到起点的距离沿路径网络计算（路径未加载时按直线距离，沿路径不可达的车辆不参与派单）。
+//! 分配时数据库再次确认车辆没有其他进行中的订单，并发派单不会把两个订单派给同一辆车。派单策略可选最近车辆、
+//! 最空闲车辆（近 24 小时接单最少）与电量优先。车辆在接单超时内未出发前往起点时先向其下发订单取消，
+//! 再改派下一辆车，没有可改派的车辆或达到尝试次数后订单失败；排队订单此时重新排队。
+
+use crate::config::DispatchConfig;
+use crate::database::{CreateTaxiOrderRequest, TaxiOrder};
+use crate::protocol_processing::types::{SendMessageTypes, TaxiOrderData};
+use crate::services::fleet_state::FleetState;
+use crate::services::path_loader::network::RouteNetwork;
+use crate::services::path_loader::PathLoader;
+use crate::services::taxi_orders::{OrderStatus, TaxiOrderService};
+use crate::services::vehicle::VehicleService;
+use crate::socket::{ConnectionManager, SocketServer};
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 可接单的空载导航状态
+const IDLE_NAV_STATUS: [u8; 2] = [1, 2];
+
+/// 派单策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatchStrategyKind {
+    /// 到起点距离最近
+    #[default]
+    Nearest,
+    /// 近 24 小时接单最少，其次距离最近
+    LeastBusy,
+    /// 排除低电量车辆，按电量折算距离
+    BatteryAware,
+}
+
+impl DispatchStrategyKind {
+    /// 订单记录中的策略名
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DispatchStrategyKind::Nearest => "nearest",
+            DispatchStrategyKind::LeastBusy => "least_busy",
+            DispatchStrategyKind::BatteryAware => "battery_aware",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [DispatchStrategyKind::Nearest, DispatchStrategyKind::LeastBusy, DispatchStrategyKind::BatteryAware]
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+    }
+
+    pub fn strategy(self, config: &DispatchConfig) -> Box<dyn DispatchStrategy> {
+        match self {
+            DispatchStrategyKind::Nearest => Box::new(Nearest),
+            DispatchStrategyKind::LeastBusy => Box::new(LeastBusy),
+            DispatchStrategyKind::BatteryAware => Box::new(BatteryAware { min_battery: config.min_battery }),
+        }
+    }
+}
+
+/// 候选车辆
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub vehicle_id: i32,
+    pub battery: f64,
+    /// 到订单起点的行驶距离（米）
+    pub pickup_distance: f64,
+    /// 近 24 小时出发接客的次数
+    pub recent_trips: i64,
+}
+
+/// 派单策略：从候选车辆中选出排序最靠前的一辆
+pub trait DispatchStrategy: Send + Sync {
+    /// 候选车辆能否参与本策略
+    fn eligible(&self, _candidate: &Candidate) -> bool {
+        true
+    }
+
+    /// 候选车辆排序（靠前者优先）
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering;
+}
+
+struct Nearest;
+
+impl DispatchStrategy for Nearest {
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        a.pickup_distance.total_cmp(&b.pickup_distance)
+    }
+}
+
+struct LeastBusy;
+
+impl DispatchStrategy for LeastBusy {
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        a.recent_trips.cmp(&b.recent_trips).then_with(|| a.pickup_distance.total_cmp(&b.pickup_distance))
+    }
+}
+
+struct BatteryAware {
+    min_battery: f64,
+}
+
+impl BatteryAware {
+    /// 电量越低折算距离越长
+    fn cost(candidate: &Candidate) -> f64 {
+        candidate.pickup_distance / (candidate.battery / 100.0).max(0.01)
+    }
+}
+
+impl DispatchStrategy for BatteryAware {
+    fn eligible(&self, candidate: &Candidate) -> bool {
+        candidate.battery >= self.min_battery
+    }
+
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        Self::cost(a).total_cmp(&Self::cost(b))
+    }
+}
+
+/// 按策略选择车辆，排序相同时取编号较小的车辆
+pub fn select<'a>(strategy: &dyn DispatchStrategy, candidates: &'a [Candidate]) -> Option<&'a Candidate> {
+    candidates
+        .iter()
+        .filter(|candidate| strategy.eligible(candidate))
+        .min_by(|a, b| strategy.compare(a, b).then_with(|| a.vehicle_id.cmp(&b.vehicle_id)))
+}
+
+/// 订单下发出口
+pub trait OrderSender: Send + Sync {
+    fn send_order(&self, vehicle_id: i32, payload: &[u8]) -> Result<(), String>;
+
+    /// 通知车辆放弃已下发的订单
+    fn cancel_order(&self, vehicle_id: i32) -> Result<(), String>;
+}
+
+impl OrderSender for ConnectionManager {
+    fn send_order(&self, vehicle_id: i32, payload: &[u8]) -> Result<(), String> {
+        SocketServer::send_to_vehicle(self, vehicle_id, SendMessageTypes::TAXI_ORDER, payload)
+    }
+
+    fn cancel_order(&self, vehicle_id: i32) -> Result<(), String> {
+        SocketServer::send_to_vehicle(self, vehicle_id, SendMessageTypes::TAXI_ORDER_CANCEL, &[vehicle_id as u8])
+    }
+}
+
+/// 派单结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Dispatch {
+    pub order_id: String,
+    pub vehicle_id: i32,
+    pub strategy: DispatchStrategyKind,
+    /// 派单时到起点的距离（米）
+    pub pickup_distance: f64,
+    /// 第几辆尝试的车辆（从 1 开始）
+    pub attempt: u32,
+}
+
+/// 按路径数据版本缓存的路径网络
+struct CachedNetwork {
+    generation: u64,
+    network: Option<Arc<RouteNetwork>>,
+}
+
+/// 出租车派单器
+#[derive(Clone)]
+pub struct TaxiDispatcher {
+    orders: TaxiOrderService,
+    fleet: FleetState,
+    sender: Arc<dyn OrderSender>,
+    paths: Option<Arc<PathLoader>>,
+    network: Arc<Mutex<Option<CachedNetwork>>>,
+    config: DispatchConfig,
+}
+
+impl TaxiDispatcher {
+    pub fn new(
+        orders: TaxiOrderService,
+        fleet: FleetState,
+        sender: Arc<dyn OrderSender>,
+        paths: Option<Arc<PathLoader>>,
+        config: DispatchConfig,
+    ) -> Self {
+        Self { orders, fleet, sender, paths, network: Arc::new(Mutex::new(None)), config }
+    }
+
+    pub fn orders(&self) -> &TaxiOrderService {
+        &self.orders
+    }
+
+    pub fn config(&self) -> &DispatchConfig {
+        &self.config
+    }
+
+    /// 创建订单并派单：首次派单完成后返回，之后由后台任务在接单超时后改派
+    pub async fn dispatch(&self, request: CreateTaxiOrderRequest, strategy: DispatchStrategyKind) -> Result<Dispatch, String> {
+        let order = self.orders.create(request, None).await?;
+        let order_id = order.order_id.clone();
+        self.start(order, strategy, false)
+            .await?
+            .ok_or_else(|| format!("订单 {} 派单失败: 没有可派单的车辆", order_id))
+    }
+
+    /// 派出排队中的订单；没有可派单的车辆时订单留在队列中，返回 None
+    pub async fn dispatch_queued(&self, order: TaxiOrder, strategy: DispatchStrategyKind) -> Result<Option<Dispatch>, String> {
+        self.start(order, strategy, true).await
+    }
+
+    /// 首次派单，成功后启动接单超时改派任务
+    async fn start(&self, order: TaxiOrder, strategy: DispatchStrategyKind, requeue: bool) -> Result<Option<Dispatch>, String> {
+        let mut tried = Vec::new();
+        let Some(dispatch) = self.assign_next(&order, strategy, &mut tried, None).await? else {
+            // 排队订单尚未分配给任何车辆时保持排队
+            if !(requeue && tried.is_empty()) {
+                self.release(&order.order_id, requeue, "没有可派单的车辆").await?;
+            }
+            return Ok(None);
+        };
+
+        let dispatcher = self.clone();
+        let vehicle_id = dispatch.vehicle_id;
+        tokio::spawn(async move { dispatcher.watch(order, strategy, tried, vehicle_id, requeue).await });
+        Ok(Some(dispatch))
+    }
+
+    /// 没有车辆可派：排队订单重新排队，其余订单失败
+    async fn release(&self, order_id: &str, requeue: bool, reason: &str) -> Result<TaxiOrder, String> {
+        let next = if requeue { OrderStatus::Queued } else { OrderStatus::Failed };
+        self.orders.transition(order_id, next, None, None, Some(reason)).await
+    }
+
+    /// 等待车辆接单，超时后取消该车辆的订单并改派
+    async fn watch(
+        self,
+        order: TaxiOrder,
+        strategy: DispatchStrategyKind,
+        mut tried: Vec<i32>,
+        mut vehicle_id: i32,
+        requeue: bool,
+    ) {
+        let timeout = Duration::from_millis(u64::from(self.config.accept_timeout));
+        loop {
+            tokio::time::sleep(timeout).await;
+            match self.awaiting_acceptance(&order.order_id, vehicle_id).await {
+                Ok(true) => {}
+                // 已接单、已取消或已被其他操作改派
+                Ok(false) => return,
+                Err(e) => {
+                    warn!("检查订单 {} 接单状态失败: {}", order.order_id, e);
+                    return;
+                }
+            }
+
+            let reason = format!("车辆 {} 未在 {} 秒内接单", vehicle_id, timeout.as_secs_f64());
+            // 车辆已收到订单，改派前先让其放弃，避免两辆车前往同一起点
+            match self.sender.cancel_order(vehicle_id) {
+                Ok(()) => info!("🚕 {}，已取消其订单 {}", reason, order.order_id),
+                Err(e) => warn!("向车辆 {} 下发订单 {} 取消失败: {}", vehicle_id, order.order_id, e),
+            }
+            match self.assign_next(&order, strategy, &mut tried, Some(&reason)).await {
+                Ok(Some(dispatch)) => vehicle_id = dispatch.vehicle_id,
+                Ok(None) => {
+                    let reason = format!("{}，已无可改派的车辆", reason);
+                    if let Err(e) = self.release(&order.order_id, requeue, &reason).await {
+                        warn!("更新订单 {} 状态失败: {}", order.order_id, e);
+                    }
+                    return;
+                }
+                Err(e) => {
+                    warn!("订单 {} 改派失败: {}", order.order_id, e);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn awaiting_acceptance(&self, order_id: &str, vehicle_id: i32) -> Result<bool, String> {
+        let order = self
+            .orders
+            .database()?
+            .get_taxi_order(order_id)
+            .await
+            .map_err(|e| format!("查询出租车订单失败: {}", e))?;
+        Ok(order.is_some_and(|order| {
+            order.status == OrderStatus::Assigned.as_str() && order.assigned_vehicle_id == Some(vehicle_id)
+        }))
+    }
+
+    /// 选择下一辆车分配并下发订单；下发失败时继续尝试其他车辆
+    async fn assign_next(
+        &self,
+        order: &TaxiOrder,
+        strategy_kind: DispatchStrategyKind,
+        tried: &mut Vec<i32>,
+        reason: Option<&str>,
+    ) -> Result<Option<Dispatch>, String> {
+        let strategy = strategy_kind.strategy(&self.config);
+        let mut reason = reason.map(str::to_string);
+        while tried.len() < self.config.max_attempts as usize {
+            let candidates = self.candidates(order, tried).await?;
+            let Some(candidate) = select(strategy.as_ref(), &candidates) else {
+                return Ok(None);
+            };
+            let vehicle_id = candidate.vehicle_id;
+            tried.push(vehicle_id);
+            if let Err(e) = self
+                .orders
+                .transition(&order.order_id, OrderStatus::Assigned, Some(vehicle_id), None, reason.as_deref())
+                .await
+            {
+                // 选车之后该车辆已被并发派单占用，换下一辆车
+                let taken = self
+                    .orders
+                    .active_order(vehicle_id)
+                    .await?
+                    .is_some_and(|(active, _)| active.order_id != order.order_id);
+                if !taken {
+                    return Err(e);
+                }
+                warn!("订单 {} 分配给车辆 {} 失败: {}，尝试下一辆车", order.order_id, vehicle_id, e);
+                continue;
+            }
+
+            let payload = VehicleService::new().build_taxi_order_payload(&TaxiOrderData {
+                vehicle_id: vehicle_id as u8,
+                start_x: order.start_x,
+                start_y: order.start_y,
+                end_x: order.end_x,
+                end_y: order.end_y,
+            });
+            match self.sender.send_order(vehicle_id, &payload) {
+                Ok(()) => {
+                    info!(
+                        "🚕 订单 {} 派给车辆 {}（策略 {:?}，距起点 {:.2} 米，第 {} 次尝试）",
+                        order.order_id, vehicle_id, strategy_kind, candidate.pickup_distance, tried.len()
+                    );
+                    return Ok(Some(Dispatch {
+                        order_id: order.order_id.clone(),
+                        vehicle_id,
+                        strategy: strategy_kind,
+                        pickup_distance: candidate.pickup_distance,
+                        attempt: tried.len() as u32,
+                    }));
+                }
+                Err(e) => {
+                    warn!("订单 {} 下发给车辆 {} 失败: {}，尝试下一辆车", order.order_id, vehicle_id, e);
+                    reason = Some(format!("订单下发给车辆 {} 失败", vehicle_id));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// 已加载路径构成的网络；路径数据重新加载前复用上次构建的结果
+    fn route_network(&self) -> Option<Arc<RouteNetwork>> {
+        let loader = self.paths.as_deref()?;
+        let generation = loader.generation();
+        let mut cached = self.network.lock();
+        if let Some(cached) = cached.as_ref().filter(|cached| cached.generation == generation) {
+            return cached.network.clone();
+        }
+        let network = RouteNetwork::from_loader(loader, self.config.junction_tolerance).map(Arc::new);
+        *cached = Some(CachedNetwork { generation, network: network.clone() });
+        network
+    }
+
+    /// 当前可接单的车辆（排除已尝试过的车辆）
+    async fn candidates(&self, order: &TaxiOrder, tried: &[i32]) -> Result<Vec<Candidate>, String> {
+        let since = (chrono::Utc::now() - chrono::Duration::hours(24)).to_rfc3339();
+        let trips = self
+            .orders
+            .database()?
+            .count_taxi_order_transitions_by_vehicle(OrderStatus::EnRouteToPickup.as_str(), &since)
+            .await
+            .map_err(|e| format!("统计车辆接单次数失败: {}", e))?;
+        let network = self.route_network();
+        let field = network.as_deref().map(|network| network.distances_to(order.start_x, order.start_y));
+
+        let mut candidates = Vec::new();
+        for snapshot in self.fleet.snapshot() {
+            let Some(info) = snapshot.info.as_ref() else {
+                continue;
+            };
+            if !snapshot.derived.online
+                || !IDLE_NAV_STATUS.contains(&info.nav_status)
+                || tried.contains(&snapshot.vehicle_id)
+                || self.orders.active_order(snapshot.vehicle_id).await?.is_some()
+            {
+                continue;
+            }
+
+            // 路径已加载时只派沿路径可达的车辆
+            let pickup_distance = match field.as_ref() {
+                Some(field) => match field.from(info.position_x, info.position_y) {
+                    Some(distance) => distance,
+                    None => continue,
+                },
+                None => (order.start_x - info.position_x).hypot(order.start_y - info.position_y),
+            };
+            candidates.push(Candidate {
+                vehicle_id: snapshot.vehicle_id,
+                battery: info.battery,
+                pickup_distance,
+                recent_trips: trips.get(&snapshot.vehicle_id).copied().unwrap_or(0),
+            });
+        }
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::VehicleDatabase;
+    use crate::protocol_processing::types::VehicleInfo;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support::{self, eventually, RecordingSender};
+    use tempfile::TempDir;
+
+    fn candidate(vehicle_id: i32, pickup_distance: f64, battery: f64, recent_trips: i64) -> Candidate {
+        Candidate { vehicle_id, battery, pickup_distance, recent_trips }
+    }
+
+    fn sample_info(vehicle_id: u8, x: f64, nav_status: u8) -> VehicleInfo {
+        VehicleInfo { position_x: x, nav_status, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    async fn order_status(db: &VehicleDatabase, order_id: &str) -> String {
+        db.get_taxi_order(order_id).await.unwrap().unwrap().status
+    }
+
+    fn request(order_id: &str) -> CreateTaxiOrderRequest {
+        CreateTaxiOrderRequest {
+            order_id: order_id.to_string(),
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 3.0,
+            end_y: 4.0,
+        }
+    }
+
+    #[test]
+    fn test_strategies() {
+        let candidates = [
+            candidate(1, 2.0, 25.0, 0),
+            candidate(2, 1.0, 90.0, 3),
+            candidate(3, 1.5, 80.0, 1),
+            candidate(4, 3.0, 100.0, 0),
+        ];
+        let config = DispatchConfig::default();
+        let pick = |kind: DispatchStrategyKind| select(kind.strategy(&config).as_ref(), &candidates).map(|c| c.vehicle_id);
+
+        assert_eq!(pick(DispatchStrategyKind::Nearest), Some(2));
+        // 接单次数相同时取距离更近的车辆
+        assert_eq!(pick(DispatchStrategyKind::LeastBusy), Some(1));
+        // 低于最低电量的车辆不参与；1.0/0.9 < 1.5/0.8
+        assert_eq!(pick(DispatchStrategyKind::BatteryAware), Some(2));
+        assert_eq!(select(&BatteryAware { min_battery: 95.0 }, &candidates).map(|c| c.vehicle_id), Some(4));
+        assert!(select(&Nearest, &[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reassigns_when_vehicle_does_not_accept() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("dispatch.db")).await.unwrap();
+        let orders = TaxiOrderService::new(Arc::new(BroadcastEventSink::new(64, Some(db.clone()))));
+        let fleet = FleetState::default();
+        for (vehicle_id, x, nav_status) in [(1, 1.0, 1), (2, 2.0, 2), (3, 0.5, 7), (4, 3.0, 1)] {
+            fleet.connect(vehicle_id, "车", format!("127.0.0.1:{}", vehicle_id), true);
+            fleet.update_info(vehicle_id, "车", &sample_info(vehicle_id as u8, x, nav_status), 1);
+        }
+        // 4 号车离线，下发失败
+        let sender = Arc::new(RecordingSender { offline: vec![4], ..RecordingSender::default() });
+        let config = DispatchConfig { accept_timeout: 300, max_attempts: 3, ..DispatchConfig::default() };
+        let dispatcher = TaxiDispatcher::new(orders.clone(), fleet, sender.clone(), None, config);
+
+        // 3 号车不在空载状态，1 号车最近
+        let dispatch = dispatcher.dispatch(request("ORDER-1"), DispatchStrategyKind::Nearest).await.unwrap();
+        assert_eq!((dispatch.vehicle_id, dispatch.attempt), (1, 1));
+
+        // 1 号车超时未接单，先取消其订单再改派 2 号车
+        eventually("改派 2 号车", || async { sender.sent.lock().len() == 2 }).await;
+        assert_eq!(*sender.sent.lock(), vec![1, 2]);
+        assert_eq!(*sender.cancelled.lock(), vec![1]);
+        orders.on_nav_status(2, 3).await.unwrap().unwrap();
+
+        // 2 号车有进行中的订单，1 号车超时后只剩离线的 4 号车可改派
+        let dispatch = dispatcher.dispatch(request("ORDER-2"), DispatchStrategyKind::Nearest).await.unwrap();
+        assert_eq!(dispatch.vehicle_id, 1);
+        eventually("ORDER-2 失败", || async { order_status(&db, "ORDER-2").await == "failed" }).await;
+        let history = db.get_taxi_order_transitions("ORDER-2").await.unwrap();
+        assert_eq!(history.last().unwrap().reason.as_deref(), Some("车辆 1 未在 0.3 秒内接单，已无可改派的车辆"));
+
+        // ORDER-2 的接单超时晚于 2 号车，此时 2 号车的超时检查已经结束：出发后不再改派
+        assert_eq!(*sender.sent.lock(), vec![1, 2, 1]);
+        // 无车可改派时超时车辆同样收到取消；2 号车已出发，不会被取消
+        assert_eq!(*sender.cancelled.lock(), vec![1, 1]);
+        let history = db.get_taxi_order_transitions("ORDER-1").await.unwrap();
+        let steps: Vec<_> = history.iter().map(|t| (t.to_status.as_str(), t.vehicle_id)).collect();
+        assert_eq!(
+            steps,
+            vec![("created", None), ("assigned", Some(1)), ("assigned", Some(2)), ("en_route_to_pickup", Some(2))]
+        );
+        assert_eq!(history[2].reason.as_deref(), Some("车辆 1 未在 0.3 秒内接单"));
+    }
+
+    #[tokio::test]
+    async fn test_skips_vehicles_unreachable_along_paths() {
+        let dir = TempDir::new().unwrap();
+        let routes = dir.path().join("routes");
+        std::fs::create_dir(&routes).unwrap();
+        // 单向路径沿 x 轴正方向经过起点 (0, 0)
+        std::fs::write(routes.join("1.txt"), "-3.0,0.0\n-2.0,0.0\n-1.0,0.0\n0.0,0.0\n1.0,0.0\n2.0,0.0\n").unwrap();
+        let paths = PathLoader::new(routes);
+        paths.preload_all_paths().unwrap();
+
+        let db = VehicleDatabase::open(&dir.path().join("dispatch.db")).await.unwrap();
+        let orders = TaxiOrderService::new(Arc::new(BroadcastEventSink::new(64, Some(db.clone()))));
+        let fleet = FleetState::default();
+        // 1 号车直线距离最近，但已驶过起点，沿单向路径不可达
+        for (vehicle_id, x) in [(1, 1.0), (2, -2.0)] {
+            fleet.connect(vehicle_id, "车", format!("127.0.0.1:{}", vehicle_id), true);
+            fleet.update_info(vehicle_id, "车", &sample_info(vehicle_id as u8, x, 1), 1);
+        }
+        let sender = Arc::new(RecordingSender::default());
+        let dispatcher = TaxiDispatcher::new(orders, fleet, sender.clone(), Some(paths.clone()), DispatchConfig::default());
+
+        let dispatch = dispatcher.dispatch(request("ORDER-1"), DispatchStrategyKind::Nearest).await.unwrap();
+        assert_eq!(dispatch.vehicle_id, 2);
+        let err = dispatcher.dispatch(request("ORDER-2"), DispatchStrategyKind::Nearest).await.unwrap_err();
+        assert!(err.contains("没有可派单的车辆"), "{}", err);
+        assert_eq!(*sender.sent.lock(), vec![2]);
+        assert_eq!(order_status(&db, "ORDER-2").await, "failed");
+
+        // 路径网络在路径重新加载前复用，重新加载后重建
+        let network = dispatcher.route_network().unwrap();
+        assert!(Arc::ptr_eq(&network, &dispatcher.route_network().unwrap()));
+        paths.preload_all_paths().unwrap();
+        assert!(!Arc::ptr_eq(&network, &dispatcher.route_network().unwrap()));
+    }
+}
@@ -0,0 +1,517 @@
+//! 出租车订单状态机
+//!
+//! 订单依次经过：已创建 → 已分配 → 前往起点 → 已接客 → 前往终点 → 已完成，
+//! 未结束的订单可随时取消或失败。创建、分配与取消由命令层发起；分配之后的推进由
+//! 车辆信息帧的导航状态变化驱动（3 去起点接客、9 到达起点、4 去终点送客、10 到达终点），
+//! 行程中车辆切换到其他导航状态或分配后断开连接时订单失败。
+//!
+//! 排队订单创建后进入排队状态，有空闲车辆时由排队派单分配（见 `taxi_queue`），
+//! 分配后无车辆接单时回到队列。
+//!
+//! 每次状态变更写入 `taxi_order_transitions`（带变更时间）并推送 `taxi-order-update` 事件。
+//! 广播订单不指定车辆，保持已创建状态直到取消。
+
+use crate::database::{CreateTaxiOrderRequest, EnqueueTaxiOrderRequest, TaxiOrder, TaxiOrderTransition, VehicleDatabase};
+use crate::services::fleet_state::{ChangeKind, FleetState, FleetStateChange};
+use crate::socket::event_sink::SharedEventSink;
+use crate::socket::fleet_update::nav_status_text;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// 订单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Created,
+    Queued,
+    Assigned,
+    EnRouteToPickup,
+    PickedUp,
+    EnRouteToDropoff,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl OrderStatus {
+    /// 已分配车辆且尚未结束的状态
+    pub const ACTIVE: [OrderStatus; 4] = [
+        OrderStatus::Assigned,
+        OrderStatus::EnRouteToPickup,
+        OrderStatus::PickedUp,
+        OrderStatus::EnRouteToDropoff,
+    ];
+
+    /// 数据库与事件中的状态名
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::Created => "created",
+            OrderStatus::Queued => "queued",
+            OrderStatus::Assigned => "assigned",
+            OrderStatus::EnRouteToPickup => "en_route_to_pickup",
+            OrderStatus::PickedUp => "picked_up",
+            OrderStatus::EnRouteToDropoff => "en_route_to_dropoff",
+            OrderStatus::Completed => "completed",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            OrderStatus::Created,
+            OrderStatus::Queued,
+            OrderStatus::Assigned,
+            OrderStatus::EnRouteToPickup,
+            OrderStatus::PickedUp,
+            OrderStatus::EnRouteToDropoff,
+            OrderStatus::Completed,
+            OrderStatus::Cancelled,
+            OrderStatus::Failed,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OrderStatus::Created => "已创建",
+            OrderStatus::Queued => "排队中",
+            OrderStatus::Assigned => "已分配",
+            OrderStatus::EnRouteToPickup => "前往起点",
+            OrderStatus::PickedUp => "已接客",
+            OrderStatus::EnRouteToDropoff => "前往终点",
+            OrderStatus::Completed => "已完成",
+            OrderStatus::Cancelled => "已取消",
+            OrderStatus::Failed => "已失败",
+        }
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, OrderStatus::Completed | OrderStatus::Cancelled | OrderStatus::Failed)
+    }
+
+    /// 车辆已按订单行驶
+    fn in_trip(self) -> bool {
+        matches!(self, OrderStatus::EnRouteToPickup | OrderStatus::PickedUp | OrderStatus::EnRouteToDropoff)
+    }
+
+    /// 导航状态对应的订单状态
+    pub fn from_nav_status(nav_status: u8) -> Option<Self> {
+        match nav_status {
+            3 => Some(OrderStatus::EnRouteToPickup),
+            9 => Some(OrderStatus::PickedUp),
+            4 => Some(OrderStatus::EnRouteToDropoff),
+            10 => Some(OrderStatus::Completed),
+            _ => None,
+        }
+    }
+
+    /// 分配之后的推进顺序
+    fn progress(self) -> Option<u8> {
+        match self {
+            OrderStatus::Assigned => Some(1),
+            OrderStatus::EnRouteToPickup => Some(2),
+            OrderStatus::PickedUp => Some(3),
+            OrderStatus::EnRouteToDropoff => Some(4),
+            OrderStatus::Completed => Some(5),
+            _ => None,
+        }
+    }
+
+    /// 是否允许变更到 `next`
+    ///
+    /// 已分配的订单只能向前推进，允许跳过中间状态（车辆断线重连或导航状态变化过快时
+    /// 可能错过某一帧）；已创建的订单需先分配车辆或进入排队，车辆出发前可改派给其他车辆或重新排队。
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        if self.is_terminal() {
+            return false;
+        }
+        match next {
+            OrderStatus::Cancelled | OrderStatus::Failed => true,
+            OrderStatus::Queued => matches!(self, OrderStatus::Created | OrderStatus::Assigned),
+            OrderStatus::Assigned => matches!(self, OrderStatus::Created | OrderStatus::Queued | OrderStatus::Assigned),
+            _ => matches!((self.progress(), next.progress()), (Some(current), Some(next)) if current < next),
+        }
+    }
+}
+
+/// 出租车订单服务：订单的创建与状态变更
+#[derive(Clone)]
+pub struct TaxiOrderService {
+    sink: SharedEventSink,
+}
+
+impl TaxiOrderService {
+    pub fn new(sink: SharedEventSink) -> Self {
+        Self { sink }
+    }
+
+    pub fn database(&self) -> Result<VehicleDatabase, String> {
+        self.sink.database().ok_or_else(|| "数据库未初始化".to_string())
+    }
+
+    fn emit(&self, order: &TaxiOrder, transition: &TaxiOrderTransition) {
+        self.sink.emit("taxi-order-update", serde_json::json!({
+            "order": order,
+            "transition": transition,
+        }));
+    }
+
+    /// 创建订单；指定车辆时随即分配给该车辆
+    pub async fn create(&self, request: CreateTaxiOrderRequest, vehicle_id: Option<i32>) -> Result<TaxiOrder, String> {
+        let db = self.database()?;
+        let (order, transition) = db
+            .create_taxi_order(request)
+            .await
+            .map_err(|e| format!("保存出租车订单失败: {}", e))?;
+        info!("🚕 出租车订单 {} 已创建", order.order_id);
+        self.emit(&order, &transition);
+
+        match vehicle_id {
+            Some(vehicle_id) => self.transition(&order.order_id, OrderStatus::Assigned, Some(vehicle_id), None, None).await,
+            None => Ok(order),
+        }
+    }
+
+    /// 创建订单并加入排队
+    pub async fn enqueue(&self, request: EnqueueTaxiOrderRequest) -> Result<TaxiOrder, String> {
+        let db = self.database()?;
+        let (order, transition) = db
+            .create_scheduled_taxi_order(&request)
+            .await
+            .map_err(|e| format!("保存出租车订单失败: {}", e))?;
+        info!("🚕 出租车订单 {} 已创建", order.order_id);
+        self.emit(&order, &transition);
+
+        self.transition(&order.order_id, OrderStatus::Queued, None, None, None).await
+    }
+
+    /// 按状态机变更订单状态
+    pub async fn transition(
+        &self,
+        order_id: &str,
+        next: OrderStatus,
+        vehicle_id: Option<i32>,
+        nav_status: Option<u8>,
+        reason: Option<&str>,
+    ) -> Result<TaxiOrder, String> {
+        let db = self.database()?;
+        let order = db
+            .get_taxi_order(order_id)
+            .await
+            .map_err(|e| format!("查询出租车订单失败: {}", e))?
+            .ok_or_else(|| format!("订单 {} 不存在", order_id))?;
+        let current = OrderStatus::parse(&order.status)
+            .ok_or_else(|| format!("订单 {} 状态未知: {}", order_id, order.status))?;
+        if !current.can_transition_to(next) {
+            return Err(format!("订单 {} 不能从{}变更为{}", order_id, current.label(), next.label()));
+        }
+
+        // 分配车辆时在同一条更新中确认车辆没有其他进行中的订单
+        let busy = if next == OrderStatus::Assigned { OrderStatus::ACTIVE.map(OrderStatus::as_str).to_vec() } else { Vec::new() };
+        let updated = db
+            .update_taxi_order_status(
+                order_id,
+                current.as_str(),
+                next.as_str(),
+                vehicle_id.map(|vehicle_id| (vehicle_id, busy.as_slice())),
+                nav_status.map(i32::from),
+                reason,
+            )
+            .await
+            .map_err(|e| format!("更新出租车订单状态失败: {}", e))?;
+        let Some((order, transition)) = updated else {
+            if let Some(vehicle_id) = vehicle_id.filter(|_| next == OrderStatus::Assigned) {
+                let active = self.active_order(vehicle_id).await?.filter(|(active, _)| active.order_id != order_id);
+                if let Some((active, _)) = active {
+                    return Err(format!("车辆 {} 已有进行中的订单 {}", vehicle_id, active.order_id));
+                }
+            }
+            return Err(format!("订单 {} 状态已被更新，请重试", order_id));
+        };
+        info!(
+            "🚕 出租车订单 {} 状态: {} -> {}{}",
+            order_id,
+            current.label(),
+            next.label(),
+            reason.map(|r| format!("（{}）", r)).unwrap_or_default()
+        );
+        self.emit(&order, &transition);
+        Ok(order)
+    }
+
+    /// 取消订单
+    pub async fn cancel(&self, order_id: &str, reason: Option<&str>) -> Result<TaxiOrder, String> {
+        self.transition(order_id, OrderStatus::Cancelled, None, None, reason).await
+    }
+
+    /// 车辆导航状态变化：推进该车辆的进行中订单，返回变更后的订单
+    pub async fn on_nav_status(&self, vehicle_id: i32, nav_status: u8) -> Result<Option<TaxiOrder>, String> {
+        let Some((order, current)) = self.active_order(vehicle_id).await? else {
+            return Ok(None);
+        };
+
+        match OrderStatus::from_nav_status(nav_status) {
+            Some(next) if current.can_transition_to(next) => {
+                self.transition(&order.order_id, next, None, Some(nav_status), None).await.map(Some)
+            }
+            // 重复或过期的导航状态
+            Some(_) => Ok(None),
+            None if current.in_trip() => {
+                let reason = format!("行程中车辆导航状态变为{}", nav_status_text(nav_status));
+                self.transition(&order.order_id, OrderStatus::Failed, None, Some(nav_status), Some(&reason))
+                    .await
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 车辆断开：该车辆的进行中订单失败
+    pub async fn on_disconnect(&self, vehicle_id: i32) -> Result<Option<TaxiOrder>, String> {
+        let Some((order, _)) = self.active_order(vehicle_id).await? else {
+            return Ok(None);
+        };
+        self.transition(&order.order_id, OrderStatus::Failed, None, None, Some("车辆断开连接"))
+            .await
+            .map(Some)
+    }
+
+    /// 车辆的进行中订单（数据库未就绪时视为没有）
+    pub async fn active_order(&self, vehicle_id: i32) -> Result<Option<(TaxiOrder, OrderStatus)>, String> {
+        let Some(db) = self.sink.database() else {
+            return Ok(None);
+        };
+        let statuses = OrderStatus::ACTIVE.map(OrderStatus::as_str);
+        let order = db
+            .get_vehicle_taxi_order(vehicle_id, &statuses)
+            .await
+            .map_err(|e| format!("查询车辆 {} 的出租车订单失败: {}", vehicle_id, e))?;
+        Ok(order.and_then(|order| OrderStatus::parse(&order.status).map(|status| (order, status))))
+    }
+}
+
+/// 订单状态跟踪：订阅车队状态变化并推进订单，释放时停止
+pub struct TaxiOrderTracker {
+    task: JoinHandle<()>,
+}
+
+impl TaxiOrderTracker {
+    pub fn start(service: TaxiOrderService, fleet: &FleetState) -> Self {
+        let mut changes = fleet.subscribe();
+        let task = tokio::spawn(async move {
+            loop {
+                match changes.recv().await {
+                    Ok(change) => {
+                        if let Err(e) = Self::handle(&service, &change).await {
+                            warn!("处理车辆 {} 的出租车订单失败: {}", change.vehicle_id, e);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("出租车订单跟踪处理过慢，跳过 {} 条车队状态变化", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        debug!("出租车订单跟踪已启动");
+        Self { task }
+    }
+
+    async fn handle(service: &TaxiOrderService, change: &FleetStateChange) -> Result<(), String> {
+        match change.kind {
+            ChangeKind::Updated => {
+                let nav_status = change
+                    .changes
+                    .iter()
+                    .find(|c| c.field == "nav_status")
+                    .and_then(|c| c.new.as_u64())
+                    .and_then(|nav| u8::try_from(nav).ok());
+                if let Some(nav_status) = nav_status {
+                    service.on_nav_status(change.vehicle_id, nav_status).await?;
+                }
+            }
+            ChangeKind::Disconnected => {
+                service.on_disconnect(change.vehicle_id).await?;
+            }
+            ChangeKind::Connected | ChangeKind::Rekeyed => {}
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TaxiOrderTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::types::VehicleInfo;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn request(order_id: &str) -> CreateTaxiOrderRequest {
+        CreateTaxiOrderRequest {
+            order_id: order_id.to_string(),
+            start_x: 1.0,
+            start_y: 2.0,
+            end_x: 3.0,
+            end_y: 4.0,
+        }
+    }
+
+    fn sample_info(vehicle_id: u8, nav_status: u8) -> VehicleInfo {
+        VehicleInfo { nav_status, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    async fn service(dir: &TempDir) -> (TaxiOrderService, Arc<BroadcastEventSink>, VehicleDatabase) {
+        let db = VehicleDatabase::open(&dir.path().join("orders.db")).await.unwrap();
+        let sink = Arc::new(BroadcastEventSink::new(64, Some(db.clone())));
+        (TaxiOrderService::new(sink.clone()), sink, db)
+    }
+
+    #[test]
+    fn test_transition_rules() {
+        use OrderStatus::*;
+        assert!(Created.can_transition_to(Assigned));
+        assert!(!Created.can_transition_to(EnRouteToPickup));
+        // 排队订单分配后无车辆接单时可重新排队
+        assert!(Created.can_transition_to(Queued));
+        assert!(Queued.can_transition_to(Assigned));
+        assert!(Assigned.can_transition_to(Queued));
+        assert!(!Queued.can_transition_to(EnRouteToPickup));
+        assert!(!EnRouteToPickup.can_transition_to(Queued));
+        assert!(Assigned.can_transition_to(EnRouteToPickup));
+        // 出发前可改派
+        assert!(Assigned.can_transition_to(Assigned));
+        assert!(!EnRouteToPickup.can_transition_to(Assigned));
+        // 错过中间状态时允许跳过，但不能回退
+        assert!(Assigned.can_transition_to(PickedUp));
+        assert!(!EnRouteToDropoff.can_transition_to(PickedUp));
+        assert!(!PickedUp.can_transition_to(PickedUp));
+        assert!(!PickedUp.can_transition_to(Assigned));
+        assert!(EnRouteToDropoff.can_transition_to(Cancelled));
+        assert!(!Completed.can_transition_to(Failed));
+        assert!(!Cancelled.can_transition_to(Assigned));
+        assert_eq!(OrderStatus::parse(EnRouteToDropoff.as_str()), Some(EnRouteToDropoff));
+        assert_eq!(serde_json::json!(EnRouteToPickup), "en_route_to_pickup");
+    }
+
+    #[tokio::test]
+    async fn test_nav_status_drives_order_to_completion() {
+        let dir = TempDir::new().unwrap();
+        let (service, sink, db) = service(&dir).await;
+        let mut events = sink.subscribe();
+
+        let order = service.create(request("ORDER-A"), Some(5)).await.unwrap();
+        assert_eq!((order.status.as_str(), order.assigned_vehicle_id), ("assigned", Some(5)));
+
+        // 其他车辆与非订单导航状态不影响订单
+        assert!(service.on_nav_status(6, 3).await.unwrap().is_none());
+        assert!(service.on_nav_status(5, 1).await.unwrap().is_none());
+        for nav_status in [3, 9, 4] {
+            service.on_nav_status(5, nav_status).await.unwrap().unwrap();
+        }
+        // 重复的导航状态不产生变更
+        assert!(service.on_nav_status(5, 4).await.unwrap().is_none());
+        let done = service.on_nav_status(5, 10).await.unwrap().unwrap();
+        assert_eq!(done.status, "completed");
+        // 已完成的订单不再跟踪
+        assert!(service.on_nav_status(5, 3).await.unwrap().is_none());
+
+        let history = db.get_taxi_order_transitions("ORDER-A").await.unwrap();
+        assert_eq!(
+            history.iter().map(|t| t.to_status.as_str()).collect::<Vec<_>>(),
+            vec!["created", "assigned", "en_route_to_pickup", "picked_up", "en_route_to_dropoff", "completed"]
+        );
+        assert_eq!((history[5].from_status.as_deref(), history[5].nav_status), (Some("en_route_to_dropoff"), Some(10)));
+        assert!(history.iter().all(|t| !t.created_at.is_empty()));
+
+        let mut emitted = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            assert_eq!(event.event, "taxi-order-update");
+            emitted.push(event.payload["transition"]["to_status"].as_str().unwrap().to_string());
+        }
+        assert_eq!(emitted.len(), 6);
+        assert_eq!(emitted.last().map(String::as_str), Some("completed"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_failure() {
+        let dir = TempDir::new().unwrap();
+        let (service, _sink, db) = service(&dir).await;
+
+        service.create(request("ORDER-B"), None).await.unwrap();
+        let cancelled = service.cancel("ORDER-B", Some("乘客取消")).await.unwrap();
+        assert_eq!(cancelled.status, "cancelled");
+        let err = service.cancel("ORDER-B", None).await.unwrap_err();
+        assert!(err.contains("已取消"), "{}", err);
+        assert!(service.cancel("ORDER-X", None).await.unwrap_err().contains("不存在"));
+
+        // 行程中切换到其他导航状态时订单失败
+        service.create(request("ORDER-C"), Some(7)).await.unwrap();
+        service.on_nav_status(7, 3).await.unwrap();
+        let failed = service.on_nav_status(7, 15).await.unwrap().unwrap();
+        assert_eq!(failed.status, "failed");
+        let history = db.get_taxi_order_transitions("ORDER-C").await.unwrap();
+        assert_eq!(history.last().unwrap().reason.as_deref(), Some("行程中车辆导航状态变为平行驾驶模式"));
+
+        // 已分配的订单在车辆断开时失败
+        service.create(request("ORDER-D"), Some(8)).await.unwrap();
+        assert_eq!(service.on_disconnect(8).await.unwrap().unwrap().status, "failed");
+        assert!(service.on_disconnect(8).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_assignment_refuses_busy_vehicle() {
+        let dir = TempDir::new().unwrap();
+        let (service, _sink, db) = service(&dir).await;
+
+        service.create(request("ORDER-F"), Some(9)).await.unwrap();
+        service.create(request("ORDER-G"), None).await.unwrap();
+        let err = service.transition("ORDER-G", OrderStatus::Assigned, Some(9), None, None).await.unwrap_err();
+        assert_eq!(err, "车辆 9 已有进行中的订单 ORDER-F");
+        assert_eq!(db.get_taxi_order("ORDER-G").await.unwrap().unwrap().status, "created");
+
+        // 同一订单改派给原车辆不受限制；车辆订单结束后可以接新订单
+        service.transition("ORDER-F", OrderStatus::Assigned, Some(9), None, None).await.unwrap();
+        service.cancel("ORDER-F", None).await.unwrap();
+        let order = service.transition("ORDER-G", OrderStatus::Assigned, Some(9), None, None).await.unwrap();
+        assert_eq!((order.status.as_str(), order.assigned_vehicle_id), ("assigned", Some(9)));
+    }
+
+    #[tokio::test]
+    async fn test_tracker_follows_fleet_state() {
+        let dir = TempDir::new().unwrap();
+        let (service, sink, db) = service(&dir).await;
+        let fleet = FleetState::default();
+        let _tracker = TaxiOrderTracker::start(service.clone(), &fleet);
+        let mut events = sink.subscribe();
+
+        service.create(request("ORDER-E"), Some(2)).await.unwrap();
+        fleet.update_info(2, "车2", &sample_info(2, 1), 1);
+        fleet.update_info(2, "车2", &sample_info(2, 3), 2);
+        fleet.update_info(2, "车2", &sample_info(2, 9), 3);
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if event.payload["transition"]["to_status"] == "picked_up" {
+                    break;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(db.get_taxi_order("ORDER-E").await.unwrap().unwrap().status, "picked_up");
+    }
+}
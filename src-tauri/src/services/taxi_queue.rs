@@ -0,0 +1,288 @@
+//! 出租车订单排队
+//!
+//! 排队订单带要求接客时间（为空表示尽快）与优先级，保存在 `taxi_orders` 中（状态为排队中），
+//! 应用重启后继续排队。后台任务按 `DispatchConfig::queue_interval` 周期检查队列，按队列顺序
+//! 派出已到时间的订单（预约订单提前 `schedule_lead_time` 派单）；队首订单没有可派单的车辆时，
+//! 后面的订单继续等待，避免低优先级订单抢走先空出的车辆。派出后无车辆接单的订单重新排队。
+//!
+//! 新订单插入到同优先级订单之后，重新排队的订单插入到同优先级订单之前，也可手动调整顺序。
+
+use crate::database::{EnqueueTaxiOrderRequest, TaxiOrder};
+use crate::services::taxi_dispatch::{Dispatch, DispatchStrategyKind, TaxiDispatcher};
+use crate::services::taxi_orders::TaxiOrderService;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// 出租车订单队列
+#[derive(Clone)]
+pub struct TaxiOrderQueue {
+    dispatcher: TaxiDispatcher,
+}
+
+impl TaxiOrderQueue {
+    pub fn new(dispatcher: TaxiDispatcher) -> Self {
+        Self { dispatcher }
+    }
+
+    fn orders(&self) -> &TaxiOrderService {
+        self.dispatcher.orders()
+    }
+
+    /// 启动周期派单任务，返回的句柄丢弃时任务随之停止
+    pub fn start(self) -> TaxiOrderQueueTask {
+        let period = Duration::from_millis(u64::from(self.dispatcher.config().queue_interval.max(1)));
+        let task = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                timer.tick().await;
+                if let Err(e) = self.process(Utc::now()).await {
+                    warn!("处理出租车订单队列失败: {}", e);
+                }
+            }
+        });
+        TaxiOrderQueueTask { task }
+    }
+
+    /// 创建订单并加入队列
+    pub async fn enqueue(&self, mut request: EnqueueTaxiOrderRequest) -> Result<TaxiOrder, String> {
+        if let Some(scheduled_at) = request.scheduled_at.as_deref() {
+            let scheduled_at = DateTime::parse_from_rfc3339(scheduled_at)
+                .map_err(|e| format!("要求接客时间格式无效: {}", e))?;
+            // 统一为 UTC，便于比较
+            request.scheduled_at = Some(scheduled_at.with_timezone(&Utc).to_rfc3339());
+        }
+        if let Some(strategy) = request.dispatch_strategy.as_deref() {
+            if DispatchStrategyKind::parse(strategy).is_none() {
+                return Err(format!("未知的派单策略: {}", strategy));
+            }
+        }
+
+        let order = self.orders().enqueue(request).await?;
+        info!(
+            "🚕 出租车订单 {} 已加入队列（优先级 {}，要求接客时间 {}）",
+            order.order_id,
+            order.priority,
+            order.scheduled_at.as_deref().unwrap_or("尽快")
+        );
+        Ok(order)
+    }
+
+    /// 排队中的订单（按队列顺序）
+    pub async fn list(&self) -> Result<Vec<TaxiOrder>, String> {
+        self.orders()
+            .database()?
+            .get_queued_taxi_orders()
+            .await
+            .map_err(|e| format!("获取出租车订单队列失败: {}", e))
+    }
+
+    /// 按给定订单号顺序重排队列，未列出的订单保持原有顺序排在后面
+    pub async fn reorder(&self, order_ids: &[String]) -> Result<Vec<TaxiOrder>, String> {
+        let queued = self.list().await?;
+        let mut listed = HashSet::new();
+        for order_id in order_ids {
+            if !queued.iter().any(|order| &order.order_id == order_id) {
+                return Err(format!("订单 {} 不在排队中", order_id));
+            }
+            if !listed.insert(order_id.as_str()) {
+                return Err(format!("订单 {} 重复", order_id));
+            }
+        }
+
+        let mut ordered = order_ids.to_vec();
+        ordered.extend(
+            queued
+                .into_iter()
+                .filter(|order| !listed.contains(order.order_id.as_str()))
+                .map(|order| order.order_id),
+        );
+        self.orders()
+            .database()?
+            .reorder_taxi_order_queue(&ordered)
+            .await
+            .map_err(|e| format!("调整出租车订单队列失败: {}", e))
+    }
+
+    /// 按队列顺序派出到时间的订单，返回本次派出的订单
+    pub async fn process(&self, now: DateTime<Utc>) -> Result<Vec<Dispatch>, String> {
+        // 数据库未就绪时跳过
+        let Ok(db) = self.orders().database() else {
+            return Ok(Vec::new());
+        };
+        let queued = db
+            .get_queued_taxi_orders()
+            .await
+            .map_err(|e| format!("获取出租车订单队列失败: {}", e))?;
+
+        let config = self.dispatcher.config();
+        let lead_time = chrono::Duration::milliseconds(i64::from(config.schedule_lead_time));
+        let mut dispatched = Vec::new();
+        for order in queued {
+            if !is_due(&order, now + lead_time) {
+                continue;
+            }
+            let order_id = order.order_id.clone();
+            let strategy = order
+                .dispatch_strategy
+                .as_deref()
+                .and_then(DispatchStrategyKind::parse)
+                .unwrap_or(config.strategy);
+            match self.dispatcher.dispatch_queued(order, strategy).await {
+                Ok(Some(dispatch)) => dispatched.push(dispatch),
+                Ok(None) => {
+                    debug!("排队订单 {} 暂无可派单的车辆", order_id);
+                    break;
+                }
+                Err(e) => warn!("排队订单 {} 派单失败: {}", order_id, e),
+            }
+        }
+        Ok(dispatched)
+    }
+}
+
+/// 要求接客时间不晚于 `deadline`（时间无法解析时视为立即）
+fn is_due(order: &TaxiOrder, deadline: DateTime<Utc>) -> bool {
+    order
+        .scheduled_at
+        .as_deref()
+        .and_then(|scheduled_at| DateTime::parse_from_rfc3339(scheduled_at).ok())
+        .map_or(true, |scheduled_at| scheduled_at <= deadline)
+}
+
+/// 周期派单任务句柄
+pub struct TaxiOrderQueueTask {
+    task: JoinHandle<()>,
+}
+
+impl Drop for TaxiOrderQueueTask {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DispatchConfig;
+    use crate::database::{CreateTaxiOrderRequest, VehicleDatabase};
+    use crate::protocol_processing::types::VehicleInfo;
+    use crate::services::fleet_state::FleetState;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support::{self, eventually, RecordingSender};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn sample_info(vehicle_id: u8, nav_status: u8) -> VehicleInfo {
+        VehicleInfo { nav_status, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    fn request(order_id: &str, priority: i32, scheduled_at: Option<&str>) -> EnqueueTaxiOrderRequest {
+        EnqueueTaxiOrderRequest {
+            order: CreateTaxiOrderRequest {
+                order_id: order_id.to_string(),
+                start_x: 1.0,
+                start_y: 0.0,
+                end_x: 3.0,
+                end_y: 4.0,
+            },
+            scheduled_at: scheduled_at.map(str::to_string),
+            priority,
+            dispatch_strategy: None,
+        }
+    }
+
+    fn order_ids(orders: &[TaxiOrder]) -> Vec<&str> {
+        orders.iter().map(|order| order.order_id.as_str()).collect()
+    }
+
+    async fn queue(dir: &TempDir, fleet: &FleetState, accept_timeout: u32) -> (TaxiOrderQueue, VehicleDatabase, Arc<RecordingSender>) {
+        let db = VehicleDatabase::open(&dir.path().join("queue.db")).await.unwrap();
+        let orders = TaxiOrderService::new(Arc::new(BroadcastEventSink::new(64, Some(db.clone()))));
+        let sender = Arc::new(RecordingSender::default());
+        let config = DispatchConfig { accept_timeout, schedule_lead_time: 0, ..DispatchConfig::default() };
+        let dispatcher = TaxiDispatcher::new(orders, fleet.clone(), sender.clone(), None, config);
+        (TaxiOrderQueue::new(dispatcher), db, sender)
+    }
+
+    #[tokio::test]
+    async fn test_queue_order_and_reorder() {
+        let dir = TempDir::new().unwrap();
+        let (queue, db, _sender) = queue(&dir, &FleetState::default(), 15000).await;
+
+        for (order_id, priority) in [("Q-1", 0), ("Q-2", 0), ("Q-3", 5), ("Q-4", 5)] {
+            let order = queue.enqueue(request(order_id, priority, None)).await.unwrap();
+            assert_eq!(order.status, "queued");
+        }
+        // 高优先级排在前面，同优先级先到先派
+        assert_eq!(order_ids(&queue.list().await.unwrap()), vec!["Q-3", "Q-4", "Q-1", "Q-2"]);
+
+        let reordered = queue.reorder(&["Q-2".to_string(), "Q-4".to_string()]).await.unwrap();
+        assert_eq!(order_ids(&reordered), vec!["Q-2", "Q-4", "Q-3", "Q-1"]);
+        assert!(queue.reorder(&["Q-9".to_string()]).await.unwrap_err().contains("不在排队中"));
+        assert!(queue.reorder(&["Q-1".to_string(), "Q-1".to_string()]).await.unwrap_err().contains("重复"));
+
+        // 取消的订单移出队列
+        queue.orders().cancel("Q-4", None).await.unwrap();
+        let remaining = queue.list().await.unwrap();
+        assert_eq!(order_ids(&remaining), vec!["Q-2", "Q-3", "Q-1"]);
+        assert!(db.get_taxi_order("Q-4").await.unwrap().unwrap().queue_position.is_none());
+
+        let err = queue.enqueue(request("Q-5", 0, Some("明天"))).await.unwrap_err();
+        assert!(err.contains("要求接客时间"), "{}", err);
+        let mut invalid = request("Q-6", 0, None);
+        invalid.dispatch_strategy = Some("random".to_string());
+        assert!(queue.enqueue(invalid).await.unwrap_err().contains("未知的派单策略"));
+    }
+
+    #[tokio::test]
+    async fn test_holds_orders_until_vehicle_free_and_due() {
+        let dir = TempDir::new().unwrap();
+        let fleet = FleetState::default();
+        let (queue, db, sender) = queue(&dir, &fleet, 300).await;
+
+        let now = Utc::now();
+        let later = (now + chrono::Duration::minutes(10)).to_rfc3339();
+        queue.enqueue(request("S-1", 0, Some(&later))).await.unwrap();
+        queue.enqueue(request("S-2", 0, None)).await.unwrap();
+        queue.enqueue(request("S-3", 0, None)).await.unwrap();
+
+        // 没有车辆时全部保持排队
+        assert!(queue.process(now).await.unwrap().is_empty());
+        assert_eq!(queue.list().await.unwrap().len(), 3);
+
+        // 一辆空闲车辆：派出第一个到时间的订单，预约订单继续等待
+        fleet.connect(1, "车1", "127.0.0.1:1".to_string(), true);
+        fleet.update_info(1, "车1", &sample_info(1, 1), 1);
+        let dispatched = queue.process(now).await.unwrap();
+        assert_eq!(dispatched.iter().map(|d| (d.order_id.as_str(), d.vehicle_id)).collect::<Vec<_>>(), vec![("S-2", 1)]);
+        assert_eq!(order_ids(&queue.list().await.unwrap()), vec!["S-1", "S-3"]);
+
+        // 车辆超时未接单：订单重新排到队首
+        eventually("S-2 重新排队", || async { db.get_taxi_order("S-2").await.unwrap().unwrap().status == "queued" }).await;
+        let order = db.get_taxi_order("S-2").await.unwrap().unwrap();
+        assert_eq!((order.status.as_str(), order.assigned_vehicle_id), ("queued", None));
+        assert_eq!(order_ids(&queue.list().await.unwrap()), vec!["S-2", "S-1", "S-3"]);
+
+        // 车辆接单后其余订单继续等待；到预约时间后按顺序派单
+        queue.process(now).await.unwrap();
+        queue.orders().on_nav_status(1, 3).await.unwrap().unwrap();
+        assert!(queue.process(now).await.unwrap().is_empty());
+        fleet.connect(2, "车2", "127.0.0.1:2".to_string(), true);
+        fleet.update_info(2, "车2", &sample_info(2, 2), 1);
+        let dispatched = queue.process(now + chrono::Duration::minutes(11)).await.unwrap();
+        assert_eq!(dispatched.iter().map(|d| d.order_id.as_str()).collect::<Vec<_>>(), vec!["S-1"]);
+        assert_eq!(*sender.sent.lock(), vec![1, 1, 2]);
+
+        let history = db.get_taxi_order_transitions("S-2").await.unwrap();
+        assert_eq!(
+            history.iter().map(|t| t.to_status.as_str()).collect::<Vec<_>>(),
+            vec!["created", "queued", "assigned", "queued", "assigned", "en_route_to_pickup"]
+        );
+        assert_eq!(history[3].reason.as_deref(), Some("车辆 1 未在 0.3 秒内接单，已无可改派的车辆"));
+    }
+}
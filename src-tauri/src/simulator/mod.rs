@@ -0,0 +1,334 @@
+//! 内置模拟器
+//!
+//! 以与真车、沙盘相同的帧协议接入车辆 Socket 服务器，供集成测试与演示使用。
+//! 可在界面中通过命令启动，也可通过 `dz-viz-sim` 命令行独立运行。
+
+pub mod sandbox;
+pub mod vehicle;
+
+pub use sandbox::{load_light_timings, SandboxSimulator, SandboxSimulatorConfig, SandboxSimulatorStatus, TrafficLightTiming};
+pub use vehicle::{SimRoute, VehicleSimulator, VehicleSimulatorConfig, VehicleSimulatorStatus};
+
+use crate::database::VehicleDatabase;
+use crate::services::path_loader::PathLoader;
+use log::{error, info, warn, LevelFilter};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 处于平行驾驶模式的车辆编号（沙盘模拟器写入，车辆模拟器读取）
+pub type ParallelDriving = Arc<RwLock<HashSet<u8>>>;
+
+/// 模拟器控制器（Tauri 全局状态）
+#[derive(Default)]
+pub struct SimulatorController {
+    vehicles: Mutex<Option<VehicleSimulator>>,
+    sandbox: Mutex<Option<SandboxSimulator>>,
+    parallel_driving: ParallelDriving,
+}
+
+impl SimulatorController {
+    /// 启动车辆模拟器；已有模拟器运行时先停止
+    pub fn start_vehicles(&self, config: VehicleSimulatorConfig, route: SimRoute) -> Result<VehicleSimulatorStatus, String> {
+        let mut vehicles = self.vehicles.lock();
+        if let Some(previous) = vehicles.take() {
+            previous.stop();
+        }
+        let simulator = VehicleSimulator::start(config, route, self.parallel_driving.clone())?;
+        let status = simulator.status();
+        *vehicles = Some(simulator);
+        Ok(status)
+    }
+
+    /// 停止车辆模拟器，返回是否有模拟器在运行
+    pub fn stop_vehicles(&self) -> bool {
+        match self.vehicles.lock().take() {
+            Some(simulator) => {
+                simulator.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn vehicle_status(&self) -> Option<VehicleSimulatorStatus> {
+        self.vehicles.lock().as_ref().map(|simulator| simulator.status())
+    }
+
+    /// 启动沙盘模拟器；已有模拟器运行时先停止
+    pub fn start_sandbox(&self, config: SandboxSimulatorConfig, timings: Vec<TrafficLightTiming>) -> SandboxSimulatorStatus {
+        let mut sandbox = self.sandbox.lock();
+        if let Some(previous) = sandbox.take() {
+            previous.stop();
+        }
+        let simulator = SandboxSimulator::start(config, timings, self.parallel_driving.clone());
+        let status = simulator.status();
+        *sandbox = Some(simulator);
+        status
+    }
+
+    /// 停止沙盘模拟器，返回是否有模拟器在运行
+    pub fn stop_sandbox(&self) -> bool {
+        match self.sandbox.lock().take() {
+            Some(simulator) => {
+                simulator.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn sandbox_status(&self) -> Option<SandboxSimulatorStatus> {
+        self.sandbox.lock().as_ref().map(|simulator| simulator.status())
+    }
+}
+
+const USAGE: &str = "用法: dz-viz-sim [选项]
+  --server <HOST:PORT>   车辆服务器地址（默认 127.0.0.1:<Socket端口>）
+  --count <N>            虚拟车辆数量（默认 1，0 表示不模拟车辆）
+  --first-id <ID>        第一辆车编号（默认 1）
+  --routes <DIR>         路径文件目录（默认 public/routes）
+  --paths <1,2,3>        行驶路径编号，缺省时沿沙盘外圈行驶
+  --speed <M/S>          行驶速度（默认 0.25）
+  --interval <MS>        车辆信息发送间隔（默认 500）
+  --auth-key <KEY>       接入认证密钥（所有虚拟车辆共用），缺省时不注册
+  --sandbox              同时模拟沙盘服务（红绿灯时长取自本地数据库）
+  --sandbox-bind <IP>    沙盘连接使用的本地地址，需与沙盘服务设置中的IP一致
+  --duration <SECS>      运行时长，缺省时一直运行
+  --debug                输出调试日志";
+
+/// 命令行参数
+struct CliOptions {
+    config: VehicleSimulatorConfig,
+    sandbox: Option<SandboxSimulatorConfig>,
+    routes_dir: Option<PathBuf>,
+    route_ids: Vec<u8>,
+    duration: Option<Duration>,
+    auth_key: Option<String>,
+    debug: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut options = CliOptions {
+        config: VehicleSimulatorConfig::default(),
+        sandbox: None,
+        routes_dir: None,
+        route_ids: Vec::new(),
+        duration: None,
+        auth_key: None,
+        debug: false,
+    };
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag == "--debug" {
+            options.debug = true;
+            continue;
+        }
+        if flag == "--sandbox" {
+            options.sandbox.get_or_insert_with(SandboxSimulatorConfig::default);
+            continue;
+        }
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("参数 {} 缺少取值\n{}", flag, USAGE))?;
+        let invalid = |_| format!("参数 {} 的取值无效: {}", flag, value);
+        match flag.as_str() {
+            "--server" => options.config.server_addr = value.clone(),
+            "--count" => options.config.vehicle_count = value.parse().map_err(invalid)?,
+            "--first-id" => options.config.first_vehicle_id = value.parse().map_err(invalid)?,
+            "--routes" => options.routes_dir = Some(PathBuf::from(value)),
+            "--paths" => {
+                options.route_ids = value
+                    .split(',')
+                    .filter(|id| !id.trim().is_empty())
+                    .map(|id| id.trim().parse::<u8>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("参数 {} 的取值无效: {}", flag, value))?;
+            }
+            "--speed" => {
+                options.config.speed = value
+                    .parse()
+                    .map_err(|_| format!("参数 {} 的取值无效: {}", flag, value))?;
+            }
+            "--interval" => options.config.info_interval_ms = value.parse().map_err(invalid)?,
+            "--auth-key" => options.auth_key = Some(value.clone()),
+            "--sandbox-bind" => {
+                let bind_ip: IpAddr = value
+                    .parse()
+                    .map_err(|_| format!("参数 {} 的取值无效: {}", flag, value))?;
+                options.sandbox.get_or_insert_with(SandboxSimulatorConfig::default).bind_ip = Some(bind_ip);
+            }
+            "--duration" => {
+                let seconds: u64 = value.parse().map_err(invalid)?;
+                options.duration = Some(Duration::from_secs(seconds));
+            }
+            _ => return Err(format!("未知参数: {}\n{}", flag, USAGE)),
+        }
+    }
+
+    let ids = vehicle_ids(options.config.first_vehicle_id, options.config.vehicle_count)?;
+    if let Some(auth_key) = &options.auth_key {
+        options.config.auth_keys = ids.into_iter().map(|vehicle_id| (vehicle_id, auth_key.clone())).collect();
+    }
+
+    // 沙盘与车辆连接同一个服务器
+    if let Some(sandbox) = options.sandbox.as_mut() {
+        sandbox.server_addr = options.config.server_addr.clone();
+    }
+    if options.config.vehicle_count == 0 && options.sandbox.is_none() {
+        return Err(format!("未指定任何模拟对象\n{}", USAGE));
+    }
+
+    Ok(options)
+}
+
+/// 模拟的车辆编号（自首车起依次递增，超出 255 时返回错误）
+pub fn vehicle_ids(first_vehicle_id: u8, count: u8) -> Result<Vec<u8>, String> {
+    (0..count)
+        .map(|offset| first_vehicle_id.checked_add(offset))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("车辆编号超出范围: 自 {} 号起 {} 辆车超过 255", first_vehicle_id, count))
+}
+
+/// 加载模拟路线：未指定路径编号时沿沙盘外圈行驶
+fn load_route(routes_dir: Option<PathBuf>, route_ids: &[u8]) -> SimRoute {
+    if route_ids.is_empty() {
+        return SimRoute::sandbox_loop();
+    }
+
+    let routes_dir = routes_dir.unwrap_or_else(|| {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        [current_dir.join("public").join("routes"), current_dir.join("..").join("public").join("routes")]
+            .into_iter()
+            .find(|dir| dir.exists())
+            .unwrap_or_else(|| current_dir.join("public").join("routes"))
+    });
+
+    let loader = PathLoader::new(routes_dir);
+    if let Err(e) = loader.preload_all_paths() {
+        error!("❌ 路径文件预加载失败: {}", e);
+    }
+    SimRoute::from_loader(&loader, route_ids)
+}
+
+/// 命令行入口（`dz-viz-sim`）
+pub fn run() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    crate::headless::install_stdout_logger(if options.debug { LevelFilter::Debug } else { LevelFilter::Info });
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("创建异步运行时失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    runtime.block_on(async move {
+        let parallel_driving = ParallelDriving::default();
+
+        let sandbox = match options.sandbox {
+            Some(config) => {
+                let db = match VehicleDatabase::new().await {
+                    Ok(db) => Some(db),
+                    Err(e) => {
+                        warn!("打开本地数据库失败，红绿灯使用默认时长: {}", e);
+                        None
+                    }
+                };
+                let timings = load_light_timings(db.as_ref(), None).await;
+                Some(SandboxSimulator::start(config, timings, parallel_driving.clone()))
+            }
+            None => None,
+        };
+
+        let vehicles = if options.config.vehicle_count > 0 {
+            let route = load_route(options.routes_dir, &options.route_ids);
+            match VehicleSimulator::start(options.config, route, parallel_driving) {
+                Ok(simulator) => Some(simulator),
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(2);
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(duration) = options.duration {
+            tokio::time::sleep(duration).await;
+            if let Some(simulator) = &vehicles {
+                simulator.stop();
+            }
+            if let Some(simulator) = &sandbox {
+                simulator.stop();
+            }
+        }
+        if let Some(simulator) = vehicles {
+            simulator.join().await;
+        }
+        if let Some(simulator) = sandbox {
+            simulator.join().await;
+        }
+        if let Some(duration) = options.duration {
+            info!("模拟运行结束 ({} 秒)", duration.as_secs());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args: Vec<String> = ["--server", "10.0.0.2:9000", "--count", "3", "--paths", "1, 2,5", "--debug"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_args(&args).unwrap();
+        assert_eq!(options.config.server_addr, "10.0.0.2:9000");
+        assert_eq!(options.config.vehicle_count, 3);
+        assert_eq!(options.route_ids, vec![1, 2, 5]);
+        assert!(options.debug);
+
+        let args: Vec<String> = ["--count", "2", "--first-id", "4", "--auth-key", "shared-key"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_args(&args).unwrap();
+        assert_eq!(options.config.auth_keys.len(), 2);
+        assert_eq!(options.config.auth_keys[&5], "shared-key");
+
+        let args: Vec<String> = ["--count", "0", "--sandbox-bind", "127.0.0.2"].iter().map(|s| s.to_string()).collect();
+        let options = parse_args(&args).unwrap();
+        let sandbox = options.sandbox.unwrap();
+        assert_eq!(sandbox.bind_ip, Some("127.0.0.2".parse().unwrap()));
+        assert_eq!(sandbox.server_addr, options.config.server_addr);
+
+        assert!(parse_args(&["--count".to_string(), "0".to_string()]).is_err());
+        assert!(parse_args(&["--count".to_string(), "x".to_string()]).is_err());
+        assert!(parse_args(&["--unknown".to_string(), "1".to_string()]).is_err());
+
+        // 车辆编号不回绕：250 号起 10 辆车超出范围
+        let args: Vec<String> = ["--count", "10", "--first-id", "250"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+        assert_eq!(vehicle_ids(250, 6).unwrap(), vec![250, 251, 252, 253, 254, 255]);
+    }
+}
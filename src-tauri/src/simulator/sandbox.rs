@@ -0,0 +1,622 @@
+//! 沙盘服务模拟器
+//!
+//! 以沙盘身份接入车辆 Socket 服务器：按 `traffic_light_items` 中的时长运行每个红绿灯的
+//! 红→绿→黄循环，每秒发送一次红绿灯状态（0x3001），并响应平行驾驶（0x2001）、
+//! 红绿灯时长设置（0x2002）与沙盘灯光控制（0x2003）。服务器按来源 IP 识别沙盘，
+//! 本机联调时可绑定到沙盘设置中配置的回环地址（如 127.0.0.2）。
+
+use super::ParallelDriving;
+use crate::config::AppConfig;
+use crate::database::VehicleDatabase;
+use crate::protocol_processing::types::{MessageTypes, SandboxLightingData};
+use crate::socket::protocol::{build_message, ProtocolParser};
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 平行驾驶指令
+const PARALLEL_DRIVING: u16 = 0x2001;
+/// 红绿灯时长设置指令
+const TRAFFIC_LIGHT_DURATION: u16 = 0x2002;
+/// 未配置时的红绿灯数量
+const DEFAULT_LIGHT_COUNT: u8 = 2;
+/// 未配置时的红/绿灯时长（秒，与数据库默认值一致）
+const DEFAULT_LIGHT_SECONDS: u16 = 30;
+
+/// 红绿灯颜色（与 0x3001 协议取值一致）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightColor {
+    Red = 1,
+    Green = 2,
+    Yellow = 3,
+}
+
+/// 单个红绿灯的时长配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficLightTiming {
+    pub light_id: u8,
+    pub red_seconds: u16,
+    pub green_seconds: u16,
+}
+
+/// 沙盘模拟器配置
+#[derive(Debug, Clone)]
+pub struct SandboxSimulatorConfig {
+    /// 车辆服务器地址（host:port）
+    pub server_addr: String,
+    /// 本地绑定地址，需与沙盘服务设置中的 IP 一致
+    pub bind_ip: Option<IpAddr>,
+    /// 黄灯时长（秒）
+    pub yellow_seconds: u16,
+    /// 断线重连间隔（毫秒）
+    pub reconnect_delay_ms: u64,
+}
+
+impl Default for SandboxSimulatorConfig {
+    fn default() -> Self {
+        let config = AppConfig::global();
+        Self {
+            server_addr: format!("127.0.0.1:{}", config.ports.socket_server),
+            bind_ip: None,
+            yellow_seconds: 3,
+            reconnect_delay_ms: config.network.retry_delay as u64,
+        }
+    }
+}
+
+/// 从数据库读取指定沙盘（未指定时为默认沙盘）的红绿灯时长：数量取沙盘服务设置，缺失的编号使用默认时长
+pub async fn load_light_timings(db: Option<&VehicleDatabase>, sandbox_id: Option<i64>) -> Vec<TrafficLightTiming> {
+    let mut count = DEFAULT_LIGHT_COUNT;
+    let mut items = Vec::new();
+
+    if let Some(db) = db {
+        let settings = match sandbox_id {
+            Some(id) => db.get_sandbox_service_settings_by_id(id).await,
+            None => db.get_sandbox_service_settings().await,
+        };
+        let sandbox_id = match settings {
+            Ok(Some(settings)) => {
+                if settings.traffic_light_count > 0 {
+                    count = settings.traffic_light_count.min(u8::MAX as i32) as u8;
+                }
+                Some(settings.id)
+            }
+            Ok(None) => sandbox_id,
+            Err(e) => {
+                warn!("读取沙盘服务设置失败，使用默认红绿灯数量: {}", e);
+                sandbox_id
+            }
+        };
+        if let Some(sandbox_id) = sandbox_id {
+            match db.get_all_traffic_light_items(sandbox_id).await {
+                Ok(list) => items = list,
+                Err(e) => warn!("读取红绿灯时长失败，使用默认时长: {}", e),
+            }
+        }
+    }
+
+    (1..=count)
+        .map(|light_id| {
+            let item = items.iter().find(|item| item.light_id == light_id as i32);
+            let seconds = |value: Option<i32>| {
+                value
+                    .filter(|&s| s > 0)
+                    .map(|s| s.min(u16::MAX as i32) as u16)
+                    .unwrap_or(DEFAULT_LIGHT_SECONDS)
+            };
+            TrafficLightTiming {
+                light_id,
+                red_seconds: seconds(item.map(|item| item.red_light_duration)),
+                green_seconds: seconds(item.map(|item| item.green_light_duration)),
+            }
+        })
+        .collect()
+}
+
+/// 单个红绿灯的循环状态
+#[derive(Debug, Clone)]
+struct LightCycle {
+    timing: TrafficLightTiming,
+    color: LightColor,
+    remaining: u16,
+}
+
+impl LightCycle {
+    fn duration(&self, color: LightColor, yellow_seconds: u16) -> u16 {
+        match color {
+            LightColor::Red => self.timing.red_seconds,
+            LightColor::Green => self.timing.green_seconds,
+            LightColor::Yellow => yellow_seconds,
+        }
+        .max(1)
+    }
+
+    fn tick(&mut self, yellow_seconds: u16) {
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 {
+            self.color = match self.color {
+                LightColor::Red => LightColor::Green,
+                LightColor::Green => LightColor::Yellow,
+                LightColor::Yellow => LightColor::Red,
+            };
+            self.remaining = self.duration(self.color, yellow_seconds);
+        }
+    }
+}
+
+/// 沙盘状态模型（不涉及网络，便于单独测试）
+pub struct SandboxModel {
+    lights: Vec<LightCycle>,
+    yellow_seconds: u16,
+    lighting: SandboxLightingData,
+    parallel_driving: ParallelDriving,
+}
+
+impl SandboxModel {
+    /// 相邻红绿灯错开相位：奇数编号从红灯开始，偶数编号从绿灯开始
+    pub fn new(timings: Vec<TrafficLightTiming>, yellow_seconds: u16, parallel_driving: ParallelDriving) -> Self {
+        let lights = timings
+            .into_iter()
+            .map(|timing| {
+                let mut light = LightCycle {
+                    timing,
+                    color: if timing.light_id % 2 == 1 { LightColor::Red } else { LightColor::Green },
+                    remaining: 0,
+                };
+                light.remaining = light.duration(light.color, yellow_seconds);
+                light
+            })
+            .collect();
+
+        Self {
+            lights,
+            yellow_seconds: yellow_seconds.max(1),
+            lighting: SandboxLightingData {
+                barrier: 0,
+                ambient: 0,
+                building: 0,
+                street: 0,
+            },
+            parallel_driving,
+        }
+    }
+
+    /// 推进一秒
+    pub fn tick_second(&mut self) {
+        for light in &mut self.lights {
+            light.tick(self.yellow_seconds);
+        }
+    }
+
+    /// 构建 0x3001 数据域：每个灯 1 字节颜色 + 1 字节剩余秒数
+    pub fn status_payload(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.lights.len() * 2);
+        for light in &self.lights {
+            data.push(light.color as u8);
+            data.push(light.remaining.min(u8::MAX as u16) as u8);
+        }
+        data
+    }
+
+    /// 执行服务器下发的沙盘指令，返回是否被处理
+    pub fn apply(&mut self, message_type: u16, data: &[u8]) -> Result<bool, String> {
+        match message_type {
+            PARALLEL_DRIVING => {
+                if data.len() < 2 {
+                    return Err(format!("平行驾驶指令数据长度不足: {} 字节", data.len()));
+                }
+                let (vehicle_id, enter) = (data[0], data[1] == 1);
+                let mut vehicles = self.parallel_driving.write();
+                if enter {
+                    vehicles.insert(vehicle_id);
+                } else {
+                    vehicles.remove(&vehicle_id);
+                }
+                info!("🎮 模拟沙盘: 车辆 {} {}平行驾驶", vehicle_id, if enter { "进入" } else { "退出" });
+                Ok(true)
+            }
+            TRAFFIC_LIGHT_DURATION => {
+                if data.len() < 5 {
+                    return Err(format!("红绿灯时长指令数据长度不足: {} 字节", data.len()));
+                }
+                let light_id = data[0];
+                let red_seconds = u16::from_le_bytes([data[1], data[2]]);
+                let green_seconds = u16::from_le_bytes([data[3], data[4]]);
+                let yellow_seconds = self.yellow_seconds;
+                let Some(light) = self.lights.iter_mut().find(|light| light.timing.light_id == light_id) else {
+                    return Err(format!("红绿灯 {} 不存在", light_id));
+                };
+                light.timing.red_seconds = red_seconds;
+                light.timing.green_seconds = green_seconds;
+                // 新时长从下一相位生效；当前相位剩余时间超过新时长时立即截断
+                let current = light.duration(light.color, yellow_seconds);
+                light.remaining = light.remaining.min(current);
+                info!("🚦 模拟沙盘: 红绿灯 {} 时长更新为 红{}秒/绿{}秒", light_id, red_seconds, green_seconds);
+                Ok(true)
+            }
+            MessageTypes::SANDBOX_LIGHTING_CONTROL => {
+                if data.len() < 4 {
+                    return Err(format!("灯光控制指令数据长度不足: {} 字节", data.len()));
+                }
+                self.lighting = SandboxLightingData {
+                    barrier: data[0],
+                    ambient: data[1],
+                    building: data[2],
+                    street: data[3],
+                };
+                info!(
+                    "💡 模拟沙盘: 停车抬杆={}, 环境灯={}, 建筑灯={}, 路灯={}",
+                    data[0], data[1], data[2], data[3]
+                );
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn light_status(&self) -> Vec<SimLightStatus> {
+        self.lights
+            .iter()
+            .map(|light| SimLightStatus {
+                light_id: light.timing.light_id,
+                color: light.color,
+                remaining: light.remaining,
+                red_seconds: light.timing.red_seconds,
+                green_seconds: light.timing.green_seconds,
+            })
+            .collect()
+    }
+}
+
+/// 单个红绿灯状态（供界面显示）
+#[derive(Debug, Clone, Serialize)]
+pub struct SimLightStatus {
+    pub light_id: u8,
+    pub color: LightColor,
+    pub remaining: u16,
+    pub red_seconds: u16,
+    pub green_seconds: u16,
+}
+
+/// 沙盘模拟器状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SandboxSimulatorStatus {
+    pub running: bool,
+    pub connected: bool,
+    pub server_addr: String,
+    pub lights: Vec<SimLightStatus>,
+    pub lighting: SandboxLightingData,
+    pub parallel_driving: Vec<u8>,
+    pub frames_sent: u64,
+    pub commands_received: u64,
+}
+
+/// 连接与收发计数
+#[derive(Default)]
+struct LinkState {
+    connected: bool,
+    frames_sent: u64,
+    commands_received: u64,
+}
+
+/// 运行中的沙盘模拟器
+pub struct SandboxSimulator {
+    config: SandboxSimulatorConfig,
+    model: Arc<Mutex<SandboxModel>>,
+    link: Arc<Mutex<LinkState>>,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl SandboxSimulator {
+    pub fn start(config: SandboxSimulatorConfig, timings: Vec<TrafficLightTiming>, parallel_driving: ParallelDriving) -> Self {
+        info!(
+            "🚦 启动沙盘模拟器: {} 个红绿灯 -> {}{}",
+            timings.len(),
+            config.server_addr,
+            config.bind_ip.map(|ip| format!(" (本地地址 {})", ip)).unwrap_or_default()
+        );
+
+        let model = Arc::new(Mutex::new(SandboxModel::new(timings, config.yellow_seconds, parallel_driving)));
+        let link = Arc::new(Mutex::new(LinkState::default()));
+        let (shutdown, receiver) = watch::channel(false);
+        let task = tokio::spawn(run_sandbox(config.clone(), model.clone(), link.clone(), receiver));
+
+        Self {
+            config,
+            model,
+            link,
+            shutdown,
+            task,
+        }
+    }
+
+    /// 断开连接并退出
+    pub fn stop(&self) {
+        let _ = self.shutdown.send(true);
+        // 沙盘离线后不再维持平行驾驶状态
+        self.model.lock().parallel_driving.write().clear();
+        info!("🛑 沙盘模拟器已停止");
+    }
+
+    /// 等待模拟任务退出
+    pub async fn join(self) {
+        let _ = self.task.await;
+    }
+
+    pub fn status(&self) -> SandboxSimulatorStatus {
+        let model = self.model.lock();
+        let link = self.link.lock();
+        let mut parallel_driving: Vec<u8> = model.parallel_driving.read().iter().copied().collect();
+        parallel_driving.sort_unstable();
+        SandboxSimulatorStatus {
+            running: !*self.shutdown.borrow() && !self.task.is_finished(),
+            connected: link.connected,
+            server_addr: self.config.server_addr.clone(),
+            lights: model.light_status(),
+            lighting: model.lighting.clone(),
+            parallel_driving,
+            frames_sent: link.frames_sent,
+            commands_received: link.commands_received,
+        }
+    }
+}
+
+/// 按配置的本地地址建立连接
+async fn connect(config: &SandboxSimulatorConfig) -> std::io::Result<TcpStream> {
+    let Some(bind_ip) = config.bind_ip else {
+        return TcpStream::connect(&config.server_addr).await;
+    };
+
+    let server: SocketAddr = lookup_host(&config.server_addr)
+        .await?
+        .find(|addr| addr.is_ipv4() == bind_ip.is_ipv4())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "无法解析服务器地址"))?;
+    let socket = if bind_ip.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    socket.bind(SocketAddr::new(bind_ip, 0))?;
+    socket.connect(server).await
+}
+
+async fn run_sandbox(
+    config: SandboxSimulatorConfig,
+    model: Arc<Mutex<SandboxModel>>,
+    link: Arc<Mutex<LinkState>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        if *shutdown.borrow() {
+            break;
+        }
+
+        match connect(&config).await {
+            Ok(stream) => {
+                info!("🚦 模拟沙盘已连接 {}", config.server_addr);
+                link.lock().connected = true;
+                let result = drive_connection(stream, &model, &link, &mut shutdown).await;
+                link.lock().connected = false;
+                match result {
+                    Ok(()) => break,
+                    Err(e) => warn!("模拟沙盘连接中断: {}", e),
+                }
+            }
+            Err(e) => warn!("模拟沙盘连接 {} 失败: {}", config.server_addr, e),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(config.reconnect_delay_ms.max(100))) => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    debug!("沙盘模拟任务退出");
+}
+
+/// 单次连接的收发循环；收到停止信号返回 Ok，连接异常返回 Err
+async fn drive_connection(
+    mut stream: TcpStream,
+    model: &Arc<Mutex<SandboxModel>>,
+    link: &Arc<Mutex<LinkState>>,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Result<(), String> {
+    let mut parser = ProtocolParser::new();
+    let mut buffer = [0u8; 4096];
+    let mut second_timer = tokio::time::interval(Duration::from_secs(1));
+    let mut first_frame = true;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => return Ok(()),
+
+            _ = second_timer.tick() => {
+                let payload = {
+                    let mut model = model.lock();
+                    // 首帧上报初始状态，之后每秒推进一次
+                    if !first_frame {
+                        model.tick_second();
+                    }
+                    model.status_payload()
+                };
+                first_frame = false;
+                stream
+                    .write_all(&build_message(MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS, &payload))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                link.lock().frames_sent += 1;
+            }
+
+            result = stream.read(&mut buffer) => {
+                let n = result.map_err(|e| e.to_string())?;
+                if n == 0 {
+                    return Err("服务器关闭连接".to_string());
+                }
+                parser.feed_data(&buffer[..n]);
+                loop {
+                    match parser.try_parse_message() {
+                        Ok(Some(message)) => match model.lock().apply(message.message_type, &message.data) {
+                            Ok(true) => link.lock().commands_received += 1,
+                            Ok(false) => debug!("模拟沙盘忽略消息类型 0x{:04X}", message.message_type),
+                            Err(e) => warn!("模拟沙盘指令处理失败: {}", e),
+                        },
+                        Ok(None) => break,
+                        // 已跳过损坏的帧头，继续解析缓冲区中的后续帧
+                        Err(e) => warn!("模拟沙盘协议解析失败: {}", e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::CreateOrUpdateSandboxServiceRequest;
+    use crate::socket::{BroadcastEventSink, SandboxConnectionManager, SocketServer};
+    use parking_lot::RwLock;
+    use std::collections::HashMap;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    fn timing(light_id: u8, red_seconds: u16, green_seconds: u16) -> TrafficLightTiming {
+        TrafficLightTiming {
+            light_id,
+            red_seconds,
+            green_seconds,
+        }
+    }
+
+    #[test]
+    fn test_light_cycle_and_duration_update() {
+        let mut model = SandboxModel::new(vec![timing(1, 2, 3), timing(2, 4, 2)], 1, ParallelDriving::default());
+        assert_eq!(model.status_payload(), vec![1, 2, 2, 2]);
+
+        model.tick_second();
+        model.tick_second();
+        // 1号灯红灯结束转绿，2号灯绿灯结束转黄
+        assert_eq!(model.status_payload(), vec![2, 3, 3, 1]);
+        model.tick_second();
+        assert_eq!(model.status_payload(), vec![2, 2, 1, 4]);
+
+        // 0x2002: 2号灯红灯改为1秒，当前红灯剩余时间被截断
+        assert!(model.apply(TRAFFIC_LIGHT_DURATION, &[2, 1, 0, 9, 0]).unwrap());
+        assert_eq!(model.status_payload()[2..], [1, 1]);
+        model.tick_second();
+        assert_eq!(model.status_payload()[2..], [2, 9]);
+
+        assert!(model.apply(TRAFFIC_LIGHT_DURATION, &[7, 1, 0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parallel_driving_and_lighting_commands() {
+        let parallel = ParallelDriving::default();
+        let mut model = SandboxModel::new(vec![timing(1, 30, 30)], 3, parallel.clone());
+
+        assert!(model.apply(PARALLEL_DRIVING, &[3, 1]).unwrap());
+        assert!(parallel.read().contains(&3));
+        assert!(model.apply(PARALLEL_DRIVING, &[3, 0]).unwrap());
+        assert!(parallel.read().is_empty());
+
+        assert!(model.apply(MessageTypes::SANDBOX_LIGHTING_CONTROL, &[1, 0, 1, 1]).unwrap());
+        assert_eq!(model.lighting.barrier, 1);
+        assert_eq!(model.lighting.street, 1);
+        assert!(!model.apply(0x1001, &[1, 1]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_simulator_end_to_end() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+        let settings = db
+            .create_or_update_sandbox_service_settings(CreateOrUpdateSandboxServiceRequest {
+                id: None,
+                name: None,
+                ip_address: "127.0.0.2".to_string(),
+                traffic_light_count: 2,
+            })
+            .await
+            .unwrap();
+        db.update_traffic_light_item(settings.id, 1, 12, 8).await.unwrap();
+
+        // 第二个沙盘的红绿灯时长互不影响
+        let other = db
+            .create_sandbox_service_settings(CreateOrUpdateSandboxServiceRequest {
+                id: None,
+                name: Some("东区沙盘".to_string()),
+                ip_address: "127.0.0.3".to_string(),
+                traffic_light_count: 1,
+            })
+            .await
+            .unwrap();
+        db.update_traffic_light_item(other.id, 1, 40, 20).await.unwrap();
+        assert_eq!(load_light_timings(Some(&db), Some(other.id)).await, vec![timing(1, 40, 20)]);
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, Some(db.clone())));
+        let mut events = sink.subscribe();
+        let sandbox: SandboxConnectionManager = Arc::new(RwLock::new(HashMap::new()));
+        let server = SocketServer::new_with_connections(
+            port,
+            sink.clone(),
+            Arc::new(RwLock::new(HashMap::new())),
+            sandbox.clone(),
+        );
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let timings = load_light_timings(Some(&db), None).await;
+        assert_eq!(timings, vec![timing(1, 12, 8), timing(2, 30, 30)]);
+
+        let config = SandboxSimulatorConfig {
+            server_addr: format!("127.0.0.1:{}", port),
+            bind_ip: Some("127.0.0.2".parse().unwrap()),
+            ..SandboxSimulatorConfig::default()
+        };
+        let simulator = SandboxSimulator::start(config, timings, ParallelDriving::default());
+
+        // 服务器按IP识别为沙盘，并解析出红绿灯状态
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                let parsed = &event.payload["parsed"];
+                if event.event == "socket-message" && parsed["type"] == "sandbox_traffic_light_status" {
+                    break parsed["lights"][0]["remaining"].as_u64();
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, Some(12));
+
+        assert!(sandbox.read().contains_key(&settings.id));
+        assert_eq!(
+            SocketServer::send_to_sandbox(&sandbox, Some(other.id), PARALLEL_DRIVING, &[1, 1]),
+            Err(format!("沙盘 {} 离线", other.id))
+        );
+        SocketServer::send_to_sandbox(&sandbox, Some(settings.id), TRAFFIC_LIGHT_DURATION, &[2, 5, 0, 6, 0]).unwrap();
+        SocketServer::send_to_sandbox(&sandbox, None, PARALLEL_DRIVING, &[1, 1]).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while simulator.status().commands_received < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let status = simulator.status();
+        assert!(status.connected);
+        assert_eq!((status.lights[1].red_seconds, status.lights[1].green_seconds), (5, 6));
+        assert_eq!(status.parallel_driving, vec![1]);
+
+        simulator.stop();
+        simulator.join().await;
+    }
+}
@@ -0,0 +1,1238 @@
+//! 虚拟车辆模拟器
+//!
+//! 每辆虚拟车通过 TCP 连接车辆 Socket 服务器，按 0xEFEFEFEF 帧协议定时发送心跳（0x0001）
+//! 与车辆信息（0x0002），沿 PathLoader 路径绕行，并响应启停/紧急制动（0x1001）、
+//! 出租车订单/订单取消（0x1003/0x100C）以及 AVP 泊车/取车（0x1004/0x1005）指令；
+//! 沙盘模拟器收到平行驾驶指令（0x2001）后，对应车辆上报导航状态 15。
+//! 配置了认证密钥的车辆先回应服务器挑战（0x100A）完成注册，再开始上报；
+//! 收到控制类指令后回复指令确认（0x000A）。
+//! 用于替代 `test/test_client.py`，让集成测试与演示无需真车即可运行。
+
+use super::ParallelDriving;
+use crate::config::AppConfig;
+use crate::protocol_processing::builder::ProtocolBuilder;
+use crate::protocol_processing::types::{
+    AvpParkingData, AvpPickupData, ControlCommandType, GearPosition, MessageTypes, PositionData,
+    ProtocolConstants, SendMessageTypes, SensorStatus, TaxiOrderData, VehicleControlCommand,
+    VehicleInfo,
+};
+use crate::services::path_loader::{PathLoader, PathPoint};
+use crate::socket::ack::{self, AckCode, CommandAck};
+use crate::socket::auth::{self, AuthStatus};
+use crate::socket::protocol::{build_message, ProtocolParser};
+use crate::utils::byte_utils;
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 电量消耗（%/秒，仅行驶时）
+const BATTERY_DRAIN_PER_SECOND: f64 = 0.025;
+/// 模拟电量下限
+const MIN_BATTERY: f64 = 20.0;
+/// 方向盘转角预瞄距离（米）
+const STEERING_LOOKAHEAD: f64 = 0.15;
+/// 方向盘最大转角（度）
+const MAX_STEERING_ANGLE: f64 = 30.0;
+
+/// 模拟器配置
+#[derive(Debug, Clone)]
+pub struct VehicleSimulatorConfig {
+    /// 车辆服务器地址（host:port）
+    pub server_addr: String,
+    /// 虚拟车辆数量
+    pub vehicle_count: u8,
+    /// 第一辆车的编号，其余依次递增
+    pub first_vehicle_id: u8,
+    /// 行驶速度（米/秒）
+    pub speed: f64,
+    /// 车辆信息发送间隔（毫秒）
+    pub info_interval_ms: u64,
+    /// 心跳间隔（毫秒）
+    pub heartbeat_interval_ms: u64,
+    /// 出租车/AVP 每个阶段的持续时间（毫秒）
+    pub stage_duration_ms: u64,
+    /// 断线重连间隔（毫秒）
+    pub reconnect_delay_ms: u64,
+    /// 相邻车辆的接入间隔（毫秒），便于服务器按首帧车辆编号完成连接登记
+    pub connect_stagger_ms: u64,
+    /// 按车辆编号的接入认证密钥；未配置的车辆不注册，按旧协议直接上报
+    pub auth_keys: HashMap<u8, String>,
+}
+
+impl Default for VehicleSimulatorConfig {
+    fn default() -> Self {
+        let config = AppConfig::global();
+        Self {
+            server_addr: format!("127.0.0.1:{}", config.ports.socket_server),
+            vehicle_count: 1,
+            first_vehicle_id: 1,
+            speed: 0.25,
+            info_interval_ms: 500,
+            heartbeat_interval_ms: config.network.heartbeat_interval as u64,
+            stage_duration_ms: 5000,
+            reconnect_delay_ms: config.network.retry_delay as u64,
+            connect_stagger_ms: 300,
+            auth_keys: HashMap::new(),
+        }
+    }
+}
+
+/// 模拟行驶路线（首尾相连的折线）
+#[derive(Debug, Clone)]
+pub struct SimRoute {
+    points: Vec<PathPoint>,
+    /// 每段起点的累计里程
+    cumulative: Vec<f64>,
+    total_length: f64,
+}
+
+impl SimRoute {
+    /// 由路径点构建路线，去除重复点；不足两个有效点时返回 None
+    pub fn from_points(points: Vec<PathPoint>) -> Option<Self> {
+        let mut deduped: Vec<PathPoint> = Vec::with_capacity(points.len());
+        for point in points {
+            if !point.x.is_finite() || !point.y.is_finite() {
+                continue;
+            }
+            match deduped.last() {
+                Some(last) if distance(last, &point) <= f64::EPSILON => {}
+                _ => deduped.push(point),
+            }
+        }
+        while deduped.len() > 1 && distance(&deduped[0], &deduped[deduped.len() - 1]) <= f64::EPSILON {
+            deduped.pop();
+        }
+        if deduped.len() < 2 {
+            return None;
+        }
+
+        let mut cumulative = Vec::with_capacity(deduped.len());
+        let mut total_length = 0.0;
+        for i in 0..deduped.len() {
+            cumulative.push(total_length);
+            total_length += distance(&deduped[i], &deduped[(i + 1) % deduped.len()]);
+        }
+
+        Some(Self {
+            points: deduped,
+            cumulative,
+            total_length,
+        })
+    }
+
+    /// 沙盘外圈矩形路线（与 Python 测试脚本一致）
+    pub fn sandbox_loop() -> Self {
+        const WIDTH: f64 = 4.81;
+        const DEPTH: f64 = 2.81;
+        const MARGIN: f64 = 0.23;
+        let corners = vec![
+            PathPoint { x: MARGIN, y: MARGIN },
+            PathPoint { x: MARGIN, y: DEPTH - MARGIN },
+            PathPoint { x: WIDTH - MARGIN, y: DEPTH - MARGIN },
+            PathPoint { x: WIDTH - MARGIN, y: MARGIN },
+        ];
+        Self::from_points(corners).expect("沙盘矩形路线至少包含两个点")
+    }
+
+    /// 从路径加载器合并指定路径；路径缺失时退回沙盘外圈路线
+    pub fn from_loader(loader: &PathLoader, route_ids: &[u8]) -> Self {
+        if route_ids.is_empty() {
+            return Self::sandbox_loop();
+        }
+        match loader.get_merged_paths(route_ids) {
+            Ok(points) => Self::from_points(points).unwrap_or_else(|| {
+                warn!("路径 {:?} 有效点不足，模拟车辆改用沙盘外圈路线", route_ids);
+                Self::sandbox_loop()
+            }),
+            Err(e) => {
+                warn!("加载模拟路径 {:?} 失败: {}，改用沙盘外圈路线", route_ids, e);
+                Self::sandbox_loop()
+            }
+        }
+    }
+
+    /// 路线总长度（米）
+    pub fn total_length(&self) -> f64 {
+        self.total_length
+    }
+
+    /// 将里程折算到 [0, 总长度) 区间
+    fn wrap(&self, distance: f64) -> f64 {
+        distance.rem_euclid(self.total_length)
+    }
+
+    fn segment_at(&self, distance: f64) -> usize {
+        let distance = self.wrap(distance);
+        self.cumulative
+            .partition_point(|&start| start <= distance)
+            .saturating_sub(1)
+    }
+
+    /// 指定里程处的坐标与朝向（弧度）
+    fn pose_at(&self, distance: f64) -> (f64, f64, f64) {
+        let distance = self.wrap(distance);
+        let index = self.segment_at(distance);
+        let start = &self.points[index];
+        let end = &self.points[(index + 1) % self.points.len()];
+        let length = self.distance_between(index);
+        let ratio = if length > 0.0 {
+            (distance - self.cumulative[index]) / length
+        } else {
+            0.0
+        };
+        let heading = (end.y - start.y).atan2(end.x - start.x);
+        (
+            start.x + (end.x - start.x) * ratio,
+            start.y + (end.y - start.y) * ratio,
+            heading,
+        )
+    }
+
+    fn distance_between(&self, index: usize) -> f64 {
+        let next = if index + 1 < self.cumulative.len() {
+            self.cumulative[index + 1]
+        } else {
+            self.total_length
+        };
+        next - self.cumulative[index]
+    }
+
+    /// 离给定坐标最近的路径点里程
+    fn nearest_distance(&self, x: f64, y: f64) -> f64 {
+        let target = PathPoint { x, y };
+        self.points
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a, &target).total_cmp(&distance(b, &target)))
+            .map(|(index, _)| self.cumulative[index])
+            .unwrap_or(0.0)
+    }
+}
+
+fn distance(a: &PathPoint, b: &PathPoint) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+/// 角度差归一化到 [-π, π]
+fn normalize_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// 模拟器可响应的下行指令
+#[derive(Debug, Clone)]
+pub enum SimCommand {
+    Control(VehicleControlCommand),
+    TaxiOrder(TaxiOrderData),
+    /// 取消已下发的出租车订单（车辆编号）
+    TaxiOrderCancel(u8),
+    AvpParking(AvpParkingData),
+    AvpPickup(AvpPickupData),
+}
+
+impl SimCommand {
+    /// 指令目标车辆编号
+    pub fn vehicle_id(&self) -> u8 {
+        match self {
+            SimCommand::Control(command) => command.vehicle_id,
+            SimCommand::TaxiOrder(order) => order.vehicle_id,
+            SimCommand::TaxiOrderCancel(vehicle_id) => *vehicle_id,
+            SimCommand::AvpParking(parking) => parking.vehicle_id,
+            SimCommand::AvpPickup(pickup) => pickup.vehicle_id,
+        }
+    }
+}
+
+/// 解析服务器下发的指令；模拟器不处理的消息类型返回 Ok(None)
+pub fn decode_command(message_type: u16, data: &[u8]) -> Result<Option<SimCommand>, String> {
+    let require = |size: usize| -> Result<(), String> {
+        if data.len() < size {
+            Err(format!(
+                "指令 0x{:04X} 数据长度不足: 需要 {} 字节, 实际 {} 字节",
+                message_type,
+                size,
+                data.len()
+            ))
+        } else {
+            Ok(())
+        }
+    };
+
+    match message_type {
+        SendMessageTypes::VEHICLE_CONTROL => {
+            require(ProtocolConstants::VEHICLE_CONTROL_BASE_SIZE)?;
+            let command = ControlCommandType::from_u8(data[ProtocolConstants::VEHICLE_CONTROL_COMMAND_OFFSET])
+                .map_err(|e| e.to_string())?;
+            let position_data = if matches!(command, ControlCommandType::InitPose) {
+                require(ProtocolConstants::VEHICLE_CONTROL_TOTAL_SIZE_WITH_POSITION)?;
+                Some(PositionData {
+                    x: byte_utils::read_f64_le(data, ProtocolConstants::VEHICLE_CONTROL_POSITION_X_OFFSET)?,
+                    y: byte_utils::read_f64_le(data, ProtocolConstants::VEHICLE_CONTROL_POSITION_Y_OFFSET)?,
+                    orientation: byte_utils::read_f64_le(data, ProtocolConstants::VEHICLE_CONTROL_ORIENTATION_OFFSET)?,
+                })
+            } else {
+                None
+            };
+            Ok(Some(SimCommand::Control(VehicleControlCommand {
+                vehicle_id: data[ProtocolConstants::VEHICLE_CONTROL_VEHICLE_ID_OFFSET],
+                command,
+                position_data,
+            })))
+        }
+        SendMessageTypes::TAXI_ORDER => {
+            // 广播订单带16字节订单号，需由调度端指派到具体车辆后再执行
+            if data.len() != ProtocolConstants::TAXI_ORDER_TOTAL_SIZE {
+                debug!("忽略非单车出租车订单 ({} 字节)", data.len());
+                return Ok(None);
+            }
+            Ok(Some(SimCommand::TaxiOrder(TaxiOrderData {
+                vehicle_id: data[ProtocolConstants::TAXI_ORDER_VEHICLE_ID_OFFSET],
+                start_x: byte_utils::read_f64_le(data, ProtocolConstants::TAXI_ORDER_START_X_OFFSET)?,
+                start_y: byte_utils::read_f64_le(data, ProtocolConstants::TAXI_ORDER_START_Y_OFFSET)?,
+                end_x: byte_utils::read_f64_le(data, ProtocolConstants::TAXI_ORDER_END_X_OFFSET)?,
+                end_y: byte_utils::read_f64_le(data, ProtocolConstants::TAXI_ORDER_END_Y_OFFSET)?,
+            })))
+        }
+        SendMessageTypes::TAXI_ORDER_CANCEL => {
+            require(1)?;
+            Ok(Some(SimCommand::TaxiOrderCancel(data[0])))
+        }
+        SendMessageTypes::AVP_PARKING => {
+            require(ProtocolConstants::AVP_PARKING_TOTAL_SIZE)?;
+            Ok(Some(SimCommand::AvpParking(AvpParkingData {
+                vehicle_id: data[ProtocolConstants::AVP_PARKING_VEHICLE_ID_OFFSET],
+                parking_spot: data[ProtocolConstants::AVP_PARKING_SPOT_OFFSET],
+            })))
+        }
+        SendMessageTypes::AVP_PICKUP => {
+            require(ProtocolConstants::AVP_PICKUP_TOTAL_SIZE)?;
+            Ok(Some(SimCommand::AvpPickup(AvpPickupData {
+                vehicle_id: data[ProtocolConstants::AVP_PICKUP_VEHICLE_ID_OFFSET],
+            })))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// 车辆运动状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionState {
+    Driving,
+    Stopped,
+    EmergencyBrake,
+}
+
+/// 脚本任务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MissionKind {
+    Taxi,
+    AvpParking,
+    AvpPickup,
+}
+
+/// 任务阶段：导航状态 + 是否沿路线行驶
+struct Stage {
+    nav_status: u8,
+    moving: bool,
+}
+
+/// 出租车：去起点 → 到达起点 → 去终点 → 到达终点
+const TAXI_STAGES: &[Stage] = &[
+    Stage { nav_status: 3, moving: true },
+    Stage { nav_status: 9, moving: false },
+    Stage { nav_status: 4, moving: true },
+    Stage { nav_status: 10, moving: false },
+];
+
+/// AVP泊车：去往停车位 → 倒车入库中，完成后进入车位停车
+const AVP_PARKING_STAGES: &[Stage] = &[
+    Stage { nav_status: 7, moving: true },
+    Stage { nav_status: 11, moving: false },
+];
+
+/// AVP取车：出库中 → 出库完成，完成后恢复正常行驶
+const AVP_PICKUP_STAGES: &[Stage] = &[
+    Stage { nav_status: 12, moving: true },
+    Stage { nav_status: 14, moving: false },
+];
+
+/// 正常行驶导航状态
+const NAV_CRUISE: u8 = 1;
+/// 车位停车中导航状态
+const NAV_PARKED: u8 = 8;
+/// 平行驾驶模式导航状态
+const NAV_PARALLEL_DRIVING: u8 = 15;
+
+impl MissionKind {
+    fn stages(self) -> &'static [Stage] {
+        match self {
+            MissionKind::Taxi => TAXI_STAGES,
+            MissionKind::AvpParking => AVP_PARKING_STAGES,
+            MissionKind::AvpPickup => AVP_PICKUP_STAGES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mission {
+    Cruise,
+    Scripted {
+        kind: MissionKind,
+        stage: usize,
+        elapsed_ms: u64,
+        parking_slot: u8,
+    },
+    Parked {
+        slot: u8,
+    },
+}
+
+/// 单辆虚拟车的运动与任务模型（不涉及网络，便于单独测试）
+#[derive(Debug, Clone)]
+pub struct SimVehicle {
+    vehicle_id: u8,
+    route: Arc<SimRoute>,
+    distance: f64,
+    cruise_speed: f64,
+    battery: f64,
+    motion: MotionState,
+    mission: Mission,
+    stage_duration_ms: u64,
+    parallel_driving: bool,
+}
+
+impl SimVehicle {
+    pub fn new(vehicle_id: u8, route: Arc<SimRoute>, start_distance: f64, cruise_speed: f64, stage_duration_ms: u64) -> Self {
+        Self {
+            vehicle_id,
+            distance: route.wrap(start_distance),
+            route,
+            cruise_speed,
+            battery: 85.0,
+            motion: MotionState::Driving,
+            mission: Mission::Cruise,
+            stage_duration_ms,
+            parallel_driving: false,
+        }
+    }
+
+    pub fn vehicle_id(&self) -> u8 {
+        self.vehicle_id
+    }
+
+    pub fn motion(&self) -> MotionState {
+        self.motion
+    }
+
+    /// 设置平行驾驶状态（由沙盘 0x2001 指令控制）
+    pub fn set_parallel_driving(&mut self, enabled: bool) {
+        if self.parallel_driving != enabled {
+            info!(
+                "🎮 模拟车辆 {} {}平行驾驶模式",
+                self.vehicle_id,
+                if enabled { "进入" } else { "退出" }
+            );
+        }
+        self.parallel_driving = enabled;
+    }
+
+    /// 当前阶段是否沿路线行驶
+    fn is_moving(&self) -> bool {
+        if self.motion != MotionState::Driving {
+            return false;
+        }
+        if self.parallel_driving {
+            return true;
+        }
+        match self.mission {
+            Mission::Cruise => true,
+            Mission::Scripted { kind, stage, .. } => kind.stages()[stage].moving,
+            Mission::Parked { .. } => false,
+        }
+    }
+
+    /// 推进模拟时间
+    pub fn tick(&mut self, elapsed_ms: u64) {
+        if self.motion != MotionState::Driving {
+            return;
+        }
+
+        if self.is_moving() {
+            let seconds = elapsed_ms as f64 / 1000.0;
+            self.distance = self.route.wrap(self.distance + self.cruise_speed * seconds);
+            self.battery = (self.battery - BATTERY_DRAIN_PER_SECOND * seconds).max(MIN_BATTERY);
+        }
+
+        // 平行驾驶期间由远程驾驶员接管，任务阶段暂停推进
+        if self.parallel_driving {
+            return;
+        }
+
+        if let Mission::Scripted { kind, stage, elapsed_ms: stage_elapsed, parking_slot } = self.mission {
+            let stage_elapsed = stage_elapsed + elapsed_ms;
+            if stage_elapsed < self.stage_duration_ms {
+                self.mission = Mission::Scripted { kind, stage, elapsed_ms: stage_elapsed, parking_slot };
+            } else if stage + 1 < kind.stages().len() {
+                self.mission = Mission::Scripted { kind, stage: stage + 1, elapsed_ms: 0, parking_slot };
+                info!("🚗 模拟车辆 {} 导航状态切换为 {}", self.vehicle_id, self.nav_status());
+            } else {
+                self.mission = match kind {
+                    MissionKind::AvpParking => Mission::Parked { slot: parking_slot },
+                    MissionKind::Taxi | MissionKind::AvpPickup => Mission::Cruise,
+                };
+                info!("🚗 模拟车辆 {} 任务完成，导航状态 {}", self.vehicle_id, self.nav_status());
+            }
+        }
+    }
+
+    /// 执行下行指令，返回指令是否被本车接受
+    pub fn apply(&mut self, command: &SimCommand) -> bool {
+        if command.vehicle_id() != self.vehicle_id {
+            return false;
+        }
+
+        match command {
+            SimCommand::Control(control) => {
+                match control.command {
+                    ControlCommandType::Start => self.motion = MotionState::Driving,
+                    ControlCommandType::Stop => self.motion = MotionState::Stopped,
+                    ControlCommandType::EmergencyBrake => self.motion = MotionState::EmergencyBrake,
+                    ControlCommandType::InitPose => {
+                        if let Some(position) = &control.position_data {
+                            self.distance = self.route.nearest_distance(position.x, position.y);
+                        }
+                    }
+                }
+                info!("🚗 模拟车辆 {} 执行{}指令", self.vehicle_id, control.command.name());
+                true
+            }
+            SimCommand::TaxiOrder(order) => {
+                if !matches!(self.mission, Mission::Cruise | Mission::Scripted { kind: MissionKind::Taxi, .. }) {
+                    warn!("模拟车辆 {} 正在执行泊车任务，忽略出租车订单", self.vehicle_id);
+                    return false;
+                }
+                self.start_mission(MissionKind::Taxi, 0);
+                info!(
+                    "🚕 模拟车辆 {} 接单: ({:.3}, {:.3}) -> ({:.3}, {:.3})",
+                    self.vehicle_id, order.start_x, order.start_y, order.end_x, order.end_y
+                );
+                true
+            }
+            SimCommand::TaxiOrderCancel(_) => {
+                if !matches!(self.mission, Mission::Scripted { kind: MissionKind::Taxi, .. }) {
+                    warn!("模拟车辆 {} 没有进行中的出租车订单，忽略订单取消", self.vehicle_id);
+                    return false;
+                }
+                self.mission = Mission::Cruise;
+                info!("🚕 模拟车辆 {} 订单已取消，恢复正常行驶", self.vehicle_id);
+                true
+            }
+            SimCommand::AvpParking(parking) => {
+                if self.mission != Mission::Cruise {
+                    warn!("模拟车辆 {} 当前有任务，忽略AVP泊车指令", self.vehicle_id);
+                    return false;
+                }
+                self.start_mission(MissionKind::AvpParking, parking.parking_spot);
+                info!("🅿️ 模拟车辆 {} 开始泊车，目标车位 {}", self.vehicle_id, parking.parking_spot);
+                true
+            }
+            SimCommand::AvpPickup(_) => {
+                let Mission::Parked { slot } = self.mission else {
+                    warn!("模拟车辆 {} 未在车位停车，忽略AVP取车指令", self.vehicle_id);
+                    return false;
+                };
+                self.start_mission(MissionKind::AvpPickup, slot);
+                info!("🅿️ 模拟车辆 {} 开始出库，车位 {}", self.vehicle_id, slot);
+                true
+            }
+        }
+    }
+
+    fn start_mission(&mut self, kind: MissionKind, parking_slot: u8) {
+        self.mission = Mission::Scripted {
+            kind,
+            stage: 0,
+            elapsed_ms: 0,
+            parking_slot,
+        };
+    }
+
+    /// 当前导航状态
+    pub fn nav_status(&self) -> u8 {
+        if self.parallel_driving {
+            return NAV_PARALLEL_DRIVING;
+        }
+        match self.mission {
+            Mission::Cruise => NAV_CRUISE,
+            Mission::Scripted { kind, stage, .. } => kind.stages()[stage].nav_status,
+            Mission::Parked { .. } => NAV_PARKED,
+        }
+    }
+
+    /// 当前车辆信息（用于构建 0x0002 数据域）
+    pub fn snapshot(&self) -> VehicleInfo {
+        let (position_x, position_y, orientation) = self.route.pose_at(self.distance);
+        let moving = self.is_moving();
+
+        let steering_angle = if moving {
+            let (_, _, ahead) = self.route.pose_at(self.distance + STEERING_LOOKAHEAD);
+            normalize_angle(ahead - orientation)
+                .to_degrees()
+                .clamp(-MAX_STEERING_ANGLE, MAX_STEERING_ANGLE)
+        } else {
+            0.0
+        };
+
+        let nav_status = self.nav_status();
+        let gear = match self.motion {
+            MotionState::Stopped => GearPosition::Park,
+            MotionState::EmergencyBrake => GearPosition::Neutral,
+            MotionState::Driving if moving => GearPosition::DriveLevel(1),
+            MotionState::Driving if nav_status == 11 => GearPosition::Reverse,
+            MotionState::Driving => GearPosition::Park,
+        };
+
+        let parking_slot = match self.mission {
+            Mission::Parked { slot } => slot,
+            Mission::Scripted { kind: MissionKind::AvpPickup, stage: 0, parking_slot, .. } => parking_slot,
+            _ => 0,
+        };
+
+        VehicleInfo {
+            vehicle_id: self.vehicle_id,
+            speed: if moving { self.cruise_speed } else { 0.0 },
+            position_x,
+            position_y,
+            orientation,
+            battery: self.battery,
+            gear,
+            steering_angle,
+            nav_status,
+            sensors: SensorStatus {
+                camera: true,
+                lidar: true,
+                gyro: true,
+            },
+            parking_slot,
+        }
+    }
+}
+
+/// 单辆虚拟车状态（供界面显示）
+#[derive(Debug, Clone, Serialize)]
+pub struct SimVehicleStatus {
+    pub vehicle_id: u8,
+    pub connected: bool,
+    pub motion: MotionState,
+    pub nav_status: u8,
+    pub position_x: f64,
+    pub position_y: f64,
+    pub battery: f64,
+    pub frames_sent: u64,
+    pub commands_received: u64,
+}
+
+/// 模拟器状态
+#[derive(Debug, Clone, Serialize)]
+pub struct VehicleSimulatorStatus {
+    pub running: bool,
+    pub server_addr: String,
+    pub vehicles: Vec<SimVehicleStatus>,
+}
+
+type SharedStatus = Arc<RwLock<HashMap<u8, SimVehicleStatus>>>;
+
+/// 运行中的车辆模拟器
+pub struct VehicleSimulator {
+    config: VehicleSimulatorConfig,
+    shutdown: watch::Sender<bool>,
+    status: SharedStatus,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl VehicleSimulator {
+    /// 启动模拟器，车辆沿路线均匀分布；车辆编号超出范围时返回错误
+    pub fn start(config: VehicleSimulatorConfig, route: SimRoute, parallel_driving: ParallelDriving) -> Result<Self, String> {
+        let count = config.vehicle_count.max(1);
+        let vehicle_ids = super::vehicle_ids(config.first_vehicle_id, count)?;
+        let (shutdown, _) = watch::channel(false);
+        let status: SharedStatus = Arc::new(RwLock::new(HashMap::new()));
+        let route = Arc::new(route);
+        let spacing = route.total_length() / count as f64;
+
+        info!(
+            "🚗 启动车辆模拟器: {} 辆车 -> {}，路线长度 {:.2} 米",
+            count,
+            config.server_addr,
+            route.total_length()
+        );
+
+        let mut tasks = Vec::with_capacity(count as usize);
+        for (index, vehicle_id) in vehicle_ids.into_iter().enumerate() {
+            let vehicle = SimVehicle::new(
+                vehicle_id,
+                route.clone(),
+                spacing * index as f64,
+                config.speed,
+                config.stage_duration_ms,
+            );
+            status.write().insert(vehicle_id, build_status(&vehicle, false, 0, 0));
+
+            let start_delay = Duration::from_millis(config.connect_stagger_ms * index as u64);
+            tasks.push(tokio::spawn(run_vehicle(
+                vehicle,
+                config.clone(),
+                status.clone(),
+                parallel_driving.clone(),
+                shutdown.subscribe(),
+                start_delay,
+            )));
+        }
+
+        Ok(Self {
+            config,
+            shutdown,
+            status,
+            tasks,
+        })
+    }
+
+    /// 通知所有虚拟车断开并退出
+    pub fn stop(&self) {
+        let _ = self.shutdown.send(true);
+        info!("🛑 车辆模拟器已停止");
+    }
+
+    /// 等待所有虚拟车任务退出
+    pub async fn join(self) {
+        for task in self.tasks {
+            let _ = task.await;
+        }
+    }
+
+    pub fn status(&self) -> VehicleSimulatorStatus {
+        let mut vehicles: Vec<SimVehicleStatus> = self.status.read().values().cloned().collect();
+        vehicles.sort_by_key(|vehicle| vehicle.vehicle_id);
+        VehicleSimulatorStatus {
+            running: !*self.shutdown.borrow() && self.tasks.iter().any(|task| !task.is_finished()),
+            server_addr: self.config.server_addr.clone(),
+            vehicles,
+        }
+    }
+}
+
+fn build_status(vehicle: &SimVehicle, connected: bool, frames_sent: u64, commands_received: u64) -> SimVehicleStatus {
+    let info = vehicle.snapshot();
+    SimVehicleStatus {
+        vehicle_id: vehicle.vehicle_id(),
+        connected,
+        motion: vehicle.motion(),
+        nav_status: info.nav_status,
+        position_x: info.position_x,
+        position_y: info.position_y,
+        battery: info.battery,
+        frames_sent,
+        commands_received,
+    }
+}
+
+/// 每辆车的连接循环：断线后按间隔重连，直到收到停止信号
+async fn run_vehicle(
+    mut vehicle: SimVehicle,
+    config: VehicleSimulatorConfig,
+    status: SharedStatus,
+    parallel_driving: ParallelDriving,
+    mut shutdown: watch::Receiver<bool>,
+    start_delay: Duration,
+) {
+    tokio::select! {
+        _ = tokio::time::sleep(start_delay) => {}
+        _ = shutdown.changed() => return,
+    }
+
+    let mut counters = (0u64, 0u64);
+    loop {
+        if *shutdown.borrow() {
+            break;
+        }
+
+        match TcpStream::connect(&config.server_addr).await {
+            Ok(stream) => {
+                info!("🚗 模拟车辆 {} 已连接 {}", vehicle.vehicle_id(), config.server_addr);
+                let result = drive_connection(&mut vehicle, stream, &config, &status, &parallel_driving, &mut shutdown, &mut counters).await;
+                status.write().insert(vehicle.vehicle_id(), build_status(&vehicle, false, counters.0, counters.1));
+                match result {
+                    Ok(()) => break,
+                    Err(e) => warn!("模拟车辆 {} 连接中断: {}", vehicle.vehicle_id(), e),
+                }
+            }
+            Err(e) => warn!("模拟车辆 {} 连接 {} 失败: {}", vehicle.vehicle_id(), config.server_addr, e),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(config.reconnect_delay_ms.max(100))) => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    debug!("模拟车辆 {} 任务退出", vehicle.vehicle_id());
+}
+
+/// 单次连接的收发循环；收到停止信号返回 Ok，连接异常返回 Err
+async fn drive_connection(
+    vehicle: &mut SimVehicle,
+    mut stream: TcpStream,
+    config: &VehicleSimulatorConfig,
+    status: &SharedStatus,
+    parallel_driving: &ParallelDriving,
+    shutdown: &mut watch::Receiver<bool>,
+    counters: &mut (u64, u64),
+) -> Result<(), String> {
+    let mut builder = ProtocolBuilder::new();
+    let mut parser = ProtocolParser::new();
+    let mut buffer = [0u8; 4096];
+
+    let registered = config.auth_keys.contains_key(&vehicle.vehicle_id());
+    if let Some(auth_key) = config.auth_keys.get(&vehicle.vehicle_id()) {
+        tokio::select! {
+            _ = shutdown.changed() => return Ok(()),
+            result = register(&mut stream, &mut parser, vehicle.vehicle_id(), auth_key) => result?,
+        }
+    }
+
+    // 接入后立即上报一帧车辆信息，服务器据此登记车辆编号
+    let frame = build_message(MessageTypes::VEHICLE_INFO, &builder.build_vehicle_info(&vehicle.snapshot()));
+    stream.write_all(&frame).await.map_err(|e| e.to_string())?;
+    counters.0 += 1;
+    status.write().insert(vehicle.vehicle_id(), build_status(vehicle, true, counters.0, counters.1));
+
+    let mut info_timer = tokio::time::interval(Duration::from_millis(config.info_interval_ms.max(10)));
+    let mut heartbeat_timer = tokio::time::interval(Duration::from_millis(config.heartbeat_interval_ms.max(100)));
+    info_timer.tick().await;
+    let mut last_tick = Instant::now();
+    let mut last_ack: Option<(u64, u16, AckCode)> = None;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => return Ok(()),
+
+            _ = info_timer.tick() => {
+                let now = Instant::now();
+                let parallel = parallel_driving.read().contains(&vehicle.vehicle_id());
+                vehicle.set_parallel_driving(parallel);
+                vehicle.tick(now.duration_since(last_tick).as_millis() as u64);
+                last_tick = now;
+
+                let frame = build_message(MessageTypes::VEHICLE_INFO, &builder.build_vehicle_info(&vehicle.snapshot()));
+                stream.write_all(&frame).await.map_err(|e| e.to_string())?;
+                counters.0 += 1;
+                status.write().insert(vehicle.vehicle_id(), build_status(vehicle, true, counters.0, counters.1));
+            }
+
+            _ = heartbeat_timer.tick() => {
+                stream
+                    .write_all(&build_message(MessageTypes::HEARTBEAT, &[]))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+
+            result = stream.read(&mut buffer) => {
+                let n = result.map_err(|e| e.to_string())?;
+                if n == 0 {
+                    return Err("服务器关闭连接".to_string());
+                }
+                parser.feed_data(&buffer[..n]);
+                loop {
+                    match parser.try_parse_message() {
+                        Ok(Some(message)) => {
+                            // 认证后服务器重发的指令序号不变，只回复确认不重复执行
+                            let code = match last_ack {
+                                Some((sequence, message_type, code))
+                                    if registered && (sequence, message_type) == (message.timestamp, message.message_type) =>
+                                {
+                                    code
+                                }
+                                _ => match decode_command(message.message_type, &message.data) {
+                                    Ok(Some(command)) => {
+                                        if vehicle.apply(&command) {
+                                            counters.1 += 1;
+                                            AckCode::Accepted
+                                        } else {
+                                            AckCode::Rejected
+                                        }
+                                    }
+                                    Ok(None) => {
+                                        debug!(
+                                            "模拟车辆 {} 忽略消息类型 0x{:04X}",
+                                            vehicle.vehicle_id(),
+                                            message.message_type
+                                        );
+                                        AckCode::Unsupported
+                                    }
+                                    Err(e) => {
+                                        warn!("模拟车辆 {} 指令解析失败: {}", vehicle.vehicle_id(), e);
+                                        AckCode::Invalid
+                                    }
+                                },
+                            };
+                            if ack::requires_ack(message.message_type) {
+                                last_ack = Some((message.timestamp, message.message_type, code));
+                                let reply = CommandAck {
+                                    sequence: message.timestamp,
+                                    message_type: message.message_type,
+                                    code: code as u8,
+                                };
+                                stream
+                                    .write_all(&build_message(MessageTypes::COMMAND_ACK, &reply.to_payload()))
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            // 已跳过损坏的帧头，继续解析缓冲区中的后续帧
+                            warn!("模拟车辆 {} 协议解析失败: {}", vehicle.vehicle_id(), e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 回应服务器的认证挑战并等待认证结果
+async fn register(stream: &mut TcpStream, parser: &mut ProtocolParser, vehicle_id: u8, auth_key: &str) -> Result<(), String> {
+    let mut buffer = [0u8; 1024];
+    loop {
+        while let Some(message) = parser.next_message() {
+            match message.message_type {
+                SendMessageTypes::AUTH_CHALLENGE => {
+                    let payload = auth::build_register_payload(vehicle_id, auth_key, &message.data);
+                    stream
+                        .write_all(&build_message(MessageTypes::VEHICLE_REGISTER, &payload))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                SendMessageTypes::AUTH_RESULT => {
+                    let code = message.data.first().copied().unwrap_or(u8::MAX);
+                    return match AuthStatus::from_code(code) {
+                        Some(AuthStatus::Accepted) => {
+                            info!("🔐 模拟车辆 {} 认证通过", vehicle_id);
+                            Ok(())
+                        }
+                        Some(status) => Err(format!("认证被拒绝: {}", status.description())),
+                        None => Err(format!("未知认证结果: {}", code)),
+                    };
+                }
+                other => debug!("模拟车辆 {} 注册前忽略消息类型 0x{:04X}", vehicle_id, other),
+            }
+        }
+
+        let n = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("服务器关闭连接".to_string());
+        }
+        parser.feed_data(&buffer[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VehicleAuthConfig;
+    use crate::database::{CreateVehicleConnectionRequest, VehicleDatabase};
+    use crate::socket::{BroadcastEventSink, ConnectionManager, SocketServer};
+    use tempfile::TempDir;
+
+    fn square_route() -> Arc<SimRoute> {
+        let points = vec![
+            PathPoint { x: 0.0, y: 0.0 },
+            PathPoint { x: 1.0, y: 0.0 },
+            PathPoint { x: 1.0, y: 1.0 },
+            PathPoint { x: 0.0, y: 1.0 },
+        ];
+        Arc::new(SimRoute::from_points(points).unwrap())
+    }
+
+    #[test]
+    fn test_route_wraps_and_interpolates() {
+        let route = square_route();
+        assert!((route.total_length() - 4.0).abs() < 1e-9);
+
+        let (x, y, heading) = route.pose_at(1.5);
+        assert!((x - 1.0).abs() < 1e-9 && (y - 0.5).abs() < 1e-9);
+        assert!((heading - PI / 2.0).abs() < 1e-9);
+
+        let (x, y, _) = route.pose_at(4.25);
+        assert!((x - 0.25).abs() < 1e-9 && y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_taxi_order_walks_nav_stages() {
+        let mut vehicle = SimVehicle::new(2, square_route(), 0.0, 0.5, 1000);
+        let order = SimCommand::TaxiOrder(TaxiOrderData {
+            vehicle_id: 2,
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 1.0,
+            end_y: 1.0,
+        });
+
+        assert!(vehicle.apply(&order));
+        let mut observed = vec![vehicle.nav_status()];
+        for _ in 0..4 {
+            vehicle.tick(1000);
+            observed.push(vehicle.nav_status());
+        }
+        assert_eq!(observed, vec![3, 9, 4, 10, 1]);
+
+        // 其他车辆的订单不受理
+        let other = SimCommand::TaxiOrder(TaxiOrderData { vehicle_id: 9, start_x: 0.0, start_y: 0.0, end_x: 0.0, end_y: 0.0 });
+        assert!(!vehicle.apply(&other));
+
+        // 订单被取消后恢复正常行驶；没有订单时忽略取消
+        assert!(vehicle.apply(&order));
+        vehicle.tick(1000);
+        let cancel = decode_command(SendMessageTypes::TAXI_ORDER_CANCEL, &[2]).unwrap().unwrap();
+        assert!(vehicle.apply(&cancel));
+        assert_eq!(vehicle.nav_status(), 1);
+        assert!(!vehicle.apply(&cancel));
+    }
+
+    #[test]
+    fn test_emergency_brake_freezes_until_start() {
+        let mut vehicle = SimVehicle::new(1, square_route(), 0.0, 0.5, 1000);
+        let brake = decode_command(SendMessageTypes::VEHICLE_CONTROL, &[1, 3]).unwrap().unwrap();
+        assert!(vehicle.apply(&brake));
+
+        let before = vehicle.snapshot();
+        vehicle.tick(2000);
+        let after = vehicle.snapshot();
+        assert_eq!(after.speed, 0.0);
+        assert_eq!(before.position_x, after.position_x);
+        assert_eq!(vehicle.motion(), MotionState::EmergencyBrake);
+
+        let start = decode_command(SendMessageTypes::VEHICLE_CONTROL, &[1, 1]).unwrap().unwrap();
+        vehicle.apply(&start);
+        vehicle.tick(1000);
+        assert!((vehicle.snapshot().position_x - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_avp_parking_then_pickup() {
+        let mut vehicle = SimVehicle::new(4, square_route(), 0.0, 0.5, 1000);
+        let pickup = SimCommand::AvpPickup(AvpPickupData { vehicle_id: 4 });
+        assert!(!vehicle.apply(&pickup));
+
+        let payload = ProtocolBuilder::new().build_avp_parking(&AvpParkingData { vehicle_id: 4, parking_spot: 6 });
+        let parking = decode_command(SendMessageTypes::AVP_PARKING, &payload).unwrap().unwrap();
+        assert!(vehicle.apply(&parking));
+        assert_eq!(vehicle.nav_status(), 7);
+        vehicle.tick(1000);
+        assert_eq!(vehicle.snapshot().gear, GearPosition::Reverse);
+        vehicle.tick(1000);
+        let parked = vehicle.snapshot();
+        assert_eq!((parked.nav_status, parked.parking_slot, parked.speed), (8, 6, 0.0));
+
+        assert!(vehicle.apply(&pickup));
+        assert_eq!(vehicle.nav_status(), 12);
+        vehicle.tick(1000);
+        vehicle.tick(1000);
+        let done = vehicle.snapshot();
+        assert_eq!((done.nav_status, done.parking_slot), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_simulated_fleet_against_socket_server() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, None));
+        let mut events = sink.subscribe();
+        let connections: ConnectionManager = Arc::new(RwLock::new(HashMap::new()));
+        // 兼容模式：未注册的车辆按首帧车辆编号登记
+        let server = SocketServer::new_with_connections(
+            port,
+            sink.clone(),
+            connections.clone(),
+            Arc::new(RwLock::new(HashMap::new())),
+        )
+        .with_vehicle_auth(VehicleAuthConfig {
+            required: false,
+            handshake_timeout: 200,
+        });
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let config = VehicleSimulatorConfig {
+            server_addr: format!("127.0.0.1:{}", port),
+            vehicle_count: 2,
+            first_vehicle_id: 1,
+            info_interval_ms: 50,
+            connect_stagger_ms: 100,
+            ..VehicleSimulatorConfig::default()
+        };
+        let simulator = VehicleSimulator::start(config, SimRoute::sandbox_loop(), ParallelDriving::default()).unwrap();
+
+        // 等待两辆车都按协议内编号完成登记
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            let registered = {
+                let conns = connections.read();
+                conns.contains_key(&1) && conns.contains_key(&2)
+            };
+            if registered {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(connections.read().contains_key(&2));
+
+        // 下发紧急制动，服务器随后收到车速为0的车辆信息
+        let brake = ProtocolBuilder::new().build_vehicle_control(&VehicleControlCommand {
+            vehicle_id: 2,
+            command: ControlCommandType::EmergencyBrake,
+            position_data: None,
+        });
+        SocketServer::send_to_vehicle(&connections, 2, SendMessageTypes::VEHICLE_CONTROL, &brake).unwrap();
+
+        let braked = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                if event.event != "fleet-update" {
+                    continue;
+                }
+                let vehicles = event.payload["vehicles"].as_array().cloned().unwrap_or_default();
+                if vehicles
+                    .iter()
+                    .any(|v| v["vehicle_id"] == 2 && v["parsed"]["speed"].as_f64() == Some(0.0))
+                {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(braked.is_ok());
+
+        let status = simulator.status();
+        assert_eq!(status.vehicles.len(), 2);
+        assert_eq!(status.vehicles[1].motion, MotionState::EmergencyBrake);
+        simulator.stop();
+        simulator.join().await;
+    }
+
+    #[tokio::test]
+    async fn test_vehicle_auth_handshake() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+        for (vehicle_id, auth_key) in [(1, Some("vehicle-key-1")), (2, None)] {
+            db.create_vehicle_connection(CreateVehicleConnectionRequest {
+                vehicle_id,
+                ip_address: "10.0.0.1".to_string(),
+                name: format!("{}号车", vehicle_id),
+                description: None,
+                color: None,
+                auth_key: auth_key.map(str::to_string),
+                cert_fingerprint: None,
+            })
+            .await
+            .unwrap();
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, Some(db)));
+        let mut events = sink.subscribe();
+        let connections: ConnectionManager = Arc::new(RwLock::new(HashMap::new()));
+        let server = SocketServer::new_with_connections(
+            port,
+            sink.clone(),
+            connections.clone(),
+            Arc::new(RwLock::new(HashMap::new())),
+        )
+        .with_vehicle_auth(VehicleAuthConfig {
+            required: true,
+            handshake_timeout: 500,
+        });
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // 1号车密钥正确；2号车未配置密钥；3号车未登记且不注册
+        let config = VehicleSimulatorConfig {
+            server_addr: format!("127.0.0.1:{}", port),
+            vehicle_count: 3,
+            first_vehicle_id: 1,
+            info_interval_ms: 50,
+            connect_stagger_ms: 0,
+            reconnect_delay_ms: 60_000,
+            auth_keys: HashMap::from([(1, "vehicle-key-1".to_string()), (2, "vehicle-key-2".to_string())]),
+            ..VehicleSimulatorConfig::default()
+        };
+        let simulator = VehicleSimulator::start(config, SimRoute::sandbox_loop(), ParallelDriving::default()).unwrap();
+
+        let mut rejected = Vec::new();
+        let collected = tokio::time::timeout(Duration::from_secs(5), async {
+            while rejected.len() < 2 {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                if event.event == "vehicle-auth-rejected" {
+                    rejected.push(event.payload["vehicle_id"].as_u64().unwrap());
+                }
+            }
+        })
+        .await;
+        assert!(collected.is_ok());
+        rejected.sort_unstable();
+        assert_eq!(rejected, vec![0, 2]);
+
+        // 认证车辆以数据库中的编号与名称登记，且不受来源 IP 影响
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !connections.read().contains_key(&1) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        {
+            let conns = connections.read();
+            assert_eq!(conns.len(), 1);
+            let conn = &conns[&1];
+            assert!(conn.authenticated);
+            assert_eq!(conn.vehicle_name, "1号车");
+        }
+
+        // 认证车辆的控制指令需车端确认，最终投递结果通过事件上报
+        let brake = ProtocolBuilder::new().build_vehicle_control(&VehicleControlCommand {
+            vehicle_id: 1,
+            command: ControlCommandType::EmergencyBrake,
+            position_data: None,
+        });
+        SocketServer::send_to_vehicle(&connections, 1, SendMessageTypes::VEHICLE_CONTROL, &brake).unwrap();
+        let delivery = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(event) = events.recv().await {
+                    if event.event == "command-delivery" {
+                        return event.payload;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(delivery["status"], "acked");
+        assert_eq!(delivery["vehicle_id"], 1);
+        assert_eq!(delivery["message_type"], SendMessageTypes::VEHICLE_CONTROL);
+
+        simulator.stop();
+        simulator.join().await;
+    }
+}
@@ -0,0 +1,426 @@
+//! 指令确认与重发
+//!
+//! 已认证车辆的下行指令（0x1001-0x1009）以帧时间戳作为序号：同一连接内单调递增，
+//! 重发时保持不变，车端可据此去重。车辆收到指令后回复 0x000A：
+//! `[序号 u64][原消息类型 u16][结果码 u8]`，结果码 0 为 ACK，其余为 NACK。
+//! 超过 `NetworkConfig::retry_delay` 未确认则重发，最多 `retry_count` 次，
+//! 最终结果通过 `command-delivery` 事件通知前端。
+//! 兼容模式（未认证）的旧车端不回复确认，指令只发送一次，并以 `unconfirmed` 状态通知前端。
+
+use super::event_sink::SharedEventSink;
+use super::send_queue::{SendError, SendLane, SendQueue};
+use crate::config::NetworkConfig;
+use crate::protocol_processing::types::SendMessageTypes;
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// 指令确认数据长度
+pub const ACK_PAYLOAD_LEN: usize = 11;
+
+/// 指令确认结果码（0x000A 第 11 字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    Accepted = 0,
+    /// 车辆当前状态不允许执行
+    Rejected = 1,
+    /// 指令数据无效
+    Invalid = 2,
+    /// 车辆不支持该指令
+    Unsupported = 3,
+}
+
+impl AckCode {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Accepted),
+            1 => Some(Self::Rejected),
+            2 => Some(Self::Invalid),
+            3 => Some(Self::Unsupported),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Accepted => "已执行",
+            Self::Rejected => "车辆拒绝执行",
+            Self::Invalid => "指令数据无效",
+            Self::Unsupported => "车辆不支持该指令",
+        }
+    }
+}
+
+/// 车辆回复的指令确认
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandAck {
+    pub sequence: u64,
+    pub message_type: u16,
+    pub code: u8,
+}
+
+impl CommandAck {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() != ACK_PAYLOAD_LEN {
+            return None;
+        }
+        Some(Self {
+            sequence: u64::from_le_bytes(data[0..8].try_into().ok()?),
+            message_type: u16::from_le_bytes([data[8], data[9]]),
+            code: data[10],
+        })
+    }
+
+    /// 构建确认帧数据域（车端/模拟器使用）
+    pub fn to_payload(self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(ACK_PAYLOAD_LEN);
+        data.extend_from_slice(&self.sequence.to_le_bytes());
+        data.extend_from_slice(&self.message_type.to_le_bytes());
+        data.push(self.code);
+        data
+    }
+
+    pub fn accepted(&self) -> bool {
+        self.code == AckCode::Accepted as u8
+    }
+
+    pub fn description(&self) -> &'static str {
+        AckCode::from_code(self.code).map_or("未知结果码", |code| code.description())
+    }
+}
+
+/// 是否为需要车辆确认的指令
+pub fn requires_ack(message_type: u16) -> bool {
+    (SendMessageTypes::VEHICLE_CONTROL..=SendMessageTypes::VEHICLE_CAMERA_TOGGLE).contains(&message_type)
+}
+
+/// 重发参数
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 首次发送后的最大重发次数
+    pub retries: u32,
+    /// 每次发送后等待确认的时长
+    pub ack_timeout: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &NetworkConfig) -> Self {
+        Self {
+            retries: config.retry_count,
+            ack_timeout: Duration::from_millis(config.retry_delay.max(10) as u64),
+        }
+    }
+}
+
+/// 最终投递结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    Acked,
+    Nacked(u8),
+    TimedOut,
+    Disconnected,
+    /// 车端不支持确认，已发送但无法确认是否执行
+    Unconfirmed,
+}
+
+impl Delivery {
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Acked => "acked",
+            Self::Nacked(_) => "nacked",
+            Self::TimedOut => "timeout",
+            Self::Disconnected => "disconnected",
+            Self::Unconfirmed => "unconfirmed",
+        }
+    }
+}
+
+struct PendingCommand {
+    message_type: u16,
+    ack: oneshot::Sender<CommandAck>,
+}
+
+struct TrackerInner {
+    queue: SendQueue,
+    sink: SharedEventSink,
+    policy: RetryPolicy,
+    /// 车端是否回复确认；为 false 时不等待确认也不重发
+    confirmed: bool,
+    last_sequence: AtomicU64,
+    pending: Mutex<HashMap<u64, PendingCommand>>,
+}
+
+/// 单个车辆连接的待确认指令表
+#[derive(Clone)]
+pub struct CommandTracker {
+    inner: Arc<TrackerInner>,
+}
+
+impl fmt::Debug for CommandTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandTracker").field("pending", &self.pending_count()).finish()
+    }
+}
+
+impl CommandTracker {
+    pub fn new(queue: SendQueue, sink: SharedEventSink, policy: RetryPolicy) -> Self {
+        Self::with_confirmation(queue, sink, policy, true)
+    }
+
+    /// 兼容模式车辆：指令只发送一次，投递结果为 unconfirmed
+    pub fn unconfirmed(queue: SendQueue, sink: SharedEventSink) -> Self {
+        Self::with_confirmation(queue, sink, RetryPolicy { retries: 0, ack_timeout: Duration::ZERO }, false)
+    }
+
+    fn with_confirmation(queue: SendQueue, sink: SharedEventSink, policy: RetryPolicy, confirmed: bool) -> Self {
+        Self {
+            inner: Arc::new(TrackerInner {
+                queue,
+                sink,
+                policy,
+                confirmed,
+                last_sequence: AtomicU64::new(0),
+                pending: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 分配序号：取当前毫秒时间戳，同一毫秒内的指令顺延
+    fn next_sequence(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let previous = self
+            .inner
+            .last_sequence
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| Some(now.max(last + 1)))
+            .unwrap_or_default();
+        now.max(previous + 1)
+    }
+
+    /// 发送指令并在后台等待确认；返回序号与发送通道
+    pub fn send(
+        &self,
+        vehicle_id: i32,
+        vehicle_name: &str,
+        message_type: u16,
+        data: &[u8],
+    ) -> Result<(u64, SendLane), SendError> {
+        let sequence = self.next_sequence();
+        let command = TrackedCommand {
+            vehicle_id,
+            vehicle_name: vehicle_name.to_string(),
+            message_type,
+            sequence,
+            data: data.to_vec(),
+        };
+        if !self.inner.confirmed {
+            let lane = self.inner.queue.send_at(message_type, data, sequence)?;
+            debug!("指令 0x{:04X} 已发送到车辆 {} (ID: {})，车端不回复确认", message_type, vehicle_name, vehicle_id);
+            self.emit_delivery(&command, Delivery::Unconfirmed, 1, Duration::ZERO);
+            return Ok((sequence, lane));
+        }
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.inner.pending.lock().insert(sequence, PendingCommand { message_type, ack: ack_tx });
+
+        let lane = match self.inner.queue.send_at(message_type, data, sequence) {
+            Ok(lane) => lane,
+            Err(e) => {
+                self.inner.pending.lock().remove(&sequence);
+                return Err(e);
+            }
+        };
+        debug!("指令 0x{:04X} 已发送到车辆 {} (ID: {})，序号 {}", message_type, vehicle_name, vehicle_id, sequence);
+
+        let tracker = self.clone();
+        tokio::spawn(async move { tracker.track(command, ack_rx).await });
+        Ok((sequence, lane))
+    }
+
+    /// 处理车辆回复的确认；返回是否匹配到待确认指令
+    pub fn acknowledge(&self, ack: CommandAck) -> bool {
+        let mut pending = self.inner.pending.lock();
+        match pending.get(&ack.sequence) {
+            Some(command) if command.message_type == ack.message_type => {}
+            Some(command) => {
+                warn!(
+                    "指令确认类型不符: 序号 {} 待确认 0x{:04X}，收到 0x{:04X}",
+                    ack.sequence, command.message_type, ack.message_type
+                );
+                return false;
+            }
+            // 重发后迟到的重复确认
+            None => return false,
+        }
+        let command = pending.remove(&ack.sequence).expect("已确认存在");
+        let _ = command.ack.send(ack);
+        true
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.inner.pending.lock().len()
+    }
+
+    /// 连接关闭：所有待确认指令以 disconnected 结束
+    pub fn close(&self) {
+        self.inner.pending.lock().clear();
+    }
+
+    async fn track(self, command: TrackedCommand, mut ack_rx: oneshot::Receiver<CommandAck>) {
+        let policy = self.inner.policy;
+        let started = Instant::now();
+        let mut attempts = 1u32;
+
+        let delivery = loop {
+            match tokio::time::timeout(policy.ack_timeout, &mut ack_rx).await {
+                Ok(Ok(ack)) if ack.accepted() => break Delivery::Acked,
+                Ok(Ok(ack)) => break Delivery::Nacked(ack.code),
+                Ok(Err(_)) => break Delivery::Disconnected,
+                Err(_) if attempts > policy.retries => break Delivery::TimedOut,
+                Err(_) => {
+                    attempts += 1;
+                    match self.inner.queue.send_at(command.message_type, &command.data, command.sequence) {
+                        Ok(_) => info!(
+                            "🔁 车辆 {} (ID: {}) 未确认指令 0x{:04X} (序号 {})，第 {} 次重发",
+                            command.vehicle_name,
+                            command.vehicle_id,
+                            command.message_type,
+                            command.sequence,
+                            attempts - 1
+                        ),
+                        Err(SendError::Closed) => break Delivery::Disconnected,
+                        Err(e) => warn!("重发指令 0x{:04X} (序号 {}) 失败: {}", command.message_type, command.sequence, e),
+                    }
+                }
+            }
+        };
+        self.inner.pending.lock().remove(&command.sequence);
+
+        match delivery {
+            Delivery::Acked => info!(
+                "✅ 车辆 {} (ID: {}) 已确认指令 0x{:04X} (序号 {})",
+                command.vehicle_name, command.vehicle_id, command.message_type, command.sequence
+            ),
+            Delivery::Nacked(code) => warn!(
+                "❌ 车辆 {} (ID: {}) 拒绝指令 0x{:04X} (序号 {}): 结果码 {}",
+                command.vehicle_name, command.vehicle_id, command.message_type, command.sequence, code
+            ),
+            Delivery::TimedOut | Delivery::Disconnected | Delivery::Unconfirmed => warn!(
+                "⚠️ 车辆 {} (ID: {}) 指令 0x{:04X} (序号 {}) 未送达: {} (共发送 {} 次)",
+                command.vehicle_name,
+                command.vehicle_id,
+                command.message_type,
+                command.sequence,
+                delivery.status(),
+                attempts
+            ),
+        }
+
+        self.emit_delivery(&command, delivery, attempts, started.elapsed());
+    }
+
+    /// 推送指令投递结果
+    fn emit_delivery(&self, command: &TrackedCommand, delivery: Delivery, attempts: u32, elapsed: Duration) {
+        let code = match delivery {
+            Delivery::Nacked(code) => Some(code),
+            _ => None,
+        };
+        self.inner.sink.emit(
+            "command-delivery",
+            serde_json::json!({
+                "type": "command_delivery",
+                "vehicle_id": command.vehicle_id,
+                "vehicle_name": command.vehicle_name,
+                "message_type": command.message_type,
+                "sequence": command.sequence,
+                "status": delivery.status(),
+                "code": code,
+                "reason": code.map(|code| AckCode::from_code(code).map_or("未知结果码", |c| c.description())),
+                "attempts": attempts,
+                "elapsed_ms": elapsed.as_millis() as u64,
+            }),
+        );
+    }
+}
+
+struct TrackedCommand {
+    vehicle_id: i32,
+    vehicle_name: String,
+    message_type: u16,
+    sequence: u64,
+    data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::protocol::ProtocolParser;
+    use crate::socket::BroadcastEventSink;
+
+    #[tokio::test]
+    async fn test_retry_until_ack_and_timeout() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let queue = SendQueue::new(16);
+        let tracker = CommandTracker::new(
+            queue.clone(),
+            sink,
+            RetryPolicy {
+                retries: 2,
+                ack_timeout: Duration::from_millis(30),
+            },
+        );
+
+        // 第一条：首次发送未确认，重发后收到 ACK
+        let (sequence, lane) = tracker.send(3, "3号车", SendMessageTypes::VEHICLE_CONTROL, &[3, 3]).unwrap();
+        assert_eq!(lane, SendLane::Emergency);
+        let first = queue.recv().await.unwrap();
+        let retry = queue.recv().await.unwrap();
+        assert_eq!(first, retry);
+        let mut parser = ProtocolParser::new();
+        parser.feed_data(&retry);
+        assert_eq!(parser.try_parse_message().unwrap().unwrap().timestamp, sequence);
+
+        let ack = CommandAck {
+            sequence,
+            message_type: SendMessageTypes::VEHICLE_CONTROL,
+            code: 0,
+        };
+        assert_eq!(CommandAck::parse(&ack.to_payload()), Some(ack));
+        assert!(tracker.acknowledge(ack));
+        assert!(!tracker.acknowledge(ack));
+        let event = events.recv().await.unwrap();
+        assert_eq!((event.payload["status"].as_str(), event.payload["attempts"].as_u64()), (Some("acked"), Some(2)));
+
+        // 第二条：始终未确认，发送 1 + 2 次后超时
+        let (second, _) = tracker.send(3, "3号车", SendMessageTypes::AVP_PICKUP, &[3]).unwrap();
+        assert!(second > sequence);
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.payload["status"], "timeout");
+        assert_eq!(event.payload["attempts"], 3);
+        assert_eq!(queue.stats().depth, 3);
+        assert_eq!(tracker.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_vehicle_sends_once() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let queue = SendQueue::new(16);
+        let tracker = CommandTracker::unconfirmed(queue.clone(), sink);
+
+        tracker.send(5, "5号车", SendMessageTypes::VEHICLE_CONTROL, &[5]).unwrap();
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, "command-delivery");
+        assert_eq!((event.payload["status"].as_str(), event.payload["attempts"].as_u64()), (Some("unconfirmed"), Some(1)));
+        assert_eq!(queue.stats().depth, 1);
+        assert_eq!(tracker.pending_count(), 0);
+    }
+}
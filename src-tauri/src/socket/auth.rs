@@ -0,0 +1,310 @@
+//! 车辆接入认证
+//!
+//! 车辆接入后服务器先下发 16 字节随机挑战（0x100A），车辆回复注册帧（0x0009）：
+//! `[车辆编号 u8][HMAC-SHA256(认证密钥, 挑战 || 车辆编号) 32字节]`。
+//! 服务器按车辆编号查找 `vehicle_connections` 中的密钥校验，结果通过 0x100B 返回：
+//! `[结果码 u8][车辆编号 u8]`。认证通过后连接以该编号登记，不再依赖来源 IP。
+//! TLS 模式下客户端证书已绑定车辆时，服务器不下发挑战，直接返回认证通过（见 `tls.rs`）。
+//! 挑战按基线协议版本下发，认证结果按车辆注册帧的版本返回。
+
+use super::protocol::{build_message, encode_message, current_timestamp, ProtocolParser, SocketMessage, VERSION};
+use crate::config::VehicleAuthConfig;
+use crate::database::{VehicleConnection, VehicleDatabase};
+use crate::protocol_processing::types::{MessageTypes, SendMessageTypes};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 挑战随机数长度
+pub const NONCE_LEN: usize = 16;
+/// HMAC-SHA256 摘要长度
+pub const MAC_LEN: usize = 32;
+/// 注册帧数据长度
+pub const REGISTER_PAYLOAD_LEN: usize = 1 + MAC_LEN;
+
+/// 认证结果码（0x100B 第 1 字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthStatus {
+    Accepted = 0,
+    /// 车辆编号未登记、未启用或未配置密钥
+    UnknownVehicle = 1,
+    /// 摘要校验失败
+    BadCredential = 2,
+    /// 未在超时时间内完成注册或注册帧格式错误
+    NotRegistered = 3,
+}
+
+impl AuthStatus {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Accepted),
+            1 => Some(Self::UnknownVehicle),
+            2 => Some(Self::BadCredential),
+            3 => Some(Self::NotRegistered),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Accepted => "认证通过",
+            Self::UnknownVehicle => "车辆未登记或未配置认证密钥",
+            Self::BadCredential => "认证密钥校验失败",
+            Self::NotRegistered => "未完成注册",
+        }
+    }
+}
+
+/// 生成挑战随机数
+pub fn generate_nonce() -> [u8; NONCE_LEN] {
+    uuid::Uuid::new_v4().into_bytes()
+}
+
+fn keyed_mac(auth_key: &str, nonce: &[u8], vehicle_id: u8) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(auth_key.as_bytes()).expect("HMAC 支持任意长度密钥");
+    mac.update(nonce);
+    mac.update(&[vehicle_id]);
+    mac
+}
+
+/// 计算注册摘要
+pub fn compute_mac(auth_key: &str, nonce: &[u8], vehicle_id: u8) -> [u8; MAC_LEN] {
+    keyed_mac(auth_key, nonce, vehicle_id).finalize().into_bytes().into()
+}
+
+/// 校验注册摘要（常数时间比较）
+pub fn verify_mac(auth_key: &str, nonce: &[u8], vehicle_id: u8, mac: &[u8]) -> bool {
+    keyed_mac(auth_key, nonce, vehicle_id).verify_slice(mac).is_ok()
+}
+
+/// 构建注册帧数据域（车端/模拟器使用）
+pub fn build_register_payload(vehicle_id: u8, auth_key: &str, nonce: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(REGISTER_PAYLOAD_LEN);
+    data.push(vehicle_id);
+    data.extend_from_slice(&compute_mac(auth_key, nonce, vehicle_id));
+    data
+}
+
+/// 握手结果
+pub(crate) struct Handshake {
+    /// 认证通过的车辆配置；兼容模式下未注册的车辆为 None
+    pub vehicle: Option<VehicleConnection>,
+    pub parser: ProtocolParser,
+    /// 握手期间已收到、尚未处理的消息
+    pub pending: Vec<SocketMessage>,
+    /// 握手期间收到的原始数据（供会话抓包）
+    pub received: Vec<u8>,
+}
+
+/// 握手失败原因
+#[derive(Debug)]
+pub(crate) struct HandshakeError {
+    pub status: AuthStatus,
+    pub vehicle_id: u8,
+    pub reason: String,
+}
+
+impl HandshakeError {
+    fn new(status: AuthStatus, vehicle_id: u8, reason: impl Into<String>) -> Self {
+        Self {
+            status,
+            vehicle_id,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// 下发挑战并等待车辆注册；失败时已向车辆返回认证结果
+///
+/// `client_cert` 为 TLS 客户端证书指纹，已绑定启用车辆时跳过挑战。
+pub(crate) async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    db: Option<&VehicleDatabase>,
+    config: &VehicleAuthConfig,
+    client_cert: Option<&str>,
+) -> Result<Handshake, HandshakeError> {
+    if let (Some(fingerprint), Some(db)) = (client_cert, db) {
+        match db.get_vehicle_connection_by_cert_fingerprint(fingerprint).await {
+            Ok(Some(vehicle)) if vehicle.is_active => {
+                send_result(stream, AuthStatus::Accepted, vehicle.vehicle_id as u8, VERSION).await;
+                info!("🔐 车辆 {} (ID: {}) 客户端证书认证通过", vehicle.name, vehicle.vehicle_id);
+                return Ok(Handshake {
+                    vehicle: Some(vehicle),
+                    parser: ProtocolParser::new(),
+                    pending: Vec::new(),
+                    received: Vec::new(),
+                });
+            }
+            Ok(Some(vehicle)) => warn!("客户端证书对应的车辆 {} 未启用，改用挑战认证", vehicle.vehicle_id),
+            Ok(None) => info!("客户端证书 {} 未绑定车辆，改用挑战认证", fingerprint),
+            Err(e) => warn!("按证书指纹查询车辆失败: {}", e),
+        }
+    }
+
+    let nonce = generate_nonce();
+    let mut parser = ProtocolParser::new();
+    let mut received = Vec::new();
+
+    let result = async {
+        stream
+            .write_all(&build_message(SendMessageTypes::AUTH_CHALLENGE, &nonce))
+            .await
+            .map_err(|e| HandshakeError::new(AuthStatus::NotRegistered, 0, format!("发送认证挑战失败: {}", e)))?;
+
+        let first = wait_first_message(stream, &mut parser, &mut received, config).await?;
+        match first {
+            Some(message) if message.message_type == MessageTypes::VEHICLE_REGISTER => {
+                let vehicle = verify_register(&message.data, &nonce, db).await?;
+                Ok((Some(vehicle), Vec::new()))
+            }
+            other if config.required => {
+                let reason = match other {
+                    Some(message) => format!("首帧不是注册帧 (类型 0x{:04X})", message.message_type),
+                    None => "等待注册帧超时".to_string(),
+                };
+                Err(HandshakeError::new(AuthStatus::NotRegistered, 0, reason))
+            }
+            other => Ok((None, other.into_iter().collect())),
+        }
+    }
+    .await;
+    let version = parser.detected_version().unwrap_or(VERSION);
+
+    match result {
+        Ok((vehicle, mut pending)) => {
+            if let Some(vehicle) = &vehicle {
+                send_result(stream, AuthStatus::Accepted, vehicle.vehicle_id as u8, version).await;
+                info!("🔐 车辆 {} (ID: {}) 认证通过", vehicle.name, vehicle.vehicle_id);
+            }
+            while let Some(message) = parser.next_message() {
+                pending.push(message);
+            }
+            Ok(Handshake {
+                vehicle,
+                parser,
+                pending,
+                received,
+            })
+        }
+        Err(e) => {
+            send_result(stream, e.status, e.vehicle_id, version).await;
+            Err(e)
+        }
+    }
+}
+
+/// 读取第一条完整消息；超时返回 None
+async fn wait_first_message<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    parser: &mut ProtocolParser,
+    received: &mut Vec<u8>,
+    config: &VehicleAuthConfig,
+) -> Result<Option<SocketMessage>, HandshakeError> {
+    let mut buffer = [0u8; 1024];
+    let read_first = async {
+        loop {
+            match parser.try_parse_message() {
+                Ok(Some(message)) => return Ok(message),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("认证握手期间协议解析失败: {}", e);
+                    continue;
+                }
+            }
+            let n = stream
+                .read(&mut buffer)
+                .await
+                .map_err(|e| HandshakeError::new(AuthStatus::NotRegistered, 0, format!("读取注册帧失败: {}", e)))?;
+            if n == 0 {
+                return Err(HandshakeError::new(AuthStatus::NotRegistered, 0, "注册前连接已关闭"));
+            }
+            received.extend_from_slice(&buffer[..n]);
+            parser.feed_data(&buffer[..n]);
+        }
+    };
+
+    match tokio::time::timeout(Duration::from_millis(config.handshake_timeout as u64), read_first).await {
+        Ok(result) => result.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// 校验注册帧并返回对应的车辆配置
+async fn verify_register(
+    data: &[u8],
+    nonce: &[u8],
+    db: Option<&VehicleDatabase>,
+) -> Result<VehicleConnection, HandshakeError> {
+    if data.len() != REGISTER_PAYLOAD_LEN {
+        return Err(HandshakeError::new(
+            AuthStatus::NotRegistered,
+            data.first().copied().unwrap_or(0),
+            format!("注册帧长度无效: {} 字节", data.len()),
+        ));
+    }
+    let vehicle_id = data[0];
+
+    let Some(db) = db else {
+        return Err(HandshakeError::new(AuthStatus::UnknownVehicle, vehicle_id, "无法获取数据库实例"));
+    };
+    let vehicle = match db.get_vehicle_connection_by_vehicle_id(vehicle_id as i32).await {
+        Ok(Some(vehicle)) if vehicle.is_active => vehicle,
+        Ok(Some(_)) => {
+            return Err(HandshakeError::new(AuthStatus::UnknownVehicle, vehicle_id, format!("车辆 {} 未启用", vehicle_id)))
+        }
+        Ok(None) => {
+            return Err(HandshakeError::new(AuthStatus::UnknownVehicle, vehicle_id, format!("车辆 {} 未登记", vehicle_id)))
+        }
+        Err(e) => {
+            return Err(HandshakeError::new(AuthStatus::UnknownVehicle, vehicle_id, format!("查询车辆连接失败: {}", e)))
+        }
+    };
+
+    let Some(auth_key) = vehicle.auth_key.as_deref() else {
+        return Err(HandshakeError::new(
+            AuthStatus::UnknownVehicle,
+            vehicle_id,
+            format!("车辆 {} 未配置认证密钥", vehicle_id),
+        ));
+    };
+    if !verify_mac(auth_key, nonce, vehicle_id, &data[1..]) {
+        return Err(HandshakeError::new(
+            AuthStatus::BadCredential,
+            vehicle_id,
+            format!("车辆 {} 认证摘要不匹配", vehicle_id),
+        ));
+    }
+
+    Ok(vehicle)
+}
+
+async fn send_result<S: AsyncWrite + Unpin>(stream: &mut S, status: AuthStatus, vehicle_id: u8, version: u8) {
+    let frame = encode_message(version, SendMessageTypes::AUTH_RESULT, &[status as u8, vehicle_id], current_timestamp());
+    if let Err(e) = stream.write_all(&frame).await {
+        warn!("发送认证结果失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_mac_binds_nonce_and_vehicle_id() {
+        let nonce = generate_nonce();
+        let payload = build_register_payload(3, "sandbox-key-03", &nonce);
+        assert_eq!(payload.len(), REGISTER_PAYLOAD_LEN);
+        assert_eq!(payload[0], 3);
+        assert!(verify_mac("sandbox-key-03", &nonce, 3, &payload[1..]));
+
+        // 密钥、车辆编号或挑战任一不同都无法通过校验
+        assert!(!verify_mac("sandbox-key-04", &nonce, 3, &payload[1..]));
+        assert!(!verify_mac("sandbox-key-03", &nonce, 4, &payload[1..]));
+        assert!(!verify_mac("sandbox-key-03", &generate_nonce(), 3, &payload[1..]));
+        assert!(!verify_mac("sandbox-key-03", &nonce, 3, &payload[1..16]));
+    }
+}
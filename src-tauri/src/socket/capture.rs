@@ -0,0 +1,485 @@
+//! 会话抓包
+//!
+//! 将 Socket 服务收到的原始字节流（未拆帧）连同相对时间戳和连接身份写入抓包文件，
+//! 供回放引擎按原始节奏重新送入 `ProtocolParser` 与消息处理流程。
+//!
+//! 文件格式（小端序）：
+//! - 文件头：`DZCAP`(5) + 格式版本(1) + 抓包开始时间 Unix 微秒(8)
+//! - 记录头：相对开始时间微秒(8) + 记录类型(1) + 连接序号(4)
+//!   - 0x01 连接建立：角色(1, 0=车辆 1=沙盘) + 车辆ID(i32) + 名称长度(2) + 名称 + 地址长度(2) + 地址
+//!   - 0x02 数据：长度(4) + 原始字节
+//!   - 0x03 连接断开：无附加字段
+//!
+//! 同一抓包器还可同时导出 pcapng（按连接与方向记录，含车辆编号，见 `pcapng.rs`）。
+
+use super::pcapng::{PacketDirection, PcapngExport};
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub const CAPTURE_MAGIC: [u8; 5] = *b"DZCAP";
+pub const CAPTURE_VERSION: u8 = 1;
+
+const RECORD_OPEN: u8 = 0x01;
+const RECORD_DATA: u8 = 0x02;
+const RECORD_CLOSE: u8 = 0x03;
+
+/// 连接角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionRole {
+    Vehicle,
+    Sandbox,
+}
+
+/// 抓包中的连接身份
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CaptureConnection {
+    pub role: ConnectionRole,
+    /// 车辆编号；沙盘连接为沙盘ID
+    pub vehicle_id: i32,
+    pub vehicle_name: String,
+    pub addr: String,
+}
+
+/// 抓包记录
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureEvent {
+    Open(CaptureConnection),
+    Data(Vec<u8>),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRecord {
+    /// 相对抓包开始的时间（微秒）
+    pub elapsed_us: u64,
+    /// 连接序号（同一次服务运行内唯一）
+    pub connection: u32,
+    pub event: CaptureEvent,
+}
+
+/// 已加载的抓包文件
+#[derive(Debug, Clone)]
+pub struct CaptureFile {
+    pub started_at_us: u64,
+    pub records: Vec<CaptureRecord>,
+}
+
+impl CaptureFile {
+    /// 抓包时长（微秒）
+    pub fn duration_us(&self) -> u64 {
+        self.records.last().map(|r| r.elapsed_us).unwrap_or(0)
+    }
+}
+
+/// 编码文件头
+pub fn encode_header(started_at_us: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(14);
+    out.extend_from_slice(&CAPTURE_MAGIC);
+    out.push(CAPTURE_VERSION);
+    out.extend_from_slice(&started_at_us.to_le_bytes());
+    out
+}
+
+/// 编码单条记录
+pub fn encode_record(record: &CaptureRecord) -> Vec<u8> {
+    let mut out = Vec::with_capacity(13);
+    out.extend_from_slice(&record.elapsed_us.to_le_bytes());
+    match &record.event {
+        CaptureEvent::Open(conn) => {
+            out.push(RECORD_OPEN);
+            out.extend_from_slice(&record.connection.to_le_bytes());
+            out.push(match conn.role {
+                ConnectionRole::Vehicle => 0,
+                ConnectionRole::Sandbox => 1,
+            });
+            out.extend_from_slice(&conn.vehicle_id.to_le_bytes());
+            write_short_str(&mut out, &conn.vehicle_name);
+            write_short_str(&mut out, &conn.addr);
+        }
+        CaptureEvent::Data(bytes) => {
+            out.push(RECORD_DATA);
+            out.extend_from_slice(&record.connection.to_le_bytes());
+            out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(bytes);
+        }
+        CaptureEvent::Close => {
+            out.push(RECORD_CLOSE);
+            out.extend_from_slice(&record.connection.to_le_bytes());
+        }
+    }
+    out
+}
+
+fn write_short_str(out: &mut Vec<u8>, value: &str) {
+    let bytes = &value.as_bytes()[..value.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// 顺序读取字节的游标
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() - self.pos < len {
+            return Err(format!("抓包文件在偏移 {} 处被截断", self.pos));
+        }
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn short_str(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// 解析抓包文件内容
+pub fn decode_capture(data: &[u8]) -> Result<CaptureFile, String> {
+    let mut cursor = Cursor { data, pos: 0 };
+    if cursor.take(CAPTURE_MAGIC.len()).map_err(|_| "不是有效的抓包文件".to_string())? != CAPTURE_MAGIC {
+        return Err("不是有效的抓包文件".to_string());
+    }
+    let version = cursor.u8()?;
+    if version != CAPTURE_VERSION {
+        return Err(format!("不支持的抓包文件版本: {}", version));
+    }
+    let started_at_us = cursor.u64()?;
+
+    let mut records = Vec::new();
+    while !cursor.is_empty() {
+        let elapsed_us = cursor.u64()?;
+        let kind = cursor.u8()?;
+        let connection = cursor.u32()?;
+        let event = match kind {
+            RECORD_OPEN => {
+                let role = match cursor.u8()? {
+                    0 => ConnectionRole::Vehicle,
+                    1 => ConnectionRole::Sandbox,
+                    other => return Err(format!("未知连接角色: {}", other)),
+                };
+                let vehicle_id = cursor.u32()? as i32;
+                let vehicle_name = cursor.short_str()?;
+                let addr = cursor.short_str()?;
+                CaptureEvent::Open(CaptureConnection { role, vehicle_id, vehicle_name, addr })
+            }
+            RECORD_DATA => {
+                let len = cursor.u32()? as usize;
+                CaptureEvent::Data(cursor.take(len)?.to_vec())
+            }
+            RECORD_CLOSE => CaptureEvent::Close,
+            other => return Err(format!("未知抓包记录类型: 0x{:02X}", other)),
+        };
+        records.push(CaptureRecord { elapsed_us, connection, event });
+    }
+
+    Ok(CaptureFile { started_at_us, records })
+}
+
+/// 读取抓包文件
+pub fn read_capture_file(path: &Path) -> Result<CaptureFile, String> {
+    let data = std::fs::read(path).map_err(|e| format!("读取抓包文件失败 {}: {}", path.display(), e))?;
+    decode_capture(&data)
+}
+
+/// 正在写入的抓包文件
+struct ActiveCapture {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    bytes_written: u64,
+}
+
+#[derive(Default)]
+struct CaptureInner {
+    active: Option<ActiveCapture>,
+    /// 正在导出的 pcapng 文件
+    pcapng: Option<PcapngExport>,
+    /// 当前存活的连接，开始抓包时补写连接建立记录
+    connections: HashMap<u32, Arc<CaptureConnection>>,
+}
+
+/// 会话抓包器（Socket 服务与命令层共享）
+#[derive(Clone, Default)]
+pub struct SessionCapture {
+    inner: Arc<Mutex<CaptureInner>>,
+    next_connection: Arc<AtomicU32>,
+}
+
+impl SessionCapture {
+    /// 开始抓包；已在抓包时先结束上一个文件
+    pub fn start(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建抓包目录失败: {}", e))?;
+        }
+        let file = File::create(path).map_err(|e| format!("创建抓包文件失败 {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+
+        let started_at_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let header = encode_header(started_at_us);
+        writer.write_all(&header).map_err(|e| format!("写入抓包文件头失败: {}", e))?;
+
+        let mut inner = self.inner.lock();
+        if let Some(previous) = inner.active.take() {
+            Self::finish(previous);
+        }
+
+        let mut active = ActiveCapture {
+            path: path.to_path_buf(),
+            writer,
+            started: Instant::now(),
+            bytes_written: header.len() as u64,
+        };
+        let mut open_connections: Vec<_> = inner.connections.iter().collect();
+        open_connections.sort_by_key(|(index, _)| **index);
+        for (index, conn) in open_connections {
+            Self::write(&mut active, *index, CaptureEvent::Open(conn.as_ref().clone()));
+        }
+        inner.active = Some(active);
+
+        info!("📼 开始会话抓包: {}", path.display());
+        Ok(())
+    }
+
+    /// 停止抓包，返回文件路径与写入字节数
+    pub fn stop(&self) -> Option<(PathBuf, u64)> {
+        let active = self.inner.lock().active.take()?;
+        let result = (active.path.clone(), active.bytes_written);
+        Self::finish(active);
+        Some(result)
+    }
+
+    /// 开始导出 pcapng；已在导出时先结束上一个文件
+    pub fn start_pcapng(&self, path: &Path) -> Result<(), String> {
+        let export = PcapngExport::start(path)?;
+        let previous = self.inner.lock().pcapng.replace(export);
+        if let Some(previous) = previous {
+            Self::finish_pcapng(previous);
+        }
+        info!("🦈 开始导出 pcapng: {}", path.display());
+        Ok(())
+    }
+
+    /// 停止导出 pcapng（等待队列写完），返回文件路径与帧数
+    pub fn stop_pcapng(&self) -> Option<(PathBuf, u64)> {
+        let export = self.inner.lock().pcapng.take()?;
+        Self::finish_pcapng(export)
+    }
+
+    /// 记录收到的原始字节（解码前，`vehicle_id` 为当前车辆编号，沙盘为沙盘ID）
+    pub fn record_inbound(&self, index: u32, vehicle_id: i32, data: &[u8]) {
+        self.record_frame(index, vehicle_id, PacketDirection::Inbound, data);
+    }
+
+    /// 记录下发的一帧
+    pub fn record_outbound(&self, index: u32, vehicle_id: i32, frame: &[u8]) {
+        self.record_frame(index, vehicle_id, PacketDirection::Outbound, frame);
+    }
+
+    fn record_frame(&self, index: u32, vehicle_id: i32, direction: PacketDirection, frame: &[u8]) {
+        let mut inner = self.inner.lock();
+        let CaptureInner { pcapng, connections, .. } = &mut *inner;
+        let (Some(export), Some(connection)) = (pcapng.as_mut(), connections.get(&index)) else {
+            return;
+        };
+        export.record(index, connection.clone(), vehicle_id, direction, frame);
+    }
+
+    fn finish_pcapng(export: PcapngExport) -> Option<(PathBuf, u64)> {
+        let path = export.path().to_path_buf();
+        match export.finish() {
+            Ok((written, packets, dropped)) => {
+                if dropped > 0 {
+                    warn!("🦈 pcapng 导出期间写入跟不上，丢弃 {} 帧", dropped);
+                }
+                info!("🦈 pcapng 导出已结束: {} ({} 帧)", path.display(), packets);
+                Some((written, packets))
+            }
+            Err(e) => {
+                error!("刷新 pcapng 文件失败 {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// 登记新连接，返回连接序号
+    pub fn open_connection(&self, connection: CaptureConnection) -> u32 {
+        let index = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        let mut inner = self.inner.lock();
+        if let Some(active) = inner.active.as_mut() {
+            Self::write(active, index, CaptureEvent::Open(connection.clone()));
+        }
+        inner.connections.insert(index, Arc::new(connection));
+        index
+    }
+
+    /// 记录收到的原始字节
+    pub fn record_data(&self, index: u32, data: &[u8]) {
+        let mut inner = self.inner.lock();
+        if let Some(active) = inner.active.as_mut() {
+            Self::write(active, index, CaptureEvent::Data(data.to_vec()));
+        }
+    }
+
+    /// 登记连接断开
+    pub fn close_connection(&self, index: u32) {
+        let mut inner = self.inner.lock();
+        inner.connections.remove(&index);
+        if let Some(active) = inner.active.as_mut() {
+            Self::write(active, index, CaptureEvent::Close);
+        }
+    }
+
+    fn write(active: &mut ActiveCapture, connection: u32, event: CaptureEvent) {
+        let record = CaptureRecord {
+            elapsed_us: active.started.elapsed().as_micros() as u64,
+            connection,
+            event,
+        };
+        let bytes = encode_record(&record);
+        match active.writer.write_all(&bytes) {
+            Ok(_) => active.bytes_written += bytes.len() as u64,
+            Err(e) => error!("写入抓包记录失败 {}: {}", active.path.display(), e),
+        }
+    }
+
+    fn finish(mut active: ActiveCapture) {
+        if let Err(e) = active.writer.flush() {
+            error!("刷新抓包文件失败 {}: {}", active.path.display(), e);
+        }
+        info!("📼 会话抓包已结束: {} ({} 字节)", active.path.display(), active.bytes_written);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn vehicle(id: i32) -> CaptureConnection {
+        CaptureConnection {
+            role: ConnectionRole::Vehicle,
+            vehicle_id: id,
+            vehicle_name: format!("车辆{}", id),
+            addr: "192.168.1.10:5000".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let records = vec![
+            CaptureRecord { elapsed_us: 0, connection: 3, event: CaptureEvent::Open(vehicle(1)) },
+            CaptureRecord { elapsed_us: 1500, connection: 3, event: CaptureEvent::Data(vec![0xEF, 0x01, 0x02]) },
+            CaptureRecord { elapsed_us: 9000, connection: 3, event: CaptureEvent::Close },
+        ];
+
+        let mut bytes = encode_header(42);
+        for record in &records {
+            bytes.extend_from_slice(&encode_record(record));
+        }
+
+        let decoded = decode_capture(&bytes).unwrap();
+        assert_eq!(decoded.started_at_us, 42);
+        assert_eq!(decoded.records, records);
+        assert_eq!(decoded.duration_us(), 9000);
+
+        // 截断的文件应返回错误而非 panic
+        assert!(decode_capture(&bytes[..bytes.len() - 2]).is_err());
+        assert!(decode_capture(b"NOTCAP").is_err());
+    }
+
+    #[test]
+    fn test_capture_started_mid_session_replays_open_connections() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.dzcap");
+        let capture = SessionCapture::default();
+
+        let index = capture.open_connection(vehicle(5));
+        capture.record_data(index, b"ignored");
+        capture.start(&path).unwrap();
+        capture.record_data(index, b"abc");
+        capture.close_connection(index);
+        let (written_path, _) = capture.stop().unwrap();
+
+        let file = read_capture_file(&written_path).unwrap();
+        let events: Vec<_> = file.records.into_iter().map(|r| r.event).collect();
+        assert_eq!(
+            events,
+            vec![
+                CaptureEvent::Open(vehicle(5)),
+                CaptureEvent::Data(b"abc".to_vec()),
+                CaptureEvent::Close,
+            ]
+        );
+        assert!(capture.stop().is_none());
+    }
+
+    #[test]
+    fn test_pcapng_records_frames_per_connection() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.pcapng");
+        let capture = SessionCapture::default();
+
+        let first = capture.open_connection(vehicle(1));
+        // 接收方向照原样记录读到的字节：前置杂字节、校验错误的帧与半帧都保留
+        let mut corrupted = crate::socket::protocol::build_message(0x0002, &[1; 55]);
+        let crc_offset = corrupted.len() - 6;
+        corrupted[crc_offset] ^= 0xFF;
+        let received = [&[0x00, 0x01][..], &corrupted, &crate::socket::protocol::build_message(0x0008, &[1])[..10]].concat();
+        capture.record_inbound(first, 1, &received);
+        capture.start_pcapng(&path).unwrap();
+        let second = capture.open_connection(vehicle(2));
+        capture.record_inbound(first, 1, &received);
+        capture.record_outbound(second, 2, &crate::socket::protocol::build_message(0x1001, &[2, 2]));
+        capture.record_outbound(first, 1, &crate::socket::protocol::build_message(0x0001, &[]));
+        let (written_path, packets) = capture.stop_pcapng().unwrap();
+        assert_eq!(packets, 3);
+
+        // 节头 + 两个接口 + 三个分组
+        let data = std::fs::read(written_path).unwrap();
+        let mut types = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            types.push(u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()));
+            pos += u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        }
+        assert_eq!(types, vec![0x0A0D_0D0A, 1, 6, 1, 6, 6]);
+        assert!(data.windows(received.len()).any(|window| window == received.as_slice()));
+        assert!(capture.stop_pcapng().is_none());
+    }
+}
@@ -0,0 +1,343 @@
+//! 帧协议版本编解码
+//!
+//! 各版本的帧格式登记在 `CodecRegistry` 中：解析器按每帧的版本字节选择编解码器，
+//! 同一连接可以混合收到不同版本的帧；连接的发送队列按对端最近一次使用的版本编码
+//! （未收到对端数据前使用基线版本 0x10）。
+//!
+//! - 0x10：`帧头(4) 版本(1) 时间戳u64 类型u16 长度u32 数据 CRC16(2) 帧尾(4)`
+//!
+//! 目前仅登记有协议文档的 0x10；新版本需按文档实现 `FrameCodec` 后在 `global()` 中登记。
+//! 测试构建额外登记 [`TEST_VERSION`]，用于覆盖多版本共存与按连接协商。
+
+use super::protocol::{ProtocolError, SocketMessage, FOOTER, HEADER, MAX_DATA_LENGTH};
+use crate::protocol_processing::types::ProtocolConstants;
+use crc::{Algorithm, Crc};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+/// 版本(1) + 时间戳(8) + 类型(2) + 长度(4)
+pub(crate) const FIXED_FIELDS_LEN: usize = 15;
+
+// CRC16校验
+const CRC_16_CCITT_FALSE: Algorithm<u16> = Algorithm {
+    width: 16,
+    poly: 0x1021,
+    init: 0xFFFF,
+    refin: false,
+    refout: false,
+    xorout: 0x0000,
+    check: 0x29B1,
+    residue: 0x0000,
+};
+
+const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_CCITT_FALSE);
+
+/// 单个协议版本的帧编解码器
+///
+/// 默认实现覆盖「帧头 + 固定字段 + 数据 + 校验 + 帧尾」结构，版本间只有校验不同时
+/// 实现 `checksum_len`/`checksum` 即可；帧结构变化时覆盖 `encode`/`decode`。
+pub trait FrameCodec: Send + Sync {
+    /// 版本字节
+    fn version(&self) -> u8;
+
+    /// 校验字段长度（字节）
+    fn checksum_len(&self) -> usize;
+
+    /// 计算校验值（从版本字节到数据域末尾）
+    fn checksum(&self, bytes: &[u8]) -> u32;
+
+    /// 不含数据域的帧长度
+    fn min_frame_len(&self) -> usize {
+        HEADER.len() + FIXED_FIELDS_LEN + self.checksum_len() + FOOTER.len()
+    }
+
+    /// 组帧
+    fn encode(&self, message_type: u16, data: &[u8], timestamp: u64) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(self.min_frame_len() + data.len());
+        packet.extend_from_slice(&HEADER);
+        packet.push(self.version());
+        packet.extend_from_slice(&timestamp.to_le_bytes());
+        packet.extend_from_slice(&message_type.to_le_bytes());
+        packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packet.extend_from_slice(data);
+
+        let checksum = self.checksum(&packet[HEADER.len()..]).to_le_bytes();
+        packet.extend_from_slice(&checksum[..self.checksum_len()]);
+        packet.extend_from_slice(&FOOTER);
+        packet
+    }
+
+    /// 从以帧头开始的缓冲区解析一帧，返回消息与该帧占用的字节数
+    fn decode(&self, buffer: &[u8]) -> Result<(SocketMessage, usize), ProtocolError> {
+        if buffer.len() < HEADER.len() + FIXED_FIELDS_LEN {
+            return Err(ProtocolError::IncompleteData);
+        }
+        if buffer[..HEADER.len()] != HEADER {
+            return Err(ProtocolError::InvalidHeader);
+        }
+        if buffer[HEADER.len()] != self.version() {
+            return Err(ProtocolError::InvalidVersion);
+        }
+
+        let fields = &buffer[HEADER.len() + 1..HEADER.len() + FIXED_FIELDS_LEN];
+        let timestamp = u64::from_le_bytes(fields[0..8].try_into().expect("8字节时间戳"));
+        let message_type = u16::from_le_bytes([fields[8], fields[9]]);
+        let data_length = u32::from_le_bytes(fields[10..14].try_into().expect("4字节长度")) as usize;
+        if data_length > MAX_DATA_LENGTH {
+            return Err(ProtocolError::InvalidLength);
+        }
+
+        let total_length = self
+            .min_frame_len()
+            .checked_add(data_length)
+            .ok_or(ProtocolError::InvalidLength)?;
+        if buffer.len() < total_length {
+            return Err(ProtocolError::IncompleteData);
+        }
+
+        let data_start = HEADER.len() + FIXED_FIELDS_LEN;
+        let data_end = data_start + data_length;
+        let checksum_end = data_end + self.checksum_len();
+
+        let mut received = [0u8; 4];
+        received[..self.checksum_len()].copy_from_slice(&buffer[data_end..checksum_end]);
+        if u32::from_le_bytes(received) != self.checksum(&buffer[HEADER.len()..data_end]) {
+            return Err(ProtocolError::InvalidCrc);
+        }
+        if buffer[checksum_end..total_length] != FOOTER {
+            return Err(ProtocolError::InvalidFooter);
+        }
+
+        Ok((
+            SocketMessage {
+                version: self.version(),
+                timestamp,
+                message_type,
+                data: buffer[data_start..data_end].to_vec(),
+            },
+            total_length,
+        ))
+    }
+}
+
+/// 0x10：CRC-16/CCITT-FALSE
+pub struct FrameV10;
+
+impl FrameCodec for FrameV10 {
+    fn version(&self) -> u8 {
+        ProtocolConstants::PROTOCOL_VERSION_V10
+    }
+
+    fn checksum_len(&self) -> usize {
+        2
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        CRC16.checksum(bytes) as u32
+    }
+}
+
+/// 测试用版本号（协议文档中不存在）
+#[cfg(test)]
+pub(crate) const TEST_VERSION: u8 = 0x7E;
+
+/// 测试用版本：帧结构同 0x10，校验取 CRC-16 的反码
+#[cfg(test)]
+pub(crate) struct FrameTest;
+
+#[cfg(test)]
+impl FrameCodec for FrameTest {
+    fn version(&self) -> u8 {
+        TEST_VERSION
+    }
+
+    fn checksum_len(&self) -> usize {
+        2
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        u32::from(!CRC16.checksum(bytes))
+    }
+}
+
+/// 协议版本注册表
+#[derive(Clone, Default)]
+pub struct CodecRegistry {
+    codecs: Vec<Arc<dyn FrameCodec>>,
+}
+
+impl CodecRegistry {
+    /// 登记编解码器（同版本后登记的覆盖先登记的）
+    pub fn register(&mut self, codec: Arc<dyn FrameCodec>) {
+        self.codecs.retain(|existing| existing.version() != codec.version());
+        self.codecs.push(codec);
+    }
+
+    pub fn get(&self, version: u8) -> Option<&dyn FrameCodec> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.version() == version)
+            .map(|codec| codec.as_ref())
+    }
+
+    /// 已支持的版本（升序）
+    pub fn versions(&self) -> Vec<u8> {
+        let mut versions: Vec<u8> = self.codecs.iter().map(|codec| codec.version()).collect();
+        versions.sort_unstable();
+        versions
+    }
+
+    /// 内置全部版本的注册表
+    pub fn global() -> &'static CodecRegistry {
+        static REGISTRY: Lazy<CodecRegistry> = Lazy::new(|| {
+            let mut registry = CodecRegistry::default();
+            registry.register(Arc::new(FrameV10));
+            #[cfg(test)]
+            registry.register(Arc::new(FrameTest));
+            registry
+        });
+        &REGISTRY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VehicleAuthConfig;
+    use crate::protocol_processing::builder::ProtocolBuilder;
+    use crate::protocol_processing::types::{MessageTypes, SendMessageTypes, VehicleInfo};
+    use crate::socket::protocol::{encode_message, ProtocolParser};
+    use crate::socket::{BroadcastEventSink, SocketServer};
+    use crate::test_support::{self, eventually};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[test]
+    fn test_unknown_versions_on_one_stream() {
+        let registry = CodecRegistry::global();
+        assert_eq!(registry.versions(), vec![0x10, TEST_VERSION]);
+        assert_eq!(registry.get(0x10).unwrap().min_frame_len(), 25);
+        assert!(registry.get(0x11).is_none());
+
+        let mut stream = encode_message(0x10, MessageTypes::HEARTBEAT, &[], 1);
+        // 未登记的版本被跳过，不影响后续帧
+        let mut unknown = encode_message(0x10, MessageTypes::PATH_FILE_SELECTION, &[2, 7], 2);
+        unknown[HEADER.len()] = 0x11;
+        stream.extend(unknown);
+        stream.extend(encode_message(TEST_VERSION, MessageTypes::PATH_FILE_SELECTION, &[2, 7], 3));
+        stream.extend(encode_message(0x10, MessageTypes::PATH_FILE_SELECTION, &[2, 7], 4));
+
+        let mut parser = ProtocolParser::new();
+        parser.feed_data(&stream);
+        let mut received = Vec::new();
+        let mut errors = 0;
+        loop {
+            match parser.try_parse_message() {
+                Ok(Some(message)) => received.push((message.version, message.timestamp, message.data)),
+                Ok(None) => break,
+                Err(_) => errors += 1,
+            }
+        }
+        assert_eq!(received, vec![(0x10, 1, vec![]), (TEST_VERSION, 3, vec![2, 7]), (0x10, 4, vec![2, 7])]);
+        assert_eq!(errors, 1);
+        assert_eq!(parser.detected_version(), Some(0x10));
+
+        // 校验按帧版本计算：CRC-16 覆盖数据域，其他版本的校验值不被接受
+        let mut corrupted = encode_message(0x10, MessageTypes::PATH_FILE_SELECTION, &[2, 7], 5);
+        corrupted[HEADER.len() + FIXED_FIELDS_LEN] ^= 0xFF;
+        let mut relabeled = encode_message(TEST_VERSION, MessageTypes::PATH_FILE_SELECTION, &[2, 7], 6);
+        relabeled[HEADER.len()] = 0x10;
+        for frame in [corrupted, relabeled] {
+            let mut parser = ProtocolParser::new();
+            parser.feed_data(&frame);
+            assert!(matches!(parser.try_parse_message(), Err(ProtocolError::InvalidCrc)));
+        }
+    }
+
+    /// 读取服务器下发的帧直到收到指定类型（跳过连接时按基线版本下发的认证挑战等）
+    async fn receive(client: &mut TcpStream, message_type: u16) -> SocketMessage {
+        let mut parser = ProtocolParser::new();
+        let mut buffer = [0u8; 1024];
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(Some(message)) = parser.try_parse_message() {
+                    if message.message_type == message_type {
+                        break message;
+                    }
+                    continue;
+                }
+                let n = client.read(&mut buffer).await.unwrap();
+                parser.feed_data(&buffer[..n]);
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_replies_in_each_peer_version() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, None));
+        let mut events = sink.subscribe();
+        let connections = Arc::new(parking_lot::RwLock::new(HashMap::new()));
+        let server = SocketServer::new_with_connections(
+            port,
+            sink.clone(),
+            connections.clone(),
+            Arc::new(parking_lot::RwLock::new(HashMap::new())),
+        )
+        .with_vehicle_auth(VehicleAuthConfig {
+            required: false,
+            handshake_timeout: 100,
+        });
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+
+        // 4 号车使用 0x10，5 号车使用测试版本；先后接入，各自按首帧车辆编号登记
+        let mut clients = Vec::new();
+        for (vehicle_id, version) in [(4u8, 0x10), (5, TEST_VERSION)] {
+            let info = VehicleInfo { position_x: 1.25, position_y: -2.5, ..test_support::vehicle_info(vehicle_id) };
+            let payload = ProtocolBuilder::new().build_vehicle_info(&info);
+            assert_eq!(payload.len(), ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE);
+
+            let mut client = test_support::connect(port).await;
+            client.write_all(&encode_message(version, MessageTypes::VEHICLE_INFO, &payload, 1)).await.unwrap();
+            let vehicle_id = i32::from(vehicle_id);
+            eventually("车辆按首帧编号登记", || async { connections.read().contains_key(&vehicle_id) }).await;
+            clients.push((vehicle_id, version, client));
+        }
+
+        // 车辆信息按帧版本解码
+        let mut parsed = HashMap::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while parsed.len() < 2 {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                if event.event != "fleet-update" {
+                    continue;
+                }
+                for vehicle in event.payload["vehicles"].as_array().into_iter().flatten() {
+                    parsed.insert(vehicle["vehicle_id"].as_i64().unwrap(), vehicle["parsed"].clone());
+                }
+            }
+        })
+        .await
+        .unwrap();
+        for vehicle_id in [4, 5] {
+            assert_eq!(parsed[&vehicle_id]["vehicle_id"], vehicle_id);
+            assert_eq!(parsed[&vehicle_id]["position"]["y"], -2.5);
+        }
+
+        // 之后下发的帧各自使用对端的版本
+        for (vehicle_id, version, client) in &mut clients {
+            SocketServer::send_to_vehicle(&connections, *vehicle_id, SendMessageTypes::HEARTBEAT, &[]).unwrap();
+            let reply = receive(client, SendMessageTypes::HEARTBEAT).await;
+            assert_eq!(reply.version, *version);
+            assert_eq!(connections.read()[vehicle_id].sender.version(), *version);
+        }
+    }
+}
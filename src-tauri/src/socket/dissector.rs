@@ -0,0 +1,335 @@
+//! Wireshark Lua 解析器生成
+//!
+//! 由帧编解码器（各版本校验长度）和 `messages.toml` 协议定义（消息类型编号、字段偏移、
+//! 取值含义）生成 Lua 解析器，协议变更后重新导出即可，无需手工维护。
+//!
+//! 解析器注册名为 `dzviz`：既可直接解析 pcapng 导出文件（Exported PDU 按名称调用），
+//! 也按服务器端口注册到 TCP，用于现场直接抓包（支持 TCP 分段重组）。
+//! 方向优先取 pcapng 标注的收发方向，否则以目的端口是否为服务器端口判断。
+
+use super::codec::{CodecRegistry, FIXED_FIELDS_LEN};
+use super::pcapng::DISSECTOR_NAME;
+use super::protocol::{FOOTER, HEADER};
+use crate::protocol_processing::schema::{FieldSchema, FieldType, MessageSchema, ProtocolSchema};
+use crate::protocol_processing::types::ProtocolConstants;
+use std::collections::BTreeMap;
+
+/// Lua 字符串字面量
+fn lua_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 取值含义表（键不是整数的忽略）
+fn value_string(field: &FieldSchema) -> Option<String> {
+    let entries: Vec<String> = field
+        .labels
+        .iter()
+        .filter_map(|(key, label)| key.parse::<i64>().ok().map(|key| format!("[{}] = {}", key, lua_string(label))))
+        .collect();
+    if entries.is_empty() {
+        (field.ty == FieldType::Bool).then(|| "{ [0] = \"否\", [1] = \"是\" }".to_string())
+    } else {
+        Some(format!("{{ {} }}", entries.join(", ")))
+    }
+}
+
+/// 消息在 Lua 中的标识（非基线版本带版本后缀）
+fn message_key(message: &MessageSchema) -> String {
+    if message.version == ProtocolConstants::PROTOCOL_VERSION_V10 {
+        message.name.to_lowercase()
+    } else {
+        format!("{}_v{:02x}", message.name.to_lowercase(), message.version)
+    }
+}
+
+/// 字段声明：`hf["abbr"] = ProtoField.xxx(...)`
+fn declare_field(out: &mut String, abbr: &str, field: &FieldSchema) {
+    let name = lua_string(&field.name);
+    let abbr_lua = lua_string(abbr);
+    let declaration = match field.ty {
+        FieldType::F32 => format!("ProtoField.float({}, {})", abbr_lua, name),
+        FieldType::F64 => format!("ProtoField.double({}, {})", abbr_lua, name),
+        FieldType::Bytes => format!("ProtoField.bytes({}, {})", abbr_lua, name),
+        FieldType::Group => return,
+        ty => {
+            let constructor = match ty {
+                FieldType::U16 => "uint16",
+                FieldType::U32 => "uint32",
+                FieldType::U64 => "uint64",
+                _ => "uint8",
+            };
+            match value_string(field) {
+                Some(values) => format!("ProtoField.{}({}, {}, base.DEC, {})", constructor, abbr_lua, name, values),
+                None => format!("ProtoField.{}({}, {}, base.DEC)", constructor, abbr_lua, name),
+            }
+        }
+    };
+    out.push_str(&format!("hf[{}] = {}\n", abbr_lua, declaration));
+}
+
+fn declare_fields(out: &mut String, prefix: &str, fields: &[FieldSchema]) {
+    for field in fields {
+        let abbr = format!("{}.{}", prefix, field.name);
+        if field.ty == FieldType::Group {
+            declare_fields(out, &abbr, &field.fields);
+        } else {
+            declare_field(out, &abbr, field);
+        }
+    }
+}
+
+/// 字段解析语句；`base` 为该层字段偏移的 Lua 表达式
+fn dissect_fields(out: &mut String, indent: &str, tree: &str, prefix: &str, base: &str, fields: &[FieldSchema]) {
+    for field in fields {
+        let abbr = lua_string(&format!("{}.{}", prefix, field.name));
+        let (offset, end) = if base == "0" {
+            (field.offset.to_string(), (field.offset + field.size).to_string())
+        } else {
+            (format!("{} + {}", base, field.offset), format!("{} + {}", base, field.offset + field.size))
+        };
+        match field.ty {
+            FieldType::Bytes => {
+                out.push_str(&format!("{}if len > {} then {}:add(hf[{}], buf({}, len - {})) end\n", indent, offset, tree, abbr, offset, offset));
+            }
+            FieldType::Group => {
+                let element_size = field.size / field.count.max(1);
+                out.push_str(&format!("{}for i = 0, {} do\n", indent, field.count.saturating_sub(1)));
+                out.push_str(&format!("{}    local at = {} + i * {}\n", indent, offset, element_size));
+                out.push_str(&format!("{}    if len >= at + {} then\n", indent, element_size));
+                out.push_str(&format!(
+                    "{}        local sub = {}:add(buf(at, {}), {} .. \"[\" .. i .. \"]\")\n",
+                    indent,
+                    tree,
+                    element_size,
+                    lua_string(&field.name)
+                ));
+                let inner_prefix = format!("{}.{}", prefix, field.name);
+                dissect_fields(out, &format!("{}        ", indent), "sub", &inner_prefix, "at", &field.fields);
+                out.push_str(&format!("{}    end\n", indent));
+                out.push_str(&format!("{}end\n", indent));
+            }
+            _ => {
+                out.push_str(&format!(
+                    "{}if len >= {} then {}:add_le(hf[{}], buf({}, {})) end\n",
+                    indent, end, tree, abbr, offset, field.size
+                ));
+            }
+        }
+    }
+}
+
+/// 生成 Lua 解析器；`port` 为 Socket 服务器端口
+pub fn generate_lua_dissector(port: u16) -> String {
+    let schema = ProtocolSchema::global();
+    let registry = CodecRegistry::global();
+    let base_version = ProtocolConstants::PROTOCOL_VERSION_V10;
+    let data_offset = HEADER.len() + FIXED_FIELDS_LEN;
+
+    let mut out = String::new();
+    out.push_str("-- dz-viz 帧协议 Wireshark 解析器\n");
+    out.push_str("-- 由 dz-viz 根据 messages.toml 与帧编解码器生成，请勿手工修改；协议变更后重新导出。\n");
+    out.push_str("-- 安装：复制到 Wireshark 个人 Lua 插件目录（帮助 → 关于 → 文件夹），重启或 Ctrl+Shift+L 重新加载。\n\n");
+
+    out.push_str(&format!("local dzviz = Proto({}, \"dz-viz 车辆通信协议\")\n\n", lua_string(DISSECTOR_NAME)));
+    out.push_str(&format!("local SERVER_PORT = {}\n", port));
+    out.push_str(&format!("local BASE_VERSION = 0x{:02X}\n", base_version));
+    out.push_str(&format!("local HEADER_LEN = {}\n", HEADER.len()));
+    out.push_str(&format!("local FOOTER_LEN = {}\n", FOOTER.len()));
+    out.push_str(&format!("local DATA_OFFSET = {}\n", data_offset));
+    out.push_str("-- 各版本校验字段长度\nlocal CHECKSUM_LEN = {\n");
+    for version in registry.versions() {
+        let codec = registry.get(version).expect("已登记的版本");
+        out.push_str(&format!("    [0x{:02X}] = {},\n", version, codec.checksum_len()));
+    }
+    out.push_str("}\n\n");
+
+    // 消息类型名称（收发编号可能重叠，按方向分表）
+    let mut receive_names = BTreeMap::new();
+    let mut send_names = BTreeMap::new();
+    for message in schema.messages().iter().filter(|m| m.version == base_version) {
+        if let Some(id) = message.receive {
+            receive_names.insert(id, &message.label);
+        }
+        if let Some(id) = message.send {
+            send_names.insert(id, &message.label);
+        }
+    }
+    for (table, names) in [("RECEIVE_NAMES", &receive_names), ("SEND_NAMES", &send_names)] {
+        out.push_str(&format!("local {} = {{\n", table));
+        for (id, label) in names {
+            out.push_str(&format!("    [0x{:04X}] = {},\n", id, lua_string(label)));
+        }
+        out.push_str("}\n");
+    }
+
+    out.push_str("\nlocal hf = {}\n");
+    out.push_str("hf.header = ProtoField.bytes(\"dzviz.header\", \"帧头\")\n");
+    out.push_str("hf.version = ProtoField.uint8(\"dzviz.version\", \"协议版本\", base.HEX)\n");
+    out.push_str("hf.timestamp = ProtoField.uint64(\"dzviz.timestamp\", \"时间戳(毫秒)\", base.DEC)\n");
+    out.push_str("hf.receive_type = ProtoField.uint16(\"dzviz.type\", \"消息类型\", base.HEX, RECEIVE_NAMES)\n");
+    out.push_str("hf.send_type = ProtoField.uint16(\"dzviz.send_type\", \"消息类型\", base.HEX, SEND_NAMES)\n");
+    out.push_str("hf.length = ProtoField.uint32(\"dzviz.length\", \"数据长度\", base.DEC)\n");
+    out.push_str("hf.data = ProtoField.bytes(\"dzviz.data\", \"数据域\")\n");
+    out.push_str("hf.checksum = ProtoField.uint32(\"dzviz.checksum\", \"校验值\", base.HEX)\n");
+    out.push_str("hf.footer = ProtoField.bytes(\"dzviz.footer\", \"帧尾\")\n");
+    out.push_str("hf.direction = ProtoField.string(\"dzviz.direction\", \"方向\")\n");
+    for message in schema.messages() {
+        declare_fields(&mut out, &format!("dzviz.{}", message_key(message)), &message.fields);
+    }
+    out.push_str("\nlocal field_list = {}\nfor _, field in pairs(hf) do field_list[#field_list + 1] = field end\ndzviz.fields = field_list\n\n");
+
+    // 各消息数据域解析函数，按 [版本][消息类型] 登记
+    out.push_str("local RECEIVE = {}\nlocal SEND = {}\n\n");
+    for message in schema.messages().iter().filter(|m| !m.fields.is_empty()) {
+        let key = message_key(message);
+        out.push_str(&format!("-- {}\nlocal function dissect_{}(buf, tree)\n    local len = buf:len()\n", message.label, key));
+        dissect_fields(&mut out, "    ", "tree", &format!("dzviz.{}", key), "0", &message.fields);
+        out.push_str("end\n");
+        for (table, id) in [("RECEIVE", message.receive), ("SEND", message.send)] {
+            if let Some(id) = id {
+                out.push_str(&format!(
+                    "{t}[0x{v:02X}] = {t}[0x{v:02X}] or {{}}\n{t}[0x{v:02X}][0x{id:04X}] = dissect_{key}\n",
+                    t = table,
+                    v = message.version,
+                    id = id,
+                    key = key
+                ));
+            }
+        }
+        out.push('\n');
+    }
+
+    out.push_str(DISSECTOR_BODY);
+    out.push_str("\nDissectorTable.get(\"tcp.port\"):add(SERVER_PORT, dzviz)\n");
+    out
+}
+
+/// 帧解析与 TCP 重组（与具体消息无关的部分）
+const DISSECTOR_BODY: &str = r#"-- 未登记新布局的版本沿用基线版本的数据域定义
+local function find_dissector(tables, version, message_type)
+    local by_version = tables[version]
+    if by_version and by_version[message_type] then
+        return by_version[message_type]
+    end
+    return tables[BASE_VERSION] and tables[BASE_VERSION][message_type]
+end
+
+-- p2p_dir：0 发送（服务器 → 车辆），1 接收（车辆 → 服务器），-1 未知
+local function is_inbound(pinfo)
+    if pinfo.p2p_dir == 1 then return true end
+    if pinfo.p2p_dir == 0 then return false end
+    return pinfo.dst_port == SERVER_PORT
+end
+
+local function dissect_frame(buf, pinfo, tree)
+    local inbound = is_inbound(pinfo)
+    local version = buf(HEADER_LEN, 1):uint()
+    local message_type = buf(HEADER_LEN + 9, 2):le_uint()
+    local data_len = buf(HEADER_LEN + 11, 4):le_uint()
+    local checksum_len = CHECKSUM_LEN[version]
+    local names = inbound and RECEIVE_NAMES or SEND_NAMES
+    local name = names[message_type] or string.format("未知类型 0x%04X", message_type)
+    local direction = inbound and "车辆 → 服务器" or "服务器 → 车辆"
+
+    pinfo.cols.protocol = "DZ-VIZ"
+    pinfo.cols.info = string.format("%s %s (0x%04X) v0x%02X 数据 %d 字节", inbound and "接收" or "发送", name, message_type, version, data_len)
+
+    local subtree = tree:add(dzviz, buf(), "dz-viz " .. name)
+    subtree:add(hf.direction, buf(0, 0), direction):set_generated()
+    subtree:add(hf.header, buf(0, HEADER_LEN))
+    subtree:add(hf.version, buf(HEADER_LEN, 1))
+    subtree:add_le(hf.timestamp, buf(HEADER_LEN + 1, 8))
+    subtree:add_le(inbound and hf.receive_type or hf.send_type, buf(HEADER_LEN + 9, 2))
+    subtree:add_le(hf.length, buf(HEADER_LEN + 11, 4))
+    if data_len > 0 then
+        local data_tree = subtree:add(hf.data, buf(DATA_OFFSET, data_len))
+        local dissect = find_dissector(inbound and RECEIVE or SEND, version, message_type)
+        if dissect then
+            dissect(buf(DATA_OFFSET, data_len):tvb(), data_tree)
+        end
+    end
+    subtree:add_le(hf.checksum, buf(DATA_OFFSET + data_len, checksum_len))
+    subtree:add(hf.footer, buf(DATA_OFFSET + data_len + checksum_len, FOOTER_LEN))
+end
+
+function dzviz.dissector(buf, pinfo, tree)
+    local total = buf:len()
+    local offset = 0
+    while offset < total do
+        local remaining = total - offset
+        if remaining < DATA_OFFSET then
+            pinfo.desegment_offset = offset
+            pinfo.desegment_len = DESEGMENT_ONE_MORE_SEGMENT
+            return total
+        end
+        if buf(offset, HEADER_LEN):uint() ~= 0xEFEFEFEF then
+            return offset
+        end
+        local checksum_len = CHECKSUM_LEN[buf(offset + HEADER_LEN, 1):uint()]
+        if not checksum_len then
+            return offset
+        end
+        local frame_len = DATA_OFFSET + buf(offset + HEADER_LEN + 11, 4):le_uint() + checksum_len + FOOTER_LEN
+        if remaining < frame_len then
+            pinfo.desegment_offset = offset
+            pinfo.desegment_len = frame_len - remaining
+            return total
+        end
+        dissect_frame(buf(offset, frame_len):tvb(), pinfo, tree)
+        offset = offset + frame_len
+    end
+    return offset
+end
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_dissector_covers_schema() {
+        let lua = generate_lua_dissector(9000);
+        assert!(lua.contains("local dzviz = Proto(\"dzviz\""));
+        assert!(lua.contains("local SERVER_PORT = 9000"));
+        assert!(lua.contains("[0x10] = 2,") && !lua.contains("[0x11]"));
+        assert!(lua.contains("local DATA_OFFSET = 19"));
+
+        // 收发编号分表，字段偏移取自协议定义
+        assert!(lua.contains("    [0x0002] = \"车辆信息\","));
+        assert!(lua.contains("    [0x100B] = \"接入认证结果\","));
+        assert!(lua.contains(&format!(
+            "if len >= {} then tree:add_le(hf[\"dzviz.vehicle_info.gear\"], buf({}, 1)) end",
+            ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET + 1,
+            ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET
+        )));
+        assert!(lua.contains("SEND[0x10][0x1001] = dissect_vehicle_control"));
+        assert!(lua.contains("[4] = \"初始化位姿\""));
+        assert!(lua.contains("hf[\"dzviz.sandbox_traffic_light_status.lights.remaining\"]"));
+        assert!(lua.contains("if len > 1 then tree:add(hf[\"dzviz.path_file_selection.path_file_ids\"], buf(1, len - 1)) end"));
+
+        // Lua 块配对（function/if/for 各自以 end 结束）
+        let opens = lua
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("--"))
+            .map(|line| {
+                let words: Vec<&str> = line.split(|c: char| !c.is_alphanumeric() && c != '_').collect();
+                let opens = words.iter().filter(|w| matches!(**w, "function" | "if" | "for" | "while")).count();
+                let ends = words.iter().filter(|w| **w == "end").count();
+                opens as i64 - ends as i64
+            })
+            .sum::<i64>();
+        assert_eq!(opens, 0);
+    }
+}
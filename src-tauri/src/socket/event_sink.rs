@@ -0,0 +1,101 @@
+//! Socket 服务事件出口
+//!
+//! 将 Socket 服务与 Tauri 窗口解耦：图形界面模式下事件直接推送到前端，
+//! 无界面服务模式下事件通过广播通道分发给任意订阅者（例如事件流 WebSocket）。
+
+use crate::database::VehicleDatabase;
+#[cfg(feature = "gui")]
+use log::error;
+use serde::Serialize;
+use std::sync::Arc;
+#[cfg(feature = "gui")]
+use tauri::{Emitter, Manager};
+use tokio::sync::broadcast;
+
+/// 事件出口：Socket 服务通过它推送事件并获取数据库实例
+pub trait EventSink: Send + Sync {
+    /// 推送事件
+    fn emit(&self, event: &str, payload: serde_json::Value);
+
+    /// 获取数据库实例（未就绪时返回 None）
+    fn database(&self) -> Option<VehicleDatabase>;
+}
+
+/// 共享事件出口
+pub type SharedEventSink = Arc<dyn EventSink>;
+
+#[cfg(feature = "gui")]
+impl EventSink for tauri::AppHandle {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        if let Err(e) = Emitter::emit(self, event, payload) {
+            error!("发送事件 {} 到前端失败: {}", event, e);
+        }
+    }
+
+    fn database(&self) -> Option<VehicleDatabase> {
+        self.try_state::<VehicleDatabase>().map(|db| db.inner().clone())
+    }
+}
+
+/// 广播事件
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+/// 广播事件出口（无界面服务模式）
+pub struct BroadcastEventSink {
+    sender: broadcast::Sender<ServerEvent>,
+    database: Option<VehicleDatabase>,
+}
+
+impl BroadcastEventSink {
+    pub fn new(capacity: usize, database: Option<VehicleDatabase>) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender, database }
+    }
+
+    /// 订阅事件
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl EventSink for BroadcastEventSink {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        // 没有订阅者时发送失败属于正常情况
+        let _ = self.sender.send(ServerEvent {
+            event: event.to_string(),
+            payload,
+        });
+    }
+
+    fn database(&self) -> Option<VehicleDatabase> {
+        self.database.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_sink_delivers_to_subscribers() {
+        let sink = BroadcastEventSink::new(8, None);
+        let mut rx = sink.subscribe();
+
+        sink.emit("vehicle-connect", serde_json::json!({"vehicle_id": 1}));
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.event, "vehicle-connect");
+        assert_eq!(event.payload["vehicle_id"], 1);
+        assert!(sink.database().is_none());
+    }
+
+    #[test]
+    fn test_broadcast_sink_without_subscribers() {
+        let sink = BroadcastEventSink::new(8, None);
+        sink.emit("socket-message", serde_json::json!({}));
+    }
+}
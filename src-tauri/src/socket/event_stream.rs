@@ -0,0 +1,85 @@
+//! 事件流 WebSocket 服务
+//!
+//! 无界面服务模式下将 Socket 服务事件以 JSON 文本帧推送给连接的客户端，
+//! 图形界面可作为其中一个订阅者接入。
+
+use super::event_sink::BroadcastEventSink;
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 启动事件流 WebSocket 服务器
+pub async fn start_event_stream_server(port: u16, sink: Arc<BroadcastEventSink>) -> Result<()> {
+    let addr = format!("0.0.0.0:{}", port);
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => {
+            log::info!("✅ 事件流 WebSocket 服务器绑定成功: {}", addr);
+            listener
+        }
+        Err(e) => {
+            log::error!("❌ 绑定事件流 WebSocket 服务器失败 {}: {}", addr, e);
+            return Err(e.into());
+        }
+    };
+
+    tokio::spawn(async move {
+        while let Ok((stream, peer)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, peer, sink.clone()));
+        }
+    });
+
+    Ok(())
+}
+
+/// 处理单个订阅连接
+async fn handle_connection(stream: TcpStream, peer: SocketAddr, sink: Arc<BroadcastEventSink>) {
+    let ws_stream = match accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            log::error!("事件流 WebSocket 握手失败 ({}): {}", peer, e);
+            return;
+        }
+    };
+    log::info!("🔌 事件流订阅者已连接: {}", peer);
+
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    let mut events = sink.subscribe();
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                match event {
+                    Ok(event) => {
+                        let text = match serde_json::to_string(&event) {
+                            Ok(text) => text,
+                            Err(e) => {
+                                log::warn!("事件序列化失败: {}", e);
+                                continue;
+                            }
+                        };
+                        if ws_sender.send(Message::Text(text)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("事件流订阅者 {} 处理过慢，丢弃 {} 条事件", peer, skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            incoming = ws_receiver.next() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    log::info!("🔌 事件流订阅者已断开: {}", peer);
+}
@@ -0,0 +1,334 @@
+//! 车辆状态合并推送
+//!
+//! 车辆信息帧不再逐帧推送 `socket-message`：每辆车只保留最近一次解析结果，
+//! 按 `PerformanceConfig::target_fps` 的频率合并为一个 `fleet-update` 事件推送，
+//! 避免多车高频上报时前端事件积压。每帧先写入 [`FleetState`]，与已有状态相同的帧、
+//! 同一周期内被新帧覆盖的帧计为合并帧；车辆在推送前断开时其待推送状态计为丢弃帧。
+//! 每辆车附带本周期内变化过的字段名，原始数据域字节仅在 `debug_raw_frames` 开启时附带。
+
+use super::event_sink::SharedEventSink;
+use crate::config::PerformanceConfig;
+use crate::protocol_processing::types::VehicleInfo;
+use crate::services::fleet_state::FleetState;
+use log::debug;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// 合并推送统计
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct FleetUpdateStats {
+    /// 收到的车辆信息帧数
+    pub received_frames: u64,
+    /// 内容未变化或被同周期新帧覆盖的帧数
+    pub merged_frames: u64,
+    /// 车辆断开时尚未推送而丢弃的帧数
+    pub dropped_frames: u64,
+    /// 已推送的 `fleet-update` 事件数
+    pub emitted_updates: u64,
+}
+
+/// 待推送的车辆状态
+struct PendingVehicle {
+    vehicle_name: String,
+    timestamp: u64,
+    parsed: serde_json::Value,
+    changed_fields: BTreeSet<&'static str>,
+    data: Option<Vec<u8>>,
+    /// 最近一帧超出协议取值范围的字段
+    warnings: Vec<String>,
+}
+
+#[derive(Default)]
+struct FleetBuffer {
+    /// 本周期待推送的状态（按车辆编号排序输出）
+    pending: BTreeMap<u8, PendingVehicle>,
+    stats: FleetUpdateStats,
+}
+
+struct FleetInner {
+    sink: SharedEventSink,
+    state: FleetState,
+    include_raw: bool,
+    buffer: Mutex<FleetBuffer>,
+}
+
+/// 车辆状态合并推送器
+#[derive(Clone)]
+pub struct FleetUpdateEmitter {
+    inner: Arc<FleetInner>,
+}
+
+impl FleetUpdateEmitter {
+    /// 启动按 `target_fps` 周期推送的后台任务；所有实例释放后任务自动结束
+    pub fn start(sink: SharedEventSink, config: &PerformanceConfig, state: FleetState) -> Self {
+        let emitter = Self {
+            inner: Arc::new(FleetInner {
+                sink,
+                state,
+                include_raw: config.debug_raw_frames,
+                buffer: Mutex::new(FleetBuffer::default()),
+            }),
+        };
+
+        let period = Duration::from_secs_f64(1.0 / f64::from(config.target_fps.max(1)));
+        let inner = Arc::downgrade(&emitter.inner);
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                timer.tick().await;
+                match inner.upgrade() {
+                    Some(inner) => FleetUpdateEmitter { inner }.flush(),
+                    None => break,
+                }
+            }
+        });
+
+        emitter
+    }
+
+    /// 推送器写入的车队状态
+    pub fn state(&self) -> &FleetState {
+        &self.inner.state
+    }
+
+    /// 记录一帧车辆信息，内容有变化时进入下一次推送（`warnings` 为取值范围告警，随状态推送）
+    pub fn update(&self, vehicle_name: &str, info: VehicleInfo, timestamp: u64, data: &[u8], warnings: Vec<String>) {
+        let changes = self.inner.state.update_info(i32::from(info.vehicle_id), vehicle_name, &info, timestamp);
+        let mut buffer = self.inner.buffer.lock();
+        buffer.stats.received_frames += 1;
+
+        if changes.is_empty() {
+            debug!("车辆 {} (ID: {}) 数据未变化，跳过更新", vehicle_name, info.vehicle_id);
+            buffer.stats.merged_frames += 1;
+            return;
+        }
+
+        let mut pending = PendingVehicle {
+            vehicle_name: vehicle_name.to_string(),
+            timestamp,
+            parsed: vehicle_info_payload(&info),
+            changed_fields: changes.iter().map(|change| change.field).collect(),
+            data: self.inner.include_raw.then(|| data.to_vec()),
+            warnings,
+        };
+        if let Some(previous) = buffer.pending.remove(&info.vehicle_id) {
+            pending.changed_fields.extend(previous.changed_fields);
+            buffer.stats.merged_frames += 1;
+        }
+        buffer.pending.insert(info.vehicle_id, pending);
+    }
+
+    /// 立即推送本周期累积的车辆状态
+    pub fn flush(&self) {
+        // 持锁推送，保证各批次按顺序到达前端
+        let mut buffer = self.inner.buffer.lock();
+        if buffer.pending.is_empty() {
+            return;
+        }
+        buffer.stats.emitted_updates += 1;
+
+        let vehicles: Vec<serde_json::Value> = std::mem::take(&mut buffer.pending)
+            .into_iter()
+            .map(|(vehicle_id, pending)| {
+                let mut vehicle = serde_json::json!({
+                    "vehicle_id": vehicle_id,
+                    "vehicle_name": pending.vehicle_name,
+                    "timestamp": pending.timestamp,
+                    "parsed": pending.parsed,
+                    "changed_fields": pending.changed_fields,
+                });
+                if let Some(data) = pending.data {
+                    vehicle["data"] = serde_json::json!(data);
+                }
+                if !pending.warnings.is_empty() {
+                    vehicle["warnings"] = serde_json::json!(pending.warnings);
+                }
+                vehicle
+            })
+            .collect();
+
+        self.inner.sink.emit("fleet-update", serde_json::json!({
+            "type": "fleet_update",
+            "timestamp": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            "vehicles": vehicles,
+            "stats": buffer.stats,
+        }));
+    }
+
+    /// 车辆断开：丢弃未推送的状态，并移除车队状态以便重连后立即推送
+    ///
+    /// `addr` 不是该车辆当前连接（已被新连接取代）时不做处理，返回 false
+    pub fn remove(&self, vehicle_id: i32, addr: &str) -> bool {
+        if !self.inner.state.disconnect(vehicle_id, addr) {
+            return false;
+        }
+        let Ok(vehicle_id) = u8::try_from(vehicle_id) else {
+            return true;
+        };
+        let mut buffer = self.inner.buffer.lock();
+        if buffer.pending.remove(&vehicle_id).is_some() {
+            buffer.stats.dropped_frames += 1;
+        }
+        true
+    }
+
+    /// 清除所有车辆状态（回放跳转时使用）
+    pub fn clear(&self) {
+        self.inner.state.clear();
+        let mut buffer = self.inner.buffer.lock();
+        buffer.stats.dropped_frames += std::mem::take(&mut buffer.pending).len() as u64;
+    }
+
+    /// 获取合并推送统计（运行时随每次推送附带在事件中）
+    #[cfg(test)]
+    pub fn stats(&self) -> FleetUpdateStats {
+        self.inner.buffer.lock().stats
+    }
+}
+
+/// 车辆信息推送内容（前端 `updateVehicleInfoFromParsed` 的输入）
+fn vehicle_info_payload(info: &VehicleInfo) -> serde_json::Value {
+    let gear = info.gear;
+    let camera_status = info.sensors.camera;
+    let lidar_status = info.sensors.lidar;
+    let gyro_status = info.sensors.gyro;
+
+    serde_json::json!({
+        "vehicle_id": info.vehicle_id,
+        "speed": info.speed,
+        "position": {"x": info.position_x, "y": info.position_y},
+        "orientation": info.orientation,
+        "battery": info.battery,
+        "gear": {
+            "value": gear.to_u8(),
+            "label": gear.label(),
+        },
+        "steeringAngle": info.steering_angle,
+        "navigation": {
+            "code": info.nav_status,
+            "text": nav_status_text(info.nav_status),
+        },
+        "sensors": {
+            "camera": {
+                "status": camera_status,
+                "text": if camera_status { "正常" } else { "异常" }
+            },
+            "lidar": {
+                "status": lidar_status,
+                "text": if lidar_status { "正常" } else { "异常" }
+            },
+            "gyro": {
+                "status": gyro_status,
+                "text": if gyro_status { "正常" } else { "异常" }
+            }
+        },
+        "parkingSlot": info.parking_slot
+    })
+}
+
+/// 导航状态说明
+pub fn nav_status_text(code: u8) -> &'static str {
+    match code {
+        1 => "正常行驶中（空载模式不倒车入库）",
+        2 => "正常行驶中（空载模式倒车入库）",
+        3 => "接客模式，去起点接客",
+        4 => "接客模式，去终点送客",
+        5 => "去往充电车位",
+        6 => "充电中",
+        7 => "去往停车位路上",
+        8 => "车位停车中",
+        9 => "到达接客起点",
+        10 => "到达接客终点",
+        11 => "正在倒车入库",
+        12 => "正在出库中",
+        13 => "正在倒车入库",
+        14 => "出库完成",
+        15 => "平行驾驶模式",
+        _ => "未知状态",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support;
+
+    fn sample_info(vehicle_id: u8, speed: f64) -> VehicleInfo {
+        VehicleInfo { speed, position_x: 1.0, position_y: 2.0, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    fn emitter(sink: &Arc<BroadcastEventSink>, fps: u32, debug_raw_frames: bool) -> FleetUpdateEmitter {
+        let config = PerformanceConfig { target_fps: fps, debug_raw_frames, ..PerformanceConfig::default() };
+        FleetUpdateEmitter::start(sink.clone(), &config, FleetState::default())
+    }
+
+    #[tokio::test]
+    async fn test_latest_state_per_vehicle_in_one_event() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        // 周期足够长，由测试手动推送
+        let fleet = emitter(&sink, 1, false);
+
+        fleet.update("车1", sample_info(1, 0.1), 1, &[1], Vec::new());
+        fleet.update("车1", sample_info(1, 0.2), 2, &[1], Vec::new());
+        fleet.update("车1", sample_info(1, 0.2), 3, &[1], Vec::new());
+        fleet.update("车2", sample_info(2, 0.3), 4, &[2], vec!["battery 越界".to_string()]);
+        fleet.flush();
+        fleet.flush();
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event, "fleet-update");
+        let vehicles = event.payload["vehicles"].as_array().unwrap();
+        assert_eq!(vehicles.len(), 2);
+        assert_eq!((vehicles[0]["vehicle_id"].as_u64(), vehicles[0]["timestamp"].as_u64()), (Some(1), Some(2)));
+        assert_eq!(vehicles[0]["parsed"]["speed"], 0.2);
+        assert!(vehicles[0].get("data").is_none());
+        assert!(vehicles[0].get("warnings").is_none());
+        assert_eq!(vehicles[1]["warnings"], serde_json::json!(["battery 越界"]));
+        assert_eq!(vehicles[0]["changed_fields"].as_array().unwrap().len(), 12);
+        assert_eq!(event.payload["stats"]["merged_frames"], 2);
+        // 没有新状态时不推送空事件
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remove_drops_pending_and_resets_dedup() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let fleet = emitter(&sink, 1, true);
+
+        fleet.update("车3", sample_info(3, 0.1), 1, &[3, 0], Vec::new());
+        assert!(fleet.remove(3, "127.0.0.1:6000"));
+        fleet.flush();
+        assert!(events.try_recv().is_err());
+
+        // 重连后相同内容也要推送
+        fleet.update("车3", sample_info(3, 0.1), 2, &[3, 0], Vec::new());
+        fleet.flush();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.payload["vehicles"][0]["data"], serde_json::json!([3, 0]));
+
+        let stats = fleet.stats();
+        assert_eq!((stats.received_frames, stats.merged_frames, stats.dropped_frames, stats.emitted_updates), (2, 0, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_periodic_flush() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let fleet = emitter(&sink, 50, false);
+
+        fleet.update("车4", sample_info(4, 0.5), 1, &[], Vec::new());
+        let event = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+        assert_eq!(event.payload["vehicles"][0]["vehicle_id"], 4);
+    }
+}
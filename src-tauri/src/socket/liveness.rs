@@ -0,0 +1,245 @@
+//! 车辆连接活性跟踪
+//!
+//! 记录每个车辆连接最近一次心跳（0x0001）与车辆信息（0x0002）的时间。
+//! 连续 N 个心跳周期无数据时判定为失联（`vehicle-stale`），超过超时时间后由
+//! `handle_client` 主动断开，避免半开 TCP 连接长时间占用车辆编号。
+
+use crate::config::NetworkConfig;
+use crate::protocol_processing::types::MessageTypes;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 尚未收到过该类消息
+const NEVER: u64 = u64::MAX;
+
+/// 活性判定参数
+#[derive(Debug, Clone, Copy)]
+pub struct LivenessPolicy {
+    /// 心跳周期（同时是服务器心跳的发送间隔）
+    pub interval: Duration,
+    /// 无数据超过该时长判定为失联
+    pub stale_after: Duration,
+    /// 无数据超过该时长强制断开
+    pub timeout: Duration,
+}
+
+impl LivenessPolicy {
+    pub fn from_config(config: &NetworkConfig) -> Self {
+        let interval = Duration::from_millis(config.heartbeat_interval.max(100) as u64);
+        let stale_after = interval * config.heartbeat_stale_misses.max(1);
+        Self {
+            interval,
+            stale_after,
+            timeout: Duration::from_millis(config.heartbeat_timeout as u64).max(stale_after),
+        }
+    }
+}
+
+/// 单次检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LivenessCheck {
+    Alive,
+    /// 本次检查刚进入失联状态
+    BecameStale,
+    /// 仍处于失联状态
+    Stale,
+    TimedOut,
+}
+
+/// 连接活性（随 `ClientConnection` 共享）
+#[derive(Debug)]
+pub struct ConnectionLiveness {
+    connected_at: Instant,
+    /// 相对 `connected_at` 的毫秒数
+    last_heartbeat: AtomicU64,
+    last_vehicle_info: AtomicU64,
+    stale: AtomicBool,
+}
+
+impl Default for ConnectionLiveness {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl ConnectionLiveness {
+    pub fn new(connected_at: Instant) -> Self {
+        Self {
+            connected_at,
+            last_heartbeat: AtomicU64::new(NEVER),
+            last_vehicle_info: AtomicU64::new(NEVER),
+            stale: AtomicBool::new(false),
+        }
+    }
+
+    fn offset(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.connected_at).as_millis() as u64
+    }
+
+    /// 记录收到的消息；返回连接是否由失联恢复
+    pub fn record(&self, message_type: u16, now: Instant) -> bool {
+        let offset = self.offset(now);
+        match message_type {
+            MessageTypes::HEARTBEAT => self.last_heartbeat.store(offset, Ordering::Relaxed),
+            MessageTypes::VEHICLE_INFO => self.last_vehicle_info.store(offset, Ordering::Relaxed),
+            // 其他上行消息同样说明链路可用，按心跳计
+            _ => self.last_heartbeat.store(offset, Ordering::Relaxed),
+        }
+        self.stale.swap(false, Ordering::Relaxed)
+    }
+
+    /// 距最近一次心跳的时长（未收到过时为 None）
+    pub fn since_heartbeat(&self, now: Instant) -> Option<Duration> {
+        self.since(&self.last_heartbeat, now)
+    }
+
+    /// 距最近一次车辆信息的时长（未收到过时为 None）
+    pub fn since_vehicle_info(&self, now: Instant) -> Option<Duration> {
+        self.since(&self.last_vehicle_info, now)
+    }
+
+    fn since(&self, value: &AtomicU64, now: Instant) -> Option<Duration> {
+        match value.load(Ordering::Relaxed) {
+            NEVER => None,
+            offset => Some(Duration::from_millis(self.offset(now).saturating_sub(offset))),
+        }
+    }
+
+    /// 距最近一次收到任何数据的时长（未收到过时从建立连接算起）
+    pub fn idle(&self, now: Instant) -> Duration {
+        [self.since_heartbeat(now), self.since_vehicle_info(now)]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or_else(|| now.saturating_duration_since(self.connected_at))
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self, policy: &LivenessPolicy, now: Instant) -> LivenessCheck {
+        let idle = self.idle(now);
+        if idle >= policy.timeout {
+            LivenessCheck::TimedOut
+        } else if idle >= policy.stale_after {
+            if self.stale.swap(true, Ordering::Relaxed) {
+                LivenessCheck::Stale
+            } else {
+                LivenessCheck::BecameStale
+            }
+        } else {
+            LivenessCheck::Alive
+        }
+    }
+
+    /// 供事件与状态查询使用的毫秒数
+    pub fn to_json(&self, now: Instant) -> serde_json::Value {
+        let millis = |d: Option<Duration>| d.map(|d| d.as_millis() as u64);
+        serde_json::json!({
+            "stale": self.is_stale(),
+            "last_heartbeat_ms_ago": millis(self.since_heartbeat(now)),
+            "last_vehicle_info_ms_ago": millis(self.since_vehicle_info(now)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VehicleAuthConfig;
+    use crate::socket::protocol::{build_message, ProtocolParser};
+    use crate::socket::{BroadcastEventSink, SocketServer};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_stale_recover_and_timeout() {
+        let start = Instant::now();
+        let policy = LivenessPolicy::from_config(&NetworkConfig {
+            heartbeat_interval: 1000,
+            heartbeat_stale_misses: 3,
+            heartbeat_timeout: 5000,
+            ..NetworkConfig::default()
+        });
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let liveness = ConnectionLiveness::new(start);
+
+        assert_eq!(liveness.check(&policy, at(2500)), LivenessCheck::Alive);
+        assert!(!liveness.record(MessageTypes::VEHICLE_INFO, at(2500)));
+        assert_eq!(liveness.since_heartbeat(at(2600)), None);
+        assert_eq!(liveness.since_vehicle_info(at(2600)), Some(Duration::from_millis(100)));
+
+        assert_eq!(liveness.check(&policy, at(5400)), LivenessCheck::Alive);
+        assert_eq!(liveness.check(&policy, at(5500)), LivenessCheck::BecameStale);
+        assert_eq!(liveness.check(&policy, at(6000)), LivenessCheck::Stale);
+
+        // 收到心跳后恢复
+        assert!(liveness.record(MessageTypes::HEARTBEAT, at(6000)));
+        assert!(!liveness.is_stale());
+        assert_eq!(liveness.idle(at(6200)), Duration::from_millis(200));
+
+        assert_eq!(liveness.check(&policy, at(11_000)), LivenessCheck::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_silent_vehicle_goes_stale_then_disconnects() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, None));
+        let mut events = sink.subscribe();
+        let server = SocketServer::new(port, sink.clone())
+            .with_vehicle_auth(VehicleAuthConfig {
+                required: false,
+                handshake_timeout: 100,
+            })
+            .with_network_config(NetworkConfig {
+                heartbeat_interval: 100,
+                heartbeat_stale_misses: 2,
+                heartbeat_timeout: 600,
+                ..NetworkConfig::default()
+            });
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // 发送一帧心跳后保持沉默，但不关闭连接（模拟半开连接）
+        let mut client = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        client.write_all(&build_message(MessageTypes::HEARTBEAT, &[])).await.unwrap();
+
+        let sequence = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut sequence = Vec::new();
+            while sequence.last().map(String::as_str) != Some("vehicle-disconnect") {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                if event.event.starts_with("vehicle-") {
+                    sequence.push(event.event);
+                }
+            }
+            sequence
+        })
+        .await
+        .unwrap();
+        assert_eq!(sequence, vec!["vehicle-connect", "vehicle-stale", "vehicle-disconnect"]);
+
+        // 失联期间服务器持续下发心跳，超时后关闭连接
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        loop {
+            match tokio::time::timeout(Duration::from_secs(2), client.read(&mut buffer)).await {
+                Ok(Ok(n)) if n > 0 => received.extend_from_slice(&buffer[..n]),
+                _ => break,
+            }
+        }
+        let mut parser = ProtocolParser::new();
+        parser.feed_data(&received);
+        let mut heartbeats = 0;
+        while let Ok(Some(message)) = parser.try_parse_message() {
+            if message.message_type == MessageTypes::HEARTBEAT {
+                heartbeats += 1;
+            }
+        }
+        assert!(heartbeats >= 2);
+    }
+}
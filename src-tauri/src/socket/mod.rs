@@ -1,4 +1,21 @@
+pub mod ack;
+pub mod auth;
+pub mod capture;
+pub mod codec;
+pub mod dissector;
+pub mod event_sink;
+pub mod event_stream;
+pub mod fleet_update;
+pub mod liveness;
+pub mod pcapng;
 pub mod protocol;
+pub mod replay;
+pub mod send_queue;
 pub mod server;
+pub mod telemetry;
+pub mod tls;
 
-pub use server::{SocketServer, ConnectionManager, SandboxConnectionManager};
\ No newline at end of file
+pub use capture::SessionCapture;
+pub use event_sink::BroadcastEventSink;
+pub use replay::ReplayController;
+pub use server::{SocketServer, ConnectionManager, SandboxConnectionManager};
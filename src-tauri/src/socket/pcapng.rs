@@ -0,0 +1,411 @@
+//! pcapng 抓包导出
+//!
+//! 与会话抓包（原始字节流，供回放）不同，这里按连接和方向记录，配合 `dissector.rs`
+//! 生成的 Lua 解析器在 Wireshark 中查看。
+//!
+//! - 每个连接一个接口描述块：if_name 为车辆/沙盘名称，if_description 为角色、编号与地址
+//! - 接收方向记录每次读到的原始字节（解码之前，校验失败或不完整的帧也照原样保留；
+//!   一个分组可能含多帧，Lua 解析器逐帧拆分），发送方向记录下发的完整帧
+//! - 分组以 Exported PDU（链路类型 252）封装，携带解析器名 `dzviz` 与对端 IP/端口，
+//!   打开文件即按 dz-viz 协议解析，无需配置端口
+//! - 增强分组块的 epb_flags 标注方向，注释记录车辆编号、方向与消息类型
+//! - 文件写入在独立的写入线程完成，记录方只把分组送入有界队列，队列满时丢弃并计数
+
+use super::capture::{CaptureConnection, ConnectionRole};
+use super::protocol::HEADER;
+use log::{error, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wireshark 解析器名称（与 Lua 解析器的 Proto 名一致）
+pub const DISSECTOR_NAME: &str = "dzviz";
+/// LINKTYPE_WIRESHARK_UPPER_PDU
+pub const LINKTYPE_UPPER_PDU: u16 = 252;
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE: u32 = 0x0000_0001;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_SHB_USERAPPL: u16 = 4;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_DESCRIPTION: u16 = 3;
+const OPT_EPB_FLAGS: u16 = 2;
+
+// Exported PDU 标签（大端序）
+const EXP_PDU_TAG_END: u16 = 0;
+const EXP_PDU_TAG_DISSECTOR_NAME: u16 = 12;
+const EXP_PDU_TAG_IPV4_SRC: u16 = 20;
+const EXP_PDU_TAG_IPV4_DST: u16 = 21;
+const EXP_PDU_TAG_IPV6_SRC: u16 = 22;
+const EXP_PDU_TAG_IPV6_DST: u16 = 23;
+const EXP_PDU_TAG_PORT_TYPE: u16 = 24;
+const EXP_PDU_TAG_SRC_PORT: u16 = 25;
+const EXP_PDU_TAG_DST_PORT: u16 = 26;
+const PORT_TYPE_TCP: u32 = 2;
+
+/// 写入队列容量（分组数）
+const EXPORT_QUEUE_CAPACITY: usize = 4096;
+
+/// 帧方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketDirection {
+    /// 车辆/沙盘 → 服务器
+    Inbound,
+    /// 服务器 → 车辆/沙盘
+    Outbound,
+}
+
+impl PacketDirection {
+    /// epb_flags 方向位（01 入站，10 出站）
+    fn flags(self) -> u32 {
+        match self {
+            Self::Inbound => 0b01,
+            Self::Outbound => 0b10,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Inbound => "接收",
+            Self::Outbound => "发送",
+        }
+    }
+}
+
+/// 从完整帧中取消息类型
+pub fn frame_message_type(frame: &[u8]) -> Option<u16> {
+    let offset = HEADER.len() + 1 + 8;
+    frame.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn push_option(out: &mut Vec<u8>, code: u16, value: &[u8]) {
+    let value = &value[..value.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&code.to_le_bytes());
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value);
+    pad32(out);
+}
+
+fn pad32(out: &mut Vec<u8>) {
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+}
+
+/// 组装块：类型 + 总长度 + 块体 + 总长度
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let total = (body.len() + 12) as u32;
+    let mut out = Vec::with_capacity(total as usize);
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(&total.to_le_bytes());
+    out
+}
+
+/// 节头块
+pub fn section_header() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&(-1i64).to_le_bytes()); // 节长度未知
+    push_option(&mut body, OPT_SHB_USERAPPL, b"dz-viz");
+    push_option(&mut body, OPT_END, &[]);
+    block(BLOCK_SECTION_HEADER, &body)
+}
+
+/// 接口描述块（每个连接一个）
+pub fn interface_description(connection: &CaptureConnection) -> Vec<u8> {
+    let role = match connection.role {
+        ConnectionRole::Vehicle => "车辆",
+        ConnectionRole::Sandbox => "沙盘",
+    };
+    let description = format!("{} {} {}", role, connection.vehicle_id, connection.addr);
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&LINKTYPE_UPPER_PDU.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes()); // 不截断
+    push_option(&mut body, OPT_IF_NAME, connection.vehicle_name.as_bytes());
+    push_option(&mut body, OPT_IF_DESCRIPTION, description.as_bytes());
+    push_option(&mut body, OPT_END, &[]);
+    block(BLOCK_INTERFACE, &body)
+}
+
+fn push_tag(out: &mut Vec<u8>, tag: u16, value: &[u8]) {
+    out.extend_from_slice(&tag.to_be_bytes());
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value);
+    pad32(out);
+}
+
+/// Exported PDU 封装：标签（解析器名、对端地址）+ 完整帧
+pub fn exported_pdu(frame: &[u8], peer: Option<SocketAddr>, direction: PacketDirection) -> Vec<u8> {
+    let mut out = Vec::with_capacity(frame.len() + 48);
+    push_tag(&mut out, EXP_PDU_TAG_DISSECTOR_NAME, DISSECTOR_NAME.as_bytes());
+    if let Some(peer) = peer {
+        let inbound = direction == PacketDirection::Inbound;
+        match peer.ip() {
+            IpAddr::V4(ip) => {
+                let tag = if inbound { EXP_PDU_TAG_IPV4_SRC } else { EXP_PDU_TAG_IPV4_DST };
+                push_tag(&mut out, tag, &ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                let tag = if inbound { EXP_PDU_TAG_IPV6_SRC } else { EXP_PDU_TAG_IPV6_DST };
+                push_tag(&mut out, tag, &ip.octets());
+            }
+        }
+        push_tag(&mut out, EXP_PDU_TAG_PORT_TYPE, &PORT_TYPE_TCP.to_be_bytes());
+        let tag = if inbound { EXP_PDU_TAG_SRC_PORT } else { EXP_PDU_TAG_DST_PORT };
+        push_tag(&mut out, tag, &u32::from(peer.port()).to_be_bytes());
+    }
+    push_tag(&mut out, EXP_PDU_TAG_END, &[]);
+    out.extend_from_slice(frame);
+    out
+}
+
+/// 增强分组块（时间戳为 Unix 微秒）
+pub fn enhanced_packet(interface_id: u32, timestamp_us: u64, packet: &[u8], direction: PacketDirection, comment: &str) -> Vec<u8> {
+    let mut body = Vec::with_capacity(packet.len() + comment.len() + 40);
+    body.extend_from_slice(&interface_id.to_le_bytes());
+    body.extend_from_slice(&((timestamp_us >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(timestamp_us as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    body.extend_from_slice(packet);
+    pad32(&mut body);
+    push_option(&mut body, OPT_EPB_FLAGS, &direction.flags().to_le_bytes());
+    push_option(&mut body, OPT_COMMENT, comment.as_bytes());
+    push_option(&mut body, OPT_END, &[]);
+    block(BLOCK_ENHANCED_PACKET, &body)
+}
+
+/// 正在写入的 pcapng 文件
+struct PcapngWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// 连接序号 → 接口编号（首个帧到达时写接口描述块）
+    interfaces: HashMap<u32, u32>,
+    packets: u64,
+}
+
+impl PcapngWriter {
+    fn create(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建抓包目录失败: {}", e))?;
+        }
+        let file = File::create(path).map_err(|e| format!("创建抓包文件失败 {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&section_header())
+            .map_err(|e| format!("写入 pcapng 节头失败: {}", e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            interfaces: HashMap::new(),
+            packets: 0,
+        })
+    }
+
+    /// 写入一个分组
+    fn write_packet(&mut self, packet: &CapturedPacket) -> std::io::Result<()> {
+        let CapturedPacket { index, ref connection, vehicle_id, direction, timestamp_us, ref frame } = *packet;
+        let next_id = self.interfaces.len() as u32;
+        let interface_id = match self.interfaces.get(&index) {
+            Some(id) => *id,
+            None => {
+                self.writer.write_all(&interface_description(connection))?;
+                self.interfaces.insert(index, next_id);
+                next_id
+            }
+        };
+
+        let role = match connection.role {
+            ConnectionRole::Vehicle => "车辆",
+            ConnectionRole::Sandbox => "沙盘",
+        };
+        let message_type = frame.starts_with(&HEADER).then(|| frame_message_type(frame)).flatten();
+        let comment = match message_type {
+            Some(message_type) => format!(
+                "{} {} ({}) {} 0x{:04X}",
+                role, vehicle_id, connection.vehicle_name, direction.label(), message_type
+            ),
+            None => format!("{} {} ({}) {}", role, vehicle_id, connection.vehicle_name, direction.label()),
+        };
+        let pdu = exported_pdu(frame, connection.addr.parse().ok(), direction);
+        self.writer
+            .write_all(&enhanced_packet(interface_id, timestamp_us, &pdu, direction, &comment))?;
+        self.packets += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<(PathBuf, u64)> {
+        self.writer.flush()?;
+        Ok((self.path, self.packets))
+    }
+}
+
+/// 送往写入线程的分组
+struct CapturedPacket {
+    /// 连接序号
+    index: u32,
+    connection: Arc<CaptureConnection>,
+    /// 记录时连接对应的车辆编号（沙盘为沙盘ID）
+    vehicle_id: i32,
+    direction: PacketDirection,
+    /// 记录时刻（Unix 微秒）
+    timestamp_us: u64,
+    frame: Vec<u8>,
+}
+
+/// 进行中的 pcapng 导出：文件 I/O 在写入线程完成，记录方不等待磁盘
+pub(crate) struct PcapngExport {
+    path: PathBuf,
+    sender: SyncSender<CapturedPacket>,
+    worker: JoinHandle<std::io::Result<(PathBuf, u64)>>,
+    /// 队列满而丢弃的分组数
+    dropped: u64,
+}
+
+impl PcapngExport {
+    /// 创建文件并启动写入线程
+    pub(crate) fn start(path: &Path) -> Result<Self, String> {
+        let mut writer = PcapngWriter::create(path)?;
+        let (sender, receiver) = mpsc::sync_channel::<CapturedPacket>(EXPORT_QUEUE_CAPACITY);
+        let worker = std::thread::Builder::new()
+            .name("pcapng-writer".to_string())
+            .spawn(move || {
+                for packet in receiver {
+                    if let Err(e) = writer.write_packet(&packet) {
+                        error!("写入 pcapng 失败 {}: {}", writer.path.display(), e);
+                    }
+                }
+                writer.finish()
+            })
+            .map_err(|e| format!("启动 pcapng 写入线程失败: {}", e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            sender,
+            worker,
+            dropped: 0,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 分组送入写入队列（不阻塞；队列满时丢弃）
+    pub(crate) fn record(
+        &mut self,
+        index: u32,
+        connection: Arc<CaptureConnection>,
+        vehicle_id: i32,
+        direction: PacketDirection,
+        frame: &[u8],
+    ) {
+        let packet = CapturedPacket {
+            index,
+            connection,
+            vehicle_id,
+            direction,
+            timestamp_us: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_micros() as u64,
+            frame: frame.to_vec(),
+        };
+        match self.sender.try_send(packet) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    warn!("pcapng 写入跟不上，开始丢弃分组: {}", self.path.display());
+                }
+                self.dropped += 1;
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// 写完队列中的分组并关闭文件，返回文件路径、写入帧数与丢弃帧数
+    pub(crate) fn finish(self) -> std::io::Result<(PathBuf, u64, u64)> {
+        drop(self.sender);
+        let (path, packets) = self
+            .worker
+            .join()
+            .map_err(|_| std::io::Error::other("pcapng 写入线程异常退出"))??;
+        Ok((path, packets, self.dropped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// 按块拆分，校验首尾长度一致
+    fn blocks(data: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let block_type = read_u32(data, pos);
+            let total = read_u32(data, pos + 4) as usize;
+            assert_eq!(total % 4, 0);
+            assert_eq!(read_u32(data, pos + total - 4) as usize, total);
+            out.push((block_type, &data[pos + 8..pos + total - 4]));
+            pos += total;
+        }
+        out
+    }
+
+    #[test]
+    fn test_pcapng_blocks() {
+        let connection = CaptureConnection {
+            role: ConnectionRole::Vehicle,
+            vehicle_id: 7,
+            vehicle_name: "7号车".to_string(),
+            addr: "192.168.1.20:40000".to_string(),
+        };
+        let frame = super::super::protocol::build_message(0x0002, &[7; 55]);
+        assert_eq!(frame_message_type(&frame), Some(0x0002));
+
+        let mut data = section_header();
+        data.extend_from_slice(&interface_description(&connection));
+        let pdu = exported_pdu(&frame, connection.addr.parse().ok(), PacketDirection::Inbound);
+        data.extend_from_slice(&enhanced_packet(0, 1_700_000_000_000_000, &pdu, PacketDirection::Inbound, "车辆 7"));
+
+        let blocks = blocks(&data);
+        assert_eq!(blocks.iter().map(|b| b.0).collect::<Vec<_>>(), vec![BLOCK_SECTION_HEADER, BLOCK_INTERFACE, BLOCK_ENHANCED_PACKET]);
+        assert_eq!(read_u32(blocks[0].1, 0), BYTE_ORDER_MAGIC);
+        assert_eq!(u16::from_le_bytes([blocks[1].1[0], blocks[1].1[1]]), LINKTYPE_UPPER_PDU);
+
+        // 分组数据：标签区以解析器名开头，以结束标签收尾，之后是原始帧
+        let packet = blocks[2].1;
+        let captured = read_u32(packet, 12) as usize;
+        let pdu = &packet[20..20 + captured];
+        assert_eq!(&pdu[..4], &[0, 12, 0, 5]);
+        assert_eq!(&pdu[4..9], DISSECTOR_NAME.as_bytes());
+        assert!(pdu.ends_with(&frame));
+        assert_eq!(&pdu[pdu.len() - frame.len() - 4..pdu.len() - frame.len()], &[0, 0, 0, 0]);
+        assert!(pdu.windows(4).any(|w| w == [192, 168, 1, 20]));
+
+        // 选项：方向标志为入站
+        let options = &packet[20 + captured.div_ceil(4) * 4..];
+        assert_eq!(&options[..4], &[2, 0, 4, 0]);
+        assert_eq!(read_u32(options, 4), 0b01);
+    }
+}
@@ -1,30 +1,22 @@
+use super::codec::CodecRegistry;
 use bytes::{Buf, BytesMut};
-use crc::{Algorithm, Crc};
 use serde::{Deserialize, Serialize};
+use std::ops::Add;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // 协议常量
 pub const HEADER: [u8; 4] = [0xEF, 0xEF, 0xEF, 0xEF];
 pub const FOOTER: [u8; 4] = [0xFE, 0xFE, 0xFE, 0xFE];
+/// 基线协议版本（未收到对端数据前按此版本发送），其他版本见 `codec.rs`
 pub const VERSION: u8 = 0x10;
-pub const MIN_PACKET_SIZE: usize = 25; // 不包含数据域的最小包大小
-
-// CRC16校验
-const CRC_16_CCITT_FALSE: Algorithm<u16> = Algorithm {
-    width: 16,
-    poly: 0x1021,
-    init: 0xFFFF,
-    refin: false,
-    refout: false,
-    xorout: 0x0000,
-    check: 0x29B1,
-    residue: 0x0000,
-};
-
-const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_CCITT_FALSE);
+pub const MIN_PACKET_SIZE: usize = 25; // 不包含数据域的最小包大小（各版本中最小）
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SocketMessage {
+    /// 帧协议版本
+    pub version: u8,
     pub timestamp: u64,
     pub message_type: u16,
     pub data: Vec<u8>,
@@ -57,214 +49,228 @@ impl std::fmt::Display for ProtocolError {
 
 impl std::error::Error for ProtocolError {}
 
+/// 单帧数据域长度上限；超过视为长度字段损坏，立即重新同步而不是等待数据
+pub const MAX_DATA_LENGTH: usize = 64 * 1024;
+
+/// 连接解码统计（解析器与连接表共享）
+#[derive(Debug, Default)]
+pub struct DecoderCounters {
+    frames: AtomicU64,
+    bytes_discarded: AtomicU64,
+    crc_failures: AtomicU64,
+    bad_footers: AtomicU64,
+    bad_versions: AtomicU64,
+    bad_lengths: AtomicU64,
+}
+
+impl DecoderCounters {
+    pub fn snapshot(&self) -> DecoderStats {
+        DecoderStats {
+            frames: self.frames.load(Ordering::Relaxed),
+            bytes_discarded: self.bytes_discarded.load(Ordering::Relaxed),
+            crc_failures: self.crc_failures.load(Ordering::Relaxed),
+            bad_footers: self.bad_footers.load(Ordering::Relaxed),
+            bad_versions: self.bad_versions.load(Ordering::Relaxed),
+            bad_lengths: self.bad_lengths.load(Ordering::Relaxed),
+        }
+    }
+
+    fn discard(&self, bytes: usize) {
+        if bytes > 0 {
+            self.bytes_discarded.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn record_error(&self, error: &ProtocolError) {
+        let counter = match error {
+            ProtocolError::InvalidCrc => &self.crc_failures,
+            ProtocolError::InvalidFooter => &self.bad_footers,
+            ProtocolError::InvalidVersion => &self.bad_versions,
+            ProtocolError::InvalidLength => &self.bad_lengths,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 解码统计快照（供状态查询）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DecoderStats {
+    /// 成功解析的帧数
+    pub frames: u64,
+    /// 重新同步时丢弃的字节数
+    pub bytes_discarded: u64,
+    pub crc_failures: u64,
+    pub bad_footers: u64,
+    /// 未支持的协议版本
+    pub bad_versions: u64,
+    /// 数据长度超过上限
+    pub bad_lengths: u64,
+}
+
+impl Add for DecoderStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            frames: self.frames + other.frames,
+            bytes_discarded: self.bytes_discarded + other.bytes_discarded,
+            crc_failures: self.crc_failures + other.crc_failures,
+            bad_footers: self.bad_footers + other.bad_footers,
+            bad_versions: self.bad_versions + other.bad_versions,
+            bad_lengths: self.bad_lengths + other.bad_lengths,
+        }
+    }
+}
+
+/// 流式解码器
+///
+/// 损坏的帧只跳过其帧头的第一个字节，再从下一个帧头重新同步（帧头可能出现在损坏帧内部）；
+/// 找不到帧头时保留缓冲区末尾可能属于下一次读取的半个帧头。
 pub struct ProtocolParser {
     buffer: BytesMut,
+    /// 最近一次成功解析的帧版本
+    detected_version: Option<u8>,
+    counters: Arc<DecoderCounters>,
 }
 
 impl ProtocolParser {
     pub fn new() -> Self {
+        Self::with_counters(Arc::default())
+    }
+
+    /// 使用共享的解码统计创建解析器
+    pub fn with_counters(counters: Arc<DecoderCounters>) -> Self {
         Self {
             buffer: BytesMut::with_capacity(4096),
+            detected_version: None,
+            counters,
         }
     }
 
+    /// 对端最近使用的协议版本（尚未解析出完整帧时为 None）
+    pub fn detected_version(&self) -> Option<u8> {
+        self.detected_version
+    }
+
+    /// 解码统计（可交给连接表共享）
+    pub fn counters(&self) -> Arc<DecoderCounters> {
+        self.counters.clone()
+    }
+
     /// 添加接收到的数据到缓冲区
     pub fn feed_data(&mut self, data: &[u8]) {
         self.buffer.extend_from_slice(data);
     }
 
-    /// 尝试解析完整的消息
-    pub fn try_parse_message(&mut self) -> Result<Option<SocketMessage>, ProtocolError> {
-        if self.buffer.len() < MIN_PACKET_SIZE {
-            return Ok(None);
-        }
-
-        // 查找帧头
-        let header_pos = self.find_header()?;
-        if header_pos.is_none() {
-            // 没找到帧头，清除无效数据
-            self.buffer.clear();
-            return Ok(None);
-        }
-
-        let header_pos = header_pos.unwrap();
-        
-        // 移除帧头之前的垃圾数据
-        if header_pos > 0 {
-            self.buffer.advance(header_pos);
+    /// 取下一条完整消息，跳过并统计损坏的帧；返回 None 表示需要更多数据
+    pub fn next_message(&mut self) -> Option<SocketMessage> {
+        loop {
+            match self.try_parse_message() {
+                Ok(message) => return message,
+                Err(e) => log::debug!("丢弃损坏的帧: {}", e),
+            }
         }
+    }
 
-        // 检查是否有足够的数据进行解析
-        if self.buffer.len() < MIN_PACKET_SIZE {
+    /// 尝试解析完整的消息；返回错误时已跳过损坏的帧头，可继续调用
+    pub fn try_parse_message(&mut self) -> Result<Option<SocketMessage>, ProtocolError> {
+        if !self.sync_to_header() || self.buffer.len() < MIN_PACKET_SIZE {
             return Ok(None);
         }
 
-        // 解析消息
         match self.parse_packet() {
-            Ok(message) => Ok(Some(message)),
+            Ok(message) => {
+                self.counters.frames.fetch_add(1, Ordering::Relaxed);
+                Ok(Some(message))
+            }
             Err(ProtocolError::IncompleteData) => Ok(None),
             Err(e) => {
-                // 解析错误，移除当前帧头，继续寻找下一个
-                self.buffer.advance(4);
+                // 只跳过一个字节，帧头之后的数据里可能就有下一帧
+                self.counters.record_error(&e);
+                self.buffer.advance(1);
+                self.counters.discard(1);
                 Err(e)
             }
         }
     }
 
-    /// 查找帧头位置
-    fn find_header(&self) -> Result<Option<usize>, ProtocolError> {
-        for i in 0..=(self.buffer.len().saturating_sub(4)) {
-            if &self.buffer[i..i + 4] == HEADER {
-                return Ok(Some(i));
+    /// 丢弃帧头之前的数据；缓冲区以帧头开始时返回 true
+    fn sync_to_header(&mut self) -> bool {
+        let found = self.buffer.windows(HEADER.len()).position(|window| window == HEADER);
+        let discard = match found {
+            Some(pos) => pos,
+            // 末尾可能是下一次读取才补全的帧头前缀
+            None => {
+                let keep = (1..HEADER.len())
+                    .rev()
+                    .find(|&len| self.buffer.ends_with(&HEADER[..len]))
+                    .unwrap_or(0);
+                self.buffer.len().saturating_sub(keep)
             }
-        }
-        Ok(None)
+        };
+        self.buffer.advance(discard);
+        self.counters.discard(discard);
+        found.is_some()
     }
 
-    /// 解析数据包
+    /// 按版本字节选择编解码器解析数据包
     fn parse_packet(&mut self) -> Result<SocketMessage, ProtocolError> {
-        let mut cursor = 0;
-
-        // 验证帧头
-        if &self.buffer[cursor..cursor + 4] != HEADER {
-            return Err(ProtocolError::InvalidHeader);
-        }
-        cursor += 4;
-
-        // 验证版本
-        if self.buffer[cursor] != VERSION {
-            return Err(ProtocolError::InvalidVersion);
-        }
-        cursor += 1;
-
-        // 解析时间戳 (小端序)
-        if self.buffer.len() < cursor + 8 {
-            return Err(ProtocolError::IncompleteData);
-        }
-        let timestamp = u64::from_le_bytes([
-            self.buffer[cursor],
-            self.buffer[cursor + 1],
-            self.buffer[cursor + 2],
-            self.buffer[cursor + 3],
-            self.buffer[cursor + 4],
-            self.buffer[cursor + 5],
-            self.buffer[cursor + 6],
-            self.buffer[cursor + 7],
-        ]);
-        cursor += 8;
-
-        // 解析消息类型
-        if self.buffer.len() < cursor + 2 {
-            return Err(ProtocolError::IncompleteData);
-        }
-        let message_type = u16::from_le_bytes([
-            self.buffer[cursor],
-            self.buffer[cursor + 1],
-        ]);
-        cursor += 2;
-
-        // 解析数据域长度
-        if self.buffer.len() < cursor + 4 {
-            return Err(ProtocolError::IncompleteData);
-        }
-        let data_length = u32::from_le_bytes([
-            self.buffer[cursor],
-            self.buffer[cursor + 1],
-            self.buffer[cursor + 2],
-            self.buffer[cursor + 3],
-        ]) as usize;
-        cursor += 4;
-
-        // 检查包总长度
-        let total_length = MIN_PACKET_SIZE + data_length;
-        if self.buffer.len() < total_length {
-            return Err(ProtocolError::IncompleteData);
-        }
-
-        // 解析数据域
-        let data = if data_length > 0 {
-            self.buffer[cursor..cursor + data_length].to_vec()
-        } else {
-            Vec::new()
-        };
-        cursor += data_length;
-
-        // 验证CRC
-        let received_crc = u16::from_le_bytes([
-            self.buffer[cursor],
-            self.buffer[cursor + 1],
-        ]);
-        cursor += 2;
-
-        // 计算CRC (从版本字节开始到CRC之前的字节)
-        let crc_data = &self.buffer[4..cursor - 2];
-        let calculated_crc = CRC16.checksum(crc_data);
-        
-        if received_crc != calculated_crc {
-            return Err(ProtocolError::InvalidCrc);
-        }
-
-        // 验证帧尾
-        if &self.buffer[cursor..cursor + 4] != FOOTER {
-            return Err(ProtocolError::InvalidFooter);
-        }
-        cursor += 4;
+        let version = self.buffer[HEADER.len()];
+        let codec = CodecRegistry::global()
+            .get(version)
+            .ok_or(ProtocolError::InvalidVersion)?;
+        let (message, consumed) = codec.decode(&self.buffer)?;
 
         // 移除已解析的数据
-        self.buffer.advance(cursor);
-
-        Ok(SocketMessage {
-            timestamp,
-            message_type,
-            data,
-        })
+        self.buffer.advance(consumed);
+        self.detected_version = Some(version);
+        Ok(message)
     }
 }
 
-/// 构建发送消息
-pub fn build_message(message_type: u16, data: &[u8]) -> Vec<u8> {
-    let mut packet = Vec::new();
-    
-    // 帧头
-    packet.extend_from_slice(&HEADER);
-    
-    // 协议版本
-    packet.push(VERSION);
-    
-    // 时间戳 (小端序) - 安全处理系统时间异常
-    let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+/// 当前时间戳（毫秒）
+pub fn current_timestamp() -> u64 {
+    // 时间戳 - 安全处理系统时间异常
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
         Ok(duration) => duration.as_millis() as u64,
         Err(_) => {
             // 系统时间异常时使用0作为默认值
             log::warn!("系统时间异常，使用默认时间戳");
             0
         }
-    };
-    packet.extend_from_slice(&timestamp.to_le_bytes());
-    
-    // 消息类型 (小端序)
-    packet.extend_from_slice(&message_type.to_le_bytes());
-    
-    // 数据域长度 (小端序)
-    packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
-    
-    // 数据域
-    packet.extend_from_slice(data);
-    
-    // 计算CRC (从版本字节开始)
-    let crc_data = &packet[4..];
-    let crc = CRC16.checksum(crc_data);
-    packet.extend_from_slice(&crc.to_le_bytes());
-    
-    // 帧尾
-    packet.extend_from_slice(&FOOTER);
-    
-    packet
+    }
+}
+
+/// 构建发送消息（基线版本）
+pub fn build_message(message_type: u16, data: &[u8]) -> Vec<u8> {
+    build_message_at(message_type, data, current_timestamp())
+}
+
+/// 使用指定时间戳构建消息（需确认的指令以时间戳作为序号，重发时保持不变）
+pub fn build_message_at(message_type: u16, data: &[u8], timestamp: u64) -> Vec<u8> {
+    encode_message(VERSION, message_type, data, timestamp)
+}
+
+/// 按指定协议版本组帧；未登记的版本按基线版本编码
+pub fn encode_message(version: u8, message_type: u16, data: &[u8], timestamp: u64) -> Vec<u8> {
+    let registry = CodecRegistry::global();
+    match registry.get(version) {
+        Some(codec) => codec.encode(message_type, data, timestamp),
+        None => {
+            log::warn!("未支持的协议版本 0x{:02X}，按 0x{:02X} 编码", version, VERSION);
+            registry
+                .get(VERSION)
+                .expect("基线协议版本已登记")
+                .encode(message_type, data, timestamp)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_build_and_parse_message() {
@@ -278,7 +284,106 @@ mod tests {
         assert!(result.is_some());
         
         let message = result.unwrap();
+        assert_eq!(message.version, VERSION);
         assert_eq!(message.message_type, 0x0001);
         assert_eq!(message.data, test_data);
     }
+
+    fn drain(parser: &mut ProtocolParser) -> Vec<u16> {
+        std::iter::from_fn(|| parser.next_message()).map(|m| m.message_type).collect()
+    }
+
+    #[test]
+    fn test_resync_across_read_boundaries() {
+        let packet = build_message(0x0002, &[7; 55]);
+        // 任意位置拆分（包括拆开帧头）都只解析出一帧，且不丢弃字节
+        for split in 1..packet.len() {
+            let mut parser = ProtocolParser::new();
+            parser.feed_data(&[0x00, 0xEF]);
+            parser.feed_data(&packet[..split]);
+            assert!(drain(&mut parser).is_empty(), "拆分位置 {}", split);
+            parser.feed_data(&packet[split..]);
+            assert_eq!(drain(&mut parser), vec![0x0002], "拆分位置 {}", split);
+            assert_eq!(parser.counters().snapshot().bytes_discarded, 2);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_frames_are_skipped_and_counted() {
+        let good = build_message(0x0001, &[]);
+        let mut bad_crc = build_message(0x0004, &[1; 33]);
+        bad_crc[20] ^= 0xFF;
+        let mut bad_footer = build_message(0x0005, &[1, 2]);
+        let last = bad_footer.len() - 1;
+        bad_footer[last] = 0x00;
+        // 长度字段损坏：不等待 4GB 数据，立即重新同步
+        let mut bad_length = build_message(0x0006, &[1]);
+        bad_length[15..19].copy_from_slice(&u32::MAX.to_le_bytes());
+        // 截断的帧后紧跟完整帧：截断帧吞掉下一帧的字节后校验失败，再从下一帧帧头恢复
+        let truncated = &build_message(0x0007, &[1, 1])[..20];
+
+        let mut stream = b"garbage".to_vec();
+        for part in [&bad_crc[..], &good, &bad_footer, &good, &bad_length, &good, truncated, &good] {
+            stream.extend_from_slice(part);
+        }
+
+        let mut parser = ProtocolParser::new();
+        parser.feed_data(&stream);
+        assert_eq!(drain(&mut parser), vec![0x0001; 4]);
+
+        let stats = parser.counters().snapshot();
+        assert_eq!(stats.frames, 4);
+        assert_eq!(stats.crc_failures, 2);
+        assert_eq!(stats.bad_footers, 1);
+        assert_eq!(stats.bad_lengths, 1);
+        let discarded = 7 + bad_crc.len() + bad_footer.len() + bad_length.len() + truncated.len();
+        assert_eq!(stats.bytes_discarded, discarded as u64);
+    }
+
+    /// 各版本的帧（类型、时间戳、数据域任意）
+    fn frame() -> impl Strategy<Value = (u8, u16, u64, Vec<u8>)> {
+        (
+            prop::sample::select(CodecRegistry::global().versions()),
+            any::<u16>(),
+            any::<u64>(),
+            prop::collection::vec(any::<u8>(), 0..80),
+        )
+    }
+
+    proptest! {
+        /// 任意读取块大小下，帧前的噪声被丢弃，所有帧按原样解出
+        #[test]
+        fn test_frames_round_trip_with_any_read_size(
+            noise in prop::collection::vec(any::<u8>().prop_filter("不含帧头字节", |b| *b != HEADER[0]), 0..16),
+            frames in prop::collection::vec(frame(), 1..6),
+            chunk in 1usize..64,
+        ) {
+            let mut stream = noise.clone();
+            for (version, message_type, timestamp, data) in &frames {
+                stream.extend(encode_message(*version, *message_type, data, *timestamp));
+            }
+
+            let mut parser = ProtocolParser::new();
+            let mut decoded = Vec::new();
+            for piece in stream.chunks(chunk) {
+                parser.feed_data(piece);
+                decoded.extend(std::iter::from_fn(|| parser.next_message()));
+            }
+            let decoded: Vec<_> = decoded.into_iter().map(|m| (m.version, m.message_type, m.timestamp, m.data)).collect();
+            prop_assert_eq!(decoded, frames.clone());
+
+            let stats = parser.counters().snapshot();
+            prop_assert_eq!(stats.frames, frames.len() as u64);
+            prop_assert_eq!(stats.bytes_discarded, noise.len() as u64);
+        }
+
+        /// 任意字节流不会使解码器崩溃，丢弃的字节不超过输入
+        #[test]
+        fn test_arbitrary_bytes_never_panic(stream in prop::collection::vec(any::<u8>(), 0..512)) {
+            let mut parser = ProtocolParser::new();
+            parser.feed_data(&stream);
+            while parser.next_message().is_some() {}
+            prop_assert!(parser.counters().snapshot().bytes_discarded <= stream.len() as u64);
+        }
+    }
 }
@@ -0,0 +1,509 @@
+//! 会话回放引擎
+//!
+//! 读取会话抓包文件，按原始时间间隔（可变速或单步）将原始字节重新送入
+//! `ProtocolParser` 与 `SocketServer::handle_message`，前端收到的事件与现场完全一致。
+
+use super::capture::{read_capture_file, CaptureConnection, CaptureEvent, CaptureFile, ConnectionRole};
+use super::event_sink::SharedEventSink;
+use super::fleet_update::FleetUpdateEmitter;
+use super::protocol::ProtocolParser;
+use super::send_queue::SendQueue;
+use super::server::{ClientConnection, ConnectionManager, SocketServer};
+use super::telemetry::TelemetryRecorder;
+use crate::config::AppConfig;
+use crate::services::fleet_state::FleetState;
+use log::{debug, info, warn};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// 回放速度范围
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 100.0;
+
+/// 回放中的连接状态
+struct ReplayConnection {
+    role: ConnectionRole,
+    vehicle_id: i32,
+    vehicle_name: String,
+    addr: String,
+    parser: ProtocolParser,
+}
+
+/// 回放引擎：逐条处理抓包记录
+pub struct ReplayEngine {
+    capture: CaptureFile,
+    position: usize,
+    sink: SharedEventSink,
+    connections: ConnectionManager,
+    fleet: FleetUpdateEmitter,
+    telemetry: TelemetryRecorder,
+    active: HashMap<u32, ReplayConnection>,
+}
+
+impl ReplayEngine {
+    pub fn new(capture: CaptureFile, sink: SharedEventSink) -> Self {
+        Self {
+            capture,
+            position: 0,
+            // 回放状态与现场车队状态相互独立
+            fleet: FleetUpdateEmitter::start(sink.clone(), &AppConfig::global().performance, FleetState::default()),
+            sink,
+            connections: ConnectionManager::default(),
+            // 回放数据不再写入遥测记录
+            telemetry: TelemetryRecorder::disabled(),
+            active: HashMap::new(),
+        }
+    }
+
+    /// 已处理的记录数
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// 记录总数
+    pub fn total_records(&self) -> usize {
+        self.capture.records.len()
+    }
+
+    /// 当前回放时间（微秒）
+    pub fn position_us(&self) -> u64 {
+        match self.position {
+            0 => 0,
+            n => self.capture.records[n - 1].elapsed_us,
+        }
+    }
+
+    /// 下一条记录的时间（微秒），已回放完时返回 None
+    pub fn next_elapsed_us(&self) -> Option<u64> {
+        self.capture.records.get(self.position).map(|r| r.elapsed_us)
+    }
+
+    pub fn duration_us(&self) -> u64 {
+        self.capture.duration_us()
+    }
+
+    /// 抓包开始时间（Unix 微秒）
+    pub fn started_at_us(&self) -> u64 {
+        self.capture.started_at_us
+    }
+
+    /// 处理下一条记录，已回放完时返回 false
+    pub async fn step(&mut self) -> bool {
+        let Some(record) = self.capture.records.get(self.position).cloned() else {
+            return false;
+        };
+        self.position += 1;
+
+        match record.event {
+            CaptureEvent::Open(conn) => self.open(record.connection, conn).await,
+            CaptureEvent::Data(bytes) => self.feed(record.connection, &bytes).await,
+            CaptureEvent::Close => self.close(record.connection).await,
+        }
+        true
+    }
+
+    /// 跳转到指定时间：丢弃跳过的数据，仅重建连接状态
+    pub async fn seek(&mut self, target_us: u64) {
+        let indices: Vec<u32> = self.active.keys().copied().collect();
+        for index in indices {
+            self.close(index).await;
+        }
+        self.fleet.clear();
+        self.connections.write().clear();
+
+        let mut open: HashMap<u32, CaptureConnection> = HashMap::new();
+        let mut position = 0;
+        for record in &self.capture.records {
+            if record.elapsed_us >= target_us {
+                break;
+            }
+            match &record.event {
+                CaptureEvent::Open(conn) => {
+                    open.insert(record.connection, conn.clone());
+                }
+                CaptureEvent::Close => {
+                    open.remove(&record.connection);
+                }
+                CaptureEvent::Data(_) => {}
+            }
+            position += 1;
+        }
+        self.position = position;
+
+        let mut open: Vec<_> = open.into_iter().collect();
+        open.sort_by_key(|(index, _)| *index);
+        for (index, conn) in open {
+            self.open(index, conn).await;
+        }
+        debug!("回放跳转到 {} 微秒 (记录 {}/{})", target_us, self.position, self.total_records());
+    }
+
+    async fn open(&mut self, index: u32, conn: CaptureConnection) {
+        let addr: SocketAddr = conn.addr.parse().unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+
+        match conn.role {
+            ConnectionRole::Sandbox => {
+                self.sink.emit("sandbox-connect", serde_json::json!({
+                    "sandbox_id": sandbox_id_of(&conn.role, conn.vehicle_id),
+                    "name": conn.vehicle_name,
+                    "ip": addr.ip().to_string(),
+                    "addr": conn.addr
+                }));
+            }
+            ConnectionRole::Vehicle => {
+                // 回放连接不会被发送数据，发送队列仅用于满足连接表结构（关闭后发送即失败）
+                let sender = SendQueue::new(1);
+                sender.close();
+                self.connections.write().insert(conn.vehicle_id, ClientConnection {
+                    vehicle_id: conn.vehicle_id,
+                    vehicle_name: conn.vehicle_name.clone(),
+                    addr,
+                    sender,
+                    authenticated: false,
+                    liveness: Default::default(),
+                    commands: None,
+                    decoder: Default::default(),
+                });
+                self.fleet.state().connect(conn.vehicle_id, &conn.vehicle_name, conn.addr.clone(), false);
+                SocketServer::send_connect_event(conn.vehicle_id, &conn.vehicle_name, &self.sink).await;
+            }
+        }
+
+        self.active.insert(index, ReplayConnection {
+            role: conn.role,
+            vehicle_id: conn.vehicle_id,
+            vehicle_name: conn.vehicle_name,
+            addr: conn.addr,
+            parser: ProtocolParser::new(),
+        });
+    }
+
+    async fn feed(&mut self, index: u32, bytes: &[u8]) {
+        let Some(conn) = self.active.get_mut(&index) else {
+            warn!("回放数据所属连接 {} 未建立，已跳过", index);
+            return;
+        };
+
+        conn.parser.feed_data(bytes);
+        while let Some(message) = conn.parser.next_message() {
+            if let Some(sandbox_id) = sandbox_id_of(&conn.role, conn.vehicle_id) {
+                SocketServer::handle_sandbox_message(message, sandbox_id, &conn.vehicle_name, &self.sink).await;
+                continue;
+            }
+            if let Some((new_id, new_name)) = SocketServer::handle_message(
+                message,
+                conn.vehicle_id,
+                &conn.vehicle_name,
+                &self.sink,
+                &self.fleet,
+                self.connections.clone(),
+                &self.telemetry,
+            ).await {
+                conn.vehicle_id = new_id;
+                conn.vehicle_name = new_name;
+            }
+        }
+    }
+
+    async fn close(&mut self, index: u32) {
+        let Some(conn) = self.active.remove(&index) else {
+            return;
+        };
+
+        match conn.role {
+            ConnectionRole::Sandbox => {
+                let ip = conn
+                    .addr
+                    .parse::<SocketAddr>()
+                    .map(|a| a.ip().to_string())
+                    .unwrap_or_default();
+                self.sink.emit("sandbox-disconnect", serde_json::json!({
+                    "sandbox_id": sandbox_id_of(&conn.role, conn.vehicle_id),
+                    "name": conn.vehicle_name,
+                    "ip": ip,
+                    "addr": conn.addr
+                }));
+            }
+            ConnectionRole::Vehicle => {
+                self.connections.write().remove(&conn.vehicle_id);
+                SocketServer::send_disconnect_event(conn.vehicle_id, &conn.vehicle_name, &conn.addr, &self.sink, &self.fleet).await;
+            }
+        }
+    }
+}
+
+/// 沙盘连接的抓包记录以 vehicle_id 保存沙盘ID（旧抓包为 -1，无沙盘ID）
+fn sandbox_id_of(role: &ConnectionRole, vehicle_id: i32) -> Option<i64> {
+    (*role == ConnectionRole::Sandbox && vehicle_id >= 0).then_some(vehicle_id as i64)
+}
+
+/// 回放控制指令
+#[derive(Debug, Clone, Copy)]
+pub enum ReplayCommand {
+    Play,
+    Pause,
+    Step,
+    Seek(u64),
+    SetSpeed(f64),
+    Stop,
+}
+
+/// 回放状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayStatus {
+    pub loaded: bool,
+    pub file_path: String,
+    /// 抓包开始时间（Unix 毫秒），用于换算现场时间
+    pub started_at_ms: u64,
+    pub playing: bool,
+    pub finished: bool,
+    pub speed: f64,
+    pub position: usize,
+    pub total_records: usize,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+}
+
+struct ReplayHandle {
+    commands: mpsc::UnboundedSender<ReplayCommand>,
+    status: Arc<RwLock<ReplayStatus>>,
+}
+
+/// 回放控制器（Tauri 状态）
+#[derive(Default)]
+pub struct ReplayController {
+    handle: Mutex<Option<ReplayHandle>>,
+}
+
+impl ReplayController {
+    /// 加载抓包文件并启动回放任务（初始为暂停状态），会替换已加载的回放
+    pub fn load(&self, path: &Path, sink: SharedEventSink) -> Result<ReplayStatus, String> {
+        let capture = read_capture_file(path)?;
+        let engine = ReplayEngine::new(capture, sink.clone());
+
+        let status = Arc::new(RwLock::new(ReplayStatus {
+            loaded: true,
+            file_path: path.display().to_string(),
+            started_at_ms: engine.started_at_us() / 1000,
+            speed: 1.0,
+            total_records: engine.total_records(),
+            duration_ms: engine.duration_us() / 1000,
+            ..ReplayStatus::default()
+        }));
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_playback(engine, receiver, status.clone(), sink));
+
+        let snapshot = status.read().clone();
+        if let Some(previous) = self.handle.lock().replace(ReplayHandle { commands, status }) {
+            let _ = previous.commands.send(ReplayCommand::Stop);
+        }
+
+        info!("已加载回放文件: {} ({} 条记录)", snapshot.file_path, snapshot.total_records);
+        Ok(snapshot)
+    }
+
+    /// 发送控制指令
+    pub fn send(&self, command: ReplayCommand) -> Result<(), String> {
+        let guard = self.handle.lock();
+        let handle = guard.as_ref().ok_or_else(|| "未加载回放文件".to_string())?;
+        handle
+            .commands
+            .send(command)
+            .map_err(|_| "回放任务已结束".to_string())
+    }
+
+    /// 卸载当前回放
+    pub fn unload(&self) {
+        if let Some(handle) = self.handle.lock().take() {
+            let _ = handle.commands.send(ReplayCommand::Stop);
+        }
+    }
+
+    /// 当前回放状态
+    pub fn status(&self) -> ReplayStatus {
+        self.handle
+            .lock()
+            .as_ref()
+            .map(|h| h.status.read().clone())
+            .unwrap_or_default()
+    }
+}
+
+/// 回放任务主循环
+async fn run_playback(
+    mut engine: ReplayEngine,
+    mut commands: mpsc::UnboundedReceiver<ReplayCommand>,
+    status: Arc<RwLock<ReplayStatus>>,
+    sink: SharedEventSink,
+) {
+    let mut playing = false;
+    let mut speed = 1.0_f64;
+    // 速度基准：(墙钟时刻, 对应的抓包时间)
+    let mut anchor: Option<(Instant, u64)> = None;
+
+    loop {
+        let deadline = if playing {
+            engine.next_elapsed_us().map(|next| {
+                let (start, base_us) = *anchor.get_or_insert((Instant::now(), next));
+                start + Duration::from_micros((next.saturating_sub(base_us) as f64 / speed) as u64)
+            })
+        } else {
+            None
+        };
+
+        if playing && deadline.is_none() {
+            playing = false;
+            publish_status(&status, &engine, playing, speed, &sink);
+        }
+
+        tokio::select! {
+            command = commands.recv() => {
+                match command {
+                    None | Some(ReplayCommand::Stop) => break,
+                    Some(ReplayCommand::Play) => {
+                        playing = true;
+                        anchor = None;
+                    }
+                    Some(ReplayCommand::Pause) => playing = false,
+                    Some(ReplayCommand::Step) => {
+                        playing = false;
+                        engine.step().await;
+                    }
+                    Some(ReplayCommand::Seek(target_us)) => {
+                        engine.seek(target_us).await;
+                        anchor = None;
+                    }
+                    Some(ReplayCommand::SetSpeed(value)) => {
+                        speed = value.clamp(MIN_SPEED, MAX_SPEED);
+                        anchor = None;
+                    }
+                }
+                publish_status(&status, &engine, playing, speed, &sink);
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                engine.step().await;
+                update_status(&status, &engine, playing, speed);
+            }
+        }
+    }
+
+    debug!("回放任务已结束");
+}
+
+fn update_status(status: &Arc<RwLock<ReplayStatus>>, engine: &ReplayEngine, playing: bool, speed: f64) {
+    let mut status = status.write();
+    status.playing = playing;
+    status.speed = speed;
+    status.position = engine.position();
+    status.position_ms = engine.position_us() / 1000;
+    status.finished = engine.next_elapsed_us().is_none();
+}
+
+/// 更新状态并通知前端
+fn publish_status(
+    status: &Arc<RwLock<ReplayStatus>>,
+    engine: &ReplayEngine,
+    playing: bool,
+    speed: f64,
+    sink: &SharedEventSink,
+) {
+    update_status(status, engine, playing, speed);
+    let snapshot = status.read().clone();
+    sink.emit("replay-status", serde_json::to_value(snapshot).unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_processing::types::MessageTypes;
+    use crate::socket::capture::CaptureRecord;
+    use crate::socket::protocol::build_message;
+    use crate::socket::BroadcastEventSink;
+
+    fn vehicle_info_payload(vehicle_id: u8, speed: f64) -> Vec<u8> {
+        let mut data = vec![0u8; 55];
+        data[0] = vehicle_id;
+        data[1..9].copy_from_slice(&speed.to_le_bytes());
+        data[41] = 1; // 档位 P（实时链路按协议定义验证取值范围）
+        data
+    }
+
+    fn sample_capture() -> CaptureFile {
+        let frame = build_message(MessageTypes::VEHICLE_INFO, &vehicle_info_payload(2, 0.5));
+        let (first, second) = frame.split_at(10);
+        CaptureFile {
+            started_at_us: 0,
+            records: vec![
+                CaptureRecord {
+                    elapsed_us: 0,
+                    connection: 0,
+                    event: CaptureEvent::Open(CaptureConnection {
+                        role: ConnectionRole::Vehicle,
+                        vehicle_id: 0,
+                        vehicle_name: "未知车辆_127.0.0.1".to_string(),
+                        addr: "127.0.0.1:6000".to_string(),
+                    }),
+                },
+                // 一帧被拆成两次读取，回放需按原样拼接
+                CaptureRecord { elapsed_us: 1_000, connection: 0, event: CaptureEvent::Data(first.to_vec()) },
+                CaptureRecord { elapsed_us: 2_000, connection: 0, event: CaptureEvent::Data(second.to_vec()) },
+                CaptureRecord { elapsed_us: 5_000, connection: 0, event: CaptureEvent::Close },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_step_reproduces_live_events() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let mut engine = ReplayEngine::new(sample_capture(), sink.clone());
+
+        for _ in 0..3 {
+            engine.step().await;
+        }
+        // 车辆状态按周期合并推送，断开前手动推送一次
+        engine.fleet.flush();
+        while engine.step().await {}
+
+        let names: Vec<String> = std::iter::from_fn(|| events.try_recv().ok()).map(|e| e.event).collect();
+        assert_eq!(names, vec!["vehicle-connect", "fleet-update", "vehicle-disconnect"]);
+        assert_eq!(engine.position_us(), 5_000);
+    }
+
+    #[tokio::test]
+    async fn test_vehicle_id_is_rekeyed_from_frame() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut events = sink.subscribe();
+        let mut engine = ReplayEngine::new(sample_capture(), sink.clone());
+
+        for _ in 0..4 {
+            engine.step().await;
+        }
+
+        let disconnect = std::iter::from_fn(|| events.try_recv().ok())
+            .find(|e| e.event == "vehicle-disconnect")
+            .unwrap();
+        assert_eq!(disconnect.payload["vehicle_id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_seek_skips_data_and_restores_connections() {
+        let sink = Arc::new(BroadcastEventSink::new(16, None));
+        let mut engine = ReplayEngine::new(sample_capture(), sink.clone());
+        let mut events = sink.subscribe();
+
+        engine.seek(2_000).await;
+        assert_eq!(engine.position(), 2);
+        assert_eq!(engine.next_elapsed_us(), Some(2_000));
+
+        let names: Vec<String> = std::iter::from_fn(|| events.try_recv().ok()).map(|e| e.event).collect();
+        assert_eq!(names, vec!["vehicle-connect"]);
+    }
+}
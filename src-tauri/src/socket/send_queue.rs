@@ -0,0 +1,312 @@
+//! 连接发送队列
+//!
+//! 每个连接一个有界队列，按优先级分为三条通道：
+//! - 紧急：紧急制动指令，始终最先发送；
+//! - 指令：其余控制/订单/沙盘指令，满时拒绝新消息并把错误返回给调用方（背压）；
+//! - 周期：服务器心跳等周期消息，同类型只保留最新一帧，满时丢弃最旧的一帧。
+//!
+//! 车辆停止读取时内存占用有上限，紧急制动也不会排在积压的普通指令之后。
+//! 入队时按连接协商的协议版本组帧（见 `codec.rs`）。
+
+use super::protocol::{current_timestamp, encode_message, VERSION};
+use crate::protocol_processing::types::{ControlCommandType, ProtocolConstants, SendMessageTypes};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Add;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// 紧急通道容量（紧急制动幂等，无需很深）
+const EMERGENCY_CAPACITY: usize = 16;
+/// 周期通道容量
+const PERIODIC_CAPACITY: usize = 8;
+
+/// 发送优先级通道
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendLane {
+    Emergency,
+    Command,
+    Periodic,
+}
+
+impl SendLane {
+    /// 按消息类型与内容划分通道
+    pub fn classify(message_type: u16, data: &[u8]) -> Self {
+        match message_type {
+            SendMessageTypes::VEHICLE_CONTROL
+                if data.get(ProtocolConstants::VEHICLE_CONTROL_COMMAND_OFFSET).copied()
+                    == Some(ControlCommandType::EmergencyBrake.to_u8()) =>
+            {
+                Self::Emergency
+            }
+            SendMessageTypes::HEARTBEAT => Self::Periodic,
+            _ => Self::Command,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Emergency => 0,
+            Self::Command => 1,
+            Self::Periodic => 2,
+        }
+    }
+}
+
+/// 入队失败原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SendError {
+    #[error("发送队列已满（{depth} 条待发送）")]
+    Full { depth: usize },
+    #[error("连接已关闭")]
+    Closed,
+}
+
+/// 队列统计（供状态查询）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SendQueueStats {
+    /// 当前待发送条数
+    pub depth: usize,
+    pub emergency_depth: usize,
+    pub command_depth: usize,
+    pub periodic_depth: usize,
+    /// 已交给连接写出的条数
+    pub sent: u64,
+    /// 周期消息因队列满被丢弃的条数
+    pub dropped: u64,
+    /// 周期消息被同类型新消息替换的条数
+    pub coalesced: u64,
+    /// 因队列满被拒绝的指令条数
+    pub rejected: u64,
+}
+
+impl Add for SendQueueStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            depth: self.depth + other.depth,
+            emergency_depth: self.emergency_depth + other.emergency_depth,
+            command_depth: self.command_depth + other.command_depth,
+            periodic_depth: self.periodic_depth + other.periodic_depth,
+            sent: self.sent + other.sent,
+            dropped: self.dropped + other.dropped,
+            coalesced: self.coalesced + other.coalesced,
+            rejected: self.rejected + other.rejected,
+        }
+    }
+}
+
+struct QueuedFrame {
+    message_type: u16,
+    packet: Vec<u8>,
+}
+
+struct QueueState {
+    lanes: [VecDeque<QueuedFrame>; 3],
+    closed: bool,
+    stats: SendQueueStats,
+}
+
+struct Inner {
+    command_capacity: usize,
+    /// 对端协议版本
+    version: AtomicU8,
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+/// 连接发送队列（克隆后共享同一队列）
+#[derive(Clone)]
+pub struct SendQueue {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for SendQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendQueue").field("stats", &self.stats()).finish()
+    }
+}
+
+impl SendQueue {
+    pub fn new(command_capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                command_capacity: command_capacity.max(1),
+                version: AtomicU8::new(VERSION),
+                state: Mutex::new(QueueState {
+                    lanes: Default::default(),
+                    closed: false,
+                    stats: SendQueueStats::default(),
+                }),
+                notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// 组帧并按优先级入队
+    pub fn send(&self, message_type: u16, data: &[u8]) -> Result<SendLane, SendError> {
+        self.send_at(message_type, data, current_timestamp())
+    }
+
+    /// 以指定时间戳组帧入队（需确认的指令重发时复用同一序号）
+    pub fn send_at(&self, message_type: u16, data: &[u8], timestamp: u64) -> Result<SendLane, SendError> {
+        let lane = SendLane::classify(message_type, data);
+        self.push(lane, message_type, encode_message(self.version(), message_type, data, timestamp))?;
+        Ok(lane)
+    }
+
+    /// 后续入队消息使用的协议版本
+    pub fn version(&self) -> u8 {
+        self.inner.version.load(Ordering::Relaxed)
+    }
+
+    /// 切换协议版本（已入队的帧不受影响）；返回版本是否变化
+    pub fn set_version(&self, version: u8) -> bool {
+        self.inner.version.swap(version, Ordering::Relaxed) != version
+    }
+
+    fn push(&self, lane: SendLane, message_type: u16, packet: Vec<u8>) -> Result<(), SendError> {
+        {
+            let mut state = self.inner.state.lock();
+            if state.closed {
+                return Err(SendError::Closed);
+            }
+            let depth: usize = state.lanes.iter().map(VecDeque::len).sum();
+            let frame = QueuedFrame { message_type, packet };
+
+            match lane {
+                SendLane::Periodic => {
+                    let queue = &mut state.lanes[lane.index()];
+                    if let Some(existing) = queue.iter_mut().find(|f| f.message_type == message_type) {
+                        *existing = frame;
+                        state.stats.coalesced += 1;
+                        return Ok(());
+                    }
+                    if queue.len() >= PERIODIC_CAPACITY {
+                        queue.pop_front();
+                        state.stats.dropped += 1;
+                    }
+                    state.lanes[lane.index()].push_back(frame);
+                }
+                SendLane::Emergency | SendLane::Command => {
+                    let capacity = if lane == SendLane::Emergency {
+                        EMERGENCY_CAPACITY
+                    } else {
+                        self.inner.command_capacity
+                    };
+                    if state.lanes[lane.index()].len() >= capacity {
+                        state.stats.rejected += 1;
+                        return Err(SendError::Full { depth });
+                    }
+                    state.lanes[lane.index()].push_back(frame);
+                }
+            }
+        }
+        self.inner.notify.notify_one();
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<Vec<u8>> {
+        let mut state = self.inner.state.lock();
+        let frame = state.lanes.iter_mut().find_map(VecDeque::pop_front)?;
+        state.stats.sent += 1;
+        Some(frame.packet)
+    }
+
+    /// 取出下一帧（按通道优先级）；队列关闭且为空时返回 None
+    pub async fn recv(&self) -> Option<Vec<u8>> {
+        loop {
+            if let Some(packet) = self.pop() {
+                return Some(packet);
+            }
+            if self.inner.state.lock().closed {
+                return None;
+            }
+            self.inner.notify.notified().await;
+        }
+    }
+
+    /// 关闭队列，之后的入队请求返回 `SendError::Closed`
+    pub fn close(&self) {
+        self.inner.state.lock().closed = true;
+        self.inner.notify.notify_one();
+    }
+
+    pub fn stats(&self) -> SendQueueStats {
+        let state = self.inner.state.lock();
+        let [emergency, command, periodic] = &state.lanes;
+        SendQueueStats {
+            depth: emergency.len() + command.len() + periodic.len(),
+            emergency_depth: emergency.len(),
+            command_depth: command.len(),
+            periodic_depth: periodic.len(),
+            ..state.stats.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket::protocol::ProtocolParser;
+
+    fn message_type_of(packet: &[u8]) -> u16 {
+        let mut parser = ProtocolParser::new();
+        parser.feed_data(packet);
+        parser.try_parse_message().unwrap().unwrap().message_type
+    }
+
+    #[tokio::test]
+    async fn test_emergency_brake_jumps_queue() {
+        let queue = SendQueue::new(4);
+        assert_eq!(queue.send(SendMessageTypes::TAXI_ORDER, &[1; 33]).unwrap(), SendLane::Command);
+        assert_eq!(queue.send(SendMessageTypes::HEARTBEAT, &[]).unwrap(), SendLane::Periodic);
+        assert_eq!(queue.send(SendMessageTypes::VEHICLE_CONTROL, &[1, 2]).unwrap(), SendLane::Command);
+        assert_eq!(queue.send(SendMessageTypes::VEHICLE_CONTROL, &[1, 3]).unwrap(), SendLane::Emergency);
+
+        let order: Vec<u16> = [
+            queue.recv().await.unwrap(),
+            queue.recv().await.unwrap(),
+            queue.recv().await.unwrap(),
+            queue.recv().await.unwrap(),
+        ]
+        .iter()
+        .map(|packet| message_type_of(packet))
+        .collect();
+        assert_eq!(
+            order,
+            vec![
+                SendMessageTypes::VEHICLE_CONTROL,
+                SendMessageTypes::TAXI_ORDER,
+                SendMessageTypes::VEHICLE_CONTROL,
+                SendMessageTypes::HEARTBEAT,
+            ]
+        );
+        assert_eq!(queue.stats().sent, 4);
+    }
+
+    #[test]
+    fn test_backpressure_and_coalescing() {
+        let queue = SendQueue::new(2);
+        queue.send(SendMessageTypes::AVP_PICKUP, &[1]).unwrap();
+        queue.send(SendMessageTypes::AVP_PICKUP, &[2]).unwrap();
+        assert_eq!(queue.send(SendMessageTypes::AVP_PICKUP, &[3]), Err(SendError::Full { depth: 2 }));
+
+        // 周期消息同类型只保留一帧，紧急通道不受指令通道积压影响
+        for _ in 0..5 {
+            queue.send(SendMessageTypes::HEARTBEAT, &[]).unwrap();
+        }
+        queue.send(SendMessageTypes::VEHICLE_CONTROL, &[1, 3]).unwrap();
+
+        let stats = queue.stats();
+        assert_eq!((stats.depth, stats.command_depth, stats.periodic_depth, stats.emergency_depth), (4, 2, 1, 1));
+        assert_eq!((stats.rejected, stats.coalesced, stats.dropped), (1, 4, 0));
+
+        queue.close();
+        assert_eq!(queue.send(SendMessageTypes::AVP_PICKUP, &[4]), Err(SendError::Closed));
+    }
+}
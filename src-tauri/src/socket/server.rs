@@ -1,15 +1,30 @@
-use super::protocol::{build_message, ProtocolParser, SocketMessage};
-use crate::database::VehicleDatabase;
-use crate::protocol_processing::types::{MessageTypes, VehicleInfo, ProtocolConstants, ParsedProtocolData, GearPosition};
-use crate::protocol_processing::parser::ProtocolParser as ProcessingProtocolParser;
+use super::ack::{self, CommandAck, CommandTracker, RetryPolicy};
+use super::auth;
+use super::capture::{CaptureConnection, ConnectionRole, SessionCapture};
+use super::event_sink::SharedEventSink;
+use super::fleet_update::FleetUpdateEmitter;
+use super::liveness::{ConnectionLiveness, LivenessCheck, LivenessPolicy};
+use super::protocol::{DecoderCounters, DecoderStats, ProtocolParser, SocketMessage};
+use super::send_queue::{SendError, SendLane, SendQueue, SendQueueStats};
+use super::telemetry::TelemetryRecorder;
+use super::tls;
+use crate::config::{AppConfig, NetworkConfig, SocketTlsConfig, VehicleAuthConfig};
+use crate::protocol_processing::types::{MessageTypes, SendMessageTypes, ParsedProtocolData};
+use crate::protocol_processing::batch_processor::TaskPriority;
+use crate::protocol_processing::pipeline::{ProcessOutcome, ProcessingPipeline};
+use crate::services::fleet_state::FleetState;
+use crate::services::parking_lot::{ParkingLot, ParkingLotTracker};
+use crate::services::path_loader::PathLoader;
+use crate::services::taxi_dispatch::TaxiDispatcher;
+use crate::services::taxi_orders::{TaxiOrderService, TaxiOrderTracker};
+use crate::services::taxi_queue::TaxiOrderQueue;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tauri::{Emitter, Manager};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
 use log::{info, debug, warn, error};
 
 // 客户端连接信息
@@ -18,65 +33,217 @@ pub struct ClientConnection {
     pub vehicle_id: i32,           // 使用整数车辆ID
     pub vehicle_name: String,      // 车辆名称
     pub addr: SocketAddr,
-    pub sender: mpsc::UnboundedSender<Vec<u8>>,
+    pub sender: SendQueue,         // 有界优先级发送队列（紧急制动优先）
+    pub authenticated: bool,       // 是否通过接入认证（认证后车辆编号不可被数据帧改写）
+    pub liveness: Arc<ConnectionLiveness>, // 最近心跳/车辆信息时间
+    pub commands: Option<CommandTracker>,  // 控制类指令投递（已认证车辆等待确认并重发，沙盘为 None）
+    pub decoder: Arc<DecoderCounters>,     // 解码统计（丢弃字节、CRC/帧尾错误）
+}
+
+impl ClientConnection {
+    /// 发送消息；控制类指令经指令跟踪发送（已认证车辆按序号等待确认并重发）
+    pub fn send(&self, message_type: u16, data: &[u8]) -> Result<SendLane, SendError> {
+        match &self.commands {
+            Some(commands) if ack::requires_ack(message_type) => commands
+                .send(self.vehicle_id, &self.vehicle_name, message_type, data)
+                .map(|(_, lane)| lane),
+            _ => self.sender.send(message_type, data),
+        }
+    }
 }
 
 // 全局连接管理器 - 使用整数车辆ID作为键
 pub type ConnectionManager = Arc<RwLock<HashMap<i32, ClientConnection>>>;
 
-// 沙盘服务连接管理器（仅保存一个特殊连接）
-pub type SandboxConnectionManager = Arc<RwLock<Option<ClientConnection>>>;
+// 沙盘服务连接管理器 - 使用沙盘ID（sandbox_service_settings.id）作为键
+pub type SandboxConnectionManager = Arc<RwLock<HashMap<i64, ClientConnection>>>;
+
+// 连接处理共享上下文
+#[derive(Clone)]
+struct ClientContext {
+    connections: ConnectionManager,
+    sandbox: SandboxConnectionManager,
+    sink: SharedEventSink,
+    fleet: FleetUpdateEmitter,
+    telemetry: TelemetryRecorder,
+    capture: SessionCapture,
+    auth: VehicleAuthConfig,
+    liveness: LivenessPolicy,
+    send_queue_capacity: usize,
+    retry: RetryPolicy,
+    /// 单次写入超时，超时视为对端不再接收并断开
+    write_timeout: Duration,
+}
 
 // Socket服务器
 pub struct SocketServer {
     port: u16,
     connections: ConnectionManager,
     sandbox: SandboxConnectionManager,
-    app_handle: tauri::AppHandle,
-    vehicle_state: Arc<RwLock<HashMap<u8, VehicleInfo>>>,
+    sink: SharedEventSink,
+    capture: SessionCapture,
+    fleet_state: FleetState,
+    parking_lot: ParkingLot,
+    paths: Option<Arc<PathLoader>>,
+    auth: VehicleAuthConfig,
+    network: NetworkConfig,
+    tls: SocketTlsConfig,
 }
 
 impl SocketServer {
-    pub fn new(port: u16, app_handle: tauri::AppHandle) -> Self {
+    pub fn new(port: u16, sink: SharedEventSink) -> Self {
         Self {
             port,
             connections: Arc::new(RwLock::new(HashMap::new())),
-            sandbox: Arc::new(RwLock::new(None)),
-            app_handle,
-            vehicle_state: Arc::new(RwLock::new(HashMap::new())),
+            sandbox: Arc::new(RwLock::new(HashMap::new())),
+            sink,
+            capture: SessionCapture::default(),
+            fleet_state: FleetState::default(),
+            parking_lot: ParkingLot::default(),
+            paths: None,
+            auth: AppConfig::global().vehicle_auth.clone(),
+            network: AppConfig::global().network.clone(),
+            tls: AppConfig::global().socket_tls.clone(),
         }
     }
     
-    pub fn new_with_connections(port: u16, app_handle: tauri::AppHandle, connections: ConnectionManager, sandbox: SandboxConnectionManager) -> Self {
+    pub fn new_with_connections(port: u16, sink: SharedEventSink, connections: ConnectionManager, sandbox: SandboxConnectionManager) -> Self {
         Self {
             port,
             connections,
             sandbox,
-            app_handle,
-            vehicle_state: Arc::new(RwLock::new(HashMap::new())),
+            sink,
+            capture: SessionCapture::default(),
+            fleet_state: FleetState::default(),
+            parking_lot: ParkingLot::default(),
+            paths: None,
+            auth: AppConfig::global().vehicle_auth.clone(),
+            network: AppConfig::global().network.clone(),
+            tls: AppConfig::global().socket_tls.clone(),
         }
     }
 
+    /// 使用共享的会话抓包器（由命令层控制开始/停止）
+    pub fn with_session_capture(mut self, capture: SessionCapture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// 使用共享的车队状态（由命令层查询与订阅）
+    pub fn with_fleet_state(mut self, fleet_state: FleetState) -> Self {
+        self.fleet_state = fleet_state;
+        self
+    }
+
+    /// 使用共享的停车场（由命令层预留与查询车位）
+    pub fn with_parking_lot(mut self, parking_lot: ParkingLot) -> Self {
+        self.parking_lot = parking_lot;
+        self
+    }
+
+    /// 使用已加载的路径（派单时按路径计算到起点的距离）
+    pub fn with_path_loader(mut self, paths: Option<Arc<PathLoader>>) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// 覆盖车辆接入认证配置（运行时取全局配置，测试中按用例指定）
+    #[cfg(test)]
+    pub fn with_vehicle_auth(mut self, auth: VehicleAuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// 覆盖心跳与超时配置（运行时取全局配置，测试中按用例指定）
+    #[cfg(test)]
+    pub fn with_network_config(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// 覆盖传输加密配置（运行时取全局配置，测试中按用例指定）
+    #[cfg(test)]
+    pub fn with_tls_config(mut self, tls: SocketTlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
     /// 启动Socket服务器
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let addr = format!("0.0.0.0:{}", self.port);
+        let acceptor = if self.tls.enabled { Some(tls::build_acceptor(&self.tls)?) } else { None };
         let listener = TcpListener::bind(&addr).await?;
         
-        info!("Socket服务器启动成功: {}", addr);
+        if acceptor.is_some() {
+            info!("🔒 Socket服务器启动成功 (TLS): {}", addr);
+        } else {
+            info!("Socket服务器启动成功: {}", addr);
+        }
+        if !self.auth.required {
+            warn!("⚠️⚠️⚠️ 车辆接入认证处于兼容模式（DZ_VIZ_VEHICLE_AUTH=0）：未完成认证握手的车辆也会被接受，任何能连接端口的设备均可冒充车辆");
+        }
+        let tls_timeout = Duration::from_millis(self.network.timeout as u64);
+
+        let context = ClientContext {
+            connections: self.connections.clone(),
+            sandbox: self.sandbox.clone(),
+            sink: self.sink.clone(),
+            fleet: FleetUpdateEmitter::start(self.sink.clone(), &AppConfig::global().performance, self.fleet_state.clone()),
+            telemetry: TelemetryRecorder::start(self.sink.clone(), AppConfig::global().telemetry.clone()),
+            capture: self.capture.clone(),
+            auth: self.auth.clone(),
+            liveness: LivenessPolicy::from_config(&self.network),
+            send_queue_capacity: self.network.send_queue_capacity as usize,
+            retry: RetryPolicy::from_config(&self.network),
+            write_timeout: Duration::from_millis(self.network.timeout as u64),
+        };
+        // 按车辆导航状态推进出租车订单，服务停止时随之停止
+        let _taxi_orders = TaxiOrderTracker::start(TaxiOrderService::new(self.sink.clone()), &self.fleet_state);
+        let database = self.sink.database();
+        // 恢复车位布局与重启前的占用，再按车队状态跟踪占用，服务停止时随之停止
+        if let Some(db) = database.as_ref() {
+            match db.get_parking_slots().await {
+                Ok(slots) => self.parking_lot.load(&slots),
+                Err(e) => error!("❌ 加载停车场车位失败: {}", e),
+            }
+        }
+        let _parking = ParkingLotTracker::start(self.parking_lot.clone(), &self.fleet_state, database.clone());
+        // 按队列顺序派出排队订单（含重启前未派出的订单），服务停止时随之停止
+        let _taxi_queue = if database.is_some() {
+            Some(TaxiOrderQueue::new(self.taxi_dispatcher()).start())
+        } else {
+            warn!("数据库未初始化，排队中的出租车订单不会派出");
+            None
+        };
         
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
                     info!("新客户端连接: {}", addr);
                     
-                    let connections = self.connections.clone();
-                    let app_handle = self.app_handle.clone();
-                    let sandbox = self.sandbox.clone();
-                    
-                    let vehicle_state = self.vehicle_state.clone();
+                    let context = context.clone();
+                    let acceptor = acceptor.clone();
 
                     let handle = tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, addr, connections, app_handle, sandbox, vehicle_state).await {
+                        let result = match acceptor {
+                            // TLS握手在连接任务内完成，避免阻塞accept
+                            Some(acceptor) => match tokio::time::timeout(tls_timeout, acceptor.accept(stream)).await {
+                                Ok(Ok(stream)) => {
+                                    let client_cert = tls::peer_fingerprint(stream.get_ref().1);
+                                    Self::handle_client(stream, addr, context, client_cert).await
+                                }
+                                Ok(Err(e)) => {
+                                    Self::send_tls_rejected(addr, &format!("TLS握手失败: {}", e), &context.sink);
+                                    Ok(())
+                                }
+                                Err(_) => {
+                                    Self::send_tls_rejected(addr, "TLS握手超时", &context.sink);
+                                    Ok(())
+                                }
+                            },
+                            None => Self::handle_client(stream, addr, context, None).await,
+                        };
+                        if let Err(e) = result {
                             error!("客户端处理错误 {}: {}", addr, e);
                         }
                     });
@@ -90,47 +257,122 @@ impl SocketServer {
         }
     }
 
-    /// 处理单个客户端连接
-    async fn handle_client(
-        mut stream: TcpStream,
+    /// 使用本服务器连接与车队状态的出租车派单器
+    fn taxi_dispatcher(&self) -> TaxiDispatcher {
+        TaxiDispatcher::new(
+            TaxiOrderService::new(self.sink.clone()),
+            self.fleet_state.clone(),
+            Arc::new(self.connections.clone()),
+            self.paths.clone(),
+            AppConfig::global().dispatch.clone(),
+        )
+    }
+
+    fn send_tls_rejected(addr: SocketAddr, reason: &str, sink: &SharedEventSink) {
+        warn!("🔒 拒绝连接 {}: {}", addr, reason);
+        sink.emit("vehicle-auth-rejected", serde_json::json!({
+            "ip": addr.ip().to_string(),
+            "addr": addr.to_string(),
+            "vehicle_id": 0,
+            "reason": reason
+        }));
+    }
+
+    /// 处理单个客户端连接（明文TCP或TLS流；client_cert为已校验的客户端证书指纹）
+    async fn handle_client<S>(
+        mut stream: S,
         addr: SocketAddr,
-        connections: ConnectionManager,
-        app_handle: tauri::AppHandle,
-        sandbox_manager: SandboxConnectionManager,
-        vehicle_state: Arc<RwLock<HashMap<u8, VehicleInfo>>>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        context: ClientContext,
+        client_cert: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let ClientContext {
+            connections,
+            sandbox: sandbox_manager,
+            sink,
+            fleet,
+            telemetry,
+            capture,
+            auth,
+            liveness: liveness_policy,
+            send_queue_capacity,
+            retry,
+            write_timeout,
+        } = context;
+
+        let tx = SendQueue::new(send_queue_capacity);
+        let liveness = Arc::new(ConnectionLiveness::default());
         
         info!("******客户端连接 IP: {}", addr.ip());
 
-        // 判断是否为沙盘服务连接（优先判定）
-        let mut is_sandbox = false;
-        if let Some(db) = app_handle.try_state::<VehicleDatabase>() {
-            match db.get_sandbox_service_settings().await {
-                Ok(Some(settings)) => {
-                    let configured_ip = settings.ip_address.trim();
+        // 判断是否为沙盘服务连接（优先判定，按IP匹配各沙盘设置）
+        let mut sandbox_identity: Option<(i64, String)> = None;
+        if let Some(db) = sink.database() {
+            match db.get_all_sandbox_service_settings().await {
+                Ok(all_settings) => {
                     let remote_ip = addr.ip().to_string();
-                    debug!("检查沙盘IP: 配置={}, 实际={}", configured_ip, remote_ip);
-                    if configured_ip == remote_ip {
-                        is_sandbox = true;
-                        info!("沙盘连接已识别");
-                        if let Err(e) = app_handle.emit("sandbox-connect", serde_json::json!({
-                            "ip": remote_ip,
-                            "addr": addr.to_string()
-                        })) {
-                            error!("通知前端沙盘连接失败: {}", e);
+                    for settings in all_settings {
+                        let configured_ip = settings.ip_address.trim();
+                        debug!("检查沙盘IP: 沙盘={}, 配置={}, 实际={}", settings.id, configured_ip, remote_ip);
+                        if configured_ip == remote_ip {
+                            let name = if settings.name.trim().is_empty() {
+                                format!("SandboxService#{}", settings.id)
+                            } else {
+                                settings.name.clone()
+                            };
+                            info!("沙盘连接已识别: {} (ID: {})", name, settings.id);
+                            sink.emit("sandbox-connect", serde_json::json!({
+                                "sandbox_id": settings.id,
+                                "name": name,
+                                "ip": remote_ip,
+                                "addr": addr.to_string()
+                            }));
+                            sandbox_identity = Some((settings.id, name));
+                            break;
                         }
-                    } else {
-                        debug!("沙盘IP不匹配: 配置={}, 实际={}", configured_ip, remote_ip);
                     }
                 }
-                _ => {}
+                Err(e) => error!("查询沙盘服务设置失败: {}", e),
             }
         }
+        let is_sandbox = sandbox_identity.is_some();
+        let sandbox_id = sandbox_identity.as_ref().map(|(id, _)| *id);
 
-        // 根据客户端IP地址查询数据库获取车辆信息（非沙盘连接）
+        // 车辆接入认证（沙盘按IP识别，不参与握手）
+        let mut handshake = None;
+        if !is_sandbox {
+            match auth::handshake(&mut stream, sink.database().as_ref(), &auth, client_cert.as_deref()).await {
+                Ok(result) => handshake = Some(result),
+                Err(e) => {
+                    warn!("🔒 拒绝车辆接入 {}: {}", addr, e.reason);
+                    sink.emit("vehicle-auth-rejected", serde_json::json!({
+                        "ip": addr.ip().to_string(),
+                        "addr": addr.to_string(),
+                        "vehicle_id": e.vehicle_id,
+                        "reason": e.reason
+                    }));
+                    return Ok(());
+                }
+            }
+        }
+        // 握手阶段的解析器继续用于后续数据，解码统计随之延续
+        let decoder = handshake.as_ref().map_or_else(Default::default, |h| h.parser.counters());
+        let authenticated_vehicle = handshake.as_mut().and_then(|h| h.vehicle.take());
+        let authenticated = authenticated_vehicle.is_some();
+        // 兼容模式的旧车端不回复确认，指令只发送一次并通知前端无法确认
+        let commands = (!is_sandbox).then(|| {
+            if authenticated {
+                CommandTracker::new(tx.clone(), sink.clone(), retry)
+            } else {
+                CommandTracker::unconfirmed(tx.clone(), sink.clone())
+            }
+        });
+
+        // 未认证车辆（兼容模式）根据客户端IP地址查询数据库获取车辆信息
         debug!("客户端连接来自: {}", addr.ip());
-        let vehicle_info = if !is_sandbox { if let Some(db) = app_handle.try_state::<VehicleDatabase>() {
+        let vehicle_info = if authenticated { authenticated_vehicle } else if !is_sandbox { if let Some(db) = sink.database() {
             // 查询数据库中匹配的车辆连接
             match db.get_all_vehicle_connections().await {
                 Ok(connections) => {
@@ -159,21 +401,25 @@ impl SocketServer {
         } } else { None };
         
         // 沙盘连接仅保存到sandbox_manager，不进入车辆连接表
-        if is_sandbox {
+        if let Some((id, name)) = &sandbox_identity {
             {
                 let mut sandbox = sandbox_manager.write();
-                *sandbox = Some(ClientConnection {
+                sandbox.insert(*id, ClientConnection {
                     vehicle_id: -1,
-                    vehicle_name: "SandboxService".to_string(),
+                    vehicle_name: name.clone(),
                     addr,
                     sender: tx.clone(),
+                    authenticated: false,
+                    liveness: liveness.clone(),
+                    commands: None,
+                    decoder: decoder.clone(),
                 });
             }
-            info!("沙盘服务连接已建立: {} (IP: {})", addr, addr.ip());
+            info!("沙盘服务 {} 连接已建立: {} (IP: {})", name, addr, addr.ip());
         }
 
-        let (mut vehicle_id, mut vehicle_name) = if is_sandbox {
-            (-1, "SandboxService".to_string())
+        let (mut vehicle_id, mut vehicle_name) = if let Some((_, name)) = &sandbox_identity {
+            (-1, name.clone())
         } else if let Some(info) = vehicle_info {
             info!("数据库匹配车辆 -> ID: {}, 名称: {}", info.vehicle_id, info.name);
             (info.vehicle_id, info.name)
@@ -195,17 +441,31 @@ impl SocketServer {
                     vehicle_name: vehicle_name.clone(),
                     addr,
                     sender: tx.clone(),
+                    authenticated,
+                    liveness: liveness.clone(),
+                    commands: commands.clone(),
+                    decoder: decoder.clone(),
                 });
                 info!("车辆 {} (ID: {}) 连接已建立，当前连接数: {}", vehicle_name, vehicle_id, conns.len());
             } // 在这里释放锁
+            fleet.state().connect(vehicle_id, &vehicle_name, addr.to_string(), authenticated);
             
             // 发送车辆连接事件到前端
-            Self::send_connect_event(vehicle_id, &vehicle_name, &app_handle).await;
+            Self::send_connect_event(vehicle_id, &vehicle_name, &sink).await;
         }
 
+        // 登记到会话抓包器（未抓包时仅记录连接身份）
+        let capture_index = capture.open_connection(CaptureConnection {
+            role: if is_sandbox { ConnectionRole::Sandbox } else { ConnectionRole::Vehicle },
+            // 沙盘连接记录沙盘ID，回放时据此区分多个沙盘
+            vehicle_id: sandbox_id.map_or(vehicle_id, |id| id as i32),
+            vehicle_name: vehicle_name.clone(),
+            addr: addr.to_string(),
+        });
+
         // 启动在线时长统计任务
         if !is_sandbox {
-            let app_handle_for_timer = app_handle.clone();
+            let sink_for_timer = sink.clone();
             let timer_vehicle_id = vehicle_id;
             let connections_for_timer = connections.clone();
             tokio::spawn(async move {
@@ -219,7 +479,7 @@ impl SocketServer {
                             break;
                         }
                     }
-                    if let Some(db) = app_handle_for_timer.try_state::<VehicleDatabase>() {
+                    if let Some(db) = sink_for_timer.database() {
                         match db.update_vehicle_online_time(timer_vehicle_id, 1).await {
                             Ok(_) => {
                                 debug!("车辆 {} 在线时长已更新 (+1分钟)", timer_vehicle_id);
@@ -235,13 +495,75 @@ impl SocketServer {
             });
         }
         
-        let mut vehicle_parser = ProtocolParser::new();
-        let mut sandbox_parser = if is_sandbox { Some(ProtocolParser::new()) } else { None };
+        // 沿用握手阶段的解析器，并处理握手期间已收到的消息
+        let mut vehicle_parser = match handshake {
+            Some(handshake) => {
+                capture.record_data(capture_index, &handshake.received);
+                // 握手期间收到的字节（注册帧及其后已缓存的消息）补记到 pcapng
+                capture.record_inbound(capture_index, vehicle_id, &handshake.received);
+                if let Some(version) = handshake.parser.detected_version() {
+                    Self::negotiate_version(&tx, version, &vehicle_name);
+                }
+                for message in handshake.pending {
+                    Self::negotiate_version(&tx, message.version, &vehicle_name);
+                    Self::record_liveness(&liveness, message.message_type, vehicle_id, &vehicle_name, &sink);
+                    if let Some((new_id, new_name)) = Self::handle_message(
+                        message,
+                        vehicle_id,
+                        &vehicle_name,
+                        &sink,
+                        &fleet,
+                        connections.clone(),
+                        &telemetry,
+                    ).await {
+                        vehicle_id = new_id;
+                        vehicle_name = new_name;
+                    }
+                }
+                handshake.parser
+            }
+            None => ProtocolParser::with_counters(decoder.clone()),
+        };
+        let mut sandbox_parser = if is_sandbox { Some(ProtocolParser::with_counters(decoder.clone())) } else { None };
  
         let mut buffer = [0u8; 4096]; // 增加缓冲区大小以处理更大的数据包
+
+        // 服务器心跳与活性检查（仅车辆连接）
+        let mut heartbeat_timer = tokio::time::interval(liveness_policy.interval);
+        heartbeat_timer.tick().await;
  
         loop {
             tokio::select! {
+                _ = heartbeat_timer.tick(), if !is_sandbox => {
+                    match liveness.check(&liveness_policy, Instant::now()) {
+                        LivenessCheck::Alive | LivenessCheck::Stale => {}
+                        LivenessCheck::BecameStale => {
+                            warn!(
+                                "⚠️ 车辆 {} (ID: {}) 已 {} 毫秒无数据，判定为失联",
+                                vehicle_name,
+                                vehicle_id,
+                                liveness.idle(Instant::now()).as_millis()
+                            );
+                            Self::send_liveness_event("vehicle-stale", vehicle_id, &vehicle_name, &liveness, &sink);
+                        }
+                        LivenessCheck::TimedOut => {
+                            warn!(
+                                "⏱️ 车辆 {} (ID: {}) 心跳超时 ({} 毫秒)，强制断开 {}",
+                                vehicle_name,
+                                vehicle_id,
+                                liveness_policy.timeout.as_millis(),
+                                addr
+                            );
+                            break;
+                        }
+                    }
+
+                    // 心跳走周期通道，车辆积压时只保留最新一帧
+                    if let Err(e) = tx.send(SendMessageTypes::HEARTBEAT, &[]) {
+                        warn!("服务器心跳入队失败 {} (车辆ID: {}): {}", addr, vehicle_id, e);
+                    }
+                }
+
                 // 接收数据
                 result = stream.read(&mut buffer) => {
                     match result {
@@ -250,39 +572,32 @@ impl SocketServer {
                             info!("沙盘服务 {} 正常断开", addr);
                             } else {
                                 info!("客户端 {} (车辆ID: {}) 正常断开", addr, vehicle_id);
-                                // 发送断开连接事件到前端
-                                Self::send_disconnect_event(vehicle_id, &vehicle_name, &app_handle).await;
                             }
                             break;
                         }
                         Ok(n) => {
-                            if is_sandbox {
-                                if let Some(parser) = sandbox_parser.as_mut() {
-                                    parser.feed_data(&buffer[..n]);
-                                    while let Ok(Some(message)) = parser.try_parse_message() {
-                                        // 记录日志
-                                        sandbox_logger::handle_parsed_message(&message);
-                                        // 发送到前端
-                                        Self::handle_message(
-                                            message,
-                                            vehicle_id,
-                                            &vehicle_name,
-                                            &app_handle,
-                                            vehicle_state.clone(),
-                                            connections.clone(),
-                                        ).await;
-                                    }
+                            capture.record_data(capture_index, &buffer[..n]);
+                            capture.record_inbound(capture_index, sandbox_id.map_or(vehicle_id, |id| id as i32), &buffer[..n]);
+                            if let (Some(sandbox_id), Some(parser)) = (sandbox_id, sandbox_parser.as_mut()) {
+                                parser.feed_data(&buffer[..n]);
+                                while let Some(message) = parser.next_message() {
+                                    Self::negotiate_version(&tx, message.version, &vehicle_name);
+                                    // 解析、记录日志并发送到前端
+                                    Self::handle_sandbox_message(message, sandbox_id, &vehicle_name, &sink).await;
                                 }
                             } else {
                                 vehicle_parser.feed_data(&buffer[..n]);
-                                while let Ok(Some(message)) = vehicle_parser.try_parse_message() {
+                                while let Some(message) = vehicle_parser.next_message() {
+                                    Self::negotiate_version(&tx, message.version, &vehicle_name);
+                                    Self::record_liveness(&liveness, message.message_type, vehicle_id, &vehicle_name, &sink);
                                     if let Some((new_id, new_name)) = Self::handle_message(
                                         message,
                                         vehicle_id,
                                         &vehicle_name,
-                                        &app_handle,
-                                        vehicle_state.clone(),
-                                        connections.clone()
+                                        &sink,
+                                        &fleet,
+                                        connections.clone(),
+                                        &telemetry,
                                     ).await {
                                         vehicle_id = new_id;
                                         vehicle_name = new_name;
@@ -292,40 +607,45 @@ impl SocketServer {
                         }
                         Err(e) => {
                             warn!("{} 连接异常: {}", vehicle_name, e);
-                            if is_sandbox {
-                                let mut sandbox = sandbox_manager.write();
-                                *sandbox = None;
-                                let _ = app_handle.emit("sandbox-disconnect", serde_json::json!({
-                                    "ip": addr.ip().to_string(),
-                                    "addr": addr.to_string()
-                                }));
-                            }
                             break;
                         }
                     }
                 }
                 
                 // 发送数据
-                Some(data) = rx.recv() => {
+                Some(data) = tx.recv() => {
                     if is_sandbox {
                         debug!("准备发送 {} 字节到沙盘服务", data.len());
                     } else {
                         debug!("准备发送 {} 字节到车辆 {} (ID: {})", data.len(), vehicle_name, vehicle_id);
                     }
                     
-                    match stream.write_all(&data).await {
-                        Err(e) => {
+                    capture.record_outbound(capture_index, sandbox_id.map_or(vehicle_id, |id| id as i32), &data);
+                    // 对端停止读取时写入会一直挂起，限时后按断开处理
+                    match tokio::time::timeout(write_timeout, stream.write_all(&data)).await {
+                        Ok(Err(e)) => {
                             if is_sandbox {
                                 error!("发送数据错误 (沙盘) {}: {}", addr, e);
                             } else {
                                 error!("发送数据错误 {} (车辆ID: {}): {}", addr, vehicle_id, e);
-                                // Send disconnect event to frontend
-                                Self::send_disconnect_event(vehicle_id, &vehicle_name, &app_handle).await;
                                 debug!("连接因发送错误而退出");
                             }
                             break;
                         }
-                        Ok(_) => {
+                        Err(_) => {
+                            if is_sandbox {
+                                error!("发送数据超时 (沙盘) {}: {} 毫秒", addr, write_timeout.as_millis());
+                            } else {
+                                error!(
+                                    "发送数据超时 {} (车辆ID: {}): {} 毫秒，强制断开",
+                                    addr,
+                                    vehicle_id,
+                                    write_timeout.as_millis()
+                                );
+                            }
+                            break;
+                        }
+                        Ok(Ok(_)) => {
                             if is_sandbox {
                                 debug!("数据成功发送到沙盘服务");
                             } else {
@@ -337,153 +657,290 @@ impl SocketServer {
             }
         }
         
-        // Clean up connections
-        if is_sandbox {
+        capture.close_connection(capture_index);
+        tx.close();
+        if let Some(commands) = &commands {
+            commands.close();
+        }
+
+        // 清理连接：正常断开、读写错误、写超时、心跳超时都在此统一处理
+        if let Some(sandbox_id) = sandbox_id {
             let mut sandbox = sandbox_manager.write();
-            *sandbox = None;
-            if let Err(e) = app_handle.emit("sandbox-disconnect", serde_json::json!({
+            // 同一沙盘已从新连接重新接入时，保留新连接
+            if sandbox.get(&sandbox_id).is_some_and(|conn| conn.addr == addr) {
+                sandbox.remove(&sandbox_id);
+            }
+            sink.emit("sandbox-disconnect", serde_json::json!({
+                "sandbox_id": sandbox_id,
+                "name": vehicle_name,
                 "ip": addr.ip().to_string(),
                 "addr": addr.to_string()
-            })) {
-                error!("通知前端沙盘断开失败: {}", e);
-            }
-            info!("沙盘服务连接已清理");
+            }));
+            info!("沙盘服务 {} 连接已清理，剩余沙盘连接: {}", vehicle_name, sandbox.len());
         } else {
-            let mut conns = connections.write();
-            conns.remove(&vehicle_id);
-            info!("车辆 {} (ID: {}) 连接已清理，剩余连接: {}", vehicle_name, vehicle_id, conns.len());
+            {
+                let mut conns = connections.write();
+                // 同一车辆已从新连接重新接入时，保留新连接
+                if conns.get(&vehicle_id).is_some_and(|conn| conn.addr == addr) {
+                    conns.remove(&vehicle_id);
+                }
+                info!("车辆 {} (ID: {}) 连接已清理，剩余连接: {}", vehicle_name, vehicle_id, conns.len());
+            }
+            Self::send_disconnect_event(vehicle_id, &vehicle_name, &addr.to_string(), &sink, &fleet).await;
         }
         
         Ok(())
     }
 
+    /// 按对端最近一帧的协议版本编码后续下发的帧
+    fn negotiate_version(tx: &SendQueue, version: u8, name: &str) {
+        let previous = tx.version();
+        if tx.set_version(version) {
+            info!("{} 协议版本切换: 0x{:02X} -> 0x{:02X}", name, previous, version);
+        }
+    }
+
     /// 处理接收到的消息
-    async fn handle_message(
+    pub(crate) async fn handle_message(
         message: SocketMessage,
         vehicle_id: i32,
         vehicle_name: &str,
-        app_handle: &tauri::AppHandle,
-        vehicle_state: Arc<RwLock<HashMap<u8, VehicleInfo>>>,
+        sink: &SharedEventSink,
+        fleet: &FleetUpdateEmitter,
         connections: ConnectionManager,
+        telemetry: &TelemetryRecorder,
     ) -> Option<(i32, String)> {
         debug!("收到消息 - 车辆: {} (ID: {}), 类型: 0x{:04X}, 数据长度: {}",
                 vehicle_name, vehicle_id, message.message_type, message.data.len());
 
-        let mut parsed_payload: Option<serde_json::Value> = None;
-        let mut reassigned_vehicle: Option<(i32, String)> = None;
+        // 解析、验证与推送由共享处理阶段限流执行，等待完成以保持本连接的消息顺序
+        ProcessingPipeline::global()
+            .process(
+                TaskPriority::for_message(message.message_type),
+                message.version,
+                message.message_type,
+                &message.data,
+                |outcome, warnings| {
+                    let mut parsed_payload: Option<serde_json::Value> = None;
+                    let mut reassigned_vehicle: Option<(i32, String)> = None;
+                    if !warnings.is_empty() {
+                        debug!(
+                            "车辆 {} (ID: {}) 消息 0x{:04X} 超出协议取值范围: {}",
+                            vehicle_name,
+                            vehicle_id,
+                            message.message_type,
+                            warnings.join("; ")
+                        );
+                    }
 
-        if message.message_type == MessageTypes::HEARTBEAT {
-            parsed_payload = Some(serde_json::json!({
-                "type": "heartbeat",
-                "vehicle_id": vehicle_id,
-                "timestamp": message.timestamp,
-            }));
-        } else if message.message_type == MessageTypes::VEHICLE_INFO {
-            if message.data.len() < ProtocolConstants::VEHICLE_INFO_TOTAL_SIZE {
-                warn!(
-                    "车辆信息数据长度不足 - 车辆: {} (ID: {}), 长度: {}",
-                    vehicle_name,
-                    vehicle_id,
-                    message.data.len()
-                );
-            } else if let Some(info_json) = parse_vehicle_info_payload(&message.data, &vehicle_state, vehicle_id, vehicle_name) {
-                if let Some(parsed_id) = info_json.get("vehicle_id").and_then(|v| v.as_u64()) {
-                    let parsed_vehicle_id = parsed_id as i32;
-                    if vehicle_id != parsed_vehicle_id && vehicle_id >= 0 {
-                        let mut conns = connections.write();
-                        if let Some(mut conn) = conns.remove(&vehicle_id) {
-                            info!(
-                                "纠正车辆连接ID: {} -> {} (名称: {})",
+                    if message.message_type == MessageTypes::HEARTBEAT {
+                        fleet.state().touch(vehicle_id);
+                        parsed_payload = Some(serde_json::json!({
+                            "type": "heartbeat",
+                            "vehicle_id": vehicle_id,
+                            "timestamp": message.timestamp,
+                        }));
+                    } else if message.message_type == MessageTypes::VEHICLE_INFO {
+                        // 处理阶段已按帧协议版本选择数据域布局；只有结构无法解码的帧被丢弃
+                        if let Some(Ok(ParsedProtocolData::VehicleInfo(info))) = outcome {
+                            let parsed_vehicle_id = info.vehicle_id as i32;
+                            if vehicle_id != parsed_vehicle_id {
+                                let mut conns = connections.write();
+                                let current = conns.get(&vehicle_id);
+                                // 已认证连接的车辆编号固定，丢弃编号不符的数据帧
+                                if current.is_some_and(|conn| conn.authenticated) {
+                                    warn!(
+                                        "🔒 车辆 {} (ID: {}) 上报的车辆编号 {} 与认证身份不符，已丢弃",
+                                        vehicle_name,
+                                        vehicle_id,
+                                        info.vehicle_id
+                                    );
+                                    return None;
+                                }
+                                // 上报的编号属于其他已认证连接时不改写，避免冒用该车辆接收指令
+                                let current_addr = current.map(|conn| conn.addr);
+                                if let Some(owner) = conns
+                                    .get(&parsed_vehicle_id)
+                                    .filter(|owner| owner.authenticated && Some(owner.addr) != current_addr)
+                                {
+                                    warn!(
+                                        "🔒 车辆 {} (ID: {}) 上报的车辆编号 {} 已由已认证连接 {} 使用，已丢弃",
+                                        vehicle_name,
+                                        vehicle_id,
+                                        parsed_vehicle_id,
+                                        owner.addr
+                                    );
+                                    return None;
+                                }
+                                if vehicle_id >= 0 {
+                                    if let Some(mut conn) = conns.remove(&vehicle_id) {
+                                        info!(
+                                            "纠正车辆连接ID: {} -> {} (名称: {})",
+                                            vehicle_id,
+                                            parsed_vehicle_id,
+                                            vehicle_name
+                                        );
+                                        conn.vehicle_id = parsed_vehicle_id;
+                                        let new_name = conn.vehicle_name.clone();
+                                        conns.insert(parsed_vehicle_id, conn);
+                                        fleet.state().rekey(vehicle_id, parsed_vehicle_id, &new_name);
+                                        reassigned_vehicle = Some((parsed_vehicle_id, new_name));
+                                    }
+                                }
+                            }
+                            // 每一帧都写入遥测记录，去重合并只影响前端推送
+                            telemetry.record(&info, message.timestamp);
+
+                            // 车辆状态按周期合并为 fleet-update 推送，不再逐帧推送 socket-message
+                            let name = reassigned_vehicle.as_ref().map_or(vehicle_name, |(_, name)| name.as_str());
+                            fleet.update(name, info, message.timestamp, &message.data, warnings);
+                            return reassigned_vehicle;
+                        } else {
+                            warn!(
+                                "车辆信息解析失败 - 车辆: {} (ID: {}), 协议版本: 0x{:02X}, 长度: {}, 原因: {}",
+                                vehicle_name,
                                 vehicle_id,
-                                parsed_vehicle_id,
-                                vehicle_name
+                                message.version,
+                                message.data.len(),
+                                outcome.and_then(Result::err).unwrap_or_default()
                             );
-                            conn.vehicle_id = parsed_vehicle_id;
-                            let new_name = conn.vehicle_name.clone();
-                            conns.insert(parsed_vehicle_id, conn);
-                            reassigned_vehicle = Some((parsed_vehicle_id, new_name));
                         }
-                    }
-                }
-                parsed_payload = Some(info_json);
-            } else {
-                return None;
-            }
-        } else if message.message_type == MessageTypes::PATH_FILE_SELECTION {
-            // 处理路径文件选择协议（0x0003）
-            let mut parser = ProcessingProtocolParser::new(false);
-            let result = parser.parse_protocol(message.message_type, &message.data);
-            if result.success {
-                if let Some(ParsedProtocolData::PathFileSelection(selection)) = result.data {
-                    info!(
-                        "路径文件选择 - 车辆ID: {}, 路径数量: {}, 路径编号: {:?}",
-                        selection.vehicle_id,
-                        selection.path_file_ids.len(),
-                        selection.path_file_ids
-                    );
-                    parsed_payload = Some(serde_json::json!({
-                        "type": "path_file_selection",
-                        "vehicle_id": selection.vehicle_id,
-                        "path_file_ids": selection.path_file_ids
-                    }));
-                }
-            } else if let Some(err) = result.error {
-                warn!("路径文件选择解析失败: {}", err);
-            }
-        } else if message.message_type == MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS {
-            let mut parser = ProcessingProtocolParser::new(false);
-            let result = parser.parse_protocol(message.message_type, &message.data);
-            if result.success {
-                if let Some(ParsedProtocolData::SandboxTrafficLightStatus(status)) = result.data {
-                    info!("沙盘红绿灯状态: {} 个灯", status.lights.len());
-                    for light in &status.lights {
-                        let color_text = match light.color {
-                            1 => "红灯",
-                            2 => "绿灯",
-                            3 => "黄灯",
-                            other => {
-                                warn!("沙盘红绿灯状态未知颜色 {}", other);
-                                "未知"
+                    } else if message.message_type == MessageTypes::COMMAND_ACK {
+                        match CommandAck::parse(&message.data) {
+                            Some(ack) => {
+                                let commands = connections.read().get(&vehicle_id).and_then(|conn| conn.commands.clone());
+                                let matched = commands.is_some_and(|commands| commands.acknowledge(ack));
+                                if !matched {
+                                    debug!("车辆 {} (ID: {}) 的指令确认无对应待确认指令 (序号 {})", vehicle_name, vehicle_id, ack.sequence);
+                                }
+                                parsed_payload = Some(serde_json::json!({
+                                    "type": "command_ack",
+                                    "sequence": ack.sequence,
+                                    "message_type": ack.message_type,
+                                    "code": ack.code,
+                                    "accepted": ack.accepted(),
+                                    "description": ack.description(),
+                                }));
                             }
-                        };
-                        info!("  - 灯{}: {} 剩余 {} 秒", light.index, color_text, light.remaining);
+                            None => warn!("指令确认数据长度无效: {} 字节", message.data.len()),
+                        }
+                    } else if message.message_type == MessageTypes::PATH_FILE_SELECTION {
+                        // 处理路径文件选择协议（0x0003）
+                        match outcome {
+                            Some(Ok(ParsedProtocolData::PathFileSelection(selection))) => {
+                                info!(
+                                    "路径文件选择 - 车辆ID: {}, 路径数量: {}, 路径编号: {:?}",
+                                    selection.vehicle_id,
+                                    selection.path_file_ids.len(),
+                                    selection.path_file_ids
+                                );
+                                parsed_payload = Some(serde_json::json!({
+                                    "type": "path_file_selection",
+                                    "vehicle_id": selection.vehicle_id,
+                                    "path_file_ids": selection.path_file_ids
+                                }));
+                            }
+                            Some(Err(err)) => warn!("路径文件选择解析失败: {}", err),
+                            _ => {}
+                        }
+                    } else if message.message_type == MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS {
+                        parsed_payload = Self::traffic_light_payload(outcome);
                     }
-                    parsed_payload = Some(serde_json::json!({
-                        "type": "sandbox_traffic_light_status",
-                        "lights": status.lights
-                    }));
+
+                    let mut frontend_message = serde_json::json!({
+                        "type": "socket_message",
+                        "vehicle_id": vehicle_id,
+                        "vehicle_name": vehicle_name,
+                        "message_type": message.message_type,
+                        "timestamp": message.timestamp
+                    });
+                    if AppConfig::global().performance.debug_raw_frames {
+                        frontend_message["data"] = serde_json::json!(message.data);
+                    }
+
+                    if let Some(parsed) = parsed_payload {
+                        frontend_message["parsed"] = parsed;
+                    }
+                    if !warnings.is_empty() {
+                        frontend_message["warnings"] = serde_json::json!(warnings);
+                    }
+
+                    sink.emit("socket-message", frontend_message);
+                    reassigned_vehicle
+                },
+            )
+            .await
+    }
+
+    /// 处理沙盘消息并推送到前端（附带沙盘ID）
+    pub(crate) async fn handle_sandbox_message(message: SocketMessage, sandbox_id: i64, sandbox_name: &str, sink: &SharedEventSink) {
+        ProcessingPipeline::global()
+            .process(
+                TaskPriority::for_message(message.message_type),
+                message.version,
+                message.message_type,
+                &message.data,
+                |outcome, warnings| {
+                    let mut frontend_message = serde_json::json!({
+                        "type": "socket_message",
+                        "vehicle_id": -1,
+                        "vehicle_name": sandbox_name,
+                        "sandbox_id": sandbox_id,
+                        "message_type": message.message_type,
+                        "timestamp": message.timestamp
+                    });
+                    if AppConfig::global().performance.debug_raw_frames {
+                        frontend_message["data"] = serde_json::json!(message.data);
+                    }
+
+                    if message.message_type == MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS {
+                        if let Some(mut parsed) = Self::traffic_light_payload(outcome) {
+                            parsed["sandbox_id"] = serde_json::json!(sandbox_id);
+                            frontend_message["parsed"] = parsed;
+                        }
+                    }
+                    if !warnings.is_empty() {
+                        frontend_message["warnings"] = serde_json::json!(warnings);
+                    }
+
+                    sink.emit("socket-message", frontend_message);
+                },
+            )
+            .await
+    }
+
+    /// 沙盘红绿灯状态（0x3001）推送内容
+    fn traffic_light_payload(outcome: ProcessOutcome) -> Option<serde_json::Value> {
+        match outcome? {
+            Ok(ParsedProtocolData::SandboxTrafficLightStatus(status)) => {
+                info!("沙盘红绿灯状态: {} 个灯", status.lights.len());
+                for light in &status.lights {
+                    let color_text = match light.color {
+                        1 => "红灯",
+                        2 => "绿灯",
+                        3 => "黄灯",
+                        other => {
+                            warn!("沙盘红绿灯状态未知颜色 {}", other);
+                            "未知"
+                        }
+                    };
+                    info!("  - 灯{}: {} 剩余 {} 秒", light.index, color_text, light.remaining);
                 }
-            } else if let Some(err) = result.error {
-                warn!("沙盘红绿灯状态解析失败: {}", err);
+                Some(serde_json::json!({
+                    "type": "sandbox_traffic_light_status",
+                    "lights": status.lights
+                }))
             }
-        }
- 
-        let mut frontend_message = serde_json::json!({
-            "type": "socket_message",
-            "vehicle_id": vehicle_id,
-            "vehicle_name": vehicle_name,
-            "message_type": message.message_type,
-            "timestamp": message.timestamp,
-            "data": message.data
-        });
- 
-        if let Some(parsed) = parsed_payload {
-            frontend_message["parsed"] = parsed;
-        }
-        
-        match app_handle.emit("socket-message", frontend_message) {
-            Ok(_) => {
-                debug!("消息成功发送到前端");
-            }
-            Err(e) => {
-                error!("发送消息到前端失败: {}", e);
+            Ok(_) => None,
+            Err(err) => {
+                warn!("沙盘红绿灯状态解析失败: {}", err);
+                None
             }
         }
-        reassigned_vehicle
     }
 
     /// 发送车辆连接事件到前端
-    async fn send_connect_event(vehicle_id: i32, vehicle_name: &str, app_handle: &tauri::AppHandle) {
+    pub(crate) async fn send_connect_event(vehicle_id: i32, vehicle_name: &str, sink: &SharedEventSink) {
         let connect_message = serde_json::json!({
             "type": "vehicle_connect",
             "vehicle_id": vehicle_id,
@@ -494,15 +951,56 @@ impl SocketServer {
                 .as_millis() as u64
         });
         
-        if let Err(e) = app_handle.emit("vehicle-connect", connect_message) {
-            error!("发送车辆连接事件到前端失败: {}", e);
-        } else {
-            info!("已通知前端车辆 {} (ID: {}) 连接", vehicle_name, vehicle_id);
+        sink.emit("vehicle-connect", connect_message);
+        info!("已通知前端车辆 {} (ID: {}) 连接", vehicle_name, vehicle_id);
+    }
+
+    /// 记录车辆上行消息，失联后重新收到数据时通知前端
+    fn record_liveness(
+        liveness: &ConnectionLiveness,
+        message_type: u16,
+        vehicle_id: i32,
+        vehicle_name: &str,
+        sink: &SharedEventSink,
+    ) {
+        if liveness.record(message_type, Instant::now()) {
+            info!("✅ 车辆 {} (ID: {}) 恢复通信", vehicle_name, vehicle_id);
+            Self::send_liveness_event("vehicle-alive", vehicle_id, vehicle_name, liveness, sink);
         }
     }
 
+    /// 发送车辆失联/恢复事件到前端
+    fn send_liveness_event(
+        event: &str,
+        vehicle_id: i32,
+        vehicle_name: &str,
+        liveness: &ConnectionLiveness,
+        sink: &SharedEventSink,
+    ) {
+        let mut payload = liveness.to_json(Instant::now());
+        payload["type"] = serde_json::json!(event.replace('-', "_"));
+        payload["vehicle_id"] = serde_json::json!(vehicle_id);
+        payload["vehicle_name"] = serde_json::json!(vehicle_name);
+        payload["timestamp"] = serde_json::json!(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64);
+        sink.emit(event, payload);
+    }
+
     /// 发送车辆断开连接事件到前端
-    async fn send_disconnect_event(vehicle_id: i32, vehicle_name: &str, app_handle: &tauri::AppHandle) {
+    pub(crate) async fn send_disconnect_event(
+        vehicle_id: i32,
+        vehicle_name: &str,
+        addr: &str,
+        sink: &SharedEventSink,
+        fleet: &FleetUpdateEmitter,
+    ) {
+        // 先丢弃尚未推送的状态，避免断开事件之后又推送该车辆
+        if !fleet.remove(vehicle_id, addr) {
+            info!("车辆 {} (ID: {}) 已由新连接接管，旧连接 {} 断开不通知前端", vehicle_name, vehicle_id, addr);
+            return;
+        }
         let disconnect_message = serde_json::json!({
             "type": "vehicle_disconnect",
             "vehicle_id": vehicle_id,
@@ -513,11 +1011,8 @@ impl SocketServer {
                 .as_millis() as u64
         });
         
-        if let Err(e) = app_handle.emit("vehicle-disconnect", disconnect_message) {
-            error!("发送车辆断开事件到前端失败: {}", e);
-        } else {
-            info!("已通知前端车辆 {} (ID: {}) 断开连接", vehicle_name, vehicle_id);
-        }
+        sink.emit("vehicle-disconnect", disconnect_message);
+        info!("已通知前端车辆 {} (ID: {}) 断开连接", vehicle_name, vehicle_id);
     }
 
     /// 发送消息给指定车辆
@@ -530,14 +1025,13 @@ impl SocketServer {
         let conns = connections.read();
         
         if let Some(connection) = conns.get(&vehicle_id) {
-            let packet = build_message(message_type, data);
-            
-            if let Err(e) = connection.sender.send(packet) {
-                return Err(format!("发送失败: {}", e));
-            }
+            let lane = connection.send(message_type, data).map_err(|e| {
+                warn!("发送消息到车辆 {} (ID: {}) 失败: {}", connection.vehicle_name, vehicle_id, e);
+                format!("发送失败: {}", e)
+            })?;
             
-            info!("发送消息到车辆 {} (ID: {}) - 类型: 0x{:04X}, 数据长度: {}", 
-                    connection.vehicle_name, vehicle_id, message_type, data.len());
+            info!("发送消息到车辆 {} (ID: {}) - 类型: 0x{:04X}, 数据长度: {}, 通道: {:?}", 
+                    connection.vehicle_name, vehicle_id, message_type, data.len(), lane);
             Ok(())
         } else {
             Err(format!("车辆 ID {} 未连接", vehicle_id))
@@ -555,11 +1049,10 @@ impl SocketServer {
         let conns = connections.read();
         debug!("当前连接数量: {}", conns.len());
         
-        let packet = build_message(message_type, data);
         let mut sent_count = 0;
         
         for (vehicle_id, connection) in conns.iter() {
-            match connection.sender.send(packet.clone()) {
+            match connection.send(message_type, data) {
                 Ok(_) => {
                     sent_count += 1;
                     debug!("广播消息到车辆 {} (ID: {}) - 类型: 0x{:04X}", 
@@ -576,194 +1069,184 @@ impl SocketServer {
         sent_count
     }
 
-    /// 发送消息给沙盘服务
+    /// 发送消息给沙盘服务；未指定沙盘ID时发送到默认沙盘（ID最小的在线沙盘），返回实际发送的沙盘ID
     pub fn send_to_sandbox(
         sandbox: &SandboxConnectionManager,
+        sandbox_id: Option<i64>,
         message_type: u16,
         data: &[u8],
-    ) -> Result<(), String> {
-        let conn_opt = sandbox.read();
-        if let Some(connection) = &*conn_opt {
-            let packet = build_message(message_type, data);
-            if let Err(e) = connection.sender.send(packet) {
-                return Err(format!("发送失败: {}", e));
-            }
-            info!("发送消息到沙盘服务 - 类型: 0x{:04X}, 数据长度: {}", message_type, data.len());
-            Ok(())
-        } else {
-            Err("沙盘服务离线".to_string())
+    ) -> Result<i64, String> {
+        let sandboxes = sandbox.read();
+        let (sandbox_id, connection) = match sandbox_id {
+            Some(id) => sandboxes
+                .get_key_value(&id)
+                .ok_or_else(|| format!("沙盘 {} 离线", id))?,
+            // 与红绿灯/摄像头的默认沙盘一致：按ID排序取第一个
+            None => sandboxes
+                .iter()
+                .min_by_key(|(id, _)| **id)
+                .ok_or_else(|| "沙盘服务离线".to_string())?,
+        };
+        if let Err(e) = connection.sender.send(message_type, data) {
+            return Err(format!("发送失败: {}", e));
         }
+        info!(
+            "发送消息到沙盘服务 {} (ID: {}) - 类型: 0x{:04X}, 数据长度: {}",
+            connection.vehicle_name, sandbox_id, message_type, data.len()
+        );
+        Ok(*sandbox_id)
+    }
+
+    /// 获取在线沙盘列表
+    pub fn get_sandbox_status(sandbox: &SandboxConnectionManager) -> Vec<serde_json::Value> {
+        let sandboxes = sandbox.read();
+        let mut status: Vec<_> = sandboxes.iter().map(|(sandbox_id, conn)| {
+            serde_json::json!({
+                "sandbox_id": sandbox_id,
+                "name": &conn.vehicle_name,
+                "address": conn.addr.to_string(),
+                "connected": true,
+                "send_queue": conn.sender.stats(),
+                "decoder": conn.decoder.snapshot()
+            })
+        }).collect();
+        status.sort_by_key(|entry| entry["sandbox_id"].as_i64());
+        status
     }
 
     /// 获取连接状态
     pub fn get_connection_status(connections: &ConnectionManager) -> Vec<serde_json::Value> {
         let conns = connections.read();
+        let now = Instant::now();
         conns.iter().map(|(vehicle_id, conn)| {
-            serde_json::json!({
+            let mut status = serde_json::json!({
                 "vehicle_id": vehicle_id,
                 "vehicle_name": &conn.vehicle_name,
                 "address": conn.addr.to_string(),
                 "connected": true
-            })
+            });
+            if let (Some(status), serde_json::Value::Object(liveness)) = (status.as_object_mut(), conn.liveness.to_json(now)) {
+                status.extend(liveness);
+            }
+            status["send_queue"] = serde_json::json!(conn.sender.stats());
+            status["protocol_version"] = serde_json::json!(conn.sender.version());
+            status["pending_commands"] = serde_json::json!(conn.commands.as_ref().map_or(0, CommandTracker::pending_count));
+            status["decoder"] = serde_json::json!(conn.decoder.snapshot());
+            status
         }).collect()
     }
+
+    /// 汇总所有车辆连接的解码统计
+    pub fn get_decoder_totals(connections: &ConnectionManager) -> DecoderStats {
+        connections.read().values().map(|conn| conn.decoder.snapshot()).fold(DecoderStats::default(), |total, stats| total + stats)
+    }
+
+    /// 汇总所有车辆连接的发送队列统计
+    pub fn get_send_queue_totals(connections: &ConnectionManager) -> SendQueueStats {
+        connections.read().values().map(|conn| conn.sender.stats()).fold(SendQueueStats::default(), |total, stats| total + stats)
+    }
 }
 
-mod sandbox_logger {
+#[cfg(test)]
+mod tests {
     use super::*;
+    use crate::config::{PerformanceConfig, TelemetryConfig};
+    use crate::protocol_processing::types::ProtocolConstants;
+    use crate::protocol_processing::ProtocolBuilder;
+    use crate::database::{ParkingSlot, VehicleDatabase};
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support;
+    use tempfile::TempDir;
 
-    pub fn handle_parsed_message(message: &SocketMessage) {
-        let mut processing_parser = ProcessingProtocolParser::new(false);
-        let result = processing_parser.parse_protocol(message.message_type, &message.data);
-        if message.message_type == MessageTypes::SANDBOX_TRAFFIC_LIGHT_STATUS {
-            if let Some(ParsedProtocolData::SandboxTrafficLightStatus(status)) = result.data {
-                log::info!("[Sandbox] 红绿灯状态: {}", status.lights.len());
-                for light in status.lights {
-                    let color_text = match light.color {
-                        1 => "红",
-                        2 => "绿",
-                        3 => "黄",
-                        _ => "未知",
-                    };
-                    log::info!("  - 灯{}: {} 剩余 {} 秒", light.index, color_text, light.remaining);
-                }
-            }
+    fn sandbox_connection(name: &str, port: u16) -> ClientConnection {
+        ClientConnection {
+            vehicle_id: -1,
+            vehicle_name: name.to_string(),
+            addr: SocketAddr::from(([127, 0, 0, 1], port)),
+            sender: SendQueue::new(4),
+            authenticated: false,
+            liveness: Arc::new(ConnectionLiveness::default()),
+            commands: None,
+            decoder: Arc::new(DecoderCounters::default()),
         }
     }
-}
 
-fn floats_close(a: f64, b: f64, epsilon: f64) -> bool {
-    (a - b).abs() <= epsilon
-}
+    #[test]
+    fn test_send_to_default_sandbox_when_several_online() {
+        let sandbox: SandboxConnectionManager = Arc::new(RwLock::new(HashMap::new()));
+        assert!(SocketServer::send_to_sandbox(&sandbox, None, 0x2001, &[1, 0]).is_err());
 
-fn vehicle_info_equal(a: &VehicleInfo, b: &VehicleInfo) -> bool {
-    a.vehicle_id == b.vehicle_id
-        && floats_close(a.speed, b.speed, 1e-6)
-        && floats_close(a.position_x, b.position_x, 1e-6)
-        && floats_close(a.position_y, b.position_y, 1e-6)
-        && floats_close(a.orientation, b.orientation, 1e-6)
-        && floats_close(a.battery, b.battery, 1e-6)
-        && a.gear == b.gear
-        && floats_close(a.steering_angle, b.steering_angle, 1e-6)
-        && a.nav_status == b.nav_status
-        && a.sensors.camera == b.sensors.camera
-        && a.sensors.lidar == b.sensors.lidar
-        && a.sensors.gyro == b.sensors.gyro
-        && a.parking_slot == b.parking_slot
-}
+        sandbox.write().insert(3, sandbox_connection("沙盘3", 9003));
+        sandbox.write().insert(2, sandbox_connection("沙盘2", 9002));
+        assert_eq!(SocketServer::send_to_sandbox(&sandbox, None, 0x2001, &[1, 0]), Ok(2));
+        assert_eq!(SocketServer::send_to_sandbox(&sandbox, Some(3), 0x2001, &[1, 0]), Ok(3));
+        assert_eq!(sandbox.read()[&2].sender.stats().depth, 1);
+    }
 
-fn parse_vehicle_info_payload(
-    data: &[u8],
-    vehicle_state: &Arc<RwLock<HashMap<u8, VehicleInfo>>>,
-    vehicle_id_i32: i32,
-    vehicle_name: &str,
-) -> Option<serde_json::Value> {
-    use crate::protocol_processing::types::ProtocolConstants;
+    #[tokio::test]
+    async fn test_startup_restores_parking_and_releases_stale_offline_occupants() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("startup.db")).await.unwrap();
+        // 1 号车位的占用车辆离线已超过保留时间，2 号车位的占用刚确认过
+        let stale = (chrono::Utc::now() - chrono::Duration::hours(48)).to_rfc3339();
+        db.save_parking_occupants(&[(1, 7, stale), (2, 8, chrono::Utc::now().to_rfc3339())]).await.unwrap();
 
-    let view = &data;
-    let vehicle_id = view[ProtocolConstants::VEHICLE_INFO_VEHICLE_ID_OFFSET];
-
-    let read_f64 = |offset: usize| -> f64 {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&view[offset..offset + 8]);
-        f64::from_le_bytes(bytes)
-    };
-
-    let speed = read_f64(ProtocolConstants::VEHICLE_INFO_SPEED_OFFSET);
-    let position_x = read_f64(ProtocolConstants::VEHICLE_INFO_POSITION_X_OFFSET);
-    let position_y = read_f64(ProtocolConstants::VEHICLE_INFO_POSITION_Y_OFFSET);
-    let orientation = read_f64(ProtocolConstants::VEHICLE_INFO_ORIENTATION_OFFSET);
-    let battery = read_f64(ProtocolConstants::VEHICLE_INFO_BATTERY_OFFSET);
-    let gear_raw = view[ProtocolConstants::VEHICLE_INFO_GEAR_OFFSET];
-    let gear = GearPosition::from_u8(gear_raw);
-    let steering_angle = read_f64(ProtocolConstants::VEHICLE_INFO_STEERING_ANGLE_OFFSET);
-    let nav_status = view[ProtocolConstants::VEHICLE_INFO_NAV_STATUS_OFFSET];
-    let camera_status = view[ProtocolConstants::VEHICLE_INFO_CAMERA_STATUS_OFFSET] != 0;
-    let lidar_status = view[ProtocolConstants::VEHICLE_INFO_LIDAR_STATUS_OFFSET] != 0;
-    let gyro_status = view[ProtocolConstants::VEHICLE_INFO_GYRO_STATUS_OFFSET] != 0;
-    let parking_slot = view[ProtocolConstants::VEHICLE_INFO_PARKING_SLOT_OFFSET];
-
-    let info = VehicleInfo {
-        vehicle_id,
-        speed,
-        position_x,
-        position_y,
-        orientation,
-        battery,
-        gear,
-        steering_angle,
-        nav_status,
-        sensors: crate::protocol_processing::types::SensorStatus {
-            camera: camera_status,
-            lidar: lidar_status,
-            gyro: gyro_status,
-        },
-        parking_slot,
-    };
-
-    let mut cache = vehicle_state.write();
-    if let Some(previous) = cache.get(&vehicle_id) {
-        if vehicle_info_equal(previous, &info) {
-            debug!(
-                "车辆 {} (ID: {}) 数据未变化，跳过更新",
-                vehicle_name,
-                vehicle_id_i32
-            );
-            return None;
-        }
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let parking_lot = ParkingLot::default();
+        let server = SocketServer::new(port, Arc::new(BroadcastEventSink::new(64, Some(db.clone()))))
+            .with_parking_lot(parking_lot.clone());
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+
+        let occupants = |slots: Vec<ParkingSlot>| slots.iter().map(|s| (s.slot_id, s.occupant)).collect::<Vec<_>>();
+        test_support::eventually("释放离线超时的占用", || async {
+            occupants(db.get_parking_slots().await.unwrap()) == vec![(1, None), (2, Some(8))]
+        })
+        .await;
+        assert_eq!(parking_lot.snapshot().iter().map(|s| s.occupant).collect::<Vec<_>>(), vec![None, Some(8)]);
     }
-    cache.insert(vehicle_id, info.clone());
-
-    Some(serde_json::json!({
-        "vehicle_id": info.vehicle_id,
-        "speed": info.speed,
-        "position": {"x": info.position_x, "y": info.position_y},
-        "orientation": info.orientation,
-        "battery": info.battery,
-        "gear": {
-            "value": gear.to_u8(),
-            "label": gear.label(),
-        },
-        "steeringAngle": info.steering_angle,
-        "navigation": {
-            "code": info.nav_status,
-            "text": nav_status_text(info.nav_status),
-        },
-        "sensors": {
-            "camera": {
-                "status": camera_status,
-                "text": if camera_status { "正常" } else { "异常" }
-            },
-            "lidar": {
-                "status": lidar_status,
-                "text": if lidar_status { "正常" } else { "异常" }
-            },
-            "gyro": {
-                "status": gyro_status,
-                "text": if gyro_status { "正常" } else { "异常" }
-            }
-        },
-        "parkingSlot": info.parking_slot
-    }))
-}
 
-fn nav_status_text(code: u8) -> &'static str {
-    match code {
-        1 => "正常行驶中（空载模式不倒车入库）",
-        2 => "正常行驶中（空载模式倒车入库）",
-        3 => "接客模式，去起点接客",
-        4 => "接客模式，去终点送客",
-        5 => "去往充电车位",
-        6 => "充电中",
-        7 => "去往停车位路上",
-        8 => "车位停车中",
-        9 => "到达接客起点",
-        10 => "到达接客终点",
-        11 => "正在倒车入库",
-        12 => "正在出库中",
-        13 => "正在倒车入库",
-        14 => "出库完成",
-        15 => "平行驾驶模式",
-        _ => "未知状态",
+    #[tokio::test]
+    async fn test_unauthenticated_peer_cannot_take_over_authenticated_id() {
+        let sink: SharedEventSink = Arc::new(BroadcastEventSink::new(64, None));
+        let fleet = FleetUpdateEmitter::start(sink.clone(), &PerformanceConfig::default(), FleetState::default());
+        let telemetry = TelemetryRecorder::start(sink.clone(), TelemetryConfig::default());
+        let connections: ConnectionManager = Arc::new(RwLock::new(HashMap::new()));
+        for (vehicle_id, authenticated) in [(1, true), (9, false)] {
+            let connection = ClientConnection {
+                vehicle_id,
+                authenticated,
+                ..sandbox_connection(&format!("{}号车", vehicle_id), 7000 + vehicle_id as u16)
+            };
+            fleet.state().connect(vehicle_id, &connection.vehicle_name, connection.addr.to_string(), authenticated);
+            connections.write().insert(vehicle_id, connection);
+        }
+        let vehicle_info = |vehicle_id: u8| SocketMessage {
+            version: ProtocolConstants::PROTOCOL_VERSION_V10,
+            timestamp: 1,
+            message_type: MessageTypes::VEHICLE_INFO,
+            data: ProtocolBuilder::new().build_vehicle_info(&test_support::vehicle_info(vehicle_id)),
+        };
+
+        // 未认证的 9 号连接冒用已认证的 1 号车编号：丢弃数据帧，连接与车辆状态保持不变
+        let reassigned =
+            SocketServer::handle_message(vehicle_info(1), 9, "9号车", &sink, &fleet, connections.clone(), &telemetry).await;
+        assert!(reassigned.is_none());
+        {
+            let conns = connections.read();
+            assert_eq!((conns[&1].addr.port(), conns[&1].authenticated), (7001, true));
+            assert_eq!(conns[&9].vehicle_id, 9);
+        }
+        let owner = fleet.state().get(1).unwrap();
+        assert_eq!(owner.connection.unwrap().addr, "127.0.0.1:7001");
+        assert!(owner.info.is_none());
+
+        // 未被已认证连接使用的编号仍按数据帧纠正
+        let reassigned =
+            SocketServer::handle_message(vehicle_info(2), 9, "9号车", &sink, &fleet, connections.clone(), &telemetry).await;
+        assert_eq!(reassigned, Some((2, "9号车".to_string())));
+        let conns = connections.read();
+        assert!(conns.contains_key(&2) && !conns.contains_key(&9));
     }
 }
@@ -0,0 +1,201 @@
+//! 车辆遥测记录器
+//!
+//! 将每一帧 0x0002 车辆信息（含协议时间戳与接收时间）异步批量写入 `vehicle_telemetry` 表，
+//! 并按保留时长与最大条数定期清理。写入在后台任务中完成，不阻塞连接处理。
+
+use super::event_sink::SharedEventSink;
+use crate::config::TelemetryConfig;
+use crate::database::VehicleTelemetry;
+use crate::protocol_processing::types::VehicleInfo;
+use log::{debug, error, info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 写入队列容量（超出后丢弃新记录）
+const QUEUE_CAPACITY: usize = 8192;
+
+/// 车辆遥测记录器
+#[derive(Clone)]
+pub struct TelemetryRecorder {
+    sender: Option<mpsc::Sender<VehicleTelemetry>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl TelemetryRecorder {
+    /// 启动后台写入任务；配置关闭时返回不记录的实例
+    pub fn start(sink: SharedEventSink, config: TelemetryConfig) -> Self {
+        if !config.enabled {
+            info!("车辆遥测记录已关闭");
+            return Self::disabled();
+        }
+
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run_writer(receiver, sink, config));
+
+        Self {
+            sender: Some(sender),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 不记录任何数据的实例（用于回放等场景）
+    pub fn disabled() -> Self {
+        Self {
+            sender: None,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 记录一帧车辆信息
+    pub fn record(&self, info: &VehicleInfo, protocol_timestamp: u64) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let record = VehicleTelemetry::from_vehicle_info(info, protocol_timestamp, chrono::Utc::now());
+        if sender.try_send(record).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped % 1000 == 1 {
+                warn!("遥测写入队列已满，累计丢弃 {} 条记录", dropped);
+            }
+        }
+    }
+}
+
+/// 后台写入循环：按间隔或批量大小刷新，并定期清理过期数据
+async fn run_writer(
+    mut receiver: mpsc::Receiver<VehicleTelemetry>,
+    sink: SharedEventSink,
+    config: TelemetryConfig,
+) {
+    let batch_size = config.batch_size.max(1);
+    let mut batch: Vec<VehicleTelemetry> = Vec::with_capacity(batch_size);
+    let mut flush_timer = tokio::time::interval(Duration::from_millis(config.flush_interval.max(1) as u64));
+    let mut prune_timer = tokio::time::interval(Duration::from_millis(config.prune_interval.max(1) as u64));
+
+    loop {
+        tokio::select! {
+            record = receiver.recv() => {
+                match record {
+                    Some(record) => {
+                        batch.push(record);
+                        if batch.len() >= batch_size {
+                            flush(&sink, &mut batch).await;
+                        }
+                    }
+                    None => {
+                        flush(&sink, &mut batch).await;
+                        debug!("遥测记录器已停止");
+                        break;
+                    }
+                }
+            }
+            _ = flush_timer.tick() => {
+                flush(&sink, &mut batch).await;
+            }
+            _ = prune_timer.tick() => {
+                if let Some(db) = sink.database() {
+                    match db.prune_vehicle_telemetry(config.retention_hours, config.max_rows).await {
+                        Ok(0) => {}
+                        Ok(removed) => info!("已清理 {} 条过期车辆遥测记录", removed),
+                        Err(e) => error!("清理车辆遥测记录失败: {}", e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn flush(sink: &SharedEventSink, batch: &mut Vec<VehicleTelemetry>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    match sink.database() {
+        Some(db) => {
+            if let Err(e) = db.insert_vehicle_telemetry_batch(batch).await {
+                error!("写入车辆遥测记录失败 ({} 条): {}", batch.len(), e);
+            }
+        }
+        None => warn!("数据库未就绪，丢弃 {} 条车辆遥测记录", batch.len()),
+    }
+    batch.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::VehicleDatabase;
+    use crate::socket::BroadcastEventSink;
+    use crate::test_support;
+    use tempfile::TempDir;
+
+    fn sample_info(vehicle_id: u8) -> VehicleInfo {
+        VehicleInfo { speed: 0.5, position_x: 1.0, position_y: 2.0, ..test_support::vehicle_info(vehicle_id) }
+    }
+
+    #[tokio::test]
+    async fn test_recorder_persists_every_frame() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+        let sink: SharedEventSink = Arc::new(BroadcastEventSink::new(8, Some(db.clone())));
+        let config = TelemetryConfig {
+            flush_interval: 20,
+            ..TelemetryConfig::default()
+        };
+
+        let recorder = TelemetryRecorder::start(sink, config);
+        // 相同内容的帧也要逐帧记录
+        for timestamp in 0..3 {
+            recorder.record(&sample_info(7), timestamp);
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let records = db.get_vehicle_telemetry(7, None, None, 100).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].protocol_timestamp, 2);
+        assert_eq!(records[0].battery, 80.0);
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_newest_rows() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+
+        let records: Vec<VehicleTelemetry> = (0..5)
+            .map(|ts| VehicleTelemetry::from_vehicle_info(&sample_info(1), ts, chrono::Utc::now()))
+            .collect();
+        db.insert_vehicle_telemetry_batch(&records).await.unwrap();
+
+        let removed = db.prune_vehicle_telemetry(72, 2).await.unwrap();
+        assert_eq!(removed, 3);
+
+        let remaining = db.get_vehicle_telemetry(1, None, None, 100).await.unwrap();
+        let timestamps: Vec<i64> = remaining.iter().map(|r| r.protocol_timestamp).collect();
+        assert_eq!(timestamps, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_query_normalizes_time_range() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+
+        let base = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let records: Vec<VehicleTelemetry> = (0..3)
+            .map(|ts| VehicleTelemetry::from_vehicle_info(&sample_info(1), ts, base + chrono::Duration::hours(ts as i64)))
+            .collect();
+        db.insert_vehicle_telemetry_batch(&records).await.unwrap();
+
+        // 带时区偏移的时间按 UTC 比较：09:00+08:00 即 01:00Z
+        let found = db
+            .get_vehicle_telemetry(1, Some("2026-01-01T09:00:00+08:00"), Some("2026-01-01T01:00:00Z"), 100)
+            .await
+            .unwrap();
+        let timestamps: Vec<i64> = found.iter().map(|r| r.protocol_timestamp).collect();
+        assert_eq!(timestamps, vec![1]);
+
+        assert!(db.get_vehicle_telemetry(1, Some("yesterday"), None, 100).await.is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! 车辆Socket传输加密
+//!
+//! 可选的 TLS 模式（rustls）：启用后 Socket 端口只接受 TLS 连接，TLS 流内仍是
+//! `protocol.rs` 定义的帧格式。配置客户端 CA 后服务器在握手时请求客户端证书，
+//! 证书 SHA-256 指纹与 `vehicle_connections.cert_fingerprint` 匹配的车辆直接以该编号
+//! 登记（服务器直接返回 0x100B 认证通过，不再下发挑战）；未绑定车辆的证书仍走
+//! `auth.rs` 的挑战握手。
+
+use crate::config::SocketTlsConfig;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio_rustls::rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth};
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, RootCertStore, ServerConfig, ServerConnection};
+
+pub use tokio_rustls::TlsAcceptor;
+
+/// TLS 配置加载失败原因
+#[derive(Debug, thiserror::Error)]
+pub enum TlsSetupError {
+    #[error("未配置{0}")]
+    Missing(&'static str),
+    #[error("读取{what}失败 ({path}): {source}")]
+    Io {
+        what: &'static str,
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{what}中没有可用的证书或私钥 ({path})")]
+    Empty { what: &'static str, path: String },
+    #[error("TLS配置无效: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// 按配置构建 TLS 接收器
+pub fn build_acceptor(config: &SocketTlsConfig) -> Result<TlsAcceptor, TlsSetupError> {
+    let cert_path = config.cert_path.as_deref().ok_or(TlsSetupError::Missing("服务器证书路径"))?;
+    let key_path = config.key_path.as_deref().ok_or(TlsSetupError::Missing("服务器私钥路径"))?;
+    let certs = load_certs(cert_path, "服务器证书")?;
+    let key = load_private_key(key_path)?;
+
+    let verifier = match config.client_ca_path.as_deref() {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path, "客户端CA证书")? {
+                roots.add(&cert).map_err(|e| rustls::Error::General(format!("客户端CA证书无效: {}", e)))?;
+            }
+            if config.require_client_cert {
+                AllowAnyAuthenticatedClient::new(roots).boxed()
+            } else {
+                AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed()
+            }
+        }
+        None if config.require_client_cert => return Err(TlsSetupError::Missing("客户端CA证书路径")),
+        None => NoClientAuth::boxed(),
+    };
+
+    let server_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+fn open(path: &str, what: &'static str) -> Result<BufReader<File>, TlsSetupError> {
+    File::open(path).map(BufReader::new).map_err(|source| TlsSetupError::Io {
+        what,
+        path: path.to_string(),
+        source,
+    })
+}
+
+fn load_certs(path: &str, what: &'static str) -> Result<Vec<Certificate>, TlsSetupError> {
+    let certs = rustls_pemfile::certs(&mut open(path, what)?).map_err(|source| TlsSetupError::Io {
+        what,
+        path: path.to_string(),
+        source,
+    })?;
+    if certs.is_empty() {
+        return Err(TlsSetupError::Empty { what, path: path.to_string() });
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_private_key(path: &str) -> Result<PrivateKey, TlsSetupError> {
+    let what = "服务器私钥";
+    let mut reader = open(path, what)?;
+    loop {
+        let item = rustls_pemfile::read_one(&mut reader).map_err(|source| TlsSetupError::Io {
+            what,
+            path: path.to_string(),
+            source,
+        })?;
+        match item {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => {}
+            None => return Err(TlsSetupError::Empty { what, path: path.to_string() }),
+        }
+    }
+}
+
+/// 证书 DER 的 SHA-256 指纹（小写十六进制）
+pub fn cert_fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 客户端证书指纹（未出示证书时为 None）
+pub fn peer_fingerprint(connection: &ServerConnection) -> Option<String> {
+    connection
+        .peer_certificates()?
+        .first()
+        .map(|cert| cert_fingerprint(&cert.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VehicleAuthConfig;
+    use crate::database::{CreateVehicleConnectionRequest, VehicleDatabase};
+    use crate::protocol_processing::types::{MessageTypes, SendMessageTypes};
+    use crate::socket::protocol::{build_message, ProtocolParser};
+    use crate::socket::{BroadcastEventSink, SocketServer};
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::ClientConfig;
+    use tokio_rustls::TlsConnector;
+
+    fn testdata(name: &str) -> String {
+        format!("{}/src/socket/testdata/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn connector(client_cert: bool) -> TlsConnector {
+        let mut roots = RootCertStore::empty();
+        for cert in load_certs(&testdata("ca.pem"), "CA").unwrap() {
+            roots.add(&cert).unwrap();
+        }
+        let builder = ClientConfig::builder().with_safe_defaults().with_root_certificates(roots);
+        let config = if client_cert {
+            builder
+                .with_client_auth_cert(
+                    load_certs(&testdata("vehicle.pem"), "车辆证书").unwrap(),
+                    load_private_key(&testdata("vehicle.key")).unwrap(),
+                )
+                .unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+        TlsConnector::from(Arc::new(config))
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_maps_to_vehicle_id() {
+        let dir = TempDir::new().unwrap();
+        let db = VehicleDatabase::open(&dir.path().join("vehicles.db")).await.unwrap();
+        // 与 `openssl x509 -fingerprint -sha256` 的输出格式一致
+        db.create_vehicle_connection(CreateVehicleConnectionRequest {
+            vehicle_id: 5,
+            ip_address: "10.0.0.5".to_string(),
+            name: "5号车".to_string(),
+            description: None,
+            color: None,
+            auth_key: None,
+            cert_fingerprint: Some(
+                "17:6D:18:85:CD:5E:DA:CD:B0:E5:4B:36:88:C4:73:86:10:7F:98:9A:B5:C8:9C:14:92:E0:DA:78:C9:43:08:DE"
+                    .to_string(),
+            ),
+        })
+        .await
+        .unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sink = Arc::new(BroadcastEventSink::new(256, Some(db)));
+        let mut events = sink.subscribe();
+        let server = SocketServer::new(port, sink.clone())
+            .with_vehicle_auth(VehicleAuthConfig {
+                required: true,
+                handshake_timeout: 500,
+            })
+            .with_tls_config(SocketTlsConfig {
+                enabled: true,
+                cert_path: Some(testdata("server.pem")),
+                key_path: Some(testdata("server.key")),
+                client_ca_path: Some(testdata("ca.pem")),
+                require_client_cert: true,
+            });
+        tokio::spawn(async move {
+            let _ = server.start().await;
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let server_name = rustls::ServerName::try_from("localhost").unwrap();
+        let tcp = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = connector(true).connect(server_name.clone(), tcp).await.unwrap();
+
+        // 证书已绑定5号车：无需挑战，直接返回认证通过
+        let mut parser = ProtocolParser::new();
+        let mut buffer = [0u8; 1024];
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(Some(message)) = parser.try_parse_message() {
+                    break message;
+                }
+                let n = stream.read(&mut buffer).await.unwrap();
+                assert!(n > 0);
+                parser.feed_data(&buffer[..n]);
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.message_type, SendMessageTypes::AUTH_RESULT);
+        assert_eq!(result.data, vec![0, 5]);
+
+        stream.write_all(&build_message(MessageTypes::HEARTBEAT, &[])).await.unwrap();
+        let connected = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let Ok(event) = events.recv().await else {
+                    continue;
+                };
+                if event.event == "vehicle-connect" {
+                    break event.payload["vehicle_id"].as_i64();
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(connected, Some(5));
+
+        // 要求客户端证书时，未出示证书的连接收不到任何帧
+        let tcp = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        if let Ok(mut anonymous) = connector(false).connect(server_name, tcp).await {
+            let read = tokio::time::timeout(Duration::from_secs(5), anonymous.read(&mut buffer)).await.unwrap();
+            assert!(!matches!(read, Ok(n) if n > 0));
+        }
+    }
+}
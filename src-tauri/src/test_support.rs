@@ -0,0 +1,79 @@
+//! 单元测试共用的样例数据与替身
+
+use crate::protocol_processing::types::{GearPosition, SensorStatus, VehicleInfo};
+use crate::services::taxi_dispatch::OrderSender;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// 轮询条件的最长等待时间
+const DEADLINE: Duration = Duration::from_secs(5);
+
+/// 样例车辆信息：原点静止、空载导航、电量 80%、传感器正常，各测试按需覆盖字段
+pub fn vehicle_info(vehicle_id: u8) -> VehicleInfo {
+    VehicleInfo {
+        vehicle_id,
+        speed: 0.0,
+        position_x: 0.0,
+        position_y: 0.0,
+        orientation: 0.0,
+        battery: 80.0,
+        gear: GearPosition::from_u8(4),
+        steering_angle: 0.0,
+        nav_status: 1,
+        sensors: SensorStatus { camera: true, lidar: true, gyro: true },
+        parking_slot: 0,
+    }
+}
+
+/// 记录下发订单与取消订单的车辆，`offline` 中的车辆下发失败
+#[derive(Default)]
+pub struct RecordingSender {
+    pub sent: Mutex<Vec<i32>>,
+    pub cancelled: Mutex<Vec<i32>>,
+    pub offline: Vec<i32>,
+}
+
+impl OrderSender for RecordingSender {
+    fn send_order(&self, vehicle_id: i32, _payload: &[u8]) -> Result<(), String> {
+        if self.offline.contains(&vehicle_id) {
+            return Err(format!("车辆 ID {} 未连接", vehicle_id));
+        }
+        self.sent.lock().push(vehicle_id);
+        Ok(())
+    }
+
+    fn cancel_order(&self, vehicle_id: i32) -> Result<(), String> {
+        if self.offline.contains(&vehicle_id) {
+            return Err(format!("车辆 ID {} 未连接", vehicle_id));
+        }
+        self.cancelled.lock().push(vehicle_id);
+        Ok(())
+    }
+}
+
+/// 轮询直到条件成立，超过 [`DEADLINE`] 仍不成立则测试失败
+pub async fn eventually<F, Fut>(what: &str, mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + DEADLINE;
+    while !condition().await {
+        assert!(tokio::time::Instant::now() < deadline, "等待超时: {}", what);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+/// 连接本机测试服务器；服务器开始监听前重试，超过 [`DEADLINE`] 仍无法连接则测试失败
+pub async fn connect(port: u16) -> TcpStream {
+    let deadline = tokio::time::Instant::now() + DEADLINE;
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(stream) => return stream,
+            Err(e) => assert!(tokio::time::Instant::now() < deadline, "连接测试服务器超时: {}", e),
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
@@ -141,6 +141,13 @@ impl VideoPacketHeader {
         
         let data_length = u32::from_le_bytes([bytes[19], bytes[20], bytes[21], bytes[22]]);
 
+        if total_fragments == 0 || fragment_index >= total_fragments {
+            return Err(format!(
+                "Invalid fragment index: {} of {}",
+                fragment_index, total_fragments
+            ));
+        }
+
         Ok(Self {
             version,
             frame_type,
@@ -199,7 +206,8 @@ pub struct FrameAssembler {
     pub expected_fragments: u16,
     pub frame_id: u32,
     pub vehicle_id: u8,
-    pub timestamp: u64,
+    /// 本地收到首个分片的时间（超时清理按本地时钟，不依赖车端时间戳）
+    pub received_at: std::time::Instant,
 }
 
 impl FrameAssembler {
@@ -209,7 +217,7 @@ impl FrameAssembler {
             expected_fragments: header.total_fragments,
             frame_id: header.frame_id,
             vehicle_id: header.vehicle_id,
-            timestamp: header.timestamp,
+            received_at: std::time::Instant::now(),
         }
     }
 
@@ -220,6 +228,11 @@ impl FrameAssembler {
             return false;
         }
 
+        // 分片数与首片不一致或索引越界的分片直接丢弃
+        if header.total_fragments != self.expected_fragments || header.fragment_index >= self.expected_fragments {
+            return false;
+        }
+
         self.fragments.insert(header.fragment_index, data);
         
         // 检查是否所有分片都已收到
@@ -248,6 +261,7 @@ impl FrameAssembler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_header_serialization() {
@@ -275,4 +289,54 @@ mod tests {
         assert_eq!(packet.data, decoded_packet.data);
         assert_eq!(packet.header.vehicle_id, decoded_packet.header.vehicle_id);
     }
+
+    #[test]
+    fn test_invalid_fragment_index_rejected() {
+        let mut header = VideoPacketHeader::new_fragment_frame(1, 1, 3, 3, 1000, 0, false, true);
+        assert!(VideoPacketHeader::from_bytes(&header.to_bytes()).is_err());
+        header.fragment_index = 0;
+        header.total_fragments = 0;
+        assert!(VideoPacketHeader::from_bytes(&header.to_bytes()).is_err());
+    }
+
+    proptest! {
+        /// 任意大小的帧按任意分片大小拆包、乱序到达后重组为原始数据
+        #[test]
+        fn test_fragments_reassemble_in_any_order(
+            (frame, order) in (prop::collection::vec(any::<u8>(), 1..2048), 1usize..512)
+                .prop_flat_map(|(frame, mtu)| {
+                    let chunks: Vec<Vec<u8>> = frame.chunks(mtu).map(<[u8]>::to_vec).collect();
+                    (Just(frame), Just(chunks.into_iter().enumerate().collect::<Vec<_>>()).prop_shuffle())
+                }),
+            vehicle_id in any::<u8>(),
+            frame_id in any::<u32>(),
+            timestamp in any::<u64>(),
+        ) {
+            let total = order.len() as u16;
+            let mut assembler: Option<FrameAssembler> = None;
+            let mut completed = Vec::new();
+            for (index, chunk) in order {
+                let index = index as u16;
+                let header = VideoPacketHeader::new_fragment_frame(
+                    vehicle_id, frame_id, index, total, timestamp, chunk.len() as u32, index == 0, index + 1 == total,
+                );
+                let packet = VideoPacket::from_udp_packet(&VideoPacket { header, data: chunk }.to_udp_packet()).unwrap();
+                let assembler = assembler.get_or_insert_with(|| FrameAssembler::new(&packet.header));
+                completed.push(assembler.add_fragment(&packet.header, packet.data));
+            }
+            // 只有最后到达的分片使重组完成
+            prop_assert_eq!(completed.iter().filter(|done| **done).count(), 1);
+            prop_assert_eq!(completed.last(), Some(&true));
+            prop_assert_eq!(assembler.unwrap().assemble_frame(), Some(frame));
+        }
+
+        /// 任意字节不会使解包崩溃；解出的包重新序列化后内容一致
+        #[test]
+        fn test_arbitrary_packets_never_panic(packet in prop::collection::vec(any::<u8>(), 0..64)) {
+            if let Ok(decoded) = VideoPacket::from_udp_packet(&packet) {
+                prop_assert!(decoded.header.fragment_index < decoded.header.total_fragments);
+                prop_assert_eq!(&decoded.to_udp_packet()[2..], &packet[2..]);
+            }
+        }
+    }
 }
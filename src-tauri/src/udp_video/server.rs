@@ -5,9 +5,10 @@ use std::time::Duration;
 use tokio::net::UdpSocket;
 use tokio::sync::{broadcast, RwLock};
 use tokio::time::timeout;
-use tauri::Emitter;
 use base64::Engine;
 
+use crate::socket::event_sink::SharedEventSink;
+
 use super::protocol::{FrameAssembler, VideoPacket, VideoPacketHeader};
 
 /// 视频帧数据
@@ -25,7 +26,7 @@ pub struct UdpVideoServer {
     frame_sender: broadcast::Sender<VideoFrame>,
     assemblers: Arc<RwLock<HashMap<(u8, u32), FrameAssembler>>>, // (vehicle_id, frame_id) -> FrameAssembler
     running: Arc<RwLock<bool>>,
-    app_handle: Option<tauri::AppHandle>,
+    event_sink: Option<SharedEventSink>,
 }
 
 impl UdpVideoServer {
@@ -41,13 +42,13 @@ impl UdpVideoServer {
             frame_sender,
             assemblers: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(RwLock::new(false)),
-            app_handle: None,
+            event_sink: None,
         })
     }
 
-    /// 设置Tauri应用句柄
-    pub fn set_app_handle(&mut self, app_handle: tauri::AppHandle) {
-        self.app_handle = Some(app_handle);
+    /// 设置事件出口（图形界面模式下为 Tauri 应用句柄）
+    pub fn set_event_sink(&mut self, event_sink: SharedEventSink) {
+        self.event_sink = Some(event_sink);
     }
 
     /// 获取视频帧接收器
@@ -130,10 +131,8 @@ impl UdpVideoServer {
                             // 没有订阅者，忽略错误
                         }
 
-                        // 发送Tauri事件到前端
-                        if let Some(app_handle) = &self.app_handle {
-                            let _ = app_handle.emit_to("main", "udp-video-frame", &frame);
-                        }
+                        // 发送事件到前端
+                        self.emit_frame(&frame);
                     }
                     _ => {
                         // 分片帧，需要重组
@@ -198,10 +197,8 @@ impl UdpVideoServer {
                     // 没有订阅者，忽略错误
                 }
 
-                // 发送Tauri事件到前端
-                if let Some(app_handle) = &self.app_handle {
-                    let _ = app_handle.emit_to("main", "udp-video-frame", &frame);
-                }
+                // 发送事件到前端
+                self.emit_frame(&frame);
             }
 
             // 移除已完成的重组器
@@ -209,6 +206,16 @@ impl UdpVideoServer {
         }
     }
 
+    /// 推送视频帧事件
+    fn emit_frame(&self, frame: &VideoFrame) {
+        if let Some(sink) = &self.event_sink {
+            match serde_json::to_value(frame) {
+                Ok(payload) => sink.emit("udp-video-frame", payload),
+                Err(e) => log::error!("序列化视频帧失败: {}", e),
+            }
+        }
+    }
+
     /// 清理超时的重组器
     async fn cleanup_task(
         assemblers: Arc<RwLock<HashMap<(u8, u32), FrameAssembler>>>,
@@ -220,17 +227,10 @@ impl UdpVideoServer {
             cleanup_interval.tick().await;
             
             let mut assemblers = assemblers.write().await;
-            let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
-                Ok(duration) => duration.as_millis() as u64,
-                Err(_) => {
-                    log::warn!("系统时间异常，跳过清理任务");
-                    continue;
-                }
-            };
             
-            // 移除超过5秒未完成的重组器
+            // 移除超过5秒未完成的重组器（按本地接收时间，车端时间戳不可信）
             assemblers.retain(|&(_vehicle_id, _frame_id), assembler| {
-                now - assembler.timestamp < 5000
+                assembler.received_at.elapsed() < Duration::from_secs(5)
             });
         }
     }
@@ -267,7 +267,7 @@ impl UdpVideoManager {
     }
 
     /// 启动UDP视频服务器
-    pub async fn start_server(&mut self, bind_addr: &str, app_handle: Option<tauri::AppHandle>) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn start_server(&mut self, bind_addr: &str, event_sink: Option<SharedEventSink>) -> Result<(), Box<dyn std::error::Error>> {
         if self.server.is_some() {
             // UDP视频服务器已经启动
             return Ok(());
@@ -275,9 +275,9 @@ impl UdpVideoManager {
 
         let mut server = UdpVideoServer::new(bind_addr).await?;
         
-        // 设置应用句柄
-        if let Some(handle) = app_handle {
-            server.set_app_handle(handle);
+        // 设置事件出口
+        if let Some(sink) = event_sink {
+            server.set_event_sink(sink);
         }
         
         let server_clone = server.clone();
@@ -331,7 +331,7 @@ impl Clone for UdpVideoServer {
             frame_sender: self.frame_sender.clone(),
             assemblers: Arc::clone(&self.assemblers),
             running: Arc::clone(&self.running),
-            app_handle: self.app_handle.clone(),
+            event_sink: self.event_sink.clone(),
         }
     }
 }
@@ -118,24 +118,8 @@ function getDefaultConfig() {
             footer_size: 4,
             min_packet_size: 25,
         },
-        vehicle_info_protocol: {
-            vehicle_id_offset: 0,
-            speed_offset: 1,
-            position_x_offset: 9,
-            position_y_offset: 17,
-            orientation_offset: 25,
-            battery_offset: 33,
-            gear_offset: 41,
-            steering_angle_offset: 42,
-            nav_status_offset: 50,
-            camera_status_offset: 51,
-            lidar_status_offset: 52,
-            gyro_status_offset: 53,
-            parking_slot_offset: 54,
-            total_size: 55,
-            min_speed: 0.0,
-            max_speed: 1.0,
-        },
+        // 数据域布局由后端 messages.toml 导出，离线默认值不含布局
+        messages: [],
         nav_status_texts: {
             '1': '正常行驶中（空载模式不倒车入库）',
             '2': '正常行驶中（空载模式倒车入库）',
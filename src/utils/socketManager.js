@@ -7,7 +7,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import vehicleBridge from '@/utils/vehicleBridge.js';
 import eventBus, { EVENTS } from '@/utils/eventBus.js';
-import { RECEIVE_MESSAGE_TYPES, MessageTypeUtils, VEHICLE_CONTROL_PROTOCOL, SEND_MESSAGE_TYPES, AVP_PARKING_PROTOCOL, VEHICLE_CAMERA_PROTOCOL, DATA_RECORDING_PROTOCOL, SANDBOX_LIGHTING_PROTOCOL, SANDBOX_TRAFFIC_LIGHT_PROTOCOL } from '@/constants/messageTypes.js';
+import { RECEIVE_MESSAGE_TYPES, MessageTypeUtils, VEHICLE_CONTROL_PROTOCOL, SEND_MESSAGE_TYPES, VEHICLE_CAMERA_PROTOCOL, DATA_RECORDING_PROTOCOL, SANDBOX_LIGHTING_PROTOCOL, SANDBOX_TRAFFIC_LIGHT_PROTOCOL } from '@/constants/messageTypes.js';
 import Toast from '@/utils/toast.js';
 import { createLogger, logger } from '@/utils/logger.js';
 import { debug as plDebug, info as plInfo, warn as plWarn, error as plError } from '@tauri-apps/plugin-log';
@@ -152,6 +152,18 @@ class SocketManager {
                 this.handleSandboxDisconnect(event.payload)
             });
             this.unlisteners.push(unlisten5);
+
+            // 监听按帧率合并后的车辆状态推送
+            const unlisten6 = await listen('fleet-update', (event) => {
+                this.handleFleetUpdate(event.payload);
+            });
+            this.unlisteners.push(unlisten6);
+
+            // 监听出租车订单状态变更
+            const unlisten7 = await listen('taxi-order-update', (event) => {
+                this.handleTaxiOrderUpdate(event.payload);
+            });
+            this.unlisteners.push(unlisten7);
             
             socketLogger.info('开始监听Socket消息和断开连接事件');
         } catch (error) {
@@ -251,6 +263,70 @@ class SocketManager {
         }
     }
 
+    /**
+     * 处理合并后的车辆状态推送（每辆车只携带周期内最新的一帧）
+     */
+    handleFleetUpdate(payload) {
+        const vehicles = Array.isArray(payload?.vehicles) ? payload.vehicles : [];
+        const handler = this.messageHandlers.get(RECEIVE_MESSAGE_TYPES.VEHICLE_INFO);
+        for (const { vehicle_id, timestamp, parsed, changed_fields } of vehicles) {
+            this.updateVehicleStatus(vehicle_id, true);
+            try {
+                handler?.(vehicle_id, parsed, timestamp, changed_fields);
+            } catch (error) {
+                socketLogger.error(`处理车辆 ${vehicle_id} 状态推送失败:`, error);
+                plError(`处理车辆 ${vehicle_id} 状态推送失败: ${error}`).catch(() => {});
+            }
+        }
+    }
+
+    /**
+     * 处理出租车订单状态变更：改派时转移打车状态，订单结束（完成/取消/失败）时清理该车辆的打车状态
+     */
+    handleTaxiOrderUpdate(payload) {
+        const { order, transition } = payload || {};
+        if (!order || !transition) {
+            return;
+        }
+        socketLogger.info(`出租车订单 ${order.order_id}: ${transition.from_status ?? '-'} -> ${transition.to_status}${transition.reason ? `（${transition.reason}）` : ''}`);
+
+        const vehicleId = order.assigned_vehicle_id;
+        if (!vehicleId) {
+            return;
+        }
+        const store = this.ensureCarStore();
+
+        // 自动派单改派：打车状态与图标转移到新车辆
+        if (transition.from_status === 'assigned' && transition.to_status === 'assigned') {
+            const previous = [...store.activeTaxiRides.entries()]
+                .find(([id, ride]) => id !== vehicleId && ride.orderId === order.order_id);
+            if (!previous) {
+                return;
+            }
+            const [previousId, ride] = previous;
+            store.removeActiveTaxiRide(previousId);
+            store.addActiveTaxiRide(vehicleId, ride.startCoords, ride.endCoords, order.order_id);
+            import('@/components/Scene3D/index.js')
+                .then(({ removeTaxiMarkersForVehicle, createTaxiMarkersForVehicle }) => {
+                    removeTaxiMarkersForVehicle(previousId);
+                    createTaxiMarkersForVehicle(vehicleId, ride.startCoords, ride.endCoords);
+                })
+                .catch((error) => console.warn('转移车辆打车图标失败:', error));
+            return;
+        }
+
+        if (!['completed', 'cancelled', 'failed'].includes(order.status)) {
+            return;
+        }
+        if (store.getActiveTaxiRide(vehicleId)?.orderId !== order.order_id) {
+            return;
+        }
+        store.removeActiveTaxiRide(vehicleId);
+        import('@/components/Scene3D/index.js')
+            .then(({ removeTaxiMarkersForVehicle }) => removeTaxiMarkersForVehicle(vehicleId))
+            .catch((error) => console.warn('清除车辆打车图标失败:', error));
+    }
+
     /**
      * 设置默认消息处理器
      */
@@ -262,13 +338,13 @@ class SocketManager {
         });
 
         // 车辆信息协议处理
-        this.setMessageHandler(RECEIVE_MESSAGE_TYPES.VEHICLE_INFO, (carId, parsed, timestamp) => {
+        this.setMessageHandler(RECEIVE_MESSAGE_TYPES.VEHICLE_INFO, (carId, parsed, timestamp, changedFields) => {
             if (!parsed || typeof parsed !== 'object') {
                 socketLogger.warn(`收到的车辆信息缺少解析数据 - 车辆: ${carId}`);
                 return;
             }
             // socketLogger.info(`收到车辆信息 - 车辆: ${carId}`);
-            this.updateVehicleInfoFromParsed(carId, parsed, timestamp);
+            this.updateVehicleInfoFromParsed(carId, parsed, timestamp, changedFields);
         });
 
         // 路径文件选择协议处理（0x0003）
@@ -287,8 +363,9 @@ class SocketManager {
      * @param {number} carId - 车辆ID
      * @param {Object} parsed - 解析后的车辆数据
      * @param {number} timestamp - 时间戳
+     * @param {string[]} [changedFields] - 后端车队状态已比对出的变化字段（fleet-update 推送时提供）
      */
-    async updateVehicleInfoFromParsed(carId, parsed, timestamp) {
+    async updateVehicleInfoFromParsed(carId, parsed, timestamp, changedFields) {
         // 空值检查：验证输入参数
         if (!parsed || typeof parsed !== 'object') {
             socketLogger.warn(`无效的解析数据: carId=${carId}`);
@@ -347,8 +424,12 @@ class SocketManager {
         if (store) {
             const prevVehicleState = store.getVehicleState(vehicleId);
             
-            // 如果有前一个状态且数据完整，使用Rust比对
-            if (prevVehicleState && prevVehicleState.state) {
+            // 后端已比对过的状态直接使用其变化字段，否则回退到Rust比对
+            if (Array.isArray(changedFields)) {
+                if (import.meta.env.DEV && changedFields.length > 0) {
+                    socketLogger.debug(`车辆 ${vehicleId} 变化字段: ${changedFields.join(', ')}`);
+                }
+            } else if (prevVehicleState && prevVehicleState.state) {
                 try {
                     // 准备前一个状态（需要包含停车位信息）
                     const prevState = {
@@ -786,6 +867,22 @@ class SocketManager {
         }
     }
 
+    /**
+     * 创建出租车订单并由后端自动派单
+     * @returns {Promise<{order_id: string, vehicle_id: number, strategy: string, pickup_distance: number, attempt: number}>}
+     */
+    async dispatchTaxiOrder(orderId, startX, startY, endX, endY, strategy = null) {
+        try {
+            socketLogger.info(`创建出租车订单并自动派单 - 订单: ${orderId}, 起点: (${startX}, ${startY}), 终点: (${endX}, ${endY})`);
+            const result = await vehicleBridge.dispatchTaxiOrder(orderId, startX, startY, endX, endY, strategy);
+            socketLogger.info(`出租车订单派单成功 - 订单: ${orderId}, 车辆: ${result.vehicle_id}`);
+            return result;
+        } catch (error) {
+            socketLogger.error(`出租车订单派单失败 - 订单: ${orderId}:`, error);
+            throw error;
+        }
+    }
+
     /**
      * 生成出租车订单UUID
      * @returns {string} 16字符的UUID字符串
@@ -812,14 +909,12 @@ class SocketManager {
                 throw new Error('车辆ID不能为空');
             }
 
-            // 使用默认停车位（如果没有提供）
-            const actualParkingSpot = parkingSpot ?? AVP_PARKING_PROTOCOL.DEFAULT_PARKING_SPOT;
-
-            socketLogger.info(`发送AVP泊车指令 - 车辆: ${vehicleId}, 车位: ${actualParkingSpot}`);
+            // 未指定车位时由后端分配空闲车位，已被占用的车位会被拒绝
+            socketLogger.info(`发送AVP泊车指令 - 车辆: ${vehicleId}, 车位: ${parkingSpot ?? '自动分配'}`);
 
-            const result = await vehicleBridge.sendAvpParking(vehicleId, actualParkingSpot);
+            const result = await vehicleBridge.sendAvpParking(vehicleId, parkingSpot);
 
-            socketLogger.info(`AVP泊车指令发送成功 - 车辆: ${vehicleId}, 车位: ${actualParkingSpot}`);
+            socketLogger.info(`AVP泊车指令发送成功 - 车辆: ${vehicleId}, ${result}`);
             return result;
         } catch (error) {
             socketLogger.error(`发送AVP泊车指令失败 - 车辆: ${vehicleId}:`, error);
@@ -37,7 +37,37 @@ const sendTaxiOrderToVehicle = (orderId, vehicleId, startX, startY, endX, endY)
     return invoke('send_taxi_order_to_vehicle', { orderId, vehicleId, startX, startY, endX, endY });
 };
 
-const sendAvpParking = (vehicleId, parkingSpot = 1) => invoke('send_avp_parking', { vehicleId, parkingSpot });
+const dispatchTaxiOrder = (orderId, startX, startY, endX, endY, strategy = null) => {
+    return invoke('dispatch_taxi_order', { orderId, startX, startY, endX, endY, strategy });
+};
+
+const cancelTaxiOrder = (orderId, reason = null) => invoke('cancel_taxi_order', { orderId, reason });
+
+const enqueueTaxiOrder = (orderId, startX, startY, endX, endY, { scheduledAt = null, priority = 0, strategy = null } = {}) => {
+    return invoke('enqueue_taxi_order', {
+        request: {
+            order_id: orderId,
+            start_x: startX,
+            start_y: startY,
+            end_x: endX,
+            end_y: endY,
+            scheduled_at: scheduledAt,
+            priority,
+            dispatch_strategy: strategy,
+        },
+    });
+};
+
+const getTaxiOrderQueue = () => invoke('get_taxi_order_queue');
+
+const reorderTaxiOrderQueue = (orderIds) => invoke('reorder_taxi_order_queue', { orderIds });
+
+// 未指定车位时由后端分配离车辆最近的空闲车位（slotType: 'normal' | 'charging'）
+const sendAvpParking = (vehicleId, parkingSpot = null, slotType = null) => {
+    return invoke('send_avp_parking', { vehicleId, parkingSpot, slotType });
+};
+
+const getParkingSlots = () => invoke('get_parking_slots');
 
 const sendAvpPickup = (vehicleId) => invoke('send_avp_pickup', { vehicleId });
 
@@ -61,7 +91,13 @@ export default {
     sendVehiclePathDisplay,
     broadcastTaxiOrder,
     sendTaxiOrderToVehicle,
+    dispatchTaxiOrder,
+    cancelTaxiOrder,
+    enqueueTaxiOrder,
+    getTaxiOrderQueue,
+    reorderTaxiOrderQueue,
     sendAvpParking,
+    getParkingSlots,
     sendAvpPickup,
     broadcastConstructionMarker,
     broadcastAllConstructionMarkers,